@@ -0,0 +1,226 @@
+use crate::archive::{Archive, CompressionType};
+use crate::backend::Scratch;
+use crate::package::{record, Package};
+use crate::Client;
+
+use anyhow::{Context, Result};
+use indicatif::ProgressBar;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// How many versions of a candidate are compared with a folder, newest first.
+const TRIES: usize = 10;
+
+/// How a folder was told to be a package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    /// Its files are the ones of the package.
+    Content,
+    /// Its plugin.cfg has the version of the package, but its files were changed.
+    Plugin,
+}
+
+/// A folder in the install dir that is a package.
+#[derive(Debug)]
+pub struct Found {
+    /// Relative to the install dir, like `gdcli`.
+    pub folder: PathBuf,
+    pub package: String,
+    pub version: String,
+    pub how: Match,
+    tarball: Vec<u8>,
+}
+
+/// The folders in `install_dir` gpm didnt install (which have no install record), like `gdcli` or `@bendn/gdcli`.
+pub fn unmanaged(install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut out = vec![];
+    let Ok(entries) = read_dir(install_dir) else {
+        return Ok(out);
+    };
+    for e in entries {
+        let e = e?;
+        let name = e.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == "__gpm_deps" || !e.file_type()?.is_dir() {
+            continue;
+        }
+        let sub = match name.starts_with('@') {
+            true => read_dir(e.path())?
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|e| Path::new(&name).join(e.file_name()))
+                .collect(),
+            false => vec![PathBuf::from(&name)],
+        };
+        out.extend(
+            sub.into_iter()
+                .filter(|rel| !record::path(install_dir, rel).exists()),
+        );
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// The name and version in the plugin.cfg of the addon in `dir`.
+pub fn plugin(dir: &Path) -> Option<(String, Option<String>)> {
+    let cfg = read_to_string(dir.join("plugin.cfg")).ok()?;
+    let value = |key: &str| {
+        cfg.lines().find_map(|l| {
+            let (k, v) = l.split_once('=')?;
+            (k.trim() == key).then(|| v.trim().trim_matches('"').to_owned())
+        })
+    };
+    Some((value("name")?, value("version")))
+}
+
+/// The packages the folder `rel` could be: itself (it is a package name, or close), what its plugin is called, and what the registry finds for that.
+async fn candidates(client: &Client, rel: &Path, plugin: Option<&str>) -> Vec<String> {
+    let folder = rel.to_string_lossy().replace('\\', "/");
+    let mut out = vec![folder.clone()];
+    if let Some(p) = plugin {
+        out.push(p.to_lowercase().replace([' ', '_'], "-"));
+    }
+    let text = plugin.unwrap_or(&folder);
+    out.extend(
+        crate::package::search::search(client.clone(), text, 5)
+            .await
+            .unwrap_or_default(),
+    );
+    let mut seen = std::collections::HashSet::new();
+    out.retain(|c| seen.insert(c.clone()));
+    out
+}
+
+/// Unpacks the tarball `bytes` into `dst`.
+fn unpack(bytes: &[u8], dst: &Path) -> Result<()> {
+    let ty = if bytes.starts_with(b"PK") {
+        "zip"
+    } else {
+        "tgz"
+    };
+    Archive::new(CompressionType::from(ty, bytes.to_vec(), String::new()))?.unpack(dst)
+}
+
+/// Finds the package the folder `rel` (of `install_dir`) is, if its one. Packages in `except` (already wanted) are not.
+pub async fn identify(
+    client: &Client,
+    install_dir: &Path,
+    rel: &Path,
+    except: &[String],
+) -> Result<Option<Found>> {
+    let dir = install_dir.join(rel);
+    let plugin = plugin(&dir);
+    let (plugin_name, plugin_version) = match &plugin {
+        Some((n, v)) => (Some(n.as_str()), v.as_deref()),
+        None => (None, None),
+    };
+    for name in candidates(client, rel, plugin_name).await {
+        if except.contains(&name) {
+            continue;
+        }
+        let Ok(packument) = Package::get_packument(client.clone(), &name).await else {
+            continue;
+        };
+        let mut versions = packument
+            .versions
+            .iter()
+            .map(|m| m.version.clone())
+            .collect::<Vec<_>>();
+        // what the plugin says its version is goes first
+        if let Some(i) = versions
+            .iter()
+            .position(|v| Some(v.as_str()) == plugin_version)
+        {
+            let v = versions.remove(i);
+            versions.insert(0, v);
+        }
+        for version in versions.into_iter().take(TRIES) {
+            let p = Package::new(name.clone(), format!("={version}"), client.clone()).await?;
+            let tarball = crate::backend::of(client, &name)
+                .fetch_tarball(client, &p, &ProgressBar::hidden())
+                .await?;
+            let s = Scratch::new()?;
+            unpack(&tarball, &s.0)?;
+            let how = if record::matches(&dir, &s.0)? {
+                Match::Content
+            } else if Some(version.as_str()) == plugin_version {
+                Match::Plugin
+            } else {
+                continue;
+            };
+            return Ok(Some(Found {
+                folder: rel.to_path_buf(),
+                package: name,
+                version,
+                how,
+                tarball,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+impl Found {
+    /// Writes the install record of the folder, as if gpm installed it: so when gpm replaces it,
+    /// whatever was changed in it is moved aside, not deleted.
+    pub fn take_over(&self, install_dir: &Path) -> Result<()> {
+        let s = Scratch::new()?;
+        unpack(&self.tarball, &s.0)?;
+        record::save(&s.0, &record::path(install_dir, &self.folder))
+            .with_context(|| format!("recording {}", self.folder.display()))
+    }
+
+    /// The folder, if it isnt where the package would go anyway.
+    pub fn dir(&self) -> Option<String> {
+        let default = crate::package::folder(crate::package::DEFAULT_DIR_TEMPLATE, &self.package);
+        (default != self.folder).then(|| self.folder.to_string_lossy().replace('\\', "/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn adopts() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let addons = t.0.path().join("addons");
+        let p = Package::new("@bendn/gdcli".into(), "=1.2.5".into(), c.clone())
+            .await
+            .unwrap();
+        let bytes = p.fetch(c.clone(), &ProgressBar::hidden()).await.unwrap();
+        unpack(&bytes, &addons.join("@bendn/gdcli")).unwrap();
+        std::fs::create_dir(addons.join("mine")).unwrap();
+        std::fs::write(
+            addons.join("mine/plugin.cfg"),
+            "[plugin]\n\nname=\"My Thing\"\nversion=\"0.1\"\nscript=\"plugin.gd\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            plugin(&addons.join("mine")),
+            Some(("My Thing".into(), Some("0.1".into())))
+        );
+        let found = unmanaged(&addons).unwrap();
+        assert_eq!(found, [Path::new("@bendn/gdcli"), Path::new("mine")]);
+
+        let gdcli = identify(&c, &addons, &found[0], &[])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (gdcli.package.as_str(), gdcli.version.as_str(), gdcli.how),
+            ("@bendn/gdcli", "1.2.5", Match::Content)
+        );
+        assert_eq!(gdcli.dir(), None);
+        assert!(identify(&c, &addons, &found[1], &[])
+            .await
+            .unwrap()
+            .is_none());
+        assert!(identify(&c, &addons, &found[0], &["@bendn/gdcli".into()])
+            .await
+            .unwrap()
+            .is_none());
+        gdcli.take_over(&addons).unwrap();
+        assert_eq!(unmanaged(&addons).unwrap(), [Path::new("mine")]);
+    }
+}
@@ -0,0 +1,142 @@
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How findings (like the ones of status, compat, and unused) are written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Lines for people.
+    #[default]
+    Human,
+    /// Github actions workflow annotations (`::warning file=godot.package,line=3::...`), which show up on the lines of pull requests.
+    Github,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Level {
+    Notice,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn command(self) -> &'static str {
+        match self {
+            Self::Notice => "notice",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// The output, the config file, and its contents (to find the lines of packages in).
+static OUTPUT: OnceLock<(Output, PathBuf, String)> = OnceLock::new();
+
+/// Sets how findings are written, and the config `file` (with `contents`) they are about.
+pub fn set(output: Output, file: PathBuf, contents: String) {
+    let _ = OUTPUT.set((output, file, contents));
+}
+
+/// If findings are written as annotations (see [set]).
+pub fn github() -> bool {
+    OUTPUT.get().is_some_and(|(o, _, _)| *o == Output::Github)
+}
+
+/// The line (from 1) of `contents` that says `what` (like a package of a config): the one it is the key of, else the first one it is quoted in.
+pub fn line(contents: &str, what: &str) -> Option<usize> {
+    let quoted = [format!("\"{what}\""), format!("'{what}'")];
+    let key = |l: &str| {
+        let l = l.trim_start();
+        quoted.iter().any(|q| l.starts_with(q.as_str()))
+            || l.strip_prefix(what)
+                .is_some_and(|rest| rest.trim_start().starts_with([':', '=']))
+    };
+    let (i, _) = contents
+        .lines()
+        .enumerate()
+        .find(|(_, l)| key(l))
+        .or_else(|| {
+            contents
+                .lines()
+                .enumerate()
+                .find(|(_, l)| quoted.iter().any(|q| l.contains(q.as_str())))
+        })?;
+    Some(i + 1)
+}
+
+/// Escapes `s` for an annotation: its message, or (if `property`) a value of its file or line.
+fn escape(s: &str, property: bool) -> String {
+    let s = s
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    match property {
+        true => s.replace(':', "%3A").replace(',', "%2C"),
+        false => s,
+    }
+}
+
+/// The workflow annotation of `message`, about `file` (at `line`, if known).
+pub fn annotation(level: Level, file: &Path, line: Option<usize>, message: &str) -> String {
+    let mut props = format!("file={}", escape(&file.to_string_lossy(), true));
+    if let Some(l) = line {
+        props.push_str(&format!(",line={l}"));
+    }
+    format!("::{} {props}::{}", level.command(), escape(message, false))
+}
+
+/// Writes `message`, found in `file` (at the line of `contents` that says `what`): as an annotation with [Output::Github],
+/// else as a line on stderr.
+pub fn report_in(level: Level, file: &Path, contents: &str, what: Option<&str>, message: &str) {
+    if github() {
+        let line = what.and_then(|w| line(contents, w));
+        println!("{}", annotation(level, file, line, message));
+        return;
+    }
+    match level {
+        Level::Notice => eprintln!("{:>12} {message}", crate::putils::green("Notice")),
+        Level::Warning => eprintln!("{:>12} {message}", crate::putils::warn()),
+        Level::Error => eprintln!("{:>12} {message}", crate::putils::err()),
+    }
+}
+
+/// Writes `message`, about the `package` (by its name in the config, if it is in it) (see [report_in]).
+pub fn report(level: Level, package: Option<&str>, message: &str) {
+    let (file, contents) = OUTPUT
+        .get()
+        .map_or((Path::new("godot.package"), ""), |(_, f, c)| {
+            (f.as_path(), c.as_str())
+        });
+    report_in(level, file, contents, package, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations() {
+        let json = "{\n  \"packages\": {\n    \"@bendn/gdcli\": \"1.2.5\",\n    \"@bendn/test\": \"^2\"\n  }\n}";
+        assert_eq!(line(json, "@bendn/test"), Some(4));
+        assert_eq!(line(json, "@bendn/gdcli"), Some(3));
+        assert_eq!(line(json, "@bendn/nope"), None);
+        let yaml = "packages:\n  gdcli: 1.2.5\n  test:\n    version: ^2\n";
+        assert_eq!(line(yaml, "test"), Some(3));
+        let toml =
+            "[packages]\ngdcli = \"1.2.5\"\n\n[packages.\"@bendn/test\"]\nversion = \"^2\"\n";
+        assert_eq!(line(toml, "@bendn/test"), Some(4));
+        assert_eq!(
+            annotation(
+                Level::Warning,
+                Path::new("godot.package"),
+                Some(4),
+                "@bendn/test is 100% deprecated:\nuse x"
+            ),
+            "::warning file=godot.package,line=4::@bendn/test is 100%25 deprecated:%0Ause x"
+        );
+        assert_eq!(
+            annotation(Level::Error, Path::new("a,b:c"), None, "x"),
+            "::error file=a%2Cb%3Ac::x"
+        );
+    }
+}
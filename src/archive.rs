@@ -5,6 +5,7 @@ use crate::Client;
 use anyhow::{Context, Result};
 use flate2::bufread::GzDecoder;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs::{create_dir_all, set_permissions, File, Permissions};
 use std::io::{self, prelude::*, Cursor};
@@ -14,6 +15,107 @@ use tar::EntryType::Directory;
 use zip::result::{ZipError, ZipResult};
 use zip::ZipArchive as Zarchive;
 
+/// What to take out of a package: everything (or just the `subdir`) but the `exclude`d files.
+#[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Filter {
+    /// The folder inside the package that gets installed, for packages nesting the addon (in `addons/<name>`, say).
+    pub subdir: Option<PathBuf>,
+    /// Globs (like `**/test/**`, or `**/*.blend`), matched against the path in the install folder.
+    pub exclude: Vec<glob::Pattern>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.subdir.is_none() && self.exclude.is_empty()
+    }
+
+    /// Which path (relative to the install folder) the file at `rel` (inside the package) is installed as, if it is.
+    pub fn select(&self, rel: &Path) -> Option<PathBuf> {
+        let rel = match &self.subdir {
+            Some(sub) => rel.strip_prefix(sub).ok()?,
+            None => rel,
+        };
+        if rel.as_os_str().is_empty() || self.exclude.iter().any(|p| p.matches_path(rel)) {
+            return None;
+        }
+        Some(rel.to_path_buf())
+    }
+
+    /// Where the file at `rel` (inside the package) should go, if anywhere: [Filter::select],
+    /// made [windows_safe] on windows.
+    pub fn map(&self, rel: &Path) -> Option<PathBuf> {
+        let p = self.select(rel)?;
+        Some(if cfg!(windows) { windows_safe(&p) } else { p })
+    }
+}
+
+/// Names windows wont make files of, whatever the extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com0", "com1", "com2", "com3", "com4", "com5", "com6", "com7",
+    "com8", "com9", "lpt0", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// `p`, with every component windows cant make (reserved names like `con.gd`, names ending in dots or spaces,
+/// or with `<>:"|?*` in them) renamed to one it can (by adding or replacing with `_`).
+pub fn windows_safe(p: &Path) -> PathBuf {
+    p.components()
+        .map(|c| {
+            let Normal(name) = c else {
+                return c.as_os_str().to_os_string();
+            };
+            let name = name
+                .to_string_lossy()
+                .replace(|c: char| "<>:\"|?*".contains(c) || c.is_control(), "_");
+            let trimmed = name.trim_end_matches(['.', ' ']);
+            let mut name = if trimmed.len() == name.len() {
+                name
+            } else {
+                format!("{trimmed}_")
+            };
+            let stem = name.split('.').next().unwrap().to_lowercase();
+            if RESERVED.contains(&stem.as_str()) {
+                name.insert(stem.len(), '_');
+            }
+            name.into()
+        })
+        .collect()
+}
+
+/// The directory `dst` to extract into, made if it isnt there, in the form files under it are written at:
+/// on windows, a `\\?\` path, which can be longer than 260 characters.
+pub fn destination(dst: &Path) -> io::Result<PathBuf> {
+    if dst.symlink_metadata().is_err() {
+        create_dir_all(dst)?;
+    }
+    Ok(dst.canonicalize().unwrap_or(dst.to_path_buf()))
+}
+
+/// Pairs of paths that differ only by case (or lie in folders that do),
+/// and so would end up as one file on a case insensitive filesystem (windows, macos).
+pub fn case_collisions<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Vec<(PathBuf, PathBuf)> {
+    let mut seen = BTreeMap::<String, PathBuf>::new();
+    let mut collisions = vec![];
+    for path in paths {
+        // the folders too: `A/x` and `a/y` are in the same folder on windows, and not on linux
+        for p in path.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            let key = p.to_string_lossy().to_lowercase();
+            match seen.get(&key) {
+                Some(other) if other != p => {
+                    collisions.push((other.clone(), p.to_path_buf()));
+                    break;
+                }
+                Some(_) => {}
+                None => {
+                    seen.insert(key, p.to_path_buf());
+                }
+            }
+        }
+    }
+    collisions.sort();
+    collisions.dedup();
+    collisions
+}
+
 type TArch = Tarchive<GzDecoder<Cursor<Vec<u8>>>>;
 type ZArch = Zarchive<Cursor<Vec<u8>>>;
 
@@ -44,7 +146,6 @@ impl Data {
 pub enum CompressionType {
     Gzip(Data),
     Zip(Data),
-    Lock(String),
     #[default]
     None,
 }
@@ -54,7 +155,6 @@ impl Display for CompressionType {
         match self {
             CompressionType::Gzip(d) => write!(f, "{}", d.uri),
             CompressionType::Zip(d) => write!(f, "{}", d.uri),
-            CompressionType::Lock(d) => write!(f, "{}", d),
             _ => unreachable!(),
         }
     }
@@ -67,14 +167,6 @@ impl CompressionType {
             _ => Self::Gzip(Data::new(bytes, uri)),
         }
     }
-
-    pub fn lock(&mut self) {
-        *self = Self::Lock(match self {
-            CompressionType::Gzip(d) => std::mem::take(&mut d.uri),
-            CompressionType::Zip(d) => std::mem::take(&mut d.uri),
-            _ => unreachable!(),
-        })
-    }
 }
 
 enum ArchiveType {
@@ -99,20 +191,23 @@ pub struct Archive {
 //     }
 // }
 
-fn unpack_zarchive(archive: &mut ZArch, dst: &Path) -> ZipResult<()> {
-    if dst.symlink_metadata().is_err() {
-        create_dir_all(dst).map_err(ZipError::Io)?;
-    }
-    let dst = &dst.canonicalize().unwrap_or(dst.to_path_buf());
+fn unpack_zarchive(archive: &mut ZArch, dst: &Path, filter: &Filter) -> ZipResult<()> {
+    let dst = &destination(dst).map_err(ZipError::Io)?;
 
     let mut directories = vec![];
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
-        let path = dst.join(skip_toplevel(
+        let Some(path) = filter.map(&skip_toplevel(
             file.enclosed_name().ok_or(ZipError::FileNotFound)?,
-        ));
+        )) else {
+            continue;
+        };
+        let path = dst.join(path);
         if file.is_dir() {
-            directories.push(path);
+            // filtered out folders would be left behind empty
+            if filter.is_empty() {
+                directories.push(path);
+            }
         } else {
             create_dir_all(path.parent().unwrap())?;
             let mut outfile = File::create(&path)?;
@@ -139,12 +234,8 @@ fn skip_toplevel(p: &Path) -> PathBuf {
         .collect::<PathBuf>()
 }
 
-fn unpack_tarchive(archive: &mut TArch, dst: &Path) -> io::Result<()> {
-    if dst.symlink_metadata().is_err() {
-        create_dir_all(dst)?;
-    }
-
-    let dst = &dst.canonicalize().unwrap_or(dst.to_path_buf());
+fn unpack_tarchive(archive: &mut TArch, dst: &Path, filter: &Filter) -> io::Result<()> {
+    let dst = &destination(dst)?;
 
     // Delay any directory entries until the end (they will be created if needed by
     // descendants), to ensure that directory permissions do not interfer with descendant
@@ -152,9 +243,15 @@ fn unpack_tarchive(archive: &mut TArch, dst: &Path) -> io::Result<()> {
     let mut directories = Vec::new();
     for entry in archive.entries()? {
         let entry = entry?;
-        let mut entry = (dst.join(skip_toplevel(&entry.path()?)), entry);
+        let Some(path) = filter.map(&skip_toplevel(&entry.path()?)) else {
+            continue;
+        };
+        let mut entry = (dst.join(path), entry);
         if entry.1.header().entry_type() == Directory {
-            directories.push(entry);
+            // filtered out folders would be left behind empty
+            if filter.is_empty() {
+                directories.push(entry);
+            }
         } else {
             create_dir_all(entry.0.parent().unwrap())?;
             entry.1.unpack(entry.0)?;
@@ -196,13 +293,76 @@ fn get_gfile(tarchive: &mut TArch, file: &str, out: &mut String) -> io::Result<(
 
 impl Archive {
     pub fn unpack(&mut self, dst: &Path) -> Result<()> {
+        self.unpack_filtered(dst, &Filter::default())
+    }
+
+    /// Unpacks only what the `filter` lets through.
+    pub fn unpack_filtered(&mut self, dst: &Path, filter: &Filter) -> Result<()> {
         match &mut self.inner {
-            ArchiveType::Gzip(g) => unpack_tarchive(g, dst)?,
-            ArchiveType::Zip(z) => unpack_zarchive(z, dst)?,
+            ArchiveType::Gzip(g) => unpack_tarchive(g, dst, filter)?,
+            ArchiveType::Zip(z) => unpack_zarchive(z, dst, filter)?,
         }
         Ok(())
     }
 
+    /// The paths of every file (without the top level directory), without reading them.
+    pub fn paths(&mut self) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        match &mut self.inner {
+            ArchiveType::Gzip(g) => {
+                for entry in g.entries()? {
+                    let entry = entry?;
+                    if entry.header().entry_type() != Directory {
+                        paths.push(skip_toplevel(&entry.path()?));
+                    }
+                }
+            }
+            ArchiveType::Zip(z) => {
+                for i in 0..z.len() {
+                    let file = z.by_index(i)?;
+                    if !file.is_dir() {
+                        paths.push(skip_toplevel(
+                            file.enclosed_name().ok_or(ZipError::FileNotFound)?,
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Reads every file into memory, keyed by path (without the top level directory).
+    pub fn files(&mut self) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        let mut files = BTreeMap::new();
+        match &mut self.inner {
+            ArchiveType::Gzip(g) => {
+                for entry in g.entries()? {
+                    let mut entry = entry?;
+                    if entry.header().entry_type() == Directory {
+                        continue;
+                    }
+                    let path = skip_toplevel(&entry.path()?);
+                    let mut bytes = vec![];
+                    entry.read_to_end(&mut bytes)?;
+                    files.insert(path, bytes);
+                }
+            }
+            ArchiveType::Zip(z) => {
+                for i in 0..z.len() {
+                    let mut file = z.by_index(i)?;
+                    if file.is_dir() {
+                        continue;
+                    }
+                    let path = skip_toplevel(file.enclosed_name().ok_or(ZipError::FileNotFound)?);
+                    let mut bytes = vec![];
+                    file.read_to_end(&mut bytes)?;
+                    files.insert(path, bytes);
+                }
+            }
+        }
+        Ok(files)
+    }
+
     pub fn get_file(&mut self, file: &str, out: &mut String) -> Result<()> {
         match &mut self.inner {
             ArchiveType::Gzip(g) => get_gfile(g, file, out)?,
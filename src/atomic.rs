@@ -0,0 +1,54 @@
+use std::fs::{canonicalize, remove_file, rename, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes `contents` to `path` so that it is never half written: into a file next to it first, synced,
+/// then renamed over it. A crash (or ctrl-c) leaves either the old file, or the new one.
+/// A symlink stays one: the file it points to is replaced.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let path = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{name}.tmp-{}", std::process::id()));
+    let written = (|| {
+        let mut f = File::create(&tmp)?;
+        f.write_all(contents.as_ref())?;
+        f.sync_all()?;
+        rename(&tmp, &path)
+    })();
+    if written.is_err() {
+        let _ = remove_file(&tmp);
+    }
+    written?;
+    // the rename is only durable once the dir is
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let _ = File::open(dir).and_then(|d| d.sync_all());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn writes() {
+        let t = tempfile::TempDir::new().unwrap();
+        let p = t.path().join("godot.package");
+        write(&p, "{}").unwrap();
+        write(&p, r#"{"packages": {}}"#).unwrap();
+        assert_eq!(read_to_string(&p).unwrap(), r#"{"packages": {}}"#);
+        assert_eq!(std::fs::read_dir(t.path()).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            let link = t.path().join("link");
+            std::os::unix::fs::symlink(&p, &link).unwrap();
+            write(&link, "x").unwrap();
+            assert!(link.is_symlink());
+            assert_eq!(read_to_string(&p).unwrap(), "x");
+        }
+        assert!(write(t.path().join("missing/godot.lock"), "[]").is_err());
+    }
+}
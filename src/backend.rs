@@ -0,0 +1,204 @@
+mod asset_library;
+mod git;
+mod npm;
+mod path;
+mod url;
+
+use crate::pack::Packed;
+use crate::package::parsing::ParsedPackument;
+use crate::package::Package;
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressBar;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Where packages come from: the registry, or something that works like one.
+/// A new source is an implementation of this, and a case in [of].
+#[async_trait::async_trait]
+pub trait RegistryBackend: Send + Sync {
+    /// Every version of `name` there is, as a packument.
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument>;
+
+    /// The tarball of `p` (a version [RegistryBackend::resolve_versions] gave), reporting downloaded bytes to `progress`.
+    async fn fetch_tarball(
+        &self,
+        client: &Client,
+        p: &Package,
+        progress: &ProgressBar,
+    ) -> Result<Vec<u8>> {
+        download(client, p, progress).await
+    }
+
+    /// Uploads `packed` as `tag`, authenticated with `token` (and `otp`), with its `provenance` bundle.
+    async fn publish(
+        &self,
+        _client: Client,
+        packed: &Packed,
+        _tag: &str,
+        _token: &str,
+        _otp: Option<&str>,
+        _provenance: Option<&Value>,
+    ) -> Result<()> {
+        bail!("{} cant be published there", packed.name)
+    }
+}
+
+/// The backend `name` comes from, for `client`.
+pub fn of(client: &Client, name: &str) -> &'static dyn RegistryBackend {
+    if crate::releases::is_release(name) {
+        &Releases
+    } else if name.starts_with("http") {
+        &url::Url
+    } else if name.starts_with(git::PREFIX) {
+        &git::Git
+    } else if name.starts_with(path::PREFIX) {
+        &path::Path
+    } else if name.starts_with(asset_library::PREFIX) {
+        &asset_library::AssetLibrary
+    } else {
+        registry(client)
+    }
+}
+
+/// The backend of the registry of `client`.
+pub fn registry(client: &Client) -> &'static dyn RegistryBackend {
+    if crate::registry::is_static(&client.registry) {
+        &Static
+    } else {
+        &npm::Npm
+    }
+}
+
+/// The name of the folder a package from somewhere other than the registry goes in (`repo`, for `gh:user/repo`),
+/// if `name` is one.
+pub fn repository(name: &str) -> Option<&str> {
+    if let Some(repo) = crate::releases::repository(name) {
+        return Some(repo);
+    }
+    let rest = [git::PREFIX, path::PREFIX, asset_library::PREFIX]
+        .into_iter()
+        .find_map(|p| name.strip_prefix(p))?;
+    let rest = rest.split('#').next().unwrap_or(rest);
+    let last = rest.trim_end_matches('/').rsplit(['/', '\\']).next()?;
+    Some(last.trim_end_matches(".git"))
+}
+
+/// The dir of a package in a local dir (`file:../shared/addon`), if `name` is one.
+pub fn local_dir(name: &str) -> Option<&std::path::Path> {
+    name.strip_prefix(path::PREFIX).map(std::path::Path::new)
+}
+
+/// Downloads the tarball of `p`, over http.
+pub async fn download(client: &Client, p: &Package, progress: &ProgressBar) -> Result<Vec<u8>> {
+    // private release assets need the token of their forge
+    let url = p.manifest.tarball.to_string();
+    let req = crate::releases::authorize(client.get(&url), &url);
+    let mut resp = req
+        .send()
+        .await
+        .with_context(|| format!("downloading the tarball of {p}"))?;
+    match resp.status() {
+        // remembered versions can be unpublished since
+        StatusCode::NOT_FOUND | StatusCode::GONE => bail!(
+            "{p} is gone from the registry (its tarball is {}): it was probably unpublished. \
+            Run `gpm update {}` to resolve it again (it is not swapped for another version silently)",
+            resp.status(),
+            p.name
+        ),
+        s if !s.is_success() => bail!("Tarball download of {p} failed: {s}"),
+        _ => {}
+    }
+    let mut bytes = vec![];
+    if let Some(len) = resp.content_length() {
+        progress.set_length(len);
+        bytes.reserve(len as usize);
+    }
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .with_context(|| format!("downloading the tarball of {p}"))?
+    {
+        progress.inc(chunk.len() as u64);
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// A dir in the temp dir (for a clone, or an unpacked tarball), deleted when dropped.
+pub struct Scratch(pub PathBuf);
+
+impl Scratch {
+    pub fn new() -> Result<Self> {
+        static N: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "gpm-{}-{}",
+            std::process::id(),
+            N.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).context("making a scratch dir")?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A static registry, see [crate::registry].
+struct Static;
+
+#[async_trait::async_trait]
+impl RegistryBackend for Static {
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument> {
+        crate::registry::packument(client.clone(), name).await
+    }
+
+    async fn publish(
+        &self,
+        client: Client,
+        _: &Packed,
+        _: &str,
+        _: &str,
+        _: Option<&str>,
+        _: Option<&Value>,
+    ) -> Result<()> {
+        bail!(
+            "{} is a static registry: put the tarball (gpm pack) in its dir, and run gpm registry build there",
+            client.registry
+        )
+    }
+}
+
+/// Releases of repositories, see [crate::releases].
+struct Releases;
+
+#[async_trait::async_trait]
+impl RegistryBackend for Releases {
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument> {
+        crate::releases::get(client.clone(), name).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repositories() {
+        assert_eq!(repository("gh:user/addon"), Some("addon"));
+        assert_eq!(
+            repository("git+https://example.com/user/addon.git"),
+            Some("addon")
+        );
+        assert_eq!(repository("file:../shared/addon/"), Some("addon"));
+        assert_eq!(repository("assetlib:1234"), Some("1234"));
+        assert_eq!(repository("@bendn/test"), None);
+        assert_eq!(repository("https://example.com/addon.zip"), None);
+    }
+}
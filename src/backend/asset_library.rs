@@ -0,0 +1,107 @@
+use super::RegistryBackend;
+use crate::package::parsing::{ParsedManifest, ParsedManifestDist, ParsedPackument};
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// What packages on the godot asset library are named like: `assetlib:1234` (the id of the asset).
+pub const PREFIX: &str = "assetlib:";
+
+/// The godot asset library (`GODOT_ASSET_LIBRARY_URL`, to use another one). It only has the newest version of an asset.
+pub struct AssetLibrary;
+
+#[derive(Deserialize)]
+struct Asset {
+    version_string: String,
+    godot_version: Option<String>,
+    download_url: String,
+}
+
+/// `v1.2` => `1.2.0`: asset library versions are free text, but mostly (not quite) semver.
+fn version(s: &str) -> Option<String> {
+    let s = s.trim().trim_start_matches(['v', 'V']);
+    if let Some(v) = crate::package::range::exact(s) {
+        return Some(v.to_string());
+    }
+    let parts = s
+        .split('.')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match parts[..] {
+        [major] => Some(format!("{major}.0.0")),
+        [major, minor] => Some(format!("{major}.{minor}.0")),
+        _ => None,
+    }
+}
+
+/// The packument of the asset in the api response `body`.
+fn packument(body: &str) -> Result<ParsedPackument> {
+    let a: Asset = serde_json::from_str(body)?;
+    let Some(v) = version(&a.version_string) else {
+        bail!(
+            "its version {:?} is not a version gpm gets",
+            a.version_string
+        );
+    };
+    let mut p = ParsedPackument::default();
+    p.versions.insert(
+        v.clone(),
+        ParsedManifest {
+            dist: ParsedManifestDist {
+                tarball: a.download_url,
+                ..Default::default()
+            },
+            version: v,
+            engines: a
+                .godot_version
+                .map(|g| HashMap::from([("godot".into(), format!("^{g}"))]))
+                .unwrap_or_default(),
+            ..Default::default()
+        },
+    );
+    Ok(p)
+}
+
+#[async_trait::async_trait]
+impl RegistryBackend for AssetLibrary {
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument> {
+        let id = name.strip_prefix(PREFIX).unwrap_or(name);
+        let api = std::env::var("GODOT_ASSET_LIBRARY_URL")
+            .unwrap_or_else(|_| "https://godotengine.org/asset-library/api".into());
+        let resp = client
+            .get(format!("{}/asset/{id}", api.trim_end_matches('/')))
+            .send()
+            .await
+            .with_context(|| format!("getting asset {id} from the asset library"))?;
+        if !resp.status().is_success() {
+            bail!("asset {id} is not on the asset library ({})", resp.status());
+        }
+        packument(&resp.text().await?).with_context(|| format!("parsing asset {id}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asset() {
+        let p = packument(
+            r#"{"asset_id": "1234", "title": "Addon", "version": "7", "version_string": "v2.1",
+                "godot_version": "4.2", "download_provider": "GitHub",
+                "download_url": "https://github.com/user/addon/archive/abc.zip", "download_hash": ""}"#,
+        )
+        .unwrap();
+        let m = &p.versions["2.1.0"];
+        assert_eq!(m.engines["godot"], "^4.2");
+        assert_eq!(
+            m.dist.tarball,
+            "https://github.com/user/addon/archive/abc.zip"
+        );
+        assert_eq!(version("1.0.2").as_deref(), Some("1.0.2"));
+        assert_eq!(version("3").as_deref(), Some("3.0.0"));
+        assert_eq!(version("beta"), None);
+    }
+}
@@ -0,0 +1,151 @@
+use super::{RegistryBackend, Scratch};
+use crate::package::parsing::{ParsedManifest, ParsedManifestDist, ParsedPackument};
+use crate::package::range;
+use crate::package::Package;
+use crate::registry::PackageJson;
+use crate::version::git;
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressBar;
+
+/// What packages in git repositories are named like: `git+https://example.com/user/addon.git`.
+pub const PREFIX: &str = "git+";
+
+/// A git repository: every tag with a version (like `v1.2.0`) and a package.json is a version,
+/// packed (like gpm pack does) from a checkout when its installed.
+pub struct Git;
+
+#[async_trait::async_trait]
+impl RegistryBackend for Git {
+    async fn resolve_versions(&self, _: &Client, name: &str) -> Result<ParsedPackument> {
+        let url = name.strip_prefix(PREFIX).unwrap_or(name);
+        let s = Scratch::new()?;
+        // only the blobs of the package.jsons are needed
+        git(
+            &s.0,
+            // after --, a url starting with - is not taken as an option
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                "--filter=blob:none",
+                "--",
+                url,
+                ".",
+            ],
+        )
+        .with_context(|| format!("cloning {url}"))?;
+        let mut packument = ParsedPackument::default();
+        for tag in git(&s.0, &["tag", "--list"])?.lines() {
+            let Some(version) = range::exact(tag) else {
+                continue;
+            };
+            let Ok(json) = git(&s.0, &["show", &format!("{tag}:package.json")]) else {
+                continue;
+            };
+            let p: PackageJson = serde_json::from_str(&json)
+                .with_context(|| format!("parsing the package.json of {url} at {tag}"))?;
+            packument.versions.insert(
+                version.to_string(),
+                ParsedManifest {
+                    version: version.to_string(),
+                    ..p.into_manifest(ParsedManifestDist {
+                        tarball: format!("{name}#{tag}"),
+                        ..Default::default()
+                    })
+                },
+            );
+        }
+        Ok(packument)
+    }
+
+    async fn fetch_tarball(&self, _: &Client, p: &Package, _: &ProgressBar) -> Result<Vec<u8>> {
+        let tarball = p.manifest.tarball.to_string();
+        let (url, tag) = tarball
+            .strip_prefix(PREFIX)
+            .and_then(|t| t.rsplit_once('#'))
+            .with_context(|| format!("{tarball} is not a tag of a git repository"))?;
+        if tag.starts_with('-') {
+            bail!("{tag} (of {url}) is not a tag: it would be an option of git");
+        }
+        let s = Scratch::new()?;
+        git(
+            &s.0,
+            &[
+                "clone", "--quiet", "--depth", "1", "--branch", tag, "--", url, ".",
+            ],
+        )
+        .with_context(|| format!("checking out {tag} of {url}"))?;
+        Ok(crate::pack::pack(&s.0)
+            .with_context(|| format!("packing {p}"))?
+            .tarball)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[tokio::test]
+    async fn tags() {
+        let t = tempfile::TempDir::new().unwrap();
+        let repo = t.path();
+        let run = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=gpm", "-c", "user.email=gpm@localhost"];
+            all.extend(args);
+            git(repo, &all).unwrap();
+        };
+        run(&["init", "--quiet"]);
+        for (version, tag) in [
+            ("1.0.0", "v1.0.0"),
+            ("1.1.0", "1.1.0"),
+            ("2.0.0", "nightly"),
+        ] {
+            std::fs::write(
+                repo.join("package.json"),
+                format!(r#"{{"name": "addon", "version": "{version}"}}"#),
+            )
+            .unwrap();
+            std::fs::write(repo.join("plugin.gd"), version).unwrap();
+            run(&["add", "."]);
+            run(&["commit", "--quiet", "-m", version]);
+            run(&["tag", tag]);
+        }
+        let name = format!("{PREFIX}file://{}", repo.display());
+        let c = crate::mkclient("http://127.0.0.1:9".into(), Cache::new());
+        let packument = Git.resolve_versions(&c, &name).await.unwrap();
+        let mut versions = packument.versions.keys().collect::<Vec<_>>();
+        versions.sort();
+        assert_eq!(versions, ["1.0.0", "1.1.0"]);
+        let p = Package::new(name.clone(), "^1.0.0".into(), c.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.manifest.version.to_string(), "1.1.0");
+        let bytes = Git
+            .fetch_tarball(&c, &p, &ProgressBar::hidden())
+            .await
+            .unwrap();
+        let mut archive = crate::archive::Archive::new(crate::archive::CompressionType::from(
+            "tgz",
+            bytes,
+            String::new(),
+        ))
+        .unwrap();
+        let mut contents = String::new();
+        archive.get_file("plugin.gd", &mut contents).unwrap();
+        assert_eq!(contents, "1.1.0");
+        let mut evil = p.clone();
+        evil.manifest.tarball = crate::archive::CompressionType::from(
+            "tgz",
+            vec![],
+            format!("{name}#--upload-pack=touch evil"),
+        );
+        let e = Git
+            .fetch_tarball(&c, &evil, &ProgressBar::hidden())
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("is not a tag"), "{e}");
+    }
+}
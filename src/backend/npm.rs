@@ -0,0 +1,78 @@
+use super::RegistryBackend;
+use crate::pack::Packed;
+use crate::package::parsing::{Malformed, ParsedPackument};
+use crate::package::search;
+use crate::publish::escape;
+use crate::{ctx, get, putils, user_config, Client};
+
+use anyhow::{bail, Context, Result};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+/// An npm registry, like registry.npmjs.org (or github packages, or an azure artifacts feed).
+pub struct Npm;
+
+#[async_trait::async_trait]
+impl RegistryBackend for Npm {
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument> {
+        let res = match packument(client, &client.registry, name).await {
+            Err(e) if e.is::<Malformed>() => {
+                let Some(mirror) = user_config::mirror(&client.registries, &client.registry) else {
+                    return Err(e);
+                };
+                eprintln!("{:>12} {e}: asking its mirror {mirror}", putils::warn());
+                packument(client, mirror, name).await?
+            }
+            r => r?,
+        };
+        if res.time.contains_key("unpublished") {
+            bail!("{name} was unpublished from {}", client.registry);
+        }
+        // println!(
+        //     "(\"{name}\", r#\"{}\"#),",
+        //     serde_json::to_string(&res)
+        //         .unwrap()
+        //         .replace("https://registry.npmjs.org", "{REGISTRY}")
+        // );
+        Ok(res)
+    }
+
+    async fn publish(
+        &self,
+        client: Client,
+        packed: &Packed,
+        tag: &str,
+        token: &str,
+        otp: Option<&str>,
+        provenance: Option<&Value>,
+    ) -> Result<()> {
+        crate::publish::publish(packed, client, tag, token, otp, provenance).await
+    }
+}
+
+/// Gets the packument of `name` from `registry` (the clients, or its mirror).
+async fn packument(client: &Client, registry: &str, name: &str) -> Result<ParsedPackument> {
+    // github packages and azure artifacts only find scoped packages escaped
+    let url = format!("{registry}/{}", escape(name));
+    let resp = get!(client, "{url}")?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Err(search::not_found(client.clone(), name).await);
+    }
+    if matches!(
+        resp.status(),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) {
+        bail!(
+                "{registry} wants credentials to read {name} ({}): give it always-auth in the registries of the user config",
+                resp.status()
+            );
+    }
+    let resp = ctx!(
+        resp.text().await,
+        "getting packument from {registry}/{name}"
+    )?;
+    if resp == "\"Not Found\"" {
+        return Err(search::not_found(client.clone(), name).await);
+    };
+    ParsedPackument::parse(&resp, name, &url)
+}
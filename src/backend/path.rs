@@ -0,0 +1,82 @@
+use super::RegistryBackend;
+use crate::package::parsing::{ParsedManifestDist, ParsedPackument};
+use crate::package::Package;
+use crate::registry::PackageJson;
+use crate::Client;
+
+use anyhow::{Context, Result};
+use indicatif::ProgressBar;
+use std::fs::read_to_string;
+
+/// What packages in a local dir are named like: `file:../shared/addon` (relative to the project).
+pub const PREFIX: &str = "file:";
+
+/// A package in a local dir, packed (like gpm pack does) when its installed. Its one version is what its package.json says.
+pub struct Path;
+
+fn dir(name: &str) -> &std::path::Path {
+    std::path::Path::new(name.strip_prefix(PREFIX).unwrap_or(name))
+}
+
+#[async_trait::async_trait]
+impl RegistryBackend for Path {
+    async fn resolve_versions(&self, _: &Client, name: &str) -> Result<ParsedPackument> {
+        let json = dir(name).join("package.json");
+        let p: PackageJson = serde_json::from_str(
+            &read_to_string(&json).with_context(|| format!("reading {}", json.display()))?,
+        )
+        .with_context(|| format!("parsing {}", json.display()))?;
+        let mut packument = ParsedPackument::default();
+        packument.versions.insert(
+            p.version.clone(),
+            p.into_manifest(ParsedManifestDist {
+                tarball: name.to_owned(),
+                ..Default::default()
+            }),
+        );
+        Ok(packument)
+    }
+
+    async fn fetch_tarball(&self, _: &Client, p: &Package, _: &ProgressBar) -> Result<Vec<u8>> {
+        let packed =
+            crate::pack::pack(dir(&p.name)).with_context(|| format!("packing {}", p.name))?;
+        Ok(packed.tarball)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[tokio::test]
+    async fn local() {
+        let t = tempfile::TempDir::new().unwrap();
+        let addon = t.path().join("addon");
+        std::fs::create_dir(&addon).unwrap();
+        std::fs::write(
+            addon.join("package.json"),
+            r#"{"name": "addon", "version": "1.2.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(addon.join("plugin.gd"), "extends Node").unwrap();
+        // the registry isnt needed
+        let c = crate::mkclient("http://127.0.0.1:9".into(), Cache::new());
+        let name = format!("{PREFIX}{}", addon.display());
+        let p = Package::new(name.clone(), "^1.0.0".into(), c.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.to_string(), format!("{name}@1.2.0"));
+        let bytes = p.fetch(c.clone(), &ProgressBar::hidden()).await.unwrap();
+        let mut archive = crate::archive::Archive::new(crate::archive::CompressionType::from(
+            "tgz",
+            bytes,
+            String::new(),
+        ))
+        .unwrap();
+        let mut contents = String::new();
+        archive.get_file("plugin.gd", &mut contents).unwrap();
+        assert_eq!(contents, "extends Node");
+        assert!(Package::new(name, "^2.0.0".into(), c).await.is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use super::RegistryBackend;
+use crate::package::parsing::{ParsedManifestDist, ParsedPackument};
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+
+/// A tarball url, like `https://example.com/addon.zip`: its one version is what its package.json says.
+/// Whats behind the url can change, so it has no checksum.
+pub struct Url;
+
+#[async_trait::async_trait]
+impl RegistryBackend for Url {
+    async fn resolve_versions(&self, client: &Client, name: &str) -> Result<ParsedPackument> {
+        let resp = client
+            .get(name)
+            .send()
+            .await
+            .with_context(|| format!("getting tarball {name}"))?;
+        if !resp.status().is_success() {
+            bail!("getting tarball {name} failed: {}", resp.status());
+        }
+        let bytes = resp.bytes().await?;
+        let p = crate::registry::package_json(&bytes)
+            .with_context(|| format!("reading the package.json of {name}"))?;
+        let mut packument = ParsedPackument::default();
+        packument.versions.insert(
+            p.version.clone(),
+            p.into_manifest(ParsedManifestDist {
+                tarball: name.to_owned(),
+                ..Default::default()
+            }),
+        );
+        Ok(packument)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::cache::Cache;
+use crate::integrity::Integrity;
+use crate::vendor::{self, Index, Vendored};
+
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+/// Packs vendored packages into one tar (the [vendor::INDEX], next to the tarballs), for [import] on a machine without the registry.
+pub fn pack(v: &Vendored) -> Result<Vec<u8>> {
+    let index = serde_json::to_vec_pretty(&v.index)?;
+    let mut builder = tar::Builder::new(vec![]);
+    for (name, bytes) in std::iter::once((vendor::INDEX, &index)).chain(
+        v.tarballs
+            .iter()
+            .map(|(name, bytes)| (name.as_str(), bytes)),
+    ) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new(name), &bytes[..])?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Puts the packages of a [pack]ed bundle into the store of `cache`, and their manifests into its resolutions,
+/// so they resolve and install from there when `registry` cant be reached. Returns the imported packages.
+pub fn import(bundle: &[u8], cache: &Cache, registry: &str) -> Result<Vec<String>> {
+    let store = cache.store().context("the cache is not on disk")?;
+    let mut index = None;
+    let mut tarballs = BTreeMap::new();
+    for e in tar::Archive::new(bundle).entries()? {
+        let mut e = e?;
+        let name = e.path()?.to_string_lossy().into_owned();
+        let mut bytes = vec![];
+        e.read_to_end(&mut bytes)?;
+        if name == vendor::INDEX {
+            index = Some(serde_json::from_slice::<Index>(&bytes).context("parsing the index")?);
+        } else {
+            tarballs.insert(name, bytes);
+        }
+    }
+    let index = index.with_context(|| format!("the bundle has no {}", vendor::INDEX))?;
+    let mut imported = vec![];
+    for (name, versions) in index.packages {
+        for (version, m) in versions {
+            let Some(bytes) = tarballs.get(&vendor::file(&name, &version)) else {
+                bail!("the bundle is missing the tarball of {name}@{version}");
+            };
+            let integrity: Integrity = m
+                .dist
+                .integrity
+                .as_deref()
+                .with_context(|| format!("{name}@{version} has no integrity"))?
+                .parse()?;
+            if !integrity.check(bytes) {
+                bail!("the tarball of {name}@{version} in the bundle is broken");
+            }
+            store.insert_tarball(&integrity.key(), bytes)?;
+            cache.resolutions().insert_manifest(registry, &name, &m);
+            imported.push(format!("{name}@{version}"));
+        }
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_file::ConfigFile;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn air_gapped() {
+        let t = crate::test_utils::mktemp().await;
+        let c = crate::mkclient(t.2.registry.clone(), Cache::new());
+        let cfg = ConfigFile::new(
+            &r#"{"packages": {"@bendn/test": "^2.0.0"}}"#.into(),
+            c.clone(),
+        )
+        .await;
+        let bundle = pack(&vendor::collect(&cfg, c).await.unwrap()).unwrap();
+
+        // nothing listens here
+        let registry = "http://127.0.0.1:9".to_string();
+        let cache = Cache::load(t.0.path().join("cache"));
+        let imported = import(&bundle, &cache, &registry).unwrap();
+        assert_eq!(imported, ["@bendn/gdcli@1.2.5", "@bendn/test@2.0.10"]);
+        let offline = crate::mkclient(registry, cache);
+        let p = Package::new("@bendn/test".into(), "^2.0.0".into(), offline.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.manifest.dependencies[0].to_string(), "@bendn/gdcli@1.2.5");
+        assert!(offline
+            .cache_ref()
+            .store()
+            .unwrap()
+            .has_tarball(&p.store_key().unwrap()));
+        assert!(import(&bundle[..100], offline.cache_ref(), "r").is_err());
+    }
+}
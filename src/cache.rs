@@ -10,14 +10,24 @@ use dashmap::mapref::multiple::{RefMulti, RefMutMulti};
 use dashmap::mapref::one::{Ref, RefMut};
 use dashmap::DashMap;
 use semver_rs::{Range, Version};
+use std::env::var_os;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+pub mod lock;
+pub mod resolutions;
+pub mod store;
+use resolutions::Resolutions;
+use store::Store;
+
 type O<'a, T> = Option<Ref<'a, String, T>>;
 pub type R<'a> = RefMutMulti<'a, String, CacheEntry>;
 
 #[derive(Clone)]
 pub struct Cache {
     inner: Arc<DashMap<String, VersionsCache>>,
+    resolutions: Arc<Resolutions>,
+    store: Option<Store>,
 }
 
 #[derive(Default, Clone)]
@@ -26,18 +36,63 @@ pub struct VersionsCache {
 }
 
 impl Cache {
+    /// Creates a in memory cache.
     pub fn new() -> Self {
         Self {
             inner: Arc::new(DashMap::default()),
+            resolutions: Arc::new(Resolutions::default()),
+            store: None,
+        }
+    }
+
+    /// Creates a cache that remembers resolutions and packages in `dir`.
+    pub fn load(dir: PathBuf) -> Self {
+        Self {
+            inner: Arc::new(DashMap::default()),
+            resolutions: Arc::new(Resolutions::load(dir.join("resolutions.json"))),
+            store: Some(Store::new(dir)),
+        }
+    }
+
+    /// The global cache directory: `GPM_CACHE_DIR`, else `configured` (the cache-dir of the user config), else the platforms cache dir.
+    pub fn dir(configured: Option<&Path>) -> PathBuf {
+        if let Some(d) = var_os("GPM_CACHE_DIR") {
+            return PathBuf::from(d);
+        }
+        if let Some(d) = configured {
+            return d.to_path_buf();
+        }
+        if let Some(d) = var_os("XDG_CACHE_HOME") {
+            return PathBuf::from(d).join("gpm");
+        }
+        if let Some(d) = var_os("LOCALAPPDATA") {
+            return PathBuf::from(d).join("gpm");
+        }
+        if let Some(h) = var_os("HOME") {
+            let h = PathBuf::from(h);
+            if cfg!(target_os = "macos") {
+                return h.join("Library").join("Caches").join("gpm");
+            }
+            return h.join(".cache").join("gpm");
         }
+        std::env::temp_dir().join("gpm")
+    }
+
+    pub fn resolutions(&self) -> &Resolutions {
+        &self.resolutions
+    }
+
+    /// The package store, if this cache lives on disk.
+    pub fn store(&self) -> Option<&Store> {
+        self.store.as_ref()
     }
 
     /// Deadlocks when mutable reference held
-    pub fn get(&self, name: &str) -> O<VersionsCache> {
+    pub fn get(&self, name: &str) -> O<'_, VersionsCache> {
         self.inner.get(name)
     }
     /// Deadlocks when reference held
-    pub fn get_mut(&self, name: &str) -> Option<RefMut<String, VersionsCache>> {
+    pub fn get_mut(&self, name: &str) -> Option<RefMut<'_, String, VersionsCache>> {
         self.inner.get_mut(name)
     }
     /// Deadlocks when reference held
@@ -59,12 +114,12 @@ impl VersionsCache {
     }
     pub fn iter_versions(
         &mut self,
-    ) -> impl Iterator<Item = (Version, RefMutMulti<String, CacheEntry>)> {
+    ) -> impl Iterator<Item = (Version, RefMutMulti<'_, String, CacheEntry>)> {
         self.iter_mut()
             .map(|x| (Self::version_of(x.key(), x.value().clone()), x))
     }
 
-    pub fn get(&self, v: &str) -> Option<Ref<String, CacheEntry>> {
+    pub fn get(&self, v: &str) -> Option<Ref<'_, String, CacheEntry>> {
         self.inner.get(v)
     }
 
@@ -81,11 +136,11 @@ impl VersionsCache {
         }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = RefMulti<String, CacheEntry>> {
+    pub fn iter(&self) -> impl Iterator<Item = RefMulti<'_, String, CacheEntry>> {
         self.inner.iter()
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = R> {
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = R<'_>> {
         self.inner.iter_mut()
     }
 
@@ -94,15 +149,38 @@ impl VersionsCache {
     }
 
     #[must_use]
-    /// if found and unparsed, swaps unparsed for parsed
-    pub fn find_version(&mut self, v: &Range) -> Option<R> {
-        let mut newest = None;
+    /// if found and unparsed, swaps unparsed for parsed.
+    /// Finds the newest version matching `v` (or the oldest, see [Pick]).
+    /// Builds of one version are equal, so between them the last (by name) wins, to always pick the same one.
+    pub fn find_version(&mut self, v: &Range, pick: Pick) -> Option<R<'_>> {
+        let fit = |e: &CacheEntry| match pick
+            .godot
+            .and_then(|g| crate::godot::compatible(e.godot(), g))
+        {
+            Some(true) => 2,
+            None => 1,
+            Some(false) => 0,
+        };
+        let mut newest: Option<(R<'_>, (u8, Version))> = None;
         for (version, entry) in self.iter_versions() {
-            if v.test(&version) {
+            if v.test(&version)
+                && pick.channel.is_none_or(|c| on_channel(&version, c))
+                && pick
+                    .build
+                    .is_none_or(|b| entry.key().split_once('+').is_some_and(|(_, k)| k == b))
+            {
+                let version = (fit(entry.value()), version);
                 // if v.exact() { return immediately }
-                if let Some((_, v)) = &newest {
-                    if version.cmp(v) == std::cmp::Ordering::Less {
-                        continue;
+                if let Some((e, v)) = &newest {
+                    let order = version.0.cmp(&v.0).then(if pick.minimal {
+                        v.1.cmp(&version.1)
+                    } else {
+                        version.1.cmp(&v.1)
+                    });
+                    match order {
+                        std::cmp::Ordering::Less => continue,
+                        std::cmp::Ordering::Equal if entry.key() < e.key() => continue,
+                        _ => {}
                     }
                 }
                 newest = Some((entry, version))
@@ -116,6 +194,26 @@ impl VersionsCache {
     }
 }
 
+/// Which of the versions matching a range to pick (see [VersionsCache::find_version]).
+#[derive(Default, Clone, Copy)]
+pub struct Pick<'a> {
+    /// Prereleases must be on this channel (see [on_channel]).
+    pub channel: Option<&'a str>,
+    /// Only this build matches (see [range::build](crate::package::range::build)).
+    pub build: Option<&'a str>,
+    /// Versions that say they work with this godot version are preferred, and ones that say they dont avoided.
+    pub godot: Option<&'a Version>,
+    /// The oldest version, instead of the newest (for `resolution = "minimal"`).
+    pub minimal: bool,
+}
+
+/// Wether `v` is a release, or a prerelease on `channel` (`2.0.0-beta.3` is on `beta`).
+pub fn on_channel(v: &Version, channel: &str) -> bool {
+    v.prerelease
+        .as_ref()
+        .is_none_or(|pre| pre.first().is_some_and(|p| p == channel))
+}
+
 impl std::fmt::Debug for VersionsCache {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut iter = self.versions();
@@ -132,7 +230,7 @@ impl std::fmt::Debug for VersionsCache {
 #[derive(Default, Clone)] // yuck, a clone
 pub enum CacheEntry {
     Unparsed(ParsedPackage),
-    Parsed(Package),
+    Parsed(Box<Package>),
     Manifest(ParsedManifest),
     Tarball(CompressionType),
     #[default]
@@ -147,7 +245,7 @@ impl From<CompressionType> for CacheEntry {
 
 impl From<Package> for CacheEntry {
     fn from(value: Package) -> Self {
-        Self::Parsed(value)
+        Self::Parsed(Box::new(value))
     }
 }
 impl From<ParsedManifest> for CacheEntry {
@@ -166,8 +264,25 @@ impl CacheEntry {
         *self = CacheEntry::from(match self {
             CacheEntry::Unparsed(p) => std::mem::take(p).into_package(client).await?,
             CacheEntry::Manifest(m) => {
+                let mut m = std::mem::take(m);
+                let id = format!("{name}@{}", m.version);
+                let client = match client.resolving(id.clone()) {
+                    Ok(c) => {
+                        c.within_limits(&id)?;
+                        c
+                    }
+                    // resolving it again would go around forever: so here, it has no dependencies
+                    Err(cycle) => {
+                        eprintln!(
+                            "{:>12} Dependency cycle {cycle}: it is cut at {name}, which gets no dependencies there",
+                            crate::putils::warn()
+                        );
+                        m.dependencies.clear();
+                        client
+                    }
+                };
                 let m = ctx!(
-                    std::mem::take(m).try_into_async(client).await,
+                    m.try_into_async(client).await,
                     "parsing ParsedManifest into Manifest in get_package()"
                 )?;
                 Package::from_manifest(m, name.clone())
@@ -182,9 +297,18 @@ impl CacheEntry {
         Ok(())
     }
 
+    /// The godot versions this entry says it works with, if it is a manifest that says.
+    pub fn godot(&self) -> Option<&str> {
+        match self {
+            CacheEntry::Parsed(p) => p.manifest.godot.as_deref(),
+            CacheEntry::Manifest(m) => m.engines.get("godot").map(String::as_str),
+            _ => None,
+        }
+    }
+
     pub fn get_package(&self) -> Package {
         match self {
-            CacheEntry::Parsed(p) => p.clone(),
+            CacheEntry::Parsed(p) => (**p).clone(),
             _ => unreachable!(),
         }
     }
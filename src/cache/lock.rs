@@ -0,0 +1,105 @@
+use std::fs::{create_dir_all, read_to_string, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another gpm to let go of a lock: `GPM_LOCK_TIMEOUT` seconds, or 2 minutes.
+pub fn timeout() -> Duration {
+    std::env::var("GPM_LOCK_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map_or(Duration::from_secs(120), Duration::from_secs)
+}
+
+/// A lock on the file at `path`, across processes, held until dropped.
+/// Many can share a lock (to read), or one can have it (to write).
+#[derive(Debug)]
+pub struct Lock(#[allow(dead_code)] File);
+
+impl Lock {
+    /// Waits (up to `wait`) for a shared lock.
+    pub fn shared(path: &Path, wait: Duration) -> io::Result<Self> {
+        Self::acquire(path, wait, File::try_lock_shared)
+    }
+
+    /// Waits (up to `wait`) for an exclusive lock.
+    pub fn exclusive(path: &Path, wait: Duration) -> io::Result<Self> {
+        Self::acquire(path, wait, File::try_lock)
+    }
+
+    /// Writes the pid of this gpm into the file, so whoever waits for it knows who has it.
+    pub fn mark(&self) -> io::Result<()> {
+        self.0.set_len(0)?;
+        (&self.0).write_all(std::process::id().to_string().as_bytes())
+    }
+
+    fn acquire(
+        path: &Path,
+        wait: Duration,
+        try_lock: fn(&File) -> Result<(), TryLockError>,
+    ) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let start = Instant::now();
+        loop {
+            match try_lock(&f) {
+                Ok(()) => return Ok(Self(f)),
+                Err(TryLockError::WouldBlock) if start.elapsed() < wait => {
+                    sleep(Duration::from_millis(50))
+                }
+                Err(TryLockError::WouldBlock) => {
+                    // see [Lock::mark]
+                    let pid = read_to_string(path)
+                        .ok()
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|p| format!(" (pid {})", p.trim()))
+                        .unwrap_or_default();
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "{} is locked by another gpm{pid} (waited {}s, see GPM_LOCK_TIMEOUT)",
+                            path.display(),
+                            wait.as_secs()
+                        ),
+                    ));
+                }
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks() {
+        let t = tempfile::TempDir::new().unwrap();
+        let p = t.path().join("locks/a.lock");
+        let none = Duration::ZERO;
+        let a = Lock::shared(&p, none).unwrap();
+        let b = Lock::shared(&p, none).unwrap();
+        assert!(Lock::exclusive(&p, none).is_err());
+        drop((a, b));
+        let x = Lock::exclusive(&p, none).unwrap();
+        let e = Lock::shared(&p, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        drop(x);
+        Lock::shared(&p, none).unwrap();
+        let x = Lock::exclusive(&p, none).unwrap();
+        x.mark().unwrap();
+        let e = Lock::exclusive(&p, none).unwrap_err();
+        assert!(e
+            .to_string()
+            .contains(&format!("(pid {})", std::process::id())));
+    }
+}
@@ -0,0 +1,330 @@
+use super::lock::{timeout, Lock};
+use crate::package::parsing::ParsedManifest;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use semver_rs::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long a range decision (like `^2.0.0`) stays valid, in seconds. Until then the registry is not asked again,
+/// so a version published in the meantime is only picked up after (or by `gpm update <package>`, which forgets the decisions of the package).
+/// Exact versions never expire, as they cant resolve to anything else.
+const RANGE_TTL: u64 = 60 * 60 * 24;
+
+/// A range to version decision.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Resolution {
+    pub version: String,
+    /// When we made this decision (unix seconds).
+    pub fetched: u64,
+}
+
+/// What a range was resolved for: the range, and everything else that changes the version it resolves to.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Key<'a> {
+    pub range: &'a str,
+    /// The prerelease channel (like `beta`) of the package.
+    pub channel: Option<&'a str>,
+    /// If prereleases were let in.
+    pub pre: bool,
+    pub godot: Option<&'a Version>,
+    pub minimal: bool,
+    /// The release asset glob of the package (see [crate::releases]).
+    pub asset: Option<&'a str>,
+}
+
+impl Key<'_> {
+    /// The key of the decision in the stored cache: `registry/name@range`, and the rest (if any of it is set) as json after it,
+    /// like `r/ms@^2 {"godot":"4.2.0","minimal":true}`.
+    fn of(&self, registry: &str, name: &str) -> String {
+        let mut rest = Map::new();
+        if let Some(c) = self.channel {
+            rest.insert("channel".into(), c.into());
+        }
+        if self.pre {
+            rest.insert("pre".into(), true.into());
+        }
+        if let Some(g) = self.godot {
+            rest.insert("godot".into(), g.to_string().into());
+        }
+        if self.minimal {
+            rest.insert("minimal".into(), true.into());
+        }
+        if let Some(a) = self.asset {
+            rest.insert("asset".into(), a.into());
+        }
+        match rest.is_empty() {
+            true => format!("{registry}/{name}@{}", self.range),
+            false => format!("{registry}/{name}@{} {}", self.range, Value::Object(rest)),
+        }
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Stored {
+    /// registry/name@range (see [Key::of]) => decision
+    decisions: HashMap<String, Resolution>,
+    /// registry/name@version => manifest
+    manifests: HashMap<String, ParsedManifest>,
+}
+
+/// The on disk resolution cache.
+/// Remembers which version a range resolved to, and the manifest of that version,
+/// so the next run can build the package tree without talking to the registry.
+#[derive(Default)]
+pub struct Resolutions {
+    path: Option<PathBuf>,
+    decisions: DashMap<String, Resolution>,
+    manifests: DashMap<String, ParsedManifest>,
+    dirty: AtomicBool,
+    /// The `registry/name@` prefixes of [Resolutions::forget]ten decisions, so saving doesnt bring them back.
+    forgotten: Mutex<Vec<String>>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reads the stored cache at `path`. A missing or unreadable file gives an empty one.
+fn read(path: &Path) -> Stored {
+    read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Stored>(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Is this range just a version? (`1.2.5`, `=1.2.5`)
+fn is_exact(range: &str) -> bool {
+    Version::new(range.trim().trim_start_matches('='))
+        .parse()
+        .is_ok()
+}
+
+impl Resolutions {
+    /// Loads the cache at `path`. A missing or unreadable file gives an empty cache.
+    pub fn load(path: PathBuf) -> Self {
+        let stored = read(&path);
+        Self {
+            path: Some(path),
+            decisions: stored.decisions.into_iter().collect(),
+            manifests: stored.manifests.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Gets the manifest the range of `key` resolved to last time, if that decision is still fresh.
+    pub fn get(&self, registry: &str, name: &str, key: &Key) -> Option<ParsedManifest> {
+        let decision = self.decisions.get(&key.of(registry, name))?;
+        if !is_exact(key.range) && now().saturating_sub(decision.fetched) > RANGE_TTL {
+            return None;
+        }
+        let m = self
+            .manifests
+            .get(&format!("{registry}/{name}@{}", decision.version))?;
+        Some(m.clone())
+    }
+
+    /// Every manifest of `name` remembered from `registry`, for when the registry cant be reached.
+    pub fn known(&self, registry: &str, name: &str) -> Vec<ParsedManifest> {
+        let prefix = format!("{registry}/{name}@");
+        self.manifests
+            .iter()
+            .filter(|e| e.key().starts_with(&prefix))
+            .map(|e| e.value().clone())
+            .collect()
+    }
+
+    /// Remembers the manifest for a version, so decisions pointing to it can be used.
+    pub fn insert_manifest(&self, registry: &str, name: &str, manifest: &ParsedManifest) {
+        self.manifests.insert(
+            format!("{registry}/{name}@{}", manifest.version),
+            manifest.clone(),
+        );
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Remembers that the range of `key` resolved to `version`.
+    pub fn insert(&self, registry: &str, name: &str, key: &Key, version: String) {
+        self.decisions.insert(
+            key.of(registry, name),
+            Resolution {
+                version,
+                fetched: now(),
+            },
+        );
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Forgets what the ranges of `name` resolved to (and its manifests), so they are resolved again.
+    pub fn forget(&self, registry: &str, name: &str) {
+        let prefix = format!("{registry}/{name}@");
+        self.decisions.retain(|k, _| !k.starts_with(&prefix));
+        self.manifests.retain(|k, _| !k.starts_with(&prefix));
+        self.forgotten.lock().unwrap().push(prefix);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Writes the cache back to disk, if anything changed.
+    /// What other gpms saved since this one loaded is kept (where this one doesnt know better).
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let _lock = Lock::exclusive(&path.with_extension("json.lock"), timeout())?;
+        let theirs = read(path);
+        let forgotten = self.forgotten.lock().unwrap();
+        for (k, v) in theirs.decisions {
+            if !forgotten.iter().any(|f| k.starts_with(f)) {
+                self.decisions.entry(k).or_insert(v);
+            }
+        }
+        for (k, v) in theirs.manifests {
+            if !forgotten.iter().any(|f| k.starts_with(f)) {
+                self.manifests.entry(k).or_insert(v);
+            }
+        }
+        let stored = Stored {
+            decisions: self
+                .decisions
+                .iter()
+                .map(|e| (e.key().clone(), e.value().clone()))
+                .collect(),
+            manifests: self
+                .manifests
+                .iter()
+                .map(|e| (e.key().clone(), e.value().clone()))
+                .collect(),
+        };
+        // readers never see half of it
+        let tmp = path.with_extension(format!("json.tmp-{}", std::process::id()));
+        write(&tmp, serde_json::to_string(&stored)?)?;
+        rename(tmp, path)?;
+        self.dirty.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Key, Resolutions};
+    use crate::cache::Cache;
+    use crate::package::Package;
+
+    #[test]
+    fn concurrent_saves() {
+        let t = tempfile::TempDir::new().unwrap();
+        let path = t.path().join("resolutions.json");
+        let (a, b) = (
+            Resolutions::load(path.clone()),
+            Resolutions::load(path.clone()),
+        );
+        let key = Key {
+            range: "^1",
+            ..Default::default()
+        };
+        a.insert("r", "a", &key, "1.0.0".into());
+        b.insert("r", "b", &key, "1.0.0".into());
+        b.insert("r", "c", &key, "1.0.0".into());
+        a.save().unwrap();
+        b.forget("r", "c");
+        b.save().unwrap();
+        let saved = Resolutions::load(path);
+        assert!(saved.decisions.contains_key("r/a@^1"));
+        assert!(saved.decisions.contains_key("r/b@^1"));
+        assert!(!saved.decisions.contains_key("r/c@^1"));
+    }
+
+    #[test]
+    fn keys() {
+        let godot = semver_rs::Version::new("4.2.0").parse().unwrap();
+        let exact = Key {
+            range: "1.0.0",
+            godot: Some(&godot),
+            minimal: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            exact.of("r", "a"),
+            r#"r/a@1.0.0 {"godot":"4.2.0","minimal":true}"#
+        );
+        let c = Resolutions::default();
+        c.insert_manifest(
+            "r",
+            "a",
+            &crate::package::parsing::ParsedManifest {
+                version: "1.0.0".into(),
+                ..Default::default()
+            },
+        );
+        c.insert("r", "a", &exact, "1.0.0".into());
+        // exact versions dont expire, whatever else they were resolved for
+        c.decisions.get_mut(&exact.of("r", "a")).unwrap().fetched = 0;
+        assert!(c.get("r", "a", &exact).is_some());
+        assert!(c
+            .get(
+                "r",
+                "a",
+                &Key {
+                    minimal: false,
+                    ..exact
+                }
+            )
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn offline_resolve() {
+        let t = crate::test_utils::mktemp().await;
+        let dir = t.0.path().join("cache");
+        let c = crate::mkclient(t.2.registry.clone(), Cache::load(dir.clone()));
+        Package::new("@bendn/test".into(), "^2.0.0".into(), c.clone())
+            .await
+            .unwrap();
+        c.cache_ref().resolutions().save().unwrap();
+
+        // nothing listens here, so this only works if nothing is fetched
+        let offline = || crate::Client {
+            real: reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all("http://127.0.0.1:9").unwrap())
+                .build()
+                .unwrap(),
+            ..crate::mkclient(t.2.registry.clone(), Cache::load(dir.clone()))
+        };
+        let p = Package::new("@bendn/test".into(), "^2.0.0".into(), offline())
+            .await
+            .unwrap();
+        assert_eq!(p.to_string(), "@bendn/test@2.0.10");
+        assert_eq!(p.manifest.dependencies[0].to_string(), "@bendn/gdcli@1.2.5");
+        // only not reaching the registry falls back, not it answering something wrong
+        let e = offline().get(&t.2.registry).send().await.unwrap_err();
+        assert!(crate::package::unreachable(&e.into()));
+        assert!(!crate::package::unreachable(&anyhow::anyhow!("malformed")));
+
+        let forgot = offline();
+        let resolutions = forgot.cache_ref().resolutions();
+        resolutions.forget(&forgot.registry, "@bendn/test");
+        resolutions.save().unwrap();
+        assert!(
+            Package::new("@bendn/test".into(), "^2.0.0".into(), offline())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use super::lock::{timeout, Lock};
+use crate::archive::{destination, Filter};
+use crate::integrity::{Algorithm, Integrity};
+
+use anyhow::Result;
+use reflink_copy::reflink_or_copy;
+use sha1::{Digest, Sha1};
+use std::fs::{
+    create_dir_all, read, read_dir, read_to_string, remove_dir_all, remove_file, rename, write,
+    File,
+};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Suffix for temporary directories, so concurrent unpacks dont collide.
+static TMP: AtomicUsize = AtomicUsize::new(0);
+
+/// The global package store: tarballs, and their unpacked contents, keyed by checksum.
+/// ```
+/// <cache>
+/// |-- tarballs
+/// |   `-- sha1-321b0e0d5edd702c609e66189380b6e1c1e6f9c7
+/// |-- packages
+/// |   `-- sha1-321b0e0d5edd702c609e66189380b6e1c1e6f9c7
+/// |       |-- main.gd
+/// |       `-- package.json
+/// |-- linked (installed copies, that projects symlink to)
+/// |   `-- sha1-321b0e0d5edd702c609e66189380b6e1c1e6f9c7-4f1c0a2b9d8e7f60
+/// `-- locks (held while a gpm uses a package, see [Lock])
+///     `-- sha1-321b0e0d5edd702c609e66189380b6e1c1e6f9c7.lock
+/// ```
+#[derive(Clone, Debug)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+/// A package in the [Store].
+#[derive(Debug)]
+pub struct Stored {
+    pub key: String,
+    /// `name@version`, from its package.json, if it was unpacked.
+    pub package: Option<String>,
+    /// Of the tarball, the unpacked files, and the linked copies.
+    pub size: u64,
+}
+
+impl Store {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Where the store is (the cache dir).
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn tarball_path(&self, key: &str) -> PathBuf {
+        self.dir.join("tarballs").join(key)
+    }
+
+    fn unpacked_path(&self, key: &str) -> PathBuf {
+        self.dir.join("packages").join(key)
+    }
+
+    fn linked_path(&self, key: &str) -> PathBuf {
+        self.dir.join("linked").join(key)
+    }
+
+    fn lock_path(&self, key: &str) -> PathBuf {
+        self.dir.join("locks").join(format!("{key}.lock"))
+    }
+
+    /// Keeps other gpms from deleting `key` (see [Store::remove] and [Store::evict]) while it is used.
+    pub fn lock(&self, key: &str) -> io::Result<Lock> {
+        Lock::shared(&self.lock_path(key), timeout())
+    }
+
+    pub fn has_tarball(&self, key: &str) -> bool {
+        self.tarball_path(key).exists()
+    }
+
+    /// How big a stored tarball is.
+    pub fn tarball_size(&self, key: &str) -> Option<u64> {
+        Some(self.tarball_path(key).metadata().ok()?.len())
+    }
+
+    /// Reads a stored tarball.
+    pub fn tarball(&self, key: &str) -> Option<Vec<u8>> {
+        let _lock = self.lock(key).ok()?;
+        let bytes = read(self.tarball_path(key)).ok()?;
+        self.used(key);
+        Some(bytes)
+    }
+
+    /// Remembers `key` was just used, for [Store::evict]: in the modification time of its tarball.
+    fn used(&self, key: &str) {
+        if let Ok(f) = File::options().write(true).open(self.tarball_path(key)) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+    }
+
+    /// Stores a tarball.
+    pub fn insert_tarball(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let _lock = self.lock(key)?;
+        let p = self.tarball_path(key);
+        create_dir_all(p.parent().unwrap())?;
+        let tmp = p.with_extension(format!("tmp-{}", std::process::id()));
+        write(&tmp, bytes)?;
+        rename(tmp, p)
+    }
+
+    /// Stores a tarball that came from somewhere else (see `gpm cache add`), under its sha512 and its sha1,
+    /// as packages without an integrity are found by their checksum. Returns the keys.
+    pub fn add(&self, bytes: &[u8]) -> io::Result<[String; 2]> {
+        let keys = [
+            Integrity::of(Algorithm::Sha512, bytes).key(),
+            format!("sha1-{:x}", Sha1::digest(bytes)),
+        ];
+        for key in &keys {
+            self.insert_tarball(key, bytes)?;
+        }
+        Ok(keys)
+    }
+
+    /// Gets the unpacked directory for `key`, using `unpack` to fill it if its not there yet.
+    pub fn unpacked(&self, key: &str, unpack: impl FnOnce(&Path) -> Result<()>) -> Result<PathBuf> {
+        self.used(key);
+        fill(self.unpacked_path(key), unpack)
+    }
+
+    /// Gets the directory that projects link to for `key`, using `install` to fill it if its not there yet.
+    /// The key should say everything that makes one install differ from another (the filter, the dependencies folders, ...).
+    pub fn linked(&self, key: &str, install: impl FnOnce(&Path) -> Result<()>) -> Result<PathBuf> {
+        fill(self.linked_path(key), install)
+    }
+
+    /// The linked copies of `key` (their keys are `key-<layout>`).
+    fn linked_of(&self, key: &str) -> Vec<PathBuf> {
+        let Ok(dir) = read_dir(self.dir.join("linked")) else {
+            return vec![];
+        };
+        let prefix = format!("{key}-");
+        dir.filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|e| e.path())
+            .collect()
+    }
+
+    /// Everything in the store, by key.
+    pub fn list(&self) -> io::Result<Vec<Stored>> {
+        let mut keys = vec![];
+        for sub in ["tarballs", "packages"] {
+            let Ok(dir) = read_dir(self.dir.join(sub)) else {
+                continue;
+            };
+            for e in dir {
+                let name = e?.file_name().to_string_lossy().into_owned();
+                // half written ones
+                if !name.contains(".tmp-") {
+                    keys.push(name);
+                }
+            }
+        }
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .map(|key| {
+                let unpacked = self.unpacked_path(&key);
+                let package = read_to_string(unpacked.join("package.json"))
+                    .ok()
+                    .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+                    .and_then(|m| {
+                        Some(format!(
+                            "{}@{}",
+                            m["name"].as_str()?,
+                            m["version"].as_str()?
+                        ))
+                    });
+                let mut size = size_of(&self.tarball_path(&key))? + size_of(&unpacked)?;
+                for l in self.linked_of(&key) {
+                    size += size_of(&l)?;
+                }
+                Ok(Stored { key, package, size })
+            })
+            .collect()
+    }
+
+    /// Wether the stored tarball `key` still hashes to its key. None if it isnt there, or the key has an unknown algorithm.
+    pub fn verify(&self, key: &str) -> Option<bool> {
+        let bytes = self.tarball(key)?;
+        let hashed = match key.split_once('-')?.0 {
+            "sha1" => format!("sha1-{:x}", Sha1::digest(&bytes)),
+            "sha512" => Integrity::of(Algorithm::Sha512, &bytes).key(),
+            "blake3" => Integrity::of(Algorithm::Blake3, &bytes).key(),
+            _ => return None,
+        };
+        Some(hashed == key)
+    }
+
+    /// Deletes the least recently used tarballs and unpacked files, until they are no bigger than `max` together.
+    /// Linked copies stay, as projects link to them. Returns what was deleted, with how much that freed.
+    pub fn evict(&self, max: u64) -> io::Result<Vec<Stored>> {
+        let mut stored = vec![];
+        let mut total = 0;
+        for s in self.list()? {
+            let tarball = self.tarball_path(&s.key);
+            let size = size_of(&tarball)? + size_of(&self.unpacked_path(&s.key))?;
+            // without a tarball, it was last used when it was unpacked
+            let used = tarball
+                .metadata()
+                .or_else(|_| self.unpacked_path(&s.key).metadata())
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            total += size;
+            stored.push((used, Stored { size, ..s }));
+        }
+        stored.sort_by_key(|(used, _)| *used);
+        let mut evicted = vec![];
+        for (_, s) in stored {
+            if total <= max {
+                break;
+            }
+            // another gpm is using it
+            let Ok(_lock) = Lock::exclusive(&self.lock_path(&s.key), Duration::ZERO) else {
+                continue;
+            };
+            remove_file(self.tarball_path(&s.key)).or_else(not_found)?;
+            remove_dir_all(self.unpacked_path(&s.key)).or_else(not_found)?;
+            total -= s.size;
+            evicted.push(s);
+        }
+        Ok(evicted)
+    }
+
+    /// Deletes everything stored for `key`: the tarball, the unpacked files, and the linked copies
+    /// (projects linked to those have to be updated again).
+    pub fn remove(&self, key: &str) -> io::Result<()> {
+        let _lock = Lock::exclusive(&self.lock_path(key), timeout())?;
+        remove_file(self.tarball_path(key)).or_else(not_found)?;
+        for d in [self.unpacked_path(key)]
+            .into_iter()
+            .chain(self.linked_of(key))
+        {
+            remove_dir_all(d).or_else(not_found)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deleting what isnt there is fine.
+fn not_found(e: io::Error) -> io::Result<()> {
+    match e.kind() {
+        io::ErrorKind::NotFound => Ok(()),
+        _ => Err(e),
+    }
+}
+
+/// How big the file, or everything in the directory, `p` is. 0 if it doesnt exist.
+fn size_of(p: &Path) -> io::Result<u64> {
+    let Ok(meta) = p.symlink_metadata() else {
+        return Ok(0);
+    };
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut size = 0;
+    for e in read_dir(p)? {
+        size += size_of(&e?.path())?;
+    }
+    Ok(size)
+}
+
+/// Fills the directory `p` with `f`, unless it already exists.
+fn fill(p: PathBuf, f: impl FnOnce(&Path) -> Result<()>) -> Result<PathBuf> {
+    if p.exists() {
+        return Ok(p);
+    }
+    // unpack elsewhere, then move it in, so a half unpacked directory is never visible
+    let tmp = p.with_extension(format!(
+        "tmp-{}-{}",
+        std::process::id(),
+        TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    f(&tmp)?;
+    if rename(&tmp, &p).is_err() {
+        // somebody else got there first
+        remove_dir_all(&tmp)?;
+    }
+    Ok(p)
+}
+
+/// Recursively clones `src` into `dst`, using reflinks (copy on write) where the filesystem supports them,
+/// and plain copies otherwise. Only clones what the `filter` lets through.
+pub fn clone_tree(src: &Path, dst: &Path, filter: &Filter) -> io::Result<()> {
+    fn walk(src: &Path, rel: &Path, dst: &Path, filter: &Filter) -> io::Result<()> {
+        for entry in read_dir(src)? {
+            let entry = entry?;
+            let rel = rel.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), &rel, dst, filter)?;
+            } else if let Some(to) = filter.map(&rel) {
+                let to = dst.join(to);
+                create_dir_all(to.parent().unwrap())?;
+                reflink_or_copy(entry.path(), to)?;
+            }
+        }
+        Ok(())
+    }
+    let dst = &destination(dst)?;
+    // long too, for the files of deep packages
+    let src = &src.canonicalize().unwrap_or(src.to_path_buf());
+    walk(src, Path::new(""), dst, filter)
+}
+
+/// Links `dst` to the directory `src`: with a symlink, or a junction on windows (where symlinks need privileges),
+/// falling back to [clone_tree] if neither can be made.
+pub fn link_tree(src: &Path, dst: &Path) -> io::Result<()> {
+    if let Some(parent) = dst.parent() {
+        create_dir_all(parent)?;
+    }
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(src, dst);
+    #[cfg(windows)]
+    let linked =
+        std::os::windows::fs::symlink_dir(src, dst).or_else(|_| junction::create(src, dst));
+    #[cfg(not(any(unix, windows)))]
+    let linked: io::Result<()> = Err(io::ErrorKind::Unsupported.into());
+    match linked {
+        Ok(()) => Ok(()),
+        Err(_) => clone_tree(src, dst, &Filter::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hashd;
+
+    #[test]
+    fn clone() {
+        let t = tempfile::TempDir::new().unwrap();
+        let store = Store::new(t.path().join("store"));
+        let src = store
+            .unpacked("sha1-0", |d| {
+                create_dir_all(d.join("sub"))?;
+                write(d.join("a.gd"), "extends Node")?;
+                write(d.join("sub/b.gd"), "extends Node2D")?;
+                Ok(())
+            })
+            .unwrap();
+        // already there, so this doesnt run
+        store.unpacked("sha1-0", |_| unreachable!()).unwrap();
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].size, listed[0].package.as_deref()), (26, None));
+        clone_tree(&src, &t.path().join("out"), &Filter::default()).unwrap();
+        assert_eq!(hashd(&src), hashd(&t.path().join("out")));
+        assert_eq!(hashd(&t.path().join("out")).len(), 2);
+    }
+
+    #[test]
+    fn evict() {
+        let t = tempfile::TempDir::new().unwrap();
+        let store = Store::new(t.path().to_path_buf());
+        for (i, key) in ["sha1-a", "sha1-b", "sha1-c"].iter().enumerate() {
+            store.insert_tarball(key, &[0; 100]).unwrap();
+            let f = File::options()
+                .write(true)
+                .open(store.tarball_path(key))
+                .unwrap();
+            let at = std::time::Duration::from_secs(i as u64 + 1);
+            f.set_modified(SystemTime::UNIX_EPOCH + at).unwrap();
+        }
+        let added = store.add(b"tarball").unwrap();
+        assert!(added.iter().all(|k| store.verify(k) == Some(true)));
+        for k in &added {
+            store.remove(k).unwrap();
+        }
+        // b was used last
+        store.tarball("sha1-b").unwrap();
+        let evicted = store.evict(150).unwrap();
+        let keys = evicted.iter().map(|s| s.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["sha1-a", "sha1-c"]);
+        assert!(store.has_tarball("sha1-b"));
+        assert!(store.evict(150).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn install_from_store() {
+        let t = crate::test_utils::mktemp().await;
+        let c = crate::mkclient(
+            t.2.registry.clone(),
+            crate::cache::Cache::load(t.0.path().join("cache")),
+        );
+        let mut p = crate::package::Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        let key = p.store_key().unwrap();
+        p.download(c.clone(), t.0.path(), crate::integrity::Algorithm::Sha512)
+            .await
+            .unwrap();
+        let store = c.cache_ref().store().unwrap();
+        assert!(store.has_tarball(&key));
+        assert_eq!(store.verify(&key), Some(true));
+        let listed = store.list().unwrap();
+        assert_eq!(listed[0].package.as_deref(), Some("@bendn/test@2.0.10"));
+        assert_eq!(
+            hashd(&t.0.path().join("cache/packages").join(&key)),
+            hashd(&p.download_dir(t.0.path()))
+        );
+        assert_eq!(hashd(&p.download_dir(t.0.path())).len(), 5);
+        // a broken tarball is downloaded again
+        write(store.tarball_path(&key), "not it").unwrap();
+        let mut p = crate::package::Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.download(c.clone(), t.0.path(), crate::integrity::Algorithm::Sha512)
+            .await
+            .unwrap();
+        assert_eq!(store.verify(&key), Some(true));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn symlinked() {
+        let t = crate::test_utils::mktemp().await;
+        let c = crate::mkclient(
+            t.2.registry.clone(),
+            crate::cache::Cache::load(t.0.path().join("cache")),
+        );
+        let mut p = crate::package::Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.symlink = true;
+        let key = p.store_key().unwrap();
+        p.download(c.clone(), t.0.path(), crate::integrity::Algorithm::Sha512)
+            .await
+            .unwrap();
+        let dir = p.download_dir(t.0.path());
+        assert!(dir.is_symlink());
+        assert!(read_dir(t.0.path().join("cache/linked")).unwrap().count() == 1);
+        assert_eq!(hashd(&dir).len(), 5);
+        p.modify(t.0.path()); // does nothing, the store copy was modified already
+        p.purge(t.0.path());
+        assert!(dir.symlink_metadata().is_err());
+        assert!(t.0.path().join("cache/linked").exists());
+        let store = c.cache_ref().store().unwrap();
+        write(store.tarball_path(&key), "not it").unwrap();
+        assert_eq!(store.verify(&key), Some(false));
+        store.remove(&key).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(
+            read_dir(t.0.path().join("cache/linked")).unwrap().count(),
+            0
+        );
+    }
+}
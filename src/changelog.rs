@@ -0,0 +1,69 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use semver_rs::Version;
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+lazy_static! {
+    static ref VERSION: Regex = Regex::new(r"\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?").unwrap();
+}
+
+/// Reads the changelog (CHANGELOG.md, CHANGES.md, HISTORY.md, ...) shipped in a package directory.
+pub fn find(dir: &Path) -> Option<String> {
+    read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            let stem = name.split('.').next().unwrap_or("");
+            matches!(stem, "changelog" | "changes" | "history") && e.path().is_file()
+        })
+        .and_then(|e| read_to_string(e.path()).ok())
+}
+
+/// The version a markdown heading is for, like `## [1.2.0] - 2023-01-01`.
+fn heading_version(line: &str) -> Option<Version> {
+    if !line.starts_with('#') {
+        return None;
+    }
+    VERSION
+        .find(line)
+        .and_then(|m| Version::new(m.as_str()).parse().ok())
+}
+
+/// The sections of a changelog for the versions after `old`, up to and including `new`.
+pub fn between(changelog: &str, old: &Version, new: &Version) -> String {
+    let mut out = String::new();
+    let mut keep = false;
+    for line in changelog.lines() {
+        if let Some(v) = heading_version(line) {
+            keep = &v > old && &v <= new;
+        }
+        if keep {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        let log =
+            "# Changelog\n## 1.2.0\n- new thing\n## [1.1.0] - 2023\n- fix\n## 1.0.0\n- first\n";
+        let v = |s| Version::new(s).parse().unwrap();
+        assert_eq!(
+            between(log, &v("1.0.0"), &v("1.2.0")),
+            "## 1.2.0\n- new thing\n## [1.1.0] - 2023\n- fix\n"
+        );
+        assert_eq!(
+            between(log, &v("1.0.0"), &v("1.1.0")),
+            "## [1.1.0] - 2023\n- fix\n"
+        );
+        assert_eq!(between(log, &v("1.2.0"), &v("1.2.0")), "");
+    }
+}
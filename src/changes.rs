@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Deserialize)]
+struct Locked {
+    name: String,
+    version: String,
+}
+
+/// Every locked version of every package, from a lockfile. Unparseable lockfiles count as empty.
+pub fn versions(lockfile: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut map = BTreeMap::<String, BTreeSet<String>>::new();
+    for l in serde_json::from_str::<Vec<Locked>>(lockfile).unwrap_or_default() {
+        map.entry(l.name).or_default().insert(l.version);
+    }
+    map
+}
+
+/// What an update changed, found by comparing the lockfiles from before and after.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// name@version
+    pub added: Vec<String>,
+    /// (name, old versions, new versions)
+    pub upgraded: Vec<(String, String, String)>,
+    /// name@version
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn between(old: &str, new: &str) -> Self {
+        let (old, new) = (versions(old), versions(new));
+        let join = |vs: &BTreeSet<String>| vs.iter().cloned().collect::<Vec<_>>().join(", ");
+        let mut c = Self::default();
+        for (name, vs) in &new {
+            match old.get(name) {
+                None => c.added.push(format!("{name}@{}", join(vs))),
+                Some(o) if o != vs => c.upgraded.push((name.clone(), join(o), join(vs))),
+                Some(_) => {}
+            }
+        }
+        for (name, vs) in &old {
+            if !new.contains_key(name) {
+                c.removed.push(format!("{name}@{}", join(vs)));
+            }
+        }
+        c
+    }
+}
+
+impl fmt::Display for Changes {
+    /// One line per change, like the other status lines.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for p in &self.added {
+            writeln!(f, "{:>12} {p}", crate::putils::green("Added"))?;
+        }
+        for (name, old, new) in &self.upgraded {
+            writeln!(
+                f,
+                "{:>12} {name} {old} -> {new}",
+                crate::putils::green("Upgraded")
+            )?;
+        }
+        for p in &self.removed {
+            writeln!(f, "{:>12} {p}", crate::putils::green("Removed"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let old = r#"[{"name":"a","version":"1.0.0"},{"name":"b","version":"1.0.0"},{"name":"c","version":"1.0.0"}]"#;
+        let new = r#"[{"name":"a","version":"1.0.0"},{"name":"b","version":"2.0.0"},{"name":"d","version":"1.0.0"}]"#;
+        assert_eq!(
+            Changes::between(old, new),
+            Changes {
+                added: vec!["d@1.0.0".into()],
+                upgraded: vec![("b".into(), "1.0.0".into(), "2.0.0".into())],
+                removed: vec!["c@1.0.0".into()],
+            }
+        );
+        assert_eq!(Changes::between("", new).added.len(), 3);
+    }
+}
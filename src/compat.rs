@@ -0,0 +1,159 @@
+use crate::annotate::Level;
+use crate::graph::Graph;
+use crate::putils;
+
+use semver_rs::Version;
+use std::fmt;
+
+/// How a package gets along with the godot of the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    /// Its `engines.godot` doesnt allow the project godot.
+    Breaks,
+    /// Its `engines.godot` isnt a range.
+    Invalid,
+    /// It says nothing (no `engines.godot`).
+    Unknown,
+    Works,
+}
+
+/// A line of the compatibility matrix.
+#[derive(Debug)]
+pub struct Row {
+    /// Like `@bendn/test@2.0.10`.
+    pub package: String,
+    /// What the config calls it (its alias, or name).
+    pub name: String,
+    /// Its `engines.godot`, like `>=4.2`.
+    pub declared: Option<String>,
+    pub verdict: Verdict,
+}
+
+/// Checks every package of `graph` against the project godot `godot`. The ones that break come first.
+pub fn matrix(graph: &Graph, godot: &Version) -> Vec<Row> {
+    let mut rows = graph
+        .iter()
+        .map(|(_, p)| {
+            let declared = p.manifest.godot.clone();
+            let verdict = match (
+                &declared,
+                crate::godot::compatible(declared.as_deref(), godot),
+            ) {
+                (_, Some(true)) => Verdict::Works,
+                (_, Some(false)) => Verdict::Breaks,
+                (Some(_), None) => Verdict::Invalid,
+                (None, None) => Verdict::Unknown,
+            };
+            Row {
+                package: p.to_string(),
+                name: p.alias.clone().unwrap_or_else(|| p.name.clone()),
+                declared,
+                verdict,
+            }
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| (a.verdict, &a.package).cmp(&(b.verdict, &b.package)));
+    rows
+}
+
+impl Row {
+    /// What is wrong, if anything, against the project godot `godot` (see [crate::annotate]).
+    pub fn finding(&self, godot: &Version) -> Option<(Level, String)> {
+        let declared = self.declared.as_deref().unwrap_or_default();
+        let p = &self.package;
+        match self.verdict {
+            Verdict::Works => None,
+            Verdict::Breaks => Some((
+                Level::Error,
+                format!("{p} says it works with godot {declared}, not {godot}"),
+            )),
+            Verdict::Invalid => Some((
+                Level::Warning,
+                format!("{p} says it works with godot {declared:?}, which isnt a range"),
+            )),
+            Verdict::Unknown => Some((
+                Level::Warning,
+                format!("{p} doesnt say which godot it works with"),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let declared = self.declared.as_deref().unwrap_or_default();
+        match self.verdict {
+            Verdict::Works => write!(
+                f,
+                "{:>12} {} (godot {declared})",
+                putils::green("Works"),
+                self.package
+            ),
+            Verdict::Breaks => write!(
+                f,
+                "{:>12} {} (godot {declared})",
+                console::style("Breaks").red().bold(),
+                self.package
+            ),
+            Verdict::Invalid => write!(
+                f,
+                "{:>12} {} says it works with godot {declared:?}, which isnt a range",
+                putils::warn(),
+                self.package
+            ),
+            Verdict::Unknown => write!(
+                f,
+                "{:>12} {} doesnt say which godot it works with",
+                putils::warn(),
+                self.package
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::{Manifest, Package};
+
+    #[test]
+    fn compat() {
+        let p = |name: &str, godot: Option<&str>| {
+            Package::from_manifest(
+                Manifest {
+                    godot: godot.map(str::to_owned),
+                    version: Version::new("1.0.0").parse().unwrap(),
+                    ..Default::default()
+                },
+                name.into(),
+            )
+        };
+        let graph = Graph::new(vec![
+            p("a", Some(">=4.2")),
+            p("b", Some("3.x")),
+            p("c", None),
+            p("d", Some("four")),
+        ]);
+        let rows = matrix(&graph, &crate::godot::parse("4.3").unwrap());
+        let got = rows
+            .iter()
+            .map(|r| (r.package.as_str(), r.verdict))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows[0]
+                .finding(&crate::godot::parse("4.3").unwrap())
+                .unwrap()
+                .1,
+            "b@1.0.0 says it works with godot 3.x, not 4.3.0"
+        );
+        assert_eq!(
+            got,
+            [
+                ("b@1.0.0", Verdict::Breaks),
+                ("d@1.0.0", Verdict::Invalid),
+                ("c@1.0.0", Verdict::Unknown),
+                ("a@1.0.0", Verdict::Works)
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::config_file::ConfigFile;
+use crate::package::DEFAULT_INSTALL_DIR;
+
+use clap::ValueEnum;
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::{Bash, EnvCompleter, Fish, Powershell, Zsh};
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs::{read_dir, read_to_string};
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Nushell,
+}
+
+/// Writes the completion script for `shell`.
+/// Everything but nushell gets a script that asks gpm for completions (with `COMPLETE=<shell> gpm`),
+/// so package names can be completed too.
+pub fn write(shell: Shell, cmd: &mut clap::Command, buf: &mut dyn Write) -> io::Result<()> {
+    let dynamic: &dyn EnvCompleter = match shell {
+        Shell::Bash => &Bash,
+        Shell::Zsh => &Zsh,
+        Shell::Fish => &Fish,
+        Shell::Powershell => &Powershell,
+        Shell::Nushell => {
+            clap_complete::generate(clap_complete_nushell::Nushell, cmd, "gpm", buf);
+            return Ok(());
+        }
+    };
+    dynamic.write_registration("COMPLETE", "gpm", "gpm", "gpm", buf)
+}
+
+/// The wanted (from ./godot.package) and installed (in ./addons, or the install_dir) package names.
+fn package_names(cwd: &Path) -> BTreeSet<String> {
+    let cfg = read_to_string(cwd.join("godot.package")).unwrap_or_default();
+    let mut names = ConfigFile::names(&cfg)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    let dirs = |p: &Path| {
+        read_dir(p)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| !n.starts_with('.') && n != "__gpm_deps")
+            .collect::<Vec<_>>()
+    };
+    let addons =
+        cwd.join(ConfigFile::install_dir_of(&cfg).unwrap_or_else(|| DEFAULT_INSTALL_DIR.into()));
+    for d in dirs(&addons) {
+        if d.starts_with('@') {
+            names.extend(
+                dirs(&addons.join(&d))
+                    .into_iter()
+                    .map(|n| format!("{d}/{n}")),
+            );
+        } else {
+            names.insert(d);
+        }
+    }
+    names
+}
+
+/// Completes package names, for the arguments that take them.
+pub fn packages(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(cwd) = std::env::current_dir() else {
+        return vec![];
+    };
+    package_names(&cwd)
+        .into_iter()
+        .filter(|n| n.starts_with(&*current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let t = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            t.path().join("godot.package"),
+            r#"{"packages": {"@bendn/test": "2.0.10"}}"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(t.path().join("addons/@bendn/gdcli")).unwrap();
+        std::fs::create_dir_all(t.path().join("addons/splitter")).unwrap();
+        std::fs::create_dir_all(t.path().join("addons/__gpm_deps/@bendn/gdcli")).unwrap();
+        assert_eq!(
+            package_names(t.path()).into_iter().collect::<Vec<_>>(),
+            ["@bendn/gdcli", "@bendn/test", "splitter"]
+        );
+    }
+}
@@ -1,25 +1,192 @@
 use crate::conversions::*;
 use crate::ctx;
+use crate::graph::{Graph, PackageId};
+use crate::integrity::Integrity;
 use crate::package::Manifest;
-use crate::package::Package;
+use crate::package::{folder, Package, DEFAULT_INSTALL_DIR};
 use crate::Client;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use console::style;
+use futures::stream::{self, StreamExt};
 use semver_rs::Version;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+mod json;
+mod strict;
 
 /// The config file: parsed from godot.package, usually.
 #[derive(Default)]
 pub struct ConfigFile {
     name: String,
     version: String,
-    pub packages: Vec<Package>,
+    /// Where packages go, relative to the project. [DEFAULT_INSTALL_DIR] if unset.
+    install_dir: Option<PathBuf>,
+    /// How package folders are named (see [folder]).
+    dir_template: Option<String>,
+    /// Globs of files no package should install.
+    exclude: Vec<String>,
+    /// Link packages from the store instead of copying them.
+    symlink: bool,
+    /// The prerelease channels of packages, by name.
+    channels: HashMap<String, String>,
+    /// The release asset globs of release packages, by name.
+    assets: HashMap<String, String>,
+    /// The wanted packages (as the config names them) only for working on the game, that its exports shouldnt ship.
+    dev: Vec<String>,
+    /// The godot version of the project, if the config says.
+    godot: Option<String>,
+    resolution: Resolution,
+    duplicates: Duplicates,
+    /// The packages whose hooks may always run.
+    allow_hooks: Vec<String>,
+    /// Install (only) from the packages `gpm vendor` put here, relative to the project.
+    vendor: Option<PathBuf>,
+    strict: bool,
+    pub graph: Graph,
     // hooks: there are no hooks now
 }
 
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+/// A entry in the lockfile.
+struct LockEntry<'a> {
+    name: &'a str,
+    tarball: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<&'a Integrity>,
+    version: String,
+    /// The hooks that were allowed to run (see [crate::hooks::consented]).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    hooks: &'a BTreeMap<String, String>,
+}
+
+/// A wanted package: just a version range, or a table with more.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+enum Spec {
+    Range(String),
+    Full {
+        version: String,
+        /// The folder (in the install dir) to put the package in, instead of its name.
+        /// For plugins hardcoding their `res://addons/<name>` path.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dir: Option<String>,
+        /// Globs of files in the package not to install.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclude: Vec<String>,
+        /// The folder in the package to install, instead of all of it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subdir: Option<PathBuf>,
+        /// The prereleases to resolve to as well, like `beta` (for `2.0.0-beta.3`).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        channel: Option<String>,
+        /// The release asset to install (a glob, like `addon-*.zip`), for release packages (like `gh:user/repo`).
+        /// Without one, the source of the tag is.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        asset: Option<String>,
+        /// Only for working on the game (like a test runner, or a debug console): exports shouldnt ship it (see `gpm export-check`).
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        dev: bool,
+    },
+}
+
+impl Spec {
+    fn range(&self) -> &str {
+        match self {
+            Self::Range(r) | Self::Full { version: r, .. } => r,
+        }
+    }
+
+    fn range_mut(&mut self) -> &mut String {
+        match self {
+            Self::Range(r) | Self::Full { version: r, .. } => r,
+        }
+    }
+
+    fn dir(&self) -> Option<&str> {
+        match self {
+            Self::Range(_) => None,
+            Self::Full { dir, .. } => dir.as_deref(),
+        }
+    }
+
+    fn exclude(&self) -> &[String] {
+        match self {
+            Self::Range(_) => &[],
+            Self::Full { exclude, .. } => exclude,
+        }
+    }
+
+    fn subdir(&self) -> Option<&Path> {
+        match self {
+            Self::Range(_) => None,
+            Self::Full { subdir, .. } => subdir.as_deref(),
+        }
+    }
+
+    fn channel(&self) -> Option<&str> {
+        match self {
+            Self::Range(_) => None,
+            Self::Full { channel, .. } => channel.as_deref(),
+        }
+    }
+
+    fn asset(&self) -> Option<&str> {
+        match self {
+            Self::Range(_) => None,
+            Self::Full { asset, .. } => asset.as_deref(),
+        }
+    }
+
+    fn dev(&self) -> bool {
+        match self {
+            Self::Range(_) => false,
+            Self::Full { dev, .. } => *dev,
+        }
+    }
+
+    /// The real name and range of an aliased package (`npm:@bendn/test@^2`), if it is one.
+    fn unalias(&self) -> Option<(&str, &str)> {
+        let rest = self.range().strip_prefix("npm:")?;
+        // a scopes @ isnt the version separator
+        Some(match rest.get(1..)?.find('@') {
+            Some(i) => (&rest[..i + 1], &rest[i + 2..]),
+            None => (rest, ""),
+        })
+    }
+
+    /// The name and range to resolve, for the package wanted as `name`.
+    fn resolve<'a>(&'a self, name: &'a str) -> (&'a str, &'a str) {
+        self.unalias().unwrap_or((name, self.range()))
+    }
+
+    /// Just the range, if nothing else is set.
+    fn simplify(self) -> Self {
+        match self {
+            Self::Full {
+                version,
+                dir: None,
+                exclude,
+                subdir: None,
+                channel: None,
+                asset: None,
+                dev: false,
+            } if exclude.is_empty() => Self::Range(version),
+            s => s,
+        }
+    }
+}
+
+/// Parses the `exclude` globs.
+fn patterns(globs: &[String]) -> Result<Vec<glob::Pattern>> {
+    globs
+        .iter()
+        .map(|g| glob::Pattern::new(g).with_context(|| format!("exclude glob {g} is invalid")))
+        .collect()
+}
+
 #[derive(Deserialize, Serialize, Default)]
 #[serde(default)]
 /// A wrapper to [ConfigFile]. This _is_ necessary.
@@ -28,20 +195,296 @@ pub struct ConfigFile {
 struct ParsedConfig {
     // support NPM package.json files (also allows gpm -c package.json -u)
     #[serde(alias = "dependencies")]
-    packages: HashMap<String, String>,
-    #[serde(default)]
+    packages: BTreeMap<String, Spec>, // sorted, so printing is stable
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    install_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dir_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    symlink: bool,
+    /// The godot version of the project (like `4.2`), if project.godot doesnt say (or says wrong).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    godot: Option<String>,
+    #[serde(default, skip_serializing_if = "Resolution::is_highest")]
+    resolution: Resolution,
+    #[serde(default, skip_serializing_if = "Duplicates::is_side_by_side")]
+    duplicates: Duplicates,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allow_hooks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vendor: Option<PathBuf>,
+    /// Check the config harder, every time (see [strict::check]).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
+}
+
+/// Which version a range resolves to.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Resolution {
+    /// The newest that matches.
+    #[default]
+    Highest,
+    /// The oldest that matches, for reproducibility, or testing the lower bounds of ranges.
+    /// Like the minimal version selection of go, a package wanted at more than one version gets
+    /// the highest of those oldest versions, if every range takes it.
+    Minimal,
+}
+
+impl Resolution {
+    fn is_highest(&self) -> bool {
+        *self == Self::Highest
+    }
+
+    /// Applies this to the package trees `roots`, which the config wants at `ranges` (by name).
+    /// Aliased packages are other versions on purpose, so they dont count.
+    fn apply(self, roots: &mut [Package], ranges: &HashMap<String, String>) {
+        if self.is_highest() {
+            return;
+        }
+        // name -> the versions it got (each the oldest its range takes), and the ranges it is wanted at
+        type Seen = BTreeMap<String, (BTreeMap<String, Manifest>, Vec<String>)>;
+        fn walk(p: &Package, range: Option<&String>, seen: &mut Seen) {
+            let (versions, wants) = seen.entry(p.name.clone()).or_default();
+            let got = p.manifest.full_version();
+            // a want without a range (a tag, or a url) takes just the version it got
+            wants.push(range.cloned().unwrap_or_else(|| format!("={got}")));
+            versions.entry(got).or_insert_with(|| p.manifest.clone());
+            for d in &p.manifest.dependencies {
+                walk(d, p.manifest.ranges.get(&d.name), seen);
+            }
+        }
+        let opts = semver_rs::Options::builder()
+            .include_prerelease(true)
+            .build();
+        // a swapped in version has its own dependencies, which may want more, so go until nothing changes
+        loop {
+            let mut seen = Seen::new();
+            for p in roots.iter().filter(|p| p.alias.is_none()) {
+                walk(p, ranges.get(&p.name), &mut seen);
+            }
+            let mut winners = HashMap::new();
+            for (name, (versions, wants)) in seen {
+                if versions.len() < 2 {
+                    continue;
+                }
+                let wants = wants
+                    .iter()
+                    .map(|r| crate::package::range::parse(r, opts))
+                    .collect::<Result<Vec<_>, _>>();
+                let Ok(wants) = wants else { continue };
+                // only ever up, so this ends
+                let highest = versions.values().max_by(|a, b| a.version.cmp(&b.version));
+                if let Some(m) = highest.filter(|m| wants.iter().all(|r| r.test(&m.version))) {
+                    winners.insert(name, m.clone());
+                }
+            }
+            if winners.is_empty() {
+                return;
+            }
+            for p in roots.iter_mut().filter(|p| p.alias.is_none()) {
+                swap(p, &winners);
+            }
+        }
+    }
+}
+
+/// Puts the `winners` (by name) in place of the other versions in the tree of `p`.
+fn swap(p: &mut Package, winners: &HashMap<String, Manifest>) {
+    if let Some(m) = winners.get(&p.name) {
+        if p.manifest.full_version() != m.full_version() {
+            p.manifest = m.clone();
+        }
+    }
+    for d in &mut p.manifest.dependencies {
+        swap(d, winners);
+    }
+}
+
+/// What happens when the tree has a package at more than one version.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Duplicates {
+    /// Every version is installed, each in its own folder (`__gpm_deps/<name>/<version>`, for dependencies).
+    #[default]
+    AllowSideBySide,
+    /// Only one is, for everything that wants any of them: the newest one every range wants takes,
+    /// or the one the config wants (it is never overridden). If there is none, resolving fails.
+    HighestWins,
+    /// Resolving fails.
+    Error,
+}
+
+impl Duplicates {
+    fn is_side_by_side(&self) -> bool {
+        *self == Self::AllowSideBySide
+    }
+
+    /// Applies this to the package trees `roots`. Aliased packages are other versions on purpose, so they dont count.
+    fn apply(self, roots: &mut [Package]) -> Result<()> {
+        if self.is_side_by_side() {
+            return Ok(());
+        }
+        // name -> version -> who wants it, and at what range (none: the config, or a tag, that want just that version)
+        type Seen = BTreeMap<String, BTreeMap<String, (Manifest, Vec<(String, Option<String>)>)>>;
+        let mut seen = Seen::new();
+        fn walk(p: &Package, by: &str, range: Option<&String>, seen: &mut Seen) {
+            let (_, wanters) = seen
+                .entry(p.name.clone())
+                .or_default()
+                .entry(p.manifest.full_version())
+                .or_insert_with(|| (p.manifest.clone(), vec![]));
+            if !wanters.iter().any(|(w, _)| w == by) {
+                wanters.push((by.to_owned(), range.cloned()));
+            }
+            for d in &p.manifest.dependencies {
+                walk(d, &p.to_string(), p.manifest.ranges.get(&d.name), seen);
+            }
+        }
+        for p in roots.iter().filter(|p| p.alias.is_none()) {
+            walk(p, "the config", None, &mut seen);
+        }
+        seen.retain(|_, versions| versions.len() > 1);
+        if seen.is_empty() {
+            return Ok(());
+        }
+        if self == Self::Error {
+            let conflicts = seen
+                .iter()
+                .map(|(name, versions)| {
+                    let versions = versions
+                        .iter()
+                        .map(|(v, (_, by))| {
+                            let by = by.iter().map(|(by, _)| by.as_str()).collect::<Vec<_>>();
+                            format!("{v} (by {})", by.join(", "))
+                        })
+                        .collect::<Vec<_>>();
+                    format!("  {name} at {}", versions.join(" and "))
+                })
+                .collect::<Vec<_>>();
+            bail!(
+                "Packages are wanted at more than one version (the config says duplicates = error):\n{}",
+                conflicts.join("\n")
+            );
+        }
+        let opts = semver_rs::Options::builder()
+            .include_prerelease(true)
+            .build();
+        let mut winners = HashMap::new();
+        let mut conflicts = vec![];
+        for (name, versions) in seen {
+            // (who, what they asked for, as a range): a want without a range takes just the version it got
+            let wants = versions
+                .values()
+                .flat_map(|(m, by)| by.iter().map(move |(by, r)| (by, r, m.full_version())))
+                .map(|(by, r, got)| {
+                    let range = r
+                        .as_deref()
+                        .and_then(|r| crate::package::range::parse(r, opts).ok());
+                    let range = range.unwrap_or_else(|| {
+                        crate::package::range::parse(&format!("={got}"), opts).unwrap()
+                    });
+                    (format!("{} (by {by})", r.as_deref().unwrap_or(&got)), range)
+                })
+                .collect::<Vec<_>>();
+            let takes = |m: &Manifest| wants.iter().all(|(_, r)| r.test(&m.version));
+            let all = || {
+                wants
+                    .iter()
+                    .map(|(w, _)| w.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let pinned = versions
+                .values()
+                .find(|(_, by)| by.iter().any(|(by, _)| by == "the config"))
+                .map(|(m, _)| m);
+            let winner = match pinned {
+                // the config is never overridden
+                Some(m) if takes(m) => m,
+                Some(m) => {
+                    conflicts.push(format!(
+                        "  {name} is {} in the config, but wanted at {}",
+                        m.full_version(),
+                        all()
+                    ));
+                    continue;
+                }
+                None => match versions
+                    .values()
+                    .map(|(m, _)| m)
+                    .filter(|m| takes(m))
+                    .max_by(|a, b| a.version.cmp(&b.version))
+                {
+                    Some(m) => m,
+                    None => {
+                        conflicts.push(format!("  no version of {name} is in all of {}", all()));
+                        continue;
+                    }
+                },
+            };
+            eprintln!(
+                "{:>12} {name} is wanted at more than one version: {} is installed for all",
+                crate::putils::warn(),
+                winner.full_version()
+            );
+            winners.insert(name, winner.clone());
+        }
+        if !conflicts.is_empty() {
+            bail!(
+                "Packages are wanted at versions that dont go together (the config says duplicates = highest-wins):\n{}",
+                conflicts.join("\n")
+            );
+        }
+        for p in roots.iter_mut().filter(|p| p.alias.is_none()) {
+            swap(p, &winners);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum ConfigType {
     JSON,
+    #[cfg(feature = "yaml")]
     YAML,
+    #[cfg(feature = "toml")]
     TOML,
 }
 
+impl ConfigType {
+    /// Every config type compiled in.
+    pub const ALL: &'static [ConfigType] = &[
+        ConfigType::JSON,
+        #[cfg(feature = "yaml")]
+        ConfigType::YAML,
+        #[cfg(feature = "toml")]
+        ConfigType::TOML,
+    ];
+
+    /// Figures out what type `contents` is written in.
+    pub fn detect(contents: &str) -> Option<Self> {
+        if contents.starts_with('{') {
+            return Some(ConfigType::JSON);
+        }
+        #[cfg(feature = "yaml")]
+        if contents.starts_with("---") {
+            return Some(ConfigType::YAML);
+        }
+        ConfigType::ALL
+            .iter()
+            .copied()
+            .find(|&t| ParsedConfig::parse(contents, t).is_ok())
+    }
+}
+
 impl std::fmt::Display for ConfigType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:#?}", self)
@@ -52,12 +495,48 @@ impl From<&ConfigFile> for ParsedConfig {
     fn from(from: &ConfigFile) -> Self {
         Self {
             packages: from
-                .packages
+                .graph
+                .roots()
                 .iter()
-                .map(|p| (p.name.to_string(), p.manifest.version.to_string()))
+                .map(|&id| from.graph.get(id))
+                .map(|p| {
+                    let version = match &p.alias {
+                        Some(_) => format!("npm:{}@{}", p.name, p.manifest.full_version()),
+                        None => p.manifest.full_version(),
+                    };
+                    // the project wide globs are in every filter
+                    let exclude = p
+                        .filter
+                        .exclude
+                        .iter()
+                        .map(|p| p.as_str().to_string())
+                        .filter(|g| !from.exclude.contains(g))
+                        .collect::<Vec<_>>();
+                    let spec = Spec::Full {
+                        version,
+                        dir: p.dir.clone(),
+                        exclude,
+                        subdir: p.filter.subdir.clone(),
+                        channel: from.channels.get(&p.name).cloned(),
+                        asset: from.assets.get(&p.name).cloned(),
+                        dev: from.dev.contains(p.alias.as_ref().unwrap_or(&p.name)),
+                    }
+                    .simplify();
+                    (p.alias.as_ref().unwrap_or(&p.name).to_string(), spec)
+                })
                 .collect(),
             name: String::new(),
             version: String::new(),
+            install_dir: from.install_dir.clone(),
+            dir_template: from.dir_template.clone(),
+            exclude: from.exclude.clone(),
+            symlink: from.symlink,
+            godot: from.godot.clone(),
+            resolution: from.resolution,
+            duplicates: from.duplicates,
+            allow_hooks: from.allow_hooks.clone(),
+            vendor: from.vendor.clone(),
+            strict: from.strict,
         }
     }
 }
@@ -65,86 +544,452 @@ impl From<&ConfigFile> for ParsedConfig {
 #[async_trait::async_trait]
 impl TryFromAsync<ParsedConfig> for ConfigFile {
     async fn try_from_async(value: ParsedConfig, client: Client) -> Result<Self> {
-        let mut packages: Vec<Package> = ctx!(
-            value.packages.try_into_async(client).await,
-            "turning ParsedConfig into ConfigFile"
-        )
-        .unwrap();
-        for mut p in &mut packages {
-            p.indirect = false
+        let channels = value
+            .packages
+            .iter()
+            .filter_map(|(name, spec)| {
+                let (name, _) = spec.resolve(name);
+                Some((
+                    crate::package::name::normalize(name),
+                    spec.channel()?.to_string(),
+                ))
+            })
+            .collect::<HashMap<_, _>>();
+        for (name, channel) in &channels {
+            if channel.is_empty()
+                || !channel
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                bail!("channel {channel} of {name} should be a prerelease name, like beta");
+            }
+        }
+        let assets = value
+            .packages
+            .iter()
+            .filter_map(|(name, spec)| Some((name.clone(), spec.asset()?.to_string())))
+            .collect::<HashMap<_, _>>();
+        let dev = value
+            .packages
+            .iter()
+            .filter(|(_, spec)| spec.dev())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let mut client = client
+            .with_channels(channels.clone())
+            .with_assets(assets.clone());
+        if let Some(g) = &value.godot {
+            client = client.with_godot(Some(crate::godot::parse(g)?));
+        }
+        if value.resolution == Resolution::Minimal {
+            client = client.minimal();
         }
-        Ok(ConfigFile {
-            packages,
+        let wanted = value
+            .packages
+            .iter()
+            .map(|(wanted, spec)| {
+                let (name, range) = spec.resolve(wanted);
+                let name = crate::package::name::normalize(name);
+                if !crate::package::name::is_source(&name) {
+                    if let Err(e) = crate::package::name::check(&name) {
+                        bail!("{wanted} in the config: {e}");
+                    }
+                }
+                Ok((wanted.clone(), name, range.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let resolved = stream::iter(wanted)
+            .map(|(wanted, name, range)| {
+                let client = client.clone();
+                async move {
+                    let p = Package::new(name, range, client).await;
+                    ctx!(p, "turning ParsedConfig into ConfigFile").map(|p| (wanted, p))
+                }
+            })
+            .buffered(crate::PARALLEL)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let mut packages = vec![];
+        let mut ranges = HashMap::new();
+        for (wanted, mut p) in resolved {
+            p.indirect = false;
+            let spec = &value.packages[&wanted];
+            ranges.insert(p.name.clone(), spec.resolve(&wanted).1.to_string());
+            if spec.unalias().is_some() {
+                p.alias = Some(wanted);
+            }
+            p.filter.exclude = patterns(spec.exclude())?;
+            if let Some(sub) = spec.subdir() {
+                if !sub
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+                {
+                    bail!(
+                        "subdir {} of {} should be a folder inside the package",
+                        sub.display(),
+                        p.name
+                    );
+                }
+                // `./addons/x` and `addons/x` are the same folder, but only one would strip
+                let sub = sub
+                    .components()
+                    .filter(|c| *c != Component::CurDir)
+                    .collect::<PathBuf>();
+                p.filter.subdir = (!sub.as_os_str().is_empty()).then_some(sub);
+            }
+            if let Some(dir) = spec.dir() {
+                let mut parts = Path::new(dir).components();
+                if !matches!(
+                    (parts.next(), parts.next()),
+                    (Some(Component::Normal(_)), None)
+                ) {
+                    bail!("dir {dir} of {} should be a plain folder name", p.name);
+                }
+                p.dir = Some(dir.to_string());
+            }
+            packages.push(p);
+        }
+        value.resolution.apply(&mut packages, &ranges);
+        value.duplicates.apply(&mut packages)?;
+        packages.sort();
+        let mut cfg = ConfigFile {
+            graph: Graph::new(packages),
             name: value.name,
             version: value.version,
-        })
+            install_dir: None,
+            dir_template: None,
+            exclude: vec![],
+            symlink: false,
+            channels,
+            assets,
+            dev,
+            godot: value.godot,
+            resolution: value.resolution,
+            duplicates: value.duplicates,
+            allow_hooks: value.allow_hooks,
+            vendor: value.vendor,
+            strict: value.strict,
+        };
+        if let Some(dir) = value.install_dir {
+            cfg.set_install_dir(dir)?;
+        }
+        if let Some(template) = value.dir_template {
+            cfg.set_dir_template(template)?;
+        }
+        cfg.set_exclude(value.exclude)?;
+        cfg.set_symlink(value.symlink);
+        Ok(cfg)
     }
 }
 
+/// Parses `txt`, written in `t`, as a `T`.
+fn from_str<T: serde::de::DeserializeOwned>(txt: &str, t: ConfigType) -> Result<T> {
+    Ok(match t {
+        #[cfg(feature = "toml")]
+        ConfigType::TOML => toml::from_str::<T>(txt)?,
+        #[cfg(feature = "hjson")]
+        ConfigType::JSON => deser_hjson::from_str::<T>(txt)?,
+        #[cfg(not(feature = "hjson"))]
+        ConfigType::JSON => serde_json::from_str::<T>(txt)?,
+        #[cfg(feature = "yaml")]
+        ConfigType::YAML => serde_yaml::from_str::<T>(txt)?,
+    })
+}
+
 impl ParsedConfig {
     pub fn parse(txt: &str, t: ConfigType) -> Result<Self> {
-        Ok(match t {
-            ConfigType::TOML => toml::from_str::<ParsedConfig>(txt)?,
-            ConfigType::JSON => deser_hjson::from_str::<ParsedConfig>(txt)?,
-            ConfigType::YAML => serde_yaml::from_str::<ParsedConfig>(txt)?,
-        })
+        from_str(txt, t)
     }
 }
 
 impl ConfigFile {
     pub fn empty() -> Self {
-        Self {
-            packages: vec![],
-            ..ConfigFile::default()
-        }
+        Self::default()
     }
 
     pub fn print(&self, t: ConfigType) -> String {
         let w = ParsedConfig::from(self);
         match t {
             ConfigType::JSON => serde_json::to_string_pretty(&w).unwrap(),
+            #[cfg(feature = "yaml")]
             ConfigType::YAML => serde_yaml::to_string(&w).unwrap(),
+            #[cfg(feature = "toml")]
             ConfigType::TOML => toml::to_string_pretty(&w).unwrap(),
         }
     }
 
     /// Creates a new [ConfigFile] from the given text
-    /// Panics if the file cant be parsed as toml, hjson or yaml.
+    /// Panics if the file cant be parsed as toml, hjson or yaml (whichever are compiled in).
     pub async fn new(contents: &String, client: Client) -> Self {
         if contents.is_empty() {
             panic!("Empty CFG");
         }
 
         // definetly not going to backfire
-        let mut cfg = if contents.as_bytes()[0] == b'{' {
+        if contents.as_bytes()[0] == b'{' {
             // json gets brute forced first so this isnt really needed
-            Self::parse(contents, ConfigType::JSON, client)
+            return Self::parse(contents, ConfigType::JSON, client)
                 .await
-                .expect("Parsing CFG from JSON should work")
-        } else if contents.len() > 3 && contents[..3] == *"---" {
-            Self::parse(contents, ConfigType::YAML, client)
+                .expect("Parsing CFG from JSON should work");
+        }
+        #[cfg(feature = "yaml")]
+        if contents.len() > 3 && contents[..3] == *"---" {
+            return Self::parse(contents, ConfigType::YAML, client)
                 .await
-                .expect("Parsing CFG from YAML should work")
-        } else {
-            for i in [ConfigType::JSON, ConfigType::YAML, ConfigType::TOML].into_iter() {
-                let res = Self::parse(contents, i, client.clone()).await;
+                .expect("Parsing CFG from YAML should work");
+        }
+        for &i in ConfigType::ALL {
+            let res = Self::parse(contents, i, client.clone()).await;
 
-                if let Ok(parsed) = res {
-                    return parsed;
-                }
+            if let Ok(parsed) = res {
+                return parsed;
+            }
+
+            println!(
+                "{:>12} Parsing CFG from {:#?} failed: `{}` (ignore if cfg not written in {:#?})",
+                crate::putils::warn(),
+                i,
+                style(res.err().unwrap()).red(),
+                i
+            )
+        }
+        panic!("Parsing CFG failed (see above warnings to find out why)");
+    }
+
+    /// Where packages go, relative to the project.
+    pub fn install_dir(&self) -> &Path {
+        self.install_dir
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_INSTALL_DIR))
+    }
 
-                println!(
-                    "{:>12} Parsing CFG from {:#?} failed: `{}` (ignore if cfg not written in {:#?})",
-                    crate::putils::warn(),
-                    i,
-                    style(res.err().unwrap()).red(),
-                    i
-                )
+    /// The packages only wanted by dev packages (`"dev": true` in the config), and by no other: the dev packages, and their dependencies
+    /// the rest of the game doesnt depend on too.
+    pub fn dev_only(&self) -> Vec<PackageId> {
+        let g = &self.graph;
+        let reach = |roots: Vec<PackageId>| {
+            let mut seen = HashSet::new();
+            let mut todo = roots;
+            while let Some(id) = todo.pop() {
+                if seen.insert(id) {
+                    todo.extend(g.deps(id));
+                }
             }
-            panic!("Parsing CFG failed (see above warnings to find out why)");
+            seen
+        };
+        let (dev, game): (Vec<_>, Vec<_>) = g.roots().iter().partition(|&&id| {
+            let p = g.get(id);
+            self.dev.contains(p.alias.as_ref().unwrap_or(&p.name))
+        });
+        let game = reach(game);
+        let mut out = reach(dev)
+            .into_iter()
+            .filter(|id| !game.contains(id))
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
+
+    /// Installs every package under `dir` (relative to the project) instead.
+    pub fn set_install_dir(&mut self, dir: PathBuf) -> Result<()> {
+        if !dir
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            || dir.as_os_str().is_empty()
+        {
+            bail!(
+                "install_dir {} should be a directory inside the project",
+                dir.display()
+            );
+        }
+        let ids = self.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            self.graph.get_mut(id).install_dir = Some(dir.clone());
+        }
+        self.install_dir = Some(dir);
+        Ok(())
+    }
+
+    /// Names package folders after `template` instead (see [folder]).
+    pub fn set_dir_template(&mut self, template: String) -> Result<()> {
+        let example = folder(&template, "@scope/name");
+        if !template.contains("{name}")
+            || !example
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            bail!("dir_template {template} should contain {{name}}, and make a path inside the install dir");
+        }
+        let ids = self.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            self.graph.get_mut(id).dir_template = Some(template.clone());
+        }
+        self.dir_template = Some(template);
+        Ok(())
+    }
+
+    /// Keeps files matching any of the `globs` out of every package.
+    pub fn set_exclude(&mut self, globs: Vec<String>) -> Result<()> {
+        let parsed = patterns(&globs)?;
+        let ids = self.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            let filter = &mut self.graph.get_mut(id).filter;
+            filter.exclude.extend(parsed.iter().cloned());
+            filter.exclude.sort();
+            filter.exclude.dedup();
+        }
+        self.exclude = globs;
+        Ok(())
+    }
+
+    /// Symlinks packages to their install in the store (when there is one), instead of copying them.
+    pub fn set_symlink(&mut self, symlink: bool) {
+        let ids = self.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            self.graph.get_mut(id).symlink = symlink;
+        }
+        self.symlink = symlink;
+    }
+
+    /// Wether the config says the hooks of `name` may always run.
+    pub fn allows_hooks(&self, name: &str) -> bool {
+        self.allow_hooks.iter().any(|n| n == name)
+    }
+
+    /// The godot version of the project, if the config says.
+    pub fn godot(&self) -> Option<Version> {
+        crate::godot::parse(self.godot.as_deref()?).ok()
+    }
+
+    /// The `vendor` dir of a config, without resolving the packages.
+    pub fn vendor_of(contents: &str) -> Option<PathBuf> {
+        let t = ConfigType::detect(contents)?;
+        ParsedConfig::parse(contents, t).ok()?.vendor
+    }
+
+    /// Wether the config `contents` wants to be checked strictly.
+    pub fn strict_of(contents: &str) -> bool {
+        ConfigType::detect(contents)
+            .and_then(|t| ParsedConfig::parse(contents, t).ok())
+            .is_some_and(|c| c.strict)
+    }
+
+    /// Checks the config `contents` strictly (see [strict::check]).
+    pub fn check_strict(contents: &str) -> Result<Vec<(String, String)>> {
+        strict::check(contents)
+    }
+
+    /// The `install_dir` of a config, without resolving the packages.
+    pub fn install_dir_of(contents: &str) -> Option<PathBuf> {
+        let t = ConfigType::detect(contents)?;
+        ParsedConfig::parse(contents, t).ok()?.install_dir
+    }
+
+    /// The dirs of the packages of a config that are in a local dir (`file:`), without resolving the packages.
+    pub fn local_dirs(contents: &str) -> Vec<PathBuf> {
+        let Some(c) =
+            ConfigType::detect(contents).and_then(|t| ParsedConfig::parse(contents, t).ok())
+        else {
+            return vec![];
         };
-        cfg.packages.sort();
-        cfg
+        c.packages
+            .iter()
+            .filter_map(|(name, spec)| {
+                Some(crate::backend::local_dir(spec.resolve(name).0.trim())?.to_path_buf())
+            })
+            .collect()
+    }
+
+    /// The names of the wanted packages, without resolving them.
+    pub fn names(contents: &str) -> Result<Vec<String>> {
+        let t = ConfigType::detect(contents).context("Unknown config type")?;
+        Ok(ParsedConfig::parse(contents, t)?
+            .packages
+            .into_keys()
+            .collect())
+    }
+
+    /// Rewrites the wanted versions in `contents`, with `f(name, old range)`, which gives the new range, if it should change.
+    /// For aliases, `f` sees the real name and range.
+    /// The config is written back in the type it was in, but formatting and comments are lost
+    /// (except in json configs, like a package.json, where whatever gpm didnt change stays as it was).
+    pub fn rewrite(
+        contents: &str,
+        mut f: impl FnMut(&str, &str) -> Option<String>,
+    ) -> Result<String> {
+        Self::edit(contents, |cfg| {
+            for (name, spec) in cfg.packages.iter_mut() {
+                // aliases are rewritten as the package they are
+                let new = match spec.unalias() {
+                    Some((real, range)) => f(real, range).map(|new| format!("npm:{real}@{new}")),
+                    None => f(name, spec.range()),
+                };
+                if let Some(new) = new {
+                    *spec.range_mut() = new;
+                }
+            }
+        })
+    }
+
+    /// Adds `name` at `range` to the wanted packages in `contents`, or sets its range, if its already wanted.
+    /// Like [ConfigFile::rewrite], formatting and comments are lost.
+    pub fn add(contents: &str, name: &str, range: String) -> Result<String> {
+        Self::edit(contents, |cfg| match cfg.packages.get_mut(name) {
+            Some(spec) => *spec.range_mut() = range,
+            None => {
+                cfg.packages.insert(name.to_owned(), Spec::Range(range));
+            }
+        })
+    }
+
+    /// Adds `name` at `range` to the wanted packages in `contents`, in the folder `dir` (of the install dir), if it isnt where it would go anyway.
+    pub fn adopt(contents: &str, name: &str, range: String, dir: Option<String>) -> Result<String> {
+        Self::edit(contents, |cfg| {
+            let spec = match dir {
+                None => Spec::Range(range),
+                Some(dir) => Spec::Full {
+                    version: range,
+                    dir: Some(dir),
+                    exclude: vec![],
+                    subdir: None,
+                    channel: None,
+                    asset: None,
+                    dev: false,
+                },
+            };
+            cfg.packages.insert(name.to_owned(), spec);
+        })
+    }
+
+    /// Removes the `names` from the wanted packages in `contents`. Like [ConfigFile::rewrite], formatting and comments are lost.
+    pub fn remove(contents: &str, names: &[String]) -> Result<String> {
+        let wanted = Self::names(contents)?;
+        if let Some(n) = names.iter().find(|n| !wanted.contains(n)) {
+            bail!("{n} is not in the config");
+        }
+        Self::edit(contents, |cfg| {
+            cfg.packages.retain(|n, _| !names.contains(n))
+        })
+    }
+
+    fn edit(contents: &str, f: impl FnOnce(&mut ParsedConfig)) -> Result<String> {
+        let t = ConfigType::detect(contents).context("Unknown config type")?;
+        let mut cfg = ParsedConfig::parse(contents, t)?;
+        f(&mut cfg);
+        Ok(match t {
+            ConfigType::JSON => match json::merge(contents, &cfg)? {
+                Some(merged) => merged,
+                // hjson, that doesnt parse as json
+                None => serde_json::to_string_pretty(&cfg)?,
+            },
+            #[cfg(feature = "yaml")]
+            ConfigType::YAML => serde_yaml::to_string(&cfg)?,
+            #[cfg(feature = "toml")]
+            ConfigType::TOML => toml::to_string_pretty(&cfg)?,
+        })
     }
 
     pub async fn parse(txt: &str, t: ConfigType, client: Client) -> Result<ConfigFile> {
@@ -155,77 +1000,93 @@ impl ConfigFile {
         Ok(Package::from_manifest(
             Manifest {
                 version: Version::new(&self.version).parse()?,
+                build: crate::package::range::build(&self.version).map(String::from),
                 shasum: None,
                 tarball: uri,
-                dependencies: self.packages,
+                integrity: None,
+                deprecated: None,
+                godot: None,
+                hooks: Default::default(),
+                ranges: Default::default(),
+                dependencies: self.graph.into_tree(),
             },
             self.name,
         ))
     }
 
-    /// Creates a lockfile for this config file.
-    /// note: Lockfiles are currently unused.
-    pub fn lock(&mut self, cwd: &Path) -> String {
-        let mut pkgs = vec![];
-        for mut p in self.collect() {
-            if p.is_installed(cwd) {
-                p.prepare_lock();
-                pkgs.push(p);
-            };
-        }
-        pkgs.sort();
-        serde_json::to_string_pretty(&pkgs).unwrap()
-    }
-
-    /// Iterates over all the packages (and their deps) in this config file.
-    fn _for_each(pkgs: &mut [Package], mut cb: impl FnMut(&mut Package)) {
-        fn inner(pkgs: &mut [Package], cb: &mut impl FnMut(&mut Package)) {
-            for p in pkgs {
-                cb(p);
-                if p.has_deps() {
-                    inner(&mut p.manifest.dependencies, cb);
+    /// Pairs of packages that would be installed into the same folder, or one into the others folder,
+    /// and so overwrite (or delete) each others files.
+    /// Case is ignored, like windows and macos do.
+    pub fn overlaps(&self) -> Vec<(&Package, &Package)> {
+        let dirs = self
+            .graph
+            .iter()
+            .map(|(_, p)| {
+                let dir = p.download_dir(Path::new(""));
+                (PathBuf::from(dir.to_string_lossy().to_lowercase()), p)
+            })
+            .collect::<Vec<_>>();
+        let mut overlaps = vec![];
+        for (i, (a, p)) in dirs.iter().enumerate() {
+            for (b, q) in &dirs[i + 1..] {
+                if a.starts_with(b) || b.starts_with(a) {
+                    overlaps.push((*p, *q));
                 }
             }
         }
-        inner(pkgs, &mut cb);
+        overlaps
     }
 
-    /// Public wrapper for _for_each, but with the initial value filled out.
-    pub fn for_each(&mut self, cb: impl FnMut(&mut Package)) {
-        Self::_for_each(&mut self.packages, cb)
-    }
-
-    /// Collect all the packages, and their dependencys.
-    /// Uses clones, because I wasn't able to get references to work
-    pub fn collect(&mut self) -> HashSet<Package> {
-        let mut pkgs: HashSet<Package> = HashSet::new();
-        self.for_each(|p| {
-            pkgs.insert(p.clone());
-        });
-        pkgs
+    /// Creates a lockfile for this config file.
+    /// note: Lockfiles are currently unused.
+    pub fn lock(&self, cwd: &Path) -> String {
+        let mut pkgs = self
+            .graph
+            .iter()
+            .filter(|(_, p)| p.is_installed(cwd))
+            .map(|(_, p)| LockEntry {
+                name: &p.name,
+                tarball: p.manifest.tarball.to_string(),
+                integrity: p.manifest.integrity.as_ref(),
+                version: p.manifest.full_version(),
+                hooks: p.hooks(),
+            })
+            .collect::<Vec<_>>();
+        pkgs.sort();
+        pkgs.dedup();
+        serde_json::to_string_pretty(&pkgs).unwrap()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::config_file::*;
+    use crate::integrity::Algorithm;
 
     #[tokio::test]
     async fn parse() {
         let t = crate::test_utils::mktemp().await;
         let c = t.2;
-        let cfgs: [&mut ConfigFile; 3] = [
-            &mut ConfigFile::new(
+        let cfgs: Vec<ConfigFile> = vec![
+            #[cfg(feature = "hjson")]
+            ConfigFile::new(
                 &r#"dependencies: { "@bendn/test": 2.0.10 }"#.into(),
                 c.clone(),
             )
             .await,
-            &mut ConfigFile::new(
+            ConfigFile::new(
+                &r#"{"dependencies": { "@bendn/test": "2.0.10" }}"#.into(),
+                c.clone(),
+            )
+            .await,
+            #[cfg(feature = "yaml")]
+            ConfigFile::new(
                 &"dependencies:\n  \"@bendn/test\": \"2.0.10\"".into(),
                 c.clone(),
             )
             .await,
-            &mut ConfigFile::new(
+            #[cfg(feature = "toml")]
+            ConfigFile::new(
                 &"[dependencies]\n\"@bendn/test\" = \"2.0.10\"".into(),
                 c.clone(),
             )
@@ -240,15 +1101,19 @@ mod tests {
             r#"[{"name":"@bendn/gdcli","version":"1.2.5"},{"name":"@bendn/test","version":"2.0.10"}]"#,
         ).unwrap();
         for cfg in cfgs {
-            assert_eq!(cfg.packages.len(), 1);
-            assert_eq!(cfg.packages[0].to_string(), "@bendn/test@2.0.10");
-            assert_eq!(cfg.packages[0].manifest.dependencies.len(), 1);
+            let g = &cfg.graph;
+            assert_eq!(g.roots().len(), 1);
+            assert_eq!(g.get(g.roots()[0]).to_string(), "@bendn/test@2.0.10");
+            assert_eq!(g.deps(g.roots()[0]).len(), 1);
             assert_eq!(
-                cfg.packages[0].manifest.dependencies[0].to_string(),
+                g.get(g.deps(g.roots()[0])[0]).to_string(),
                 "@bendn/gdcli@1.2.5"
             );
-            for mut p in cfg.collect() {
-                p.download(c.clone(), t.0.path()).await
+            for (_, p) in g.iter() {
+                p.clone()
+                    .download(c.clone(), t.0.path(), Algorithm::Sha512)
+                    .await
+                    .unwrap();
             }
             assert_eq!(
                 serde_json::from_str::<Vec<LockFileEntry>>(cfg.lock(t.0.path()).as_str()).unwrap(),
@@ -256,4 +1121,266 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn rewrite() {
+        let new = ConfigFile::rewrite(
+            r#"{"packages": {"b": "^1.0.0", "a": "1.0.0"}, "name": "p"}"#,
+            |name, _| (name == "b").then(|| "^2.0.0".into()),
+        )
+        .unwrap();
+        assert_eq!(
+            new,
+            "{\n  \"packages\": {\n    \"a\": \"1.0.0\",\n    \"b\": \"^2.0.0\"\n  },\n  \"name\": \"p\"\n}"
+        );
+        let added = ConfigFile::add(&new, "c", "~1.2.0".into()).unwrap();
+        let added = ConfigFile::add(&added, "a", "1.1.0".into()).unwrap();
+        assert_eq!(ConfigFile::names(&added).unwrap(), ["a", "b", "c"]);
+        assert!(added.contains(r#""a": "1.1.0""#) && added.contains(r#""c": "~1.2.0""#));
+    }
+
+    #[tokio::test]
+    async fn minimal() {
+        let c = crate::test_utils::mktemp().await.2;
+        let resolved = |txt: &str| {
+            let c = c.clone();
+            let txt = txt.to_string();
+            async move {
+                let cfg = ConfigFile::new(&txt, c).await;
+                cfg.graph.get(cfg.graph.roots()[0]).to_string()
+            }
+        };
+        assert_eq!(
+            resolved(r#"{"packages": {"ms": "^2.0.5"}}"#).await,
+            "ms@2.1.3"
+        );
+        let txt = r#"{"packages": {"ms": "^2.0.5"}, "resolution": "minimal"}"#;
+        assert_eq!(resolved(txt).await, "ms@2.1.0");
+        let printed = ConfigFile::new(&txt.into(), c.clone())
+            .await
+            .print(ConfigType::JSON);
+        assert!(printed.contains(r#""resolution": "minimal""#), "{printed}");
+        // a wants c at ^1.0.0, and b at ^1.2.0: both get 1.2.0, which is the oldest b takes
+        use crate::test_utils::known;
+        for v in ["1.0.0", "1.1.0", "1.2.0", "1.3.0"] {
+            known(&c, "c", v, &[]);
+        }
+        known(&c, "a", "1.0.0", &["c ^1.0.0"]);
+        known(&c, "b", "1.0.0", &["c ^1.2.0"]);
+        let cfg = ConfigFile::new(
+            &r#"{"packages": {"a": "^1", "b": "^1"}, "resolution": "minimal"}"#.into(),
+            c.clone(),
+        )
+        .await;
+        let mut all = cfg
+            .graph
+            .iter()
+            .map(|(_, p)| p.to_string())
+            .collect::<Vec<_>>();
+        all.sort();
+        assert_eq!(all, ["a@1.0.0", "b@1.0.0", "c@1.2.0"]);
+    }
+
+    #[tokio::test]
+    async fn duplicates() {
+        let c = crate::test_utils::mktemp().await.2.metadata_only();
+        // @bendn/test@2.0.10 wants @bendn/gdcli@1.2.5
+        let cfg = |duplicates: &str| {
+            let txt = format!(
+                r#"{{"packages": {{"@bendn/test": "2.0.10", "@bendn/gdcli": "1.0.0"}}{duplicates}}}"#
+            );
+            let c = c.clone();
+            async move { ConfigFile::parse(&txt, ConfigType::JSON, c).await }
+        };
+        let versions = |cfg: ConfigFile| {
+            let mut v = cfg
+                .graph
+                .iter()
+                .filter(|(_, p)| p.name == "@bendn/gdcli")
+                .map(|(_, p)| p.manifest.version.to_string())
+                .collect::<Vec<_>>();
+            v.sort();
+            v
+        };
+        assert_eq!(versions(cfg("").await.unwrap()), ["1.0.0", "1.2.5"]);
+        // the config is never overridden
+        let e = cfg(r#", "duplicates": "highest-wins""#)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            e.contains("@bendn/gdcli is 1.0.0 in the config, but wanted at 1.0.0 (by the config), 1.2.5 (by @bendn/test@2.0.10)"),
+            "{e}"
+        );
+        let e = cfg(r#", "duplicates": "error""#)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            e.contains("@bendn/gdcli at 1.0.0 (by the config) and 1.2.5 (by @bendn/test@2.0.10)"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn highest_wins() {
+        let p = |name: &str, version: &str, deps: Vec<(&str, Package)>| {
+            Package::from_manifest(
+                Manifest {
+                    version: Version::new(version).parse().unwrap(),
+                    ranges: deps
+                        .iter()
+                        .map(|(r, d)| (d.name.clone(), r.to_string()))
+                        .collect(),
+                    dependencies: deps.into_iter().map(|(_, d)| d).collect(),
+                    ..Default::default()
+                },
+                name.into(),
+            )
+        };
+        let c = |v| p("c", v, vec![]);
+        // b wants c at ^1 and got 1.0.0 (like it was remembered), d wants it at >=1.1, and got 1.2.0
+        let mut roots = [
+            p("b", "1.0.0", vec![("^1", c("1.0.0"))]),
+            p("d", "1.0.0", vec![(">=1.1", c("1.2.0"))]),
+        ];
+        Duplicates::HighestWins.apply(&mut roots).unwrap();
+        assert_eq!(roots[0].manifest.dependencies[0].to_string(), "c@1.2.0");
+        // nothing satisfies both
+        let mut roots = [
+            p("b", "1.0.0", vec![("^1", c("1.0.0"))]),
+            p("d", "1.0.0", vec![("^2", c("2.0.0"))]),
+        ];
+        let e = Duplicates::HighestWins
+            .apply(&mut roots)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.contains("no version of c is in all of ^1 (by b@1.0.0), ^2 (by d@1.0.0)"),
+            "{e}"
+        );
+        // the config wants c at 1.0.0, which d doesnt take
+        let mut roots = [c("1.0.0"), p("d", "1.0.0", vec![(">=1.1", c("1.2.0"))])];
+        let e = Duplicates::HighestWins
+            .apply(&mut roots)
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("c is 1.0.0 in the config"), "{e}");
+        // but it wins when it does
+        let mut roots = [c("1.1.0"), p("d", "1.0.0", vec![(">=1.1", c("1.2.0"))])];
+        Duplicates::HighestWins.apply(&mut roots).unwrap();
+        assert_eq!(roots[1].manifest.dependencies[0].to_string(), "c@1.1.0");
+    }
+
+    #[tokio::test]
+    async fn names() {
+        let c = crate::test_utils::mktemp().await.2.metadata_only();
+        let parse = |txt: &'static str| ConfigFile::parse(txt, ConfigType::JSON, c.clone());
+        let cfg = parse(r#"{"packages": {" @Bendn/test ": "2.0.10"}}"#)
+            .await
+            .unwrap();
+        assert_eq!(cfg.graph.get(cfg.graph.roots()[0]).name, "@bendn/test");
+        let e = parse(r#"{"packages": {"@bendn/te st": "2.0.10"}}"#)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            e.contains("@bendn/te st in the config: name @bendn/te st cant contain ' '"),
+            "{e}"
+        );
+    }
+
+    #[tokio::test]
+    async fn aliases() {
+        let c = crate::test_utils::mktemp().await.2.metadata_only();
+        let txt = r#"{"packages": {"@bendn/test": "2.0.10", "old": "npm:@bendn/test@~2.0.0"}}"#;
+        let cfg = ConfigFile::new(&txt.into(), c).await;
+        let old = cfg
+            .graph
+            .roots()
+            .iter()
+            .map(|&id| cfg.graph.get(id))
+            .find(|p| p.alias.is_some())
+            .unwrap();
+        assert_eq!(old.to_string(), "old (@bendn/test@2.0.10)");
+        assert_eq!(old.download_dir(Path::new("")), Path::new("addons/old"));
+        assert!(cfg.overlaps().is_empty());
+        let printed = cfg.print(ConfigType::JSON);
+        assert!(
+            printed.contains(r#""old": "npm:@bendn/test@2.0.10""#),
+            "{printed}"
+        );
+        let new = ConfigFile::rewrite(txt, |name, old| {
+            assert_eq!(name, "@bendn/test");
+            (old == "~2.0.0").then(|| "^2.0.0".into())
+        })
+        .unwrap();
+        assert!(new.contains(r#""old": "npm:@bendn/test@^2.0.0""#), "{new}");
+    }
+
+    #[tokio::test]
+    async fn install_dir() {
+        let c = crate::test_utils::mktemp().await.2;
+        let txt = r#"{"packages": {"@bendn/test": "2.0.10"}, "install_dir": "third_party"}"#;
+        let mut cfg = ConfigFile::new(&txt.into(), c).await;
+        assert_eq!(cfg.install_dir(), Path::new("third_party"));
+        assert_eq!(
+            ConfigFile::install_dir_of(txt),
+            Some(PathBuf::from("third_party"))
+        );
+        assert!(cfg
+            .graph
+            .iter()
+            .all(|(_, p)| p.download_dir(Path::new("")).starts_with("third_party")));
+        assert!(cfg.set_install_dir("../elsewhere".into()).is_err());
+        assert!(cfg.set_install_dir("/abs".into()).is_err());
+        assert!(cfg.overlaps().is_empty());
+        cfg.set_install_dir("vendor".into()).unwrap();
+        cfg.set_dir_template("{name}".into()).unwrap();
+        let root = cfg.graph.get(cfg.graph.roots()[0]);
+        assert_eq!(root.download_dir(Path::new("")), Path::new("vendor/test"));
+        assert!(cfg.set_dir_template("{scope}".into()).is_err());
+        assert!(cfg.set_dir_template("../{name}".into()).is_err());
+        assert!(cfg
+            .print(ConfigType::JSON)
+            .contains(r#""install_dir": "vendor""#));
+    }
+
+    #[cfg(feature = "toml")]
+    #[tokio::test]
+    async fn dir() {
+        let c = crate::test_utils::mktemp().await.2;
+        let txt = "[packages]\n\"@bendn/test\" = { version = \"2.0.10\", dir = \"t\" }\n";
+        let cfg = ConfigFile::new(&txt.into(), c.clone()).await;
+        let p = cfg.graph.get(cfg.graph.roots()[0]);
+        assert_eq!(p.download_dir(Path::new("")), Path::new("addons/t"));
+        let dep = cfg.graph.get(cfg.graph.deps(cfg.graph.roots()[0])[0]);
+        assert!(dep.dir.is_none());
+        let both = "[packages]\n\"@bendn/test\" = { version = \"2.0.10\", dir = \"same\" }\n\"@bendn/gdcli\" = { version = \"1.2.5\", dir = \"same\" }\n";
+        let overlapping = ConfigFile::new(&both.into(), c.clone()).await;
+        let overlaps = overlapping.overlaps();
+        assert_eq!(overlaps.len(), 1, "{overlaps:?}");
+        let printed = cfg.print(ConfigType::TOML);
+        assert!(printed.contains("dir = 't'"), "{printed}");
+        let new = ConfigFile::rewrite(txt, |_, _| Some("^2.0.10".into())).unwrap();
+        assert!(new.contains("version = '^2.0.10'"), "{new}");
+        let bad = "[packages]\n\"@bendn/test\" = { version = \"2.0.10\", dir = \"../t\" }\n";
+        assert!(ConfigFile::parse(bad, ConfigType::TOML, c.clone())
+            .await
+            .is_err());
+        let sub =
+            "[packages]\n\"@bendn/test\" = { version = \"2.0.10\", subdir = \"./addons/test\" }\n";
+        let cfg = ConfigFile::new(&sub.into(), c.clone()).await;
+        let f = &cfg.graph.get(cfg.graph.roots()[0]).filter;
+        assert_eq!(f.map(Path::new("addons/test/a.gd")), Some("a.gd".into()));
+        assert_eq!(f.map(Path::new("README.md")), None);
+        assert!(cfg
+            .print(ConfigType::TOML)
+            .contains("subdir = 'addons/test'"));
+        let bad = "[packages]\n\"@bendn/test\" = { version = \"2.0.10\", subdir = \"../t\" }\n";
+        assert!(ConfigFile::parse(bad, ConfigType::TOML, c).await.is_err());
+    }
 }
@@ -0,0 +1,122 @@
+use super::ParsedConfig;
+
+use anyhow::Result;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::fmt;
+
+/// A json value: as it was written, or changed.
+enum Entry {
+    Kept(Box<RawValue>),
+    Changed(Value),
+}
+
+impl Serialize for Entry {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Kept(raw) => raw.serialize(s),
+            Self::Changed(v) => v.serialize(s),
+        }
+    }
+}
+
+/// A json object, in the order it was written.
+struct Object(Vec<(String, Entry)>);
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct V;
+        impl<'de> Visitor<'de> for V {
+            type Value = Object;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a json object")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+                let mut entries = vec![];
+                while let Some((k, v)) = map.next_entry::<String, Box<RawValue>>()? {
+                    entries.push((k, Entry::Kept(v)));
+                }
+                Ok(Object(entries))
+            }
+        }
+        d.deserialize_map(V)
+    }
+}
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+/// Writes `cfg` over the json config `contents`, so everything else in it (like the rest of a package.json) stays as it was, where it was.
+/// The packages go where they were read from (`dependencies`, in a package.json). None if `contents` isnt plain json.
+pub fn merge(contents: &str, cfg: &ParsedConfig) -> Result<Option<String>> {
+    let Ok(mut old) = serde_json::from_str::<Object>(contents) else {
+        return Ok(None);
+    };
+    let has = |old: &Object, k: &str| old.0.iter().any(|(key, _)| key == k);
+    let packages = if !has(&old, "packages") && has(&old, "dependencies") {
+        "dependencies"
+    } else {
+        "packages"
+    };
+    let Value::Object(new) = serde_json::to_value(cfg)? else {
+        unreachable!("configs are objects")
+    };
+    for (k, v) in new {
+        let k = if k == "packages" { packages.into() } else { k };
+        match old.0.iter_mut().find(|(key, _)| *key == k) {
+            Some((_, e)) => {
+                // unchanged values keep their formatting too
+                let same = matches!(e, Entry::Kept(raw)
+                    if serde_json::from_str::<Value>(raw.get()).ok().as_ref() == Some(&v));
+                if !same {
+                    *e = Entry::Changed(v);
+                }
+            }
+            None => old.0.push((k, Entry::Changed(v))),
+        }
+    }
+    let mut merged = serde_json::to_string_pretty(&old)?;
+    if contents.ends_with('\n') {
+        merged.push('\n');
+    }
+    Ok(Some(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_file::ConfigFile;
+
+    #[test]
+    fn package_json() {
+        let pkg = r#"{
+  "name": "game",
+  "scripts": { "build": "godot --export" },
+  "dependencies": {
+    "@bendn/test": "^2.0.0"
+  },
+  "private": true
+}"#;
+        let added = ConfigFile::add(pkg, "@bendn/gdcli", "^1.2.5".into()).unwrap();
+        assert_eq!(
+            added,
+            r#"{
+  "name": "game",
+  "scripts": { "build": "godot --export" },
+  "dependencies": {
+    "@bendn/gdcli": "^1.2.5",
+    "@bendn/test": "^2.0.0"
+  },
+  "private": true
+}"#
+        );
+        let removed = ConfigFile::remove(&added, &["@bendn/gdcli".into()]).unwrap();
+        assert!(!removed.contains("gdcli"));
+        assert!(removed.contains(r#""private": true"#));
+        assert!(ConfigFile::remove(pkg, &["nope".into()]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use super::{from_str, ConfigType, Spec};
+use crate::package::is_tag;
+
+use anyhow::{bail, Result};
+use serde::de::{DeserializeOwned, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+/// The keys of a [super::ParsedConfig].
+const KNOWN: &[&str] = &[
+    "packages",
+    "dependencies",
+    "name",
+    "version",
+    "install_dir",
+    "dir_template",
+    "exclude",
+    "symlink",
+    "godot",
+    "resolution",
+    "duplicates",
+    "allow_hooks",
+    "vendor",
+    "strict",
+];
+
+/// A map, in order, with the keys that are there twice (a map keeps the last).
+struct Pairs<V>(Vec<(String, V)>);
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for Pairs<V> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct PairsVisitor<V>(PhantomData<V>);
+        impl<'de, V: Deserialize<'de>> Visitor<'de> for PairsVisitor<V> {
+            type Value = Pairs<V>;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut out = vec![];
+                while let Some(e) = map.next_entry()? {
+                    out.push(e);
+                }
+                Ok(Pairs(out))
+            }
+        }
+        d.deserialize_map(PairsVisitor(PhantomData))
+    }
+}
+
+#[derive(Deserialize)]
+struct Sections {
+    packages: Option<Pairs<Spec>>,
+    dependencies: Option<Pairs<Spec>>,
+}
+
+fn parse<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    let Some(t) = ConfigType::detect(contents) else {
+        bail!("the config isnt any of {:?}", ConfigType::ALL);
+    };
+    from_str(contents, t)
+}
+
+/// `range`, written the one way: without spaces around it, `v`s, or leading zeros.
+fn canonical(range: &str) -> String {
+    range
+        .split_whitespace()
+        .map(|token| {
+            let ops = token.len() - token.trim_start_matches(['^', '~', '<', '>', '=']).len();
+            let (ops, v) = token.split_at(ops);
+            let v = v.strip_prefix(['v', 'V']).unwrap_or(v);
+            let v = v
+                .split('.')
+                .map(|part| match part.bytes().all(|b| b.is_ascii_digit()) {
+                    true => match part.trim_start_matches('0') {
+                        "" if !part.is_empty() => "0",
+                        p => p,
+                    },
+                    false => part,
+                })
+                .collect::<Vec<_>>()
+                .join(".");
+            format!("{ops}{v}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks the config `contents` harder than parsing does: no unknown keys (like `dependancies`),
+/// no package wanted twice, and only canonical versions (`1.2.0`, not `v1.2.0`).
+/// A package.json (a config with `dependencies`) has keys of its own, so those are let be.
+/// The problems, each with the key or package its about (to find its line with [crate::annotate::line]).
+pub fn check(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut problems = vec![];
+    let keys = parse::<Pairs<IgnoredAny>>(contents)?.0;
+    let mut seen = HashSet::new();
+    for (key, _) in &keys {
+        if !seen.insert(key.as_str()) {
+            problems.push((key.clone(), format!("{key} is there twice")));
+        }
+    }
+    if !seen.contains("dependencies") {
+        for (key, _) in keys.iter().filter(|(k, _)| !KNOWN.contains(&k.as_str())) {
+            let near = KNOWN
+                .iter()
+                .map(|k| (strsim::levenshtein(key, k), k))
+                .filter(|(d, _)| *d <= 3)
+                .min();
+            let message = match near {
+                Some((_, k)) => format!("{key} is not a setting (did you mean {k}?)"),
+                None => format!("{key} is not a setting"),
+            };
+            problems.push((key.clone(), message));
+        }
+    }
+    let sections = parse::<Sections>(contents)?;
+    let wanted = [sections.packages, sections.dependencies]
+        .into_iter()
+        .flatten()
+        .flat_map(|p| p.0);
+    let mut names = HashSet::new();
+    for (name, spec) in wanted {
+        if !names.insert(name.clone()) {
+            problems.push((name.clone(), format!("{name} is wanted twice")));
+        }
+        let (real, range) = spec.resolve(&name);
+        if real.starts_with("http") || range.starts_with("http") || is_tag(range) {
+            continue;
+        }
+        let c = canonical(range);
+        if c != range {
+            let message = format!("{name} wants {range:?}, which should be written {c:?}");
+            problems.push((name.clone(), message));
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict() {
+        assert_eq!(canonical(" v1.02.0 "), "1.2.0");
+        assert_eq!(canonical(">=V1.0.0  <2"), ">=1.0.0 <2");
+        assert_eq!(canonical("^2.0.0-beta.1 || 3.x"), "^2.0.0-beta.1 || 3.x");
+        assert_eq!(
+            check(r#"{"packages": {"@bendn/test": "^2.0.10", "old": "npm:@bendn/test@2.0.5"}, "strict": true}"#)
+                .unwrap(),
+            []
+        );
+        // package.jsons have more
+        assert_eq!(
+            check(r#"{"name": "x", "scripts": {}, "dependencies": {"@bendn/test": "latest"}}"#)
+                .unwrap(),
+            []
+        );
+        let p = check(
+            r#"{"dependancies": {}, "packages": {"@bendn/test": "v2.0.10", "@bendn/test": "2.0.10", "ms": "old"}}"#,
+        )
+        .unwrap();
+        let problem = |key: &str, message: &str| (key.to_owned(), message.to_owned());
+        assert_eq!(
+            p,
+            [
+                problem(
+                    "dependancies",
+                    "dependancies is not a setting (did you mean dependencies?)"
+                ),
+                problem(
+                    "@bendn/test",
+                    r#"@bendn/test wants "v2.0.10", which should be written "2.0.10""#
+                ),
+                problem("@bendn/test", "@bendn/test is wanted twice"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use similar::{ChangeTag, TextDiff};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+type Files = BTreeMap<PathBuf, Vec<u8>>;
+
+/// The contents of a file, or none if its not text.
+fn text(bytes: Option<&Vec<u8>>) -> Option<&str> {
+    bytes.map_or(Some(""), |b| std::str::from_utf8(b).ok())
+}
+
+/// Every path in either version, with both versions of the file, if they differ.
+fn changed<'a>(
+    old: &'a Files,
+    new: &'a Files,
+) -> impl Iterator<Item = (&'a PathBuf, Option<&'a Vec<u8>>, Option<&'a Vec<u8>>)> {
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|p| (p, old.get(p), new.get(p)))
+        .filter(|(_, o, n)| o != n)
+}
+
+/// A unified diff between two versions of a package.
+pub fn unified(old: &Files, new: &Files, old_name: &str, new_name: &str) -> String {
+    let mut out = String::new();
+    for (path, o, n) in changed(old, new) {
+        let p = path.display();
+        match (text(o), text(n)) {
+            (Some(o), Some(n)) => {
+                write!(
+                    out,
+                    "{}",
+                    TextDiff::from_lines(o, n)
+                        .unified_diff()
+                        .header(&format!("{old_name}/{p}"), &format!("{new_name}/{p}"))
+                )
+                .unwrap();
+            }
+            _ => writeln!(out, "Binary files {old_name}/{p} and {new_name}/{p} differ").unwrap(),
+        }
+    }
+    out
+}
+
+/// A summary of the lines added and removed, per file.
+pub fn stat(old: &Files, new: &Files) -> String {
+    let mut rows = vec![];
+    for (path, o, n) in changed(old, new) {
+        let change = match (text(o), text(n)) {
+            (Some(o), Some(n)) => {
+                let diff = TextDiff::from_lines(o, n);
+                let count = |tag| diff.iter_all_changes().filter(|c| c.tag() == tag).count();
+                (count(ChangeTag::Insert), count(ChangeTag::Delete))
+            }
+            _ => (0, 0),
+        };
+        rows.push((
+            path.display().to_string(),
+            change,
+            text(o).is_none() || text(n).is_none(),
+        ));
+    }
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    let (mut ins, mut del) = (0, 0);
+    for (path, (i, d), binary) in &rows {
+        if *binary {
+            writeln!(out, " {path:width$} | Bin").unwrap();
+        } else {
+            writeln!(out, " {path:width$} | +{i} -{d}").unwrap();
+        }
+        ins += i;
+        del += d;
+    }
+    write!(
+        out,
+        " {} file{} changed, {ins} insertion{}(+), {del} deletion{}(-)",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" },
+        if ins == 1 { "" } else { "s" },
+        if del == 1 { "" } else { "s" },
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn versions() {
+        let c = crate::test_utils::mktemp().await.2;
+        let old = Package::new("@bendn/test".into(), "2.0.10".into(), c.clone())
+            .await
+            .unwrap()
+            .files(c)
+            .await
+            .unwrap();
+        assert_eq!(old.len(), 5);
+        assert_eq!(
+            stat(&old, &old),
+            " 0 files changed, 0 insertions(+), 0 deletions(-)"
+        );
+        let mut new = old.clone();
+        new.insert("new.gd".into(), b"extends Node\n".to_vec());
+        new.insert("icon.png".into(), vec![0xff, 0xfe]);
+        assert_eq!(
+            stat(&old, &new),
+            " icon.png | Bin\n new.gd   | +1 -0\n 2 files changed, 1 insertion(+), 0 deletions(-)"
+        );
+        let u = unified(&old, &new, "a", "b");
+        assert!(u.contains("--- a/new.gd\n+++ b/new.gd\n"), "{u}");
+        assert!(
+            u.contains("Binary files a/icon.png and b/icon.png differ"),
+            "{u}"
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use crate::annotate::Level;
+use crate::config_file::ConfigFile;
+use crate::graph::Graph;
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// A native library (or a library it needs) a GDExtension (or GDNative library) of an installed package loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Library {
+    /// Like `@bendn/test@2.0.10`.
+    pub package: String,
+    /// The feature tags it is for, like `windows.debug.x86_64` (or `X11.64`, for GDNative).
+    pub features: String,
+    /// Its path in the project, without the `res://`.
+    pub path: String,
+    /// The `.gdextension` (or `.gdnlib`) that says it, in the project.
+    pub config: String,
+}
+
+/// A library an export preset leaves out.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Gap {
+    /// The section of the preset, like `preset.0`.
+    pub section: String,
+    /// The name of the preset, like `Windows Desktop`.
+    pub preset: String,
+    pub library: Library,
+}
+
+/// The libraries of the `.gdextension` (`[libraries]` and `[dependencies]`) or `.gdnlib` (`[entry]` and `[dependencies]`) `config` says.
+fn libraries_in(config: &str, file: &str, package: &str) -> Vec<Library> {
+    let mut section = "";
+    let mut out = vec![];
+    for l in config.lines().map(str::trim) {
+        if l.starts_with('[') {
+            section = l;
+            continue;
+        }
+        let Some((key, value)) = l.split_once('=') else {
+            continue;
+        };
+        if !matches!(section, "[libraries]" | "[entry]" | "[dependencies]") {
+            continue;
+        }
+        // libraries are "res://a.dll", dependencies { "res://a.dll": "" } (or [ "res://a.dll" ])
+        out.extend(
+            value
+                .split('"')
+                .skip(1)
+                .step_by(2)
+                .filter_map(|v| v.strip_prefix("res://"))
+                .map(|path| Library {
+                    package: package.to_owned(),
+                    features: key.trim().trim_matches('"').to_owned(),
+                    path: path.to_owned(),
+                    config: file.to_owned(),
+                }),
+        );
+    }
+    out
+}
+
+/// The `.gdextension` and `.gdnlib` files in `dir` (and in the folders in it), with what they say.
+fn configs(dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    for e in entries.filter_map(|e| e.ok()) {
+        let p = e.path();
+        if p.is_dir() {
+            configs(&p, out);
+        } else if p
+            .extension()
+            .is_some_and(|x| x == "gdextension" || x == "gdnlib")
+        {
+            if let Ok(config) = read_to_string(&p) {
+                out.push((p, config));
+            }
+        }
+    }
+}
+
+/// The native libraries the installed packages of `graph` (in the project `cwd`) load.
+pub fn libraries(graph: &Graph, cwd: &Path) -> Vec<Library> {
+    let mut out = vec![];
+    for (_, p) in graph.iter().filter(|(_, p)| p.is_installed(cwd)) {
+        let mut found = vec![];
+        configs(&p.download_dir(cwd), &mut found);
+        for (file, c) in found {
+            out.extend(libraries_in(&c, &res(&file, cwd), &p.to_string()));
+        }
+    }
+    out.dedup();
+    out
+}
+
+/// The path of `file` in the project `cwd`, like a `res://` path without the `res://`.
+fn res(file: &Path, cwd: &Path) -> String {
+    let rel = file.strip_prefix(cwd).unwrap_or(file);
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+/// The feature tags of the platform of an export preset (like `Windows Desktop`, or `Linux/X11`),
+/// in godot 4 (`linux`) and godot 3 (`X11`) terms.
+fn features(platform: &str) -> &'static [&'static str] {
+    let platform = platform.to_lowercase();
+    if platform.contains("mac") || platform.contains("osx") {
+        &["macos", "osx"]
+    } else if platform.contains("windows") {
+        &["windows"]
+    } else if platform.contains("linux") || platform.contains("x11") || platform.contains("bsd") {
+        &["linux", "x11", "bsd"]
+    } else if platform.contains("android") {
+        &["android"]
+    } else if platform.contains("ios") {
+        &["ios"]
+    } else if platform.contains("web") || platform.contains("html5") {
+        &["web", "html5"]
+    } else {
+        &[]
+    }
+}
+
+/// Wether `library` is for one of the feature tags `features` (of a platform).
+fn is_for(library: &Library, features: &[&str]) -> bool {
+    library
+        .features
+        .split('.')
+        .any(|f| features.contains(&f.to_lowercase().as_str()))
+}
+
+/// A `[preset.N]` of an export_presets.cfg: its section, name, platform, and include and exclude filters.
+struct Preset<'a> {
+    section: &'a str,
+    name: &'a str,
+    platform: &'a str,
+    filters: Vec<&'a str>,
+    excludes: Vec<&'a str>,
+}
+
+fn presets<'a>(cfg: &'a str) -> Vec<Preset<'a>> {
+    let mut out: Vec<Preset> = vec![];
+    let quoted = |v: &'a str| v.trim().trim_matches('"');
+    let filters = |v: &'a str| {
+        quoted(v)
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect()
+    };
+    for l in cfg.lines().map(str::trim) {
+        if let Some(section) = l.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            // [preset.0], not [preset.0.options]
+            if section
+                .strip_prefix("preset.")
+                .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+            {
+                out.push(Preset {
+                    section,
+                    name: "",
+                    platform: "",
+                    filters: vec![],
+                    excludes: vec![],
+                });
+            }
+            continue;
+        }
+        let Some(p) = out.last_mut() else {
+            continue;
+        };
+        if let Some((key, value)) = l.split_once('=') {
+            match key.trim() {
+                "name" => p.name = quoted(value),
+                "platform" => p.platform = quoted(value),
+                "include_filter" => p.filters = filters(value),
+                "exclude_filter" => p.excludes = filters(value),
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+/// Wether the include filter `filter` (a glob, like `addons/*`) takes in `path`.
+fn includes(filter: &str, path: &str) -> bool {
+    let filter = filter.trim_start_matches("res://");
+    filter == path || glob::Pattern::new(filter).is_ok_and(|p| p.matches(path))
+}
+
+/// The libraries each preset of the export_presets.cfg `cfg` is for (by its platform) that its include filters leave out.
+pub fn gaps(cfg: &str, libraries: &[Library]) -> Vec<Gap> {
+    let mut out = vec![];
+    for p in presets(cfg) {
+        let features = features(p.platform);
+        for l in libraries {
+            let ours = is_for(l, features);
+            // debug and release builds can be the same file
+            let seen = out
+                .iter()
+                .any(|g: &Gap| g.section == p.section && g.library.path == l.path);
+            if ours && !seen && !p.filters.iter().any(|f| includes(f, &l.path)) {
+                out.push(Gap {
+                    section: p.section.to_owned(),
+                    preset: p.name.to_owned(),
+                    library: l.clone(),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// `cfg` (an export_presets.cfg) with the libraries of `gaps` in the include filters of their presets.
+pub fn fix(cfg: &str, gaps: &[Gap]) -> String {
+    let mut lines = cfg.lines().map(str::to_owned).collect::<Vec<_>>();
+    let mut sections = gaps.iter().map(|g| g.section.as_str()).collect::<Vec<_>>();
+    sections.dedup();
+    for section in sections {
+        let header = format!("[{section}]");
+        let Some(start) = lines.iter().position(|l| l.trim() == header) else {
+            continue;
+        };
+        let end = lines[start + 1..]
+            .iter()
+            .position(|l| l.trim_start().starts_with('['))
+            .map_or(lines.len(), |i| start + 1 + i);
+        let add = gaps
+            .iter()
+            .filter(|g| g.section == section)
+            .map(|g| g.library.path.as_str())
+            .collect::<Vec<_>>();
+        let at = (start + 1..end).find(|&i| lines[i].trim_start().starts_with("include_filter="));
+        let old = at.map_or("", |i| {
+            lines[i]
+                .trim()
+                .trim_start_matches("include_filter=")
+                .trim_matches('"')
+        });
+        let mut filters = old
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect::<Vec<_>>();
+        filters.extend(add);
+        let line = format!("include_filter=\"{}\"", filters.join(", "));
+        match at {
+            Some(i) => lines[i] = line,
+            None => lines.insert(start + 1, line),
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Something that would make an export broken (or ship what it shouldnt), found by [check].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub level: Level,
+    /// The file it is about: export_presets.cfg or project.godot.
+    pub file: &'static str,
+    /// What it is about in that file (to find its line with), like the name of the preset.
+    pub about: String,
+    pub message: String,
+}
+
+/// The files in `dir` (and in the folders in it), as paths in the project `cwd` (see [res]).
+fn files(dir: &Path, cwd: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    for e in entries.filter_map(|e| e.ok()) {
+        let p = e.path();
+        if p.is_dir() {
+            files(&p, cwd, out);
+        } else {
+            out.push(res(&p, cwd));
+        }
+    }
+}
+
+/// Checks the installed packages of `cfg` are ready to export with the presets of the export_presets.cfg `presets`
+/// (only the one named `only`, if given), in the project `cwd` (with the project.godot `project`):
+/// - every GDExtension has a native library for the platform of every preset, and it is there
+/// - no dev package (see [ConfigFile::dev_only]) is autoloaded (which would run it in the game), or exported
+/// - no file of the other packages is left out by the exclude filter of a preset
+pub fn check(
+    cfg: &ConfigFile,
+    presets: &str,
+    project: &str,
+    cwd: &Path,
+    only: Option<&str>,
+) -> Vec<Finding> {
+    let g = &cfg.graph;
+    let dev = cfg.dev_only();
+    let presets = self::presets(presets)
+        .into_iter()
+        .filter(|p| only.is_none_or(|o| o == p.name))
+        .collect::<Vec<_>>();
+    let mut out = vec![];
+    let finding = |level, file, about: &str, message| Finding {
+        level,
+        file,
+        about: about.to_owned(),
+        message,
+    };
+    let libraries = libraries(g, cwd);
+    for p in &presets {
+        let features = features(p.platform);
+        if features.is_empty() {
+            continue;
+        }
+        let mut configs = libraries
+            .iter()
+            .map(|l| (&l.package, &l.config))
+            .collect::<Vec<_>>();
+        configs.dedup();
+        for (package, config) in configs {
+            let ours = libraries
+                .iter()
+                .filter(|l| &l.config == config && is_for(l, features))
+                .collect::<Vec<_>>();
+            if ours.is_empty() {
+                out.push(finding(
+                    Level::Error,
+                    "export_presets.cfg",
+                    p.name,
+                    format!(
+                        "{} exports for {}, but {config} (of {package}) has no native library for it",
+                        p.name, p.platform
+                    ),
+                ));
+            }
+            for l in ours.iter().filter(|l| !cwd.join(&l.path).is_file()) {
+                out.push(finding(
+                    Level::Error,
+                    "export_presets.cfg",
+                    p.name,
+                    format!(
+                        "{} exports for {}, but {} (the {} library of {package}) isnt there",
+                        p.name, p.platform, l.path, l.features
+                    ),
+                ));
+            }
+        }
+    }
+    // autoloads look like Name="*res://addons/x/x.gd"
+    let mut section = "";
+    let autoloads = project
+        .lines()
+        .map(str::trim)
+        .filter_map(|l| {
+            if l.starts_with('[') {
+                section = l;
+            }
+            let (name, path) = l.split_once('=')?;
+            let path = path.trim().trim_matches('"').trim_start_matches('*');
+            (section == "[autoload]").then(|| (name.trim(), path.strip_prefix("res://")))
+        })
+        .filter_map(|(name, path)| Some((name, path?)))
+        .collect::<Vec<_>>();
+    for (id, p) in g.iter().filter(|(_, p)| p.is_installed(cwd)) {
+        let dir = res(&p.download_dir(cwd), cwd);
+        let mut owned = vec![];
+        files(&p.download_dir(cwd), cwd, &mut owned);
+        if dev.contains(&id) {
+            for (name, path) in &autoloads {
+                if path.starts_with(&format!("{dir}/")) {
+                    out.push(finding(
+                        Level::Error,
+                        "project.godot",
+                        name,
+                        format!("{p} is a dev package, but project.godot autoloads it ({name}), so exports run it"),
+                    ));
+                }
+            }
+            for preset in &presets {
+                if owned
+                    .iter()
+                    .any(|f| !preset.excludes.iter().any(|e| includes(e, f)))
+                {
+                    out.push(finding(
+                        Level::Warning,
+                        "export_presets.cfg",
+                        preset.name,
+                        format!(
+                            "{} exports {p}, a dev package (add {dir}/* to its exclude filter)",
+                            preset.name
+                        ),
+                    ));
+                }
+            }
+            continue;
+        }
+        for preset in &presets {
+            let left_out = owned
+                .iter()
+                .filter(|f| preset.excludes.iter().any(|e| includes(e, f)))
+                .collect::<Vec<_>>();
+            if let Some(first) = left_out.first() {
+                out.push(finding(
+                    Level::Error,
+                    "export_presets.cfg",
+                    preset.name,
+                    format!(
+                        "the exclude filter of {} leaves out {} file{} of {p}, like {first}",
+                        preset.name,
+                        left_out.len(),
+                        if left_out.len() == 1 { "" } else { "s" }
+                    ),
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports() {
+        let gdextension = "[configuration]\n\nentry_symbol = \"init\"\n\n[libraries]\n\nwindows.debug.x86_64 = \"res://addons/x/bin/x.windows.dll\"\nwindows.release.x86_64 = \"res://addons/x/bin/x.windows.dll\"\nlinux.debug.x86_64 = \"res://addons/x/bin/libx.linux.so\"\n\n[dependencies]\n\nwindows.debug.x86_64 = { \"res://addons/x/bin/dep.dll\" : \"\" }\n";
+        let libs = libraries_in(gdextension, "addons/x/x.gdextension", "x@1.0.0");
+        assert_eq!(
+            libs.iter().map(|l| l.path.as_str()).collect::<Vec<_>>(),
+            [
+                "addons/x/bin/x.windows.dll",
+                "addons/x/bin/x.windows.dll",
+                "addons/x/bin/libx.linux.so",
+                "addons/x/bin/dep.dll"
+            ]
+        );
+        let cfg = "[preset.0]\n\nname=\"Windows Desktop\"\nplatform=\"Windows Desktop\"\ninclude_filter=\"*.json\"\nexclude_filter=\"\"\n\n[preset.0.options]\n\ncustom_template/debug=\"\"\n\n[preset.1]\n\nname=\"Linux\"\nplatform=\"Linux/X11\"\ninclude_filter=\"addons/x/bin/*\"\n";
+        let gaps = gaps(cfg, &libs);
+        assert_eq!(
+            gaps.iter()
+                .map(|g| (g.preset.as_str(), g.library.path.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("Windows Desktop", "addons/x/bin/x.windows.dll"),
+                ("Windows Desktop", "addons/x/bin/dep.dll")
+            ]
+        );
+        let fixed = fix(cfg, &gaps);
+        assert!(fixed.contains("include_filter=\"*.json, addons/x/bin/x.windows.dll, addons/x/bin/dep.dll\"\nexclude_filter"), "{fixed}");
+        assert!(super::gaps(&fixed, &libs).is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_check() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let cwd = t.0.path();
+        let contents = r#"{"packages": {"@bendn/test": "2.0.10", "fork": {"version": "npm:@bendn/test@2.0.10", "dev": true}}}"#;
+        let mut cfg = ConfigFile::new(&contents.into(), c.clone()).await;
+        let ids = cfg.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            cfg.graph
+                .get_mut(id)
+                .download(c.clone(), cwd, Default::default())
+                .await
+                .unwrap();
+        }
+        let dir = cwd.join("addons/@bendn/test");
+        std::fs::write(dir.join("x.gdextension"), "[libraries]\nwindows.debug.x86_64 = \"res://addons/@bendn/test/x.dll\"\nlinux.debug.x86_64 = \"res://addons/@bendn/test/libx.so\"\n").unwrap();
+        std::fs::write(dir.join("x.dll"), "").unwrap();
+        let presets = "[preset.0]\n\nname=\"Windows\"\nplatform=\"Windows Desktop\"\nexclude_filter=\"*.dll, addons/fork/*\"\n\n[preset.1]\n\nname=\"Linux\"\nplatform=\"Linux/X11\"\nexclude_filter=\"\"\n";
+        let project = "[autoload]\n\nFork=\"*res://addons/fork/main.gd\"\n";
+        let found = check(&cfg, presets, project, cwd, None)
+            .into_iter()
+            .map(|f| (f.level, f.message))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Level::Error, "Linux exports for Linux/X11, but addons/@bendn/test/libx.so (the linux.debug.x86_64 library of @bendn/test@2.0.10) isnt there".into()),
+                (Level::Error, "the exclude filter of Windows leaves out 1 file of @bendn/test@2.0.10, like addons/@bendn/test/x.dll".into()),
+                (Level::Error, "fork (@bendn/test@2.0.10) is a dev package, but project.godot autoloads it (Fork), so exports run it".into()),
+                (Level::Warning, "Linux exports fork (@bendn/test@2.0.10), a dev package (add addons/fork/* to its exclude filter)".into()),
+            ]
+        );
+        assert!(check(&cfg, presets, "", cwd, Some("Windows")).len() == 1);
+    }
+}
@@ -0,0 +1,43 @@
+use std::env::{consts::EXE_SUFFIX, split_paths};
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// The executable of the external subcommand `gpm <name>`: `gpm-<name>`, in one of the `path` dirs (`PATH`).
+pub fn find(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let exe = format!("gpm-{name}{EXE_SUFFIX}");
+    split_paths(path)
+        .map(|dir| dir.join(&exe))
+        .find(|p| p.is_file())
+}
+
+/// The one of `commands` `name` is most likely a typo of, if any is near enough.
+pub fn nearest<'a>(name: &str, commands: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    commands
+        .into_iter()
+        .map(|c| (strsim::levenshtein(name, c), c))
+        .filter(|(d, _)| *d <= 2)
+        .min()
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::join_paths;
+
+    #[test]
+    fn finds() {
+        let (a, b) = (
+            tempfile::TempDir::new().unwrap(),
+            tempfile::TempDir::new().unwrap(),
+        );
+        let exe = b.path().join(format!("gpm-foo{EXE_SUFFIX}"));
+        std::fs::write(&exe, "").unwrap();
+        let path = join_paths([a.path(), b.path()]).unwrap();
+        assert_eq!(find("foo", &path), Some(exe));
+        assert_eq!(find("bar", &path), None);
+        let commands = ["update", "purge", "tree"];
+        assert_eq!(nearest("updte", commands), Some("update"));
+        assert_eq!(nearest("deploy", commands), None);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::integrity::Integrity;
+use crate::package::Package;
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Locked {
+    name: String,
+    version: String,
+    integrity: Option<String>,
+}
+
+/// A package [fetch] put in the store.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Fetched {
+    /// Like `@bendn/test@2.0.10`.
+    pub package: String,
+    /// False if it was in the store already.
+    pub downloaded: bool,
+}
+
+/// Puts the tarball of every package in the `lockfile` into the store (and their manifests into the resolution cache),
+/// so installing it afterwards doesnt need the registry. Packages only known by their tarball url are left out.
+pub async fn fetch(lockfile: &str, client: Client) -> Result<Vec<Fetched>> {
+    let locked: Vec<Locked> = serde_json::from_str(lockfile).context("parsing the lock file")?;
+    if client.cache_ref().store().is_none() {
+        bail!("the cache is not on disk");
+    }
+    stream::iter(locked.into_iter().filter(|l| {
+        let url = l.name.starts_with("http");
+        if url {
+            eprintln!(
+                "{:>12} {} is a tarball url, so it cant be fetched",
+                crate::putils::warn(),
+                l.name
+            );
+        }
+        !url
+    }))
+    .map(|l| {
+        let client = client.clone();
+        async move {
+            let p = Package::new(l.name, format!("={}", l.version), client.clone()).await?;
+            let store = client.cache_ref().store().unwrap();
+            let key = p
+                .store_key()
+                .with_context(|| format!("{p} has no checksum"))?;
+            let downloaded = !store.has_tarball(&key);
+            // from the store, if its there
+            let bytes = p.fetch(client.clone(), &ProgressBar::hidden()).await?;
+            let locked = l.integrity.as_deref().map(str::parse::<Integrity>);
+            if !p.matches(&bytes) || locked.is_some_and(|i| !i.is_ok_and(|i| i.check(&bytes))) {
+                bail!("the tarball of {p} doesnt match the lock file");
+            }
+            if downloaded {
+                store.insert_tarball(&key, &bytes)?;
+            }
+            Ok(Fetched {
+                package: p.to_string(),
+                downloaded,
+            })
+        }
+    })
+    .buffered(crate::PARALLEL)
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[tokio::test]
+    async fn warms() {
+        let t = crate::test_utils::mktemp().await;
+        let lockfile = r#"[{"name": "@bendn/gdcli", "tarball": "", "version": "1.2.5"}]"#;
+        let c = crate::mkclient(t.2.registry.clone(), Cache::load(t.0.path().join("cache")));
+        let fetched = fetch(lockfile, c.clone()).await.unwrap();
+        assert_eq!(
+            fetched,
+            [Fetched {
+                package: "@bendn/gdcli@1.2.5".into(),
+                downloaded: true
+            }]
+        );
+        assert!(!fetch(lockfile, c.clone()).await.unwrap()[0].downloaded);
+        let wrong = r#"[{"name": "@bendn/gdcli", "tarball": "", "version": "1.2.5", "integrity": "sha512-AAAA"}]"#;
+        assert!(fetch(wrong, c).await.is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::config_file::ConfigFile;
+
+use std::fs::{read_dir, read_to_string, write};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where the fingerprint of the last successful update lives.
+fn path(install_dir: &Path) -> PathBuf {
+    install_dir.join(".gpm-fingerprint")
+}
+
+/// Fingerprints the config, the lockfile, the installed tree (in `install_dir`), and the dirs of local packages
+/// (see [ConfigFile::local_dirs]): they are installed from what is in them now, so changing them needs an update.
+/// Trees are fingerprinted by file names, sizes, and modification times, not contents, so this is fast.
+pub fn compute(cfg: &str, lockfile: &Path, install_dir: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(cfg.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(read_to_string(lockfile).unwrap_or_default().as_bytes());
+    hasher.update(&[0]);
+    fn walk(dir: &Path, root: &Path, skip: &Path, hasher: &mut blake3::Hasher) -> io::Result<()> {
+        let mut entries = read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for e in entries {
+            let p = e.path();
+            if p == skip {
+                continue;
+            }
+            let meta = e.metadata()?;
+            hasher.update(
+                p.strip_prefix(root)
+                    .unwrap_or(&p)
+                    .to_string_lossy()
+                    .as_bytes(),
+            );
+            if meta.is_dir() {
+                hasher.update(b"/");
+                walk(&p, root, skip, hasher)?;
+            } else {
+                hasher.update(&meta.len().to_le_bytes());
+                let mtime = meta
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0);
+                hasher.update(&mtime.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+    walk(install_dir, install_dir, &path(install_dir), &mut hasher)?;
+    for dir in ConfigFile::local_dirs(cfg) {
+        hasher.update(&[0]);
+        hasher.update(dir.to_string_lossy().as_bytes());
+        // a dir thats gone fails the update, not this
+        let _ = walk(&dir, &dir, &path(install_dir), &mut hasher);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Is the project in the same state as after the last successful update?
+pub fn is_fresh(cfg: &str, lockfile: &Path, install_dir: &Path) -> bool {
+    let Ok(old) = read_to_string(path(install_dir)) else {
+        return false;
+    };
+    matches!(compute(cfg, lockfile, install_dir), Ok(new) if new == old.trim())
+}
+
+/// Remembers the current state of the project.
+pub fn save(cfg: &str, lockfile: &Path, install_dir: &Path) -> io::Result<()> {
+    write(path(install_dir), compute(cfg, lockfile, install_dir)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh() {
+        let t = tempfile::TempDir::new().unwrap();
+        let cwd = t.path();
+        std::fs::create_dir_all(cwd.join("addons/foo")).unwrap();
+        write(cwd.join("addons/foo/main.gd"), "extends Node").unwrap();
+        let lock = cwd.join("godot.lock");
+        let addons = &cwd.join("addons");
+        write(&lock, "[]").unwrap();
+        assert!(!is_fresh("packages: {}", &lock, addons));
+        save("packages: {}", &lock, addons).unwrap();
+        assert!(is_fresh("packages: {}", &lock, addons));
+        assert!(!is_fresh("packages: {foo: 1}", &lock, addons));
+        write(cwd.join("addons/foo/main.gd"), "extends Node2D").unwrap();
+        assert!(!is_fresh("packages: {}", &lock, addons));
+        let local = cwd.join("addon");
+        std::fs::create_dir(&local).unwrap();
+        write(local.join("main.gd"), "extends Node").unwrap();
+        let cfg = format!(r#"{{"packages": {{"file:{}": "*"}}}}"#, local.display());
+        save(&cfg, &lock, addons).unwrap();
+        assert!(is_fresh(&cfg, &lock, addons));
+        write(local.join("main.gd"), "extends Node2D").unwrap();
+        assert!(!is_fresh(&cfg, &lock, addons));
+    }
+}
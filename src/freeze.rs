@@ -0,0 +1,60 @@
+use crate::changes;
+use crate::config_file::ConfigFile;
+use crate::package::range;
+
+use anyhow::Result;
+use semver_rs::{Options, Version};
+
+/// Rewrites every wanted range in the config `contents` to the version the `lockfile` has for it.
+/// Returns the new config, and the packages the lockfile doesnt have (which are left as they were).
+pub fn freeze(contents: &str, lockfile: &str) -> Result<(String, Vec<String>)> {
+    let locked = changes::versions(lockfile);
+    let opts = Options::builder().include_prerelease(true).build();
+    let mut missing = vec![];
+    let new = ConfigFile::rewrite(contents, |name, wanted| {
+        // urls are already as pinned as they get
+        if name.starts_with("http") {
+            return None;
+        }
+        // a package can be locked more than once (as a dependency of something else, too)
+        let r = range::parse(wanted, opts).ok();
+        let v = locked
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|v| Some((Version::new(v).parse().ok()?, v)))
+            .filter(|(v, _)| r.as_ref().is_none_or(|r| r.test(v)))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, v)| v.clone());
+        if v.is_none() {
+            missing.push(name.to_owned());
+        }
+        v
+    })?;
+    Ok((new, missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins() {
+        let lock = r#"[
+            {"name": "a", "tarball": "", "version": "1.2.0+godot4.2"},
+            {"name": "b", "tarball": "", "version": "1.0.0"},
+            {"name": "b", "tarball": "", "version": "2.1.0"}
+        ]"#;
+        let cfg = r#"{"packages": {"a": "^1", "b": "^2.0.0", "c": "*", "old": "npm:b@<2"}}"#;
+        let (new, missing) = freeze(cfg, lock).unwrap();
+        for pinned in [
+            r#""a": "1.2.0+godot4.2""#,
+            r#""b": "2.1.0""#,
+            r#""c": "*""#,
+            r#""old": "npm:b@1.0.0""#,
+        ] {
+            assert!(new.contains(pinned), "{new}");
+        }
+        assert_eq!(missing, ["c"]);
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+/// Marks the git hooks gpm wrote, so they can be replaced (and others are left alone).
+const MARKER: &str = "# written by gpm hooks install-git";
+
+/// Quotes `p` for sh.
+fn quote(p: &Path) -> String {
+    format!("'{}'", p.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// The git hook that has `gpm` check the project in `dir` (with the config `cfg` and lockfile `lock`, relative to it) is up to date.
+pub fn script(gpm: &Path, dir: &Path, cfg: &Path, lock: &Path) -> String {
+    format!(
+        "#!/bin/sh\n{MARKER}\ncd {} && exec {} hooks check --cfg-file {} --lock-file {}\n",
+        quote(dir),
+        quote(gpm),
+        quote(cfg),
+        quote(lock)
+    )
+}
+
+/// Writes the git hook `name` (like `pre-commit`) into `hooks` (the git hooks dir).
+/// Hooks gpm didnt write are only replaced with `force`.
+pub fn install(hooks: &Path, name: &str, script: &str, force: bool) -> Result<PathBuf> {
+    let path = hooks.join(name);
+    if let Ok(old) = read_to_string(&path) {
+        if !force && !old.contains(MARKER) {
+            bail!(
+                "there already is a {name} hook ({}), use --force to replace it",
+                path.display()
+            );
+        }
+    }
+    create_dir_all(hooks).context("making the git hooks dir")?;
+    write(&path, script).with_context(|| format!("writing {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn installs() {
+        let t = tempfile::TempDir::new().unwrap();
+        let s = script(
+            Path::new("gpm"),
+            Path::new("/it's/here"),
+            Path::new("godot.package"),
+            Path::new("godot.lock"),
+        );
+        assert!(s.contains(r"cd '/it'\''s/here' && exec 'gpm' hooks check"));
+        let p = install(t.path(), "pre-commit", &s, false).unwrap();
+        assert_eq!(read_to_string(&p).unwrap(), s);
+        // gpms own hook is replaced, others are not
+        install(t.path(), "pre-commit", &s, false).unwrap();
+        write(t.path().join("pre-push"), "#!/bin/sh\nmake test\n").unwrap();
+        assert!(install(t.path(), "pre-push", &s, false).is_err());
+        install(t.path(), "pre-push", &s, true).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use semver_rs::{Range, Version};
+use std::env::var_os;
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// A godot version like `4.2` (or `4.2.1`), as a full version.
+pub fn parse(v: &str) -> Result<Version> {
+    let v = v.trim().trim_start_matches('v');
+    let full = match v.matches('.').count() {
+        0 => format!("{v}.0.0"),
+        1 => format!("{v}.0"),
+        _ => v.to_string(),
+    };
+    Version::new(&full)
+        .parse()
+        .ok()
+        .filter(|v| !v.is_empty())
+        .with_context(|| format!("{v} is not a godot version (like 4.2)"))
+}
+
+/// The godot version of the project in `dir`, from the features of its project.godot
+/// (`config/features=PackedStringArray("4.2", "Forward Plus")`). Godot 3 projects dont say.
+pub fn project_version(dir: &Path) -> Option<Version> {
+    let project = read_to_string(dir.join("project.godot")).ok()?;
+    let features = project
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("config/features="))?;
+    features
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .find_map(|f| parse(f).ok())
+}
+
+/// The `key` line of the `[section]` of `project` (a project.godot), by line number, and its value.
+fn setting<'a>(project: &'a str, section: &str, key: &str) -> Option<(usize, &'a str)> {
+    let header = format!("[{section}]");
+    let mut current = "";
+    project.lines().enumerate().find_map(|(i, l)| {
+        let l = l.trim();
+        if l.starts_with('[') {
+            current = l;
+        }
+        (current == header)
+            .then(|| l.strip_prefix(key)?.strip_prefix('='))
+            .flatten()
+            .map(|v| (i, v))
+    })
+}
+
+/// The strings of the `key` array of the `[section]` of `project` (a project.godot), like the
+/// `"a", "b"` of `enabled=PackedStringArray("a", "b")`.
+pub fn array(project: &str, section: &str, key: &str) -> Vec<String> {
+    let Some((_, v)) = setting(project, section, key) else {
+        return vec![];
+    };
+    v.split('"').skip(1).step_by(2).map(str::to_owned).collect()
+}
+
+/// `project` (a project.godot) with the `key` array of its `[section]` set to `values`, made if it isnt there.
+/// Godot 4 (config_version=5) writes `PackedStringArray("a", "b")`, godot 3 `PoolStringArray( "a", "b" )`.
+pub fn set_array(project: &str, section: &str, key: &str, values: &[String]) -> String {
+    let quoted = values
+        .iter()
+        .map(|p| format!("\"{p}\""))
+        .collect::<Vec<_>>();
+    let mut lines = project.lines().map(str::to_owned).collect::<Vec<_>>();
+    let old = setting(project, section, key);
+    let godot3 = match old {
+        Some((_, v)) => v.starts_with("PoolStringArray"),
+        None => !project.contains("config_version=5"),
+    };
+    let line = match godot3 {
+        true => format!("{key}=PoolStringArray( {} )", quoted.join(", ")),
+        false => format!("{key}=PackedStringArray({})", quoted.join(", ")),
+    };
+    let header = format!("[{section}]");
+    match old {
+        Some((i, _)) => lines[i] = line,
+        None => match lines.iter().position(|l| l.trim() == header) {
+            Some(i) => lines.insert(i + 1, line),
+            None => {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.extend([header, String::new(), line]);
+            }
+        },
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The godot to run (for GDScript hooks): `GODOT`, or `godot` on the PATH.
+pub fn binary() -> OsString {
+    var_os("GODOT").unwrap_or_else(|| "godot".into())
+}
+
+/// Imports the project in `dir` with the godot [binary] runs (`godot --headless --import`, godot 4.2 and up),
+/// so the `.godot/imported` files and uids of new files are there before the editor (or an export) needs them.
+pub fn import(dir: &Path) -> Result<()> {
+    let status = std::process::Command::new(binary())
+        .args(["--headless", "--import", "--path"])
+        .arg(dir)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .with_context(|| {
+            format!(
+                "Could not run {} to import the project (set GODOT to the godot to run)",
+                binary().to_string_lossy()
+            )
+        })?;
+    anyhow::ensure!(status.success(), "godot --import failed ({status})");
+    Ok(())
+}
+
+/// The version of the godot that [binary] runs, if it runs.
+pub fn installed() -> Option<Version> {
+    let out = std::process::Command::new(binary())
+        .arg("--version")
+        .output()
+        .ok()?;
+    // like 4.2.1.stable.official.b09f793f5
+    let out = String::from_utf8_lossy(&out.stdout);
+    let numbers = out
+        .trim()
+        .split('.')
+        .take_while(|p| p.chars().all(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>();
+    parse(&numbers.join(".")).ok()
+}
+
+/// Wether the godot range a package declares (its `engines.godot`) allows `godot`.
+/// None if it doesnt declare one (or one that doesnt parse).
+pub fn compatible(declared: Option<&str>, godot: &Version) -> Option<bool> {
+    let r = Range::new(declared?).parse().ok()?;
+    Some(r.test(godot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        let t = tempfile::TempDir::new().unwrap();
+        assert!(project_version(t.path()).is_none());
+        std::fs::write(
+            t.path().join("project.godot"),
+            "[application]\n\nconfig/name=\"x\"\nconfig/features=PackedStringArray(\"4.2\", \"Forward Plus\")\n",
+        )
+        .unwrap();
+        let v = project_version(t.path()).unwrap();
+        assert_eq!(v.to_string(), "4.2.0");
+        assert_eq!(compatible(Some(">=4.1"), &v), Some(true));
+        assert_eq!(compatible(Some("3.x"), &v), Some(false));
+        assert_eq!(compatible(None, &v), None);
+    }
+}
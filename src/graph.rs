@@ -0,0 +1,291 @@
+use crate::package::Package;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+/// A stable handle to a package in a [Graph].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct PackageId(usize);
+
+type Key = (String, String, bool, Option<String>);
+
+/// What `gpm graph` renders to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Graphviz (`dot -Tsvg`).
+    Dot,
+    /// Mermaid, which github and most doc sites draw in a ```mermaid block.
+    Mermaid,
+}
+
+/// The resolved packages, each stored once, with the dependencies as edges between [PackageId]s.
+/// The [Package]s in here have no `manifest.dependencies`: use [Graph::deps].
+#[derive(Default)]
+pub struct Graph {
+    nodes: Vec<Package>,
+    edges: Vec<Vec<PackageId>>,
+    roots: Vec<PackageId>,
+}
+
+impl Graph {
+    /// Builds a graph out of package trees.
+    /// Packages that show up multiple times in the trees (same name, version, directness, and alias) become one node.
+    pub fn new(roots: Vec<Package>) -> Self {
+        let mut g = Self::default();
+        let mut index = HashMap::new();
+        g.roots = roots.into_iter().map(|p| g.add(p, &mut index)).collect();
+        g
+    }
+
+    fn add(&mut self, mut p: Package, index: &mut HashMap<Key, PackageId>) -> PackageId {
+        let deps = std::mem::take(&mut p.manifest.dependencies);
+        let key = (
+            p.name.clone(),
+            p.manifest.full_version(),
+            p.indirect,
+            p.alias.clone(),
+        );
+        if let Some(id) = index.get(&key) {
+            return *id;
+        }
+        let id = PackageId(self.nodes.len());
+        index.insert(key, id);
+        self.nodes.push(p);
+        self.edges.push(vec![]);
+        // the node is inserted before its dependencies, so a cycle just points back at it
+        self.edges[id.0] = deps.into_iter().map(|d| self.add(d, index)).collect();
+        id
+    }
+
+    /// The directly wanted packages.
+    pub fn roots(&self) -> &[PackageId] {
+        &self.roots
+    }
+
+    /// The direct dependencies of `id`.
+    pub fn deps(&self, id: PackageId) -> &[PackageId] {
+        &self.edges[id.0]
+    }
+
+    pub fn get(&self, id: PackageId) -> &Package {
+        &self.nodes[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: PackageId) -> &mut Package {
+        &mut self.nodes[id.0]
+    }
+
+    /// Iterates over every package, once.
+    pub fn iter(&self) -> impl Iterator<Item = (PackageId, &Package)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (PackageId(i), p))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Clones the package `id`, with its direct dependencies (but not theirs) filled in.
+    /// This is what [Package::modify] needs.
+    pub fn with_deps(&self, id: PackageId) -> Package {
+        let mut p = self.get(id).clone();
+        p.manifest.dependencies = self.deps(id).iter().map(|&d| self.get(d).clone()).collect();
+        p
+    }
+
+    /// How many packages are below `id` (its dependencies, theirs, and so on), counted once.
+    pub fn below(&self, id: PackageId) -> usize {
+        let mut seen = HashSet::new();
+        let mut todo = self.deps(id).to_vec();
+        while let Some(d) = todo.pop() {
+            if d != id && seen.insert(d) {
+                todo.extend_from_slice(self.deps(d));
+            }
+        }
+        seen.len()
+    }
+
+    /// Renders this graph, with the project `title` pointing at the roots.
+    /// Packages with dependencies say how many are below them, so heavy subtrees stand out.
+    pub fn render(&self, format: Format, title: &str) -> String {
+        let label = |id: PackageId| match self.below(id) {
+            0 => self.get(id).to_string(),
+            n => format!("{} ({n} below)", self.get(id)),
+        };
+        let edges = self
+            .iter()
+            .flat_map(|(id, _)| {
+                self.deps(id)
+                    .iter()
+                    .map(move |&d| (format!("n{}", id.0), d))
+            })
+            .chain(self.roots.iter().map(|&r| ("root".to_string(), r)));
+        let mut out = String::new();
+        match format {
+            Format::Dot => {
+                let q = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+                out.push_str("digraph dependencies {\n    node [shape=box];\n");
+                writeln!(out, "    root [label={}, style=bold];", q(title)).unwrap();
+                for (id, _) in self.iter() {
+                    writeln!(out, "    n{} [label={}];", id.0, q(&label(id))).unwrap();
+                }
+                for (from, to) in edges {
+                    writeln!(out, "    {from} -> n{};", to.0).unwrap();
+                }
+                out.push_str("}\n");
+            }
+            Format::Mermaid => {
+                let q = |s: &str| format!("\"{}\"", s.replace('"', "#quot;"));
+                out.push_str("graph TD\n");
+                writeln!(out, "    root[{}]", q(title)).unwrap();
+                for (id, _) in self.iter() {
+                    writeln!(out, "    n{}[{}]", id.0, q(&label(id))).unwrap();
+                }
+                for (from, to) in edges {
+                    writeln!(out, "    {from} --> n{}", to.0).unwrap();
+                }
+            }
+        }
+        out
+    }
+
+    /// This graph as json (see [Export]), for dashboards and dependency bots.
+    pub fn json(&self) -> String {
+        let nodes = self
+            .iter()
+            .map(|(id, p)| Node {
+                id,
+                name: &p.name,
+                version: p
+                    .unresolved
+                    .clone()
+                    .unwrap_or_else(|| p.manifest.full_version()),
+                alias: p.alias.as_deref(),
+                integrity: p.manifest.integrity.as_ref().map(|i| i.to_string()),
+                shasum: p.manifest.shasum.as_deref(),
+                source: match p.manifest.tarball {
+                    crate::archive::CompressionType::None => None,
+                    ref t => Some(t.to_string()),
+                },
+                path: p
+                    .download_dir(Path::new(""))
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                direct: !p.indirect,
+            })
+            .collect();
+        let edges = self
+            .iter()
+            .flat_map(|(from, _)| self.deps(from).iter().map(move |&to| Edge { from, to }))
+            .collect();
+        serde_json::to_string_pretty(&Export {
+            roots: &self.roots,
+            nodes,
+            edges,
+        })
+        .unwrap()
+    }
+
+    /// Turns this graph back into package trees.
+    pub fn into_tree(self) -> Vec<Package> {
+        fn build(g: &Graph, id: PackageId) -> Package {
+            let mut p = g.get(id).clone();
+            p.manifest.dependencies = g.deps(id).iter().map(|&d| build(g, d)).collect();
+            p
+        }
+        self.roots.iter().map(|&id| build(&self, id)).collect()
+    }
+}
+
+/// What [Graph::json] writes: every package once, and who depends on who.
+#[derive(Serialize)]
+struct Export<'a> {
+    /// The directly wanted packages.
+    roots: &'a [PackageId],
+    nodes: Vec<Node<'a>>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Serialize)]
+struct Node<'a> {
+    id: PackageId,
+    name: &'a str,
+    /// The resolved version, or the wanted range, if it was left unresolved.
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias: Option<&'a str>,
+    integrity: Option<String>,
+    /// The sha1, in hex: every registry gives one, unlike an integrity.
+    shasum: Option<&'a str>,
+    /// The tarball url.
+    source: Option<String>,
+    /// Where it is installed, relative to the project.
+    path: String,
+    direct: bool,
+}
+
+/// `from` depends on `to`.
+#[derive(Serialize)]
+struct Edge {
+    from: PackageId,
+    to: PackageId,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dedup() {
+        let c = crate::test_utils::mktemp().await.2;
+        let a = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        let b = Package::create_from_str("@bendn/test@1.0.0", c)
+            .await
+            .unwrap();
+        let g = Graph::new(vec![a, b]);
+        // both versions of test depend on the same gdcli
+        assert_eq!(g.iter().count(), 3);
+        let [a, b] = g.roots() else { unreachable!() };
+        assert_eq!(g.get(*a).to_string(), "@bendn/test@2.0.10");
+        assert_eq!(g.deps(*a), g.deps(*b));
+        assert_eq!(g.get(g.deps(*a)[0]).to_string(), "@bendn/gdcli@1.2.5");
+        assert!(g.get(*a).manifest.dependencies.is_empty());
+        assert_eq!(g.with_deps(*a).manifest.dependencies.len(), 1);
+        assert_eq!(g.below(*a), 1);
+        let dot = g.render(Format::Dot, "my \"game\"");
+        assert!(
+            dot.contains(r#"root [label="my \"game\"", style=bold];"#),
+            "{dot}"
+        );
+        assert!(
+            dot.contains(r#"n0 [label="@bendn/test@2.0.10 (1 below)"];"#),
+            "{dot}"
+        );
+        assert!(dot.contains("n0 -> n1;\n"), "{dot}");
+        assert!(dot.contains("root -> n2;\n"), "{dot}");
+        let mermaid = g.render(Format::Mermaid, "my \"game\"");
+        assert!(
+            mermaid.starts_with("graph TD\n    root[\"my #quot;game#quot;\"]\n"),
+            "{mermaid}"
+        );
+        assert!(mermaid.contains("    n2 --> n1\n"), "{mermaid}");
+        let json: serde_json::Value = serde_json::from_str(&g.json()).unwrap();
+        assert_eq!(json["roots"], serde_json::json!([0, 2]));
+        assert_eq!(json["edges"][0], serde_json::json!({"from": 0, "to": 1}));
+        let gdcli = &json["nodes"][1];
+        assert_eq!(
+            (&gdcli["name"], &gdcli["version"], &gdcli["direct"]),
+            (&"@bendn/gdcli".into(), &"1.2.5".into(), &false.into())
+        );
+        assert_eq!(gdcli["shasum"].as_str().unwrap().len(), 40);
+        assert!(gdcli["source"].as_str().unwrap().ends_with(".tgz"));
+        assert_eq!(json["nodes"][0]["path"], "addons/@bendn/test");
+        assert_eq!(g.into_tree()[1].manifest.dependencies.len(), 1);
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// The `hooks.<event>` command of a package.json, if it has one.
+pub fn get<'a>(manifest: &'a Value, event: &str) -> Option<&'a str> {
+    manifest.get("hooks")?.get(event)?.as_str()
+}
+
+/// Runs the `hooks.<event>` command of a package.json in `dir`, through the shell.
+/// Returns whether there was a hook to run. Fails if it exits unsuccessfully.
+pub fn run(manifest: &Value, event: &str, dir: &Path) -> Result<bool> {
+    let Some(cmd) = get(manifest, event) else {
+        return Ok(false);
+    };
+    exec(cmd, event, dir, dir, &[])?;
+    Ok(true)
+}
+
+/// The command that runs the hook `cmd`: through the shell, or, for a GDScript (`res://addons/foo/install.gd`),
+/// with `godot --headless --script`, in the project at `root`.
+fn command(cmd: &str, root: &Path) -> Command {
+    if cmd.starts_with("res://") {
+        let mut c = Command::new(crate::godot::binary());
+        c.arg("--headless")
+            .arg("--path")
+            .arg(root)
+            .arg("--script")
+            .arg(cmd);
+        return c;
+    }
+    shell(cmd)
+}
+
+/// The command that runs `cmd` through the shell (`sh`, or `cmd` on windows).
+pub fn shell(cmd: &str) -> Command {
+    let mut c = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    c.arg(cmd);
+    c
+}
+
+/// Runs the hook command `cmd` (for `event`) in `dir`, with `env` set (and `GPM_EVENT`), for the project at `root`
+/// (see [command]). Fails if it exits unsuccessfully.
+pub fn exec(cmd: &str, event: &str, dir: &Path, root: &Path, env: &[(&str, &str)]) -> Result<()> {
+    let status = command(cmd, root)
+        .current_dir(dir)
+        .env("GPM_EVENT", event)
+        .envs(env.iter().copied())
+        .status()
+        .with_context(|| match cmd.starts_with("res://") {
+            true => format!("running the {event} hook with godot (set GODOT to where it is)"),
+            false => format!("running the {event} hook"),
+        })?;
+    if !status.success() {
+        bail!("the {event} hook ({cmd}) failed: {status}");
+    }
+    Ok(())
+}
+
+static PLAN: OnceLock<String> = OnceLock::new();
+
+/// Sets the install plan package hooks get (as `GPM_PLAN`): JSON, like
+/// `[{"name": "@bendn/test", "version": "2.0.10", "dir": "addons/@bendn/test", "action": "update", "from": ["2.0.9"]}]`.
+/// The action is `install` (it wasnt installed), `update` (it was, at another version), `reinstall`, or `uninstall`.
+pub fn set_plan(plan: String) {
+    let _ = PLAN.set(plan);
+}
+
+/// The install plan (see [set_plan]), `[]` if there is none.
+pub fn plan() -> &'static str {
+    PLAN.get().map_or("[]", String::as_str)
+}
+
+/// The hooks of packages that were allowed to run, as the `lockfile` remembers them, by package name.
+/// Only these exact commands were allowed: changed ones have to be allowed again.
+pub fn consented(lockfile: &str) -> HashMap<String, BTreeMap<String, String>> {
+    #[derive(Deserialize)]
+    struct Locked {
+        name: String,
+        #[serde(default)]
+        hooks: BTreeMap<String, String>,
+    }
+    serde_json::from_str::<Vec<Locked>>(lockfile)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|l| !l.hooks.is_empty())
+        .map(|l| (l.name, l.hooks))
+        .collect()
+}
+
+/// The package.json in `dir`, for its hooks.
+pub fn manifest(dir: &Path) -> Result<Value> {
+    serde_json::from_str(&read_to_string(dir.join("package.json")).context("reading package.json")?)
+        .context("parsing package.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(unix)]
+    #[test]
+    fn hooks() {
+        let t = tempfile::TempDir::new().unwrap();
+        let m = json!({"hooks": {"ok": "echo $GPM_EVENT > out", "fail": "exit 3"}});
+        assert!(run(&m, "ok", t.path()).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(t.path().join("out")).unwrap(),
+            "ok\n"
+        );
+        assert!(run(&m, "fail", t.path()).is_err());
+        assert!(!run(&m, "missing", t.path()).unwrap());
+    }
+
+    #[test]
+    fn gdscript() {
+        let c = command("res://addons/foo/install.gd", Path::new("proj"));
+        assert_eq!(
+            c.get_args().collect::<Vec<_>>(),
+            [
+                "--headless",
+                "--path",
+                "proj",
+                "--script",
+                "res://addons/foo/install.gd"
+            ]
+        );
+        assert_ne!(
+            command("make", Path::new("proj")).get_program(),
+            c.get_program()
+        );
+    }
+
+    #[test]
+    fn consent() {
+        let lock = r#"[{"name": "a", "tarball": "", "version": "1.0.0", "hooks": {"postinstall": "make"}},
+            {"name": "b", "tarball": "", "version": "1.0.0"}]"#;
+        let c = consented(lock);
+        assert_eq!(c.len(), 1);
+        assert_eq!(c["a"]["postinstall"], "make");
+        assert!(consented("not json").is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha512};
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+/// Hash algorithm for integrity strings.
+pub enum Algorithm {
+    /// sha512, what npm uses.
+    #[default]
+    Sha512,
+    /// blake3, much faster for big packages.
+    Blake3,
+}
+
+impl Algorithm {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+        }
+    }
+}
+
+/// A [subresource integrity](https://w3c.github.io/webappsec-subresource-integrity/) string,
+/// like `sha512-<base64>` or `blake3-<base64>`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Integrity {
+    pub algorithm: Algorithm,
+    digest: Vec<u8>,
+}
+
+impl Integrity {
+    /// Hashes `bytes` with `algorithm`.
+    pub fn of(algorithm: Algorithm, bytes: &[u8]) -> Self {
+        let digest = match algorithm {
+            Algorithm::Sha512 => Sha512::digest(bytes).to_vec(),
+            Algorithm::Blake3 => blake3::hash(bytes).as_bytes().to_vec(),
+        };
+        Self { algorithm, digest }
+    }
+
+    /// A file name safe form, `sha512-<hex>`.
+    pub fn key(&self) -> String {
+        let mut key = format!("{}-", self.algorithm.prefix());
+        for b in &self.digest {
+            key.push_str(&format!("{b:02x}"));
+        }
+        key
+    }
+
+    /// Does `bytes` hash to this?
+    pub fn check(&self, bytes: &[u8]) -> bool {
+        Self::of(self.algorithm, bytes) == *self
+    }
+}
+
+impl std::str::FromStr for Integrity {
+    type Err = anyhow::Error;
+
+    /// Parses a integrity string. If there are multiple (space separated) hashes, takes the first one we know.
+    fn from_str(s: &str) -> Result<Self> {
+        for hash in s.split_whitespace() {
+            let Some((algo, digest)) = hash.split_once('-') else {
+                continue;
+            };
+            let algorithm = match algo {
+                "sha512" => Algorithm::Sha512,
+                "blake3" => Algorithm::Blake3,
+                _ => continue,
+            };
+            // strip options (sha512-...?foo)
+            let digest = digest.split('?').next().unwrap_or(digest);
+            return Ok(Self {
+                algorithm,
+                digest: STANDARD
+                    .decode(digest)
+                    .map_err(|e| anyhow!("Invalid integrity {hash}: {e}"))?,
+            });
+        }
+        Err(anyhow!("No supported hash in integrity {s}"))
+    }
+}
+
+impl fmt::Display for Integrity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.algorithm.prefix(),
+            STANDARD.encode(&self.digest)
+        )
+    }
+}
+
+impl Serialize for Integrity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for algo in [Algorithm::Sha512, Algorithm::Blake3] {
+            let i = Integrity::of(algo, b"godot");
+            assert_eq!(i.to_string().parse::<Integrity>().unwrap(), i);
+            assert!(i.check(b"godot"));
+            assert!(!i.check(b"unity"));
+        }
+        assert_eq!(
+            Integrity::of(Algorithm::Blake3, b"").to_string(),
+            "blake3-rxNJufX5oaagQE3qNtzJSZvLJcmtwRK3zJqTyuQfMmI="
+        );
+        assert!("md5-AAAA".parse::<Integrity>().is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::package::record;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{read_to_string, remove_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where the journal lives, in the install dir. Hidden, so godot leaves it alone.
+const JOURNAL: &str = ".gpm-journal.json";
+
+/// The package folders (relative to the install dir, with `/`s) of an install, by how far they got.
+#[derive(Serialize, Deserialize, Default)]
+struct Steps {
+    planned: BTreeSet<String>,
+    /// Their old install is gone, and the new one is being unpacked.
+    started: BTreeSet<String>,
+    done: BTreeSet<String>,
+}
+
+/// What an install did so far, on disk, so a gpm that is killed in the middle of one can be cleaned up after.
+pub struct Journal {
+    path: PathBuf,
+    steps: Mutex<Steps>,
+}
+
+fn key(rel: &Path) -> String {
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+/// Cleans up after an install in `install_dir` that didnt finish: the package folders it was in the middle of
+/// are half unpacked, so they (and their records) are removed. Returns them.
+/// The ones it didnt get to still have their old install, so they stay.
+pub fn recover(install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let path = install_dir.join(JOURNAL);
+    let Ok(s) = read_to_string(&path) else {
+        return Ok(vec![]);
+    };
+    let steps: Steps = serde_json::from_str(&s).context("parsing the install journal")?;
+    let mut removed = vec![];
+    for rel in steps.started.difference(&steps.done) {
+        let dir = install_dir.join(rel);
+        match dir.is_symlink() {
+            true => remove_file(&dir),
+            false if dir.exists() => remove_dir_all(&dir),
+            false => Ok(()),
+        }
+        .with_context(|| format!("removing the half installed {}", dir.display()))?;
+        let _ = remove_file(record::path(install_dir, Path::new(rel)));
+        removed.push(PathBuf::from(rel));
+    }
+    remove_file(&path).context("removing the install journal")?;
+    Ok(removed)
+}
+
+impl Journal {
+    /// Starts the journal of installing the package folders `planned` into `install_dir`,
+    /// after [recover]ing from the last one, if it didnt finish.
+    pub fn begin(
+        install_dir: &Path,
+        planned: impl IntoIterator<Item = PathBuf>,
+    ) -> Result<(Self, Vec<PathBuf>)> {
+        let removed = recover(install_dir)?;
+        let j = Self {
+            path: install_dir.join(JOURNAL),
+            steps: Mutex::new(Steps {
+                planned: planned.into_iter().map(|p| key(&p)).collect(),
+                ..Default::default()
+            }),
+        };
+        j.save(&j.steps.lock().unwrap())?;
+        Ok((j, removed))
+    }
+
+    fn save(&self, steps: &Steps) -> Result<()> {
+        crate::atomic::write(&self.path, serde_json::to_string(steps)?)
+            .context("writing the install journal")
+    }
+
+    /// Notes that the old install of `rel` is gone, and the new one is going in.
+    pub fn start(&self, rel: &Path) -> Result<()> {
+        let mut steps = self.steps.lock().unwrap();
+        steps.started.insert(key(rel));
+        self.save(&steps)
+    }
+
+    /// Notes that `rel` is installed.
+    pub fn done(&self, rel: &Path) -> Result<()> {
+        let mut steps = self.steps.lock().unwrap();
+        steps.done.insert(key(rel));
+        self.save(&steps)
+    }
+
+    /// Ends the journal: everything is installed.
+    pub fn finish(&self) -> Result<()> {
+        remove_file(&self.path).context("removing the install journal")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn recovers() {
+        let t = tempfile::TempDir::new().unwrap();
+        let addons = t.path();
+        for p in ["a", "b"] {
+            create_dir_all(addons.join(p)).unwrap();
+            write(addons.join(p).join("x.gd"), "").unwrap();
+            record::save(&addons.join(p), &record::path(addons, Path::new(p))).unwrap();
+        }
+        let (j, removed) = Journal::begin(addons, ["a".into(), "b".into()]).unwrap();
+        assert!(removed.is_empty());
+        j.start(Path::new("a")).unwrap();
+        // killed here: a is half unpacked, b wasnt touched
+        drop(j);
+        let (j, removed) = Journal::begin(addons, ["a".into(), "b".into()]).unwrap();
+        assert_eq!(removed, [Path::new("a")]);
+        assert!(!addons.join("a").exists());
+        assert!(!record::path(addons, Path::new("a")).exists());
+        assert!(addons.join("b/x.gd").exists());
+        j.start(Path::new("a")).unwrap();
+        j.done(Path::new("a")).unwrap();
+        j.finish().unwrap();
+        assert!(!addons.join(JOURNAL).exists());
+        assert!(recover(addons).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::graph::Graph;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+/// What `gpm licenses` writes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A line per package: its license, and its license files.
+    Text,
+    Json,
+    /// Every license file, one after the other: a THIRD_PARTY_LICENSES.txt to ship with the game (or put in its credits).
+    Bundle,
+}
+
+/// The license of an installed package.
+#[derive(Serialize, Debug)]
+pub struct License {
+    pub name: String,
+    pub version: String,
+    /// What its package.json says (like `MIT`), if it says.
+    pub license: Option<String>,
+    /// Its license files (like `LICENSE.md`), by name.
+    pub files: BTreeMap<String, String>,
+}
+
+/// If `file` is a license file, like `LICENSE`, `LICENCE.md`, `COPYING` or `NOTICE.txt`.
+fn is_license(file: &str) -> bool {
+    let upper = file.to_uppercase();
+    ["LICENSE", "LICENCE", "COPYING", "NOTICE", "UNLICENSE"]
+        .iter()
+        .any(|l| {
+            upper == *l
+                || upper.starts_with(&format!("{l}."))
+                || upper.starts_with(&format!("{l}-"))
+        })
+}
+
+/// The license of the package.json in `dir`: its `license` (a string, or the old `{"type": ..}`),
+/// or its even older `licenses` list, joined with ORs.
+fn declared(dir: &Path) -> Option<String> {
+    let json: serde_json::Value =
+        serde_json::from_str(&read_to_string(dir.join("package.json")).ok()?).ok()?;
+    let of = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        v => v["type"].as_str().map(str::to_owned),
+    };
+    match (&json["license"], json["licenses"].as_array()) {
+        (serde_json::Value::Null, Some(all)) => {
+            let all = all.iter().filter_map(of).collect::<Vec<_>>();
+            (!all.is_empty()).then(|| all.join(" OR "))
+        }
+        (l, _) => of(l),
+    }
+}
+
+/// The licenses of the installed packages of `graph`, in the project `cwd`, by name.
+pub fn collect(graph: &Graph, cwd: &Path) -> Vec<License> {
+    let mut out = graph
+        .iter()
+        .filter(|(_, p)| p.is_installed(cwd))
+        .map(|(_, p)| {
+            let dir = p.download_dir(cwd);
+            let files = read_dir(&dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|f| is_license(f))
+                .filter_map(|f| Some((f.clone(), read_to_string(dir.join(&f)).ok()?)))
+                .collect();
+            License {
+                name: p.name.clone(),
+                version: p.manifest.full_version(),
+                license: declared(&dir),
+                files,
+            }
+        })
+        .collect::<Vec<_>>();
+    out.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    // an aliased package is installed twice, but its one license
+    out.dedup_by(|a, b| (&a.name, &a.version) == (&b.name, &b.version));
+    out
+}
+
+/// Writes the `licenses` in `format`.
+pub fn report(licenses: &[License], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Json => out = serde_json::to_string_pretty(licenses).unwrap(),
+        Format::Text => {
+            for l in licenses {
+                let files = l.files.keys().map(String::as_str).collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "{}@{}: {}{}",
+                    l.name,
+                    l.version,
+                    l.license.as_deref().unwrap_or("no license"),
+                    match files.is_empty() {
+                        true => String::new(),
+                        false => format!(" ({})", files.join(", ")),
+                    }
+                )
+                .unwrap();
+            }
+        }
+        Format::Bundle => {
+            for l in licenses {
+                let title = format!("{} {}", l.name, l.version);
+                writeln!(out, "{title}\n{}", "=".repeat(title.len())).unwrap();
+                if let Some(license) = &l.license {
+                    writeln!(out, "License: {license}").unwrap();
+                }
+                for text in l.files.values() {
+                    writeln!(out, "\n{}", text.trim_end()).unwrap();
+                }
+                out.push_str("\n\n");
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn licenses() {
+        assert!(is_license("LICENSE") && is_license("License.md") && is_license("COPYING-MIT"));
+        assert!(!is_license("licenses.gd") && !is_license("README.md"));
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let p = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        let cwd = t.0.path();
+        let mut graph = Graph::new(vec![p]);
+        let ids = graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            graph
+                .get_mut(id)
+                .download(c.clone(), cwd, Default::default())
+                .await
+                .unwrap();
+        }
+        let l = collect(&graph, cwd);
+        assert_eq!(
+            report(&l, Format::Text),
+            "@bendn/gdcli@1.2.5: MIT (LICENSE)\n@bendn/test@2.0.10: MIT\n"
+        );
+        let bundle = report(&l, Format::Bundle);
+        assert!(
+            bundle
+                .starts_with("@bendn/gdcli 1.2.5\n==================\nLicense: MIT\n\nMIT License"),
+            "{bundle}"
+        );
+    }
+}
@@ -1,32 +1,88 @@
+mod adopt;
+mod annotate;
 mod archive;
+mod atomic;
+mod backend;
+mod bundle;
 mod cache;
+mod changelog;
+mod changes;
+mod compat;
+mod completions;
 mod config_file;
 mod conversions;
+mod diff;
+mod exports;
+mod external;
+mod fetch;
+mod fingerprint;
+mod freeze;
+mod git_hook;
+mod godot;
+mod graph;
+mod hooks;
+mod integrity;
+mod journal;
+mod licenses;
+mod migrate;
+mod pack;
 mod package;
+mod plugins;
+mod publish;
+mod registry;
+mod releases;
+mod report;
+mod scan;
+mod size;
+mod snapshot;
+mod status;
+mod template;
 mod theme;
+mod timings;
+mod translations;
+mod unused;
+mod upgrade;
+mod user_config;
+mod vendor;
 mod verbosity;
+mod version;
+mod watch;
 
+use annotate::Level;
+use cache::lock::Lock;
 use cache::Cache;
 use config_file::{ConfigFile, ConfigType};
 use conversions::*;
-use package::parsing::ParsedPackage;
+use graph::{Graph, PackageId};
+use integrity::{Algorithm, Integrity};
+use package::parsing::{ParsedPackage, VersionType};
 use package::Package;
 
 use anyhow::Result;
 use async_recursion::async_recursion;
-use clap::{ColorChoice, Parser, Subcommand, ValueEnum};
+use clap::{ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::{engine::ArgValueCompleter, CompleteEnv};
 use console::{self, Term};
+use futures::channel::oneshot;
+use futures::future::join_all;
 use futures::stream::{self, StreamExt};
-use indicatif::{HumanCount, HumanDuration, ProgressBar, ProgressIterator};
+use indicatif::{
+    HumanBytes, HumanCount, HumanDuration, MultiProgress, ProgressBar, ProgressIterator,
+};
 use lazy_static::lazy_static;
 use reqwest::{Client as RealClient, IntoUrl, RequestBuilder};
-use std::collections::HashSet;
-use std::fs::{create_dir, read_dir, read_to_string, remove_dir, write};
+use semver_rs::Version;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir, write};
 use std::io::{stdin, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc::channel;
+use std::sync::Arc;
 use std::thread;
 use std::{env::current_dir, panic, time::Instant};
+use user_config::UserConfig;
 use verbosity::Verbosity;
 
 #[derive(Parser)]
@@ -72,16 +128,219 @@ struct Args {
     )]
     /// Registry to use.
     registry: String,
+    #[arg(long = "integrity", global = true)]
+    /// Hash algorithm for the lock file integrity. Defaults to whatever the lock file already uses, or sha512.
+    integrity: Option<Algorithm>,
+    #[arg(long = "timings", global = true)]
+    /// Print how long each phase (resolve, download, verify, extract) took, per package.
+    timings: bool,
+    #[arg(long = "dry-run", visible_alias = "plan", global = true)]
+    /// Print what would be downloaded, written, and deleted, without doing it. Only metadata is fetched.
+    dry_run: bool,
+    #[arg(long = "install-dir", global = true)]
+    /// Where to put the packages, relative to the project. Overrides the install_dir of the config. Defaults to addons.
+    install_dir: Option<PathBuf>,
+    #[arg(long, global = true)]
+    /// Symlink packages to their install in the global store, instead of copying them (like symlink = true in the config).
+    /// Falls back to copying where links cant be made.
+    symlink: bool,
+    #[arg(long, global = true)]
+    /// Let version ranges resolve to prereleases (like 2.0.0-beta.3) too.
+    /// Without it, only ranges naming a prerelease (^2.0.0-beta.1) do, or packages with a channel in the config.
+    pre: bool,
+    #[arg(long = "ignore-scripts", global = true)]
+    /// Never run the hooks of packages, not even allowed ones.
+    ignore_scripts: bool,
+    #[arg(long, global = true)]
+    /// Fail on unknown keys in the config (like dependancies), packages wanted twice, and versions not written canonically (like v1.0.0).
+    /// Like strict = true in the config.
+    strict: bool,
+    #[arg(long = "output", value_enum, default_value = "human", global = true)]
+    /// How to write findings (of status, compat, unused, plugins, the strict check, and deprecations):
+    /// github writes them as workflow annotations on the lines of the config.
+    output: annotate::Output,
 }
 
 #[derive(Subcommand)]
 enum Actions {
     #[clap(short_flag = 'u')]
     /// Downloads the latest versions of your wanted packages.
-    Update,
+    Update {
+        #[arg(long = "force", short = 'f')]
+        /// Update even if nothing changed since the last update, or packages overlap.
+        force: bool,
+        #[arg(long = "interactive", short = 'i')]
+        /// Pick which newer versions (outside of the wanted ranges) to update to.
+        interactive: bool,
+        #[arg(long = "patch", group = "scope")]
+        /// Bump the wanted versions to the newest patch release.
+        patch: bool,
+        #[arg(long = "minor", group = "scope")]
+        /// Bump the wanted versions to the newest minor release.
+        minor: bool,
+        #[arg(long = "latest", group = "scope")]
+        /// Bump the wanted versions to the newest release, ignoring the wanted ranges.
+        latest: bool,
+        #[arg(long = "changelog")]
+        /// Print the changelog entries of upgraded packages.
+        changelog: bool,
+        #[arg(add = ArgValueCompleter::new(completions::packages))]
+        /// Resolve these packages again, instead of reusing what their versions resolved to before
+        /// (like when that version was unpublished).
+        packages: Vec<String>,
+        #[arg(long = "watch", short = 'w')]
+        /// Keep watching the config file, and update again whenever it changes.
+        watch: bool,
+        #[arg(long = "snapshot", conflicts_with = "from_snapshot")]
+        /// Save the packuments the resolution used next to the lockfile (godot-lock.snapshot.json), for --from-snapshot.
+        snapshot: bool,
+        #[arg(long = "from-snapshot")]
+        /// Resolve from the saved snapshot alone, so the same versions come out, whatever the registry says now.
+        from_snapshot: bool,
+        #[arg(long = "import")]
+        /// Run godot --headless --import after (the godot of GODOT, or the PATH), so the new files are imported before the editor opens.
+        import: bool,
+    },
+    /// Adds a package to the config file, at its newest version (run update to install it).
+    #[command(long_about = "
+Add a package to the config file, for example
+    gpm add @bendn/test
+writes ^2.0.10 (the newest version, with the save prefix). A range given (@bendn/test@~2.0) is written as is.
+The save prefix is ^, unless the save-prefix of the user config (config.json, in ~/.config/gpm) says otherwise.")]
+    Add {
+        /// The package, and optionally the version range.
+        package: ParsedPackage,
+        #[arg(long = "exact", short = 'E', group = "prefix")]
+        /// Pin the newest version (like 2.0.10), instead of writing a range.
+        exact: bool,
+        #[arg(long = "save-prefix", group = "prefix")]
+        /// What to put before the newest version: ^, ~, or nothing.
+        save_prefix: Option<String>,
+    },
+    /// Removes packages from the config file (run update to uninstall them).
+    Remove {
+        #[arg(required = true, add = ArgValueCompleter::new(completions::packages))]
+        /// The packages to remove.
+        packages: Vec<String>,
+    },
+    /// Finds the addons in the install dir gpm didnt install, and adds the packages they are to the config.
+    #[command(long_about = "
+Find the folders in the install dir gpm didnt install (like addons copied in by hand),
+and the packages they are: by their files, or by the name and version in their plugin.cfg.
+Adopted packages are added to the config (at the version they are), and managed from then on:
+when gpm replaces them, whatever was changed in them is moved aside, not deleted.")]
+    Adopt {
+        #[arg(long = "yes", short = 'y')]
+        /// Adopt everything found, without asking.
+        yes: bool,
+    },
+    /// Copies every package into the vendor dir, to install from without the registry.
+    #[command(long_about = "
+Copy the tarball of every package (and their dependencies) into the vendor dir, with an index to resolve them from.
+Commit it, and set vendor = \"vendor\" in the config, to install only from there, without the registry.")]
+    Vendor {
+        #[arg(long = "dir")]
+        /// Where to put the packages. Defaults to the vendor dir of the config, or vendor.
+        dir: Option<PathBuf>,
+    },
+    /// Downloads every package in the lockfile into the store, without installing anything.
+    #[command(long_about = "
+Download every package in the lockfile into the global store, without touching the install dir,
+so installing it afterwards (like in the next step of a CI job) doesnt need the registry.")]
+    Fetch,
+    /// Pins every wanted version in the config to the version in the lockfile.
+    #[command(long_about = "
+Pin every wanted version in the config to the version in the lockfile (so run update first):
+^2.0.0 becomes 2.0.10, if thats what was installed.")]
+    Freeze,
     #[clap(short_flag = 'p')]
     /// Deletes all installed packages.
     Purge,
+    /// Prints the differences between the contents of two versions of a package.
+    Diff {
+        #[arg(add = ArgValueCompleter::new(completions::packages))]
+        /// The package to compare.
+        package: String,
+        /// The old version.
+        old: String,
+        /// The new version.
+        new: String,
+        #[arg(long = "stat")]
+        /// Only print how many lines changed in each file.
+        stat: bool,
+    },
+    /// Packs the package in the current directory into a tarball, for publishing or distribution.
+    Pack {
+        #[arg(long = "out", short = 'o')]
+        /// Where to write the tarball. Defaults to <name>-<version>.tgz.
+        out: Option<PathBuf>,
+    },
+    /// Bumps the version of the package in the current directory.
+    #[command(long_about = "
+Bump the version in the package.json of the package in the current directory.
+Runs the hooks.preversion and hooks.postversion commands of the package.json before and after.")]
+    Version {
+        /// patch, minor, major, or an exact version.
+        to: String,
+        #[arg(long = "git")]
+        /// Also commit the package.json, and tag the commit v<version>.
+        git: bool,
+    },
+    /// Packs the package in the current directory, checks it, and uploads it to the registry.
+    Publish {
+        #[arg(long = "tag", default_value = "latest")]
+        /// The dist-tag to publish under.
+        tag: String,
+        #[arg(long = "max-size", default_value = "50M", value_parser = publish::parse_size)]
+        /// Refuse to publish tarballs bigger than this (like 500K, 50M, 1G).
+        max_size: u64,
+        #[arg(long = "provenance")]
+        /// Publish a signed statement of where the package was built (needs GitHub Actions or GitLab CI).
+        provenance: bool,
+        #[command(flatten)]
+        auth: Auth,
+    },
+    /// Deprecates published versions of a package, or undeprecates them with an empty message.
+    #[command(long_about = "
+Deprecate published versions of a package, for example
+    gpm deprecate @bendn/test@\"<2\" \"use 2.x\"
+Pass an empty message to undeprecate.")]
+    Deprecate {
+        /// The package, and the range of versions to deprecate.
+        package: ParsedPackage,
+        /// Why, shown to everyone installing these versions.
+        message: String,
+        #[command(flatten)]
+        auth: Auth,
+    },
+    /// Makes static registries.
+    Registry {
+        #[command(subcommand)]
+        action: RegistryActions,
+    },
+    /// Manages the global package store.
+    Cache {
+        #[command(subcommand)]
+        action: CacheActions,
+    },
+    /// Manages the git hooks that keep the lockfile in sync.
+    Hooks {
+        #[command(subcommand)]
+        action: HooksActions,
+    },
+    /// Manages the dist-tags of a published package.
+    Tag {
+        #[command(subcommand)]
+        action: TagActions,
+    },
+    /// Prints a shell completion script.
+    #[command(long_about = "
+Print a shell completion script. Load it with, for example:
+    source <(gpm completions bash)")]
+    Completions {
+        /// The shell to complete for.
+        shell: completions::Shell,
+    },
     /// Prints a tree of all the wanted packages, and their dependencies.
     #[command(long_about = "
 Print a tree of all the wanted packages, and their dependencies.
@@ -102,15 +361,273 @@ Produces output like
         /// To print download urls next to the package name.
         print_tarballs: bool,
     },
+    /// Prints the dependency graph, for graphviz or mermaid.
+    #[command(long_about = "
+Print the graph of all the wanted packages, and their dependencies, as graphviz (dot) or mermaid.
+Each package is drawn once, however many depend on it, and says how many packages are below it. For example
+    gpm graph | dot -Tsvg > deps.svg
+    gpm graph --format mermaid >> docs/dependencies.md")]
+    Graph {
+        #[arg(value_enum, default_value = "dot", long = "format")]
+        /// What to render to.
+        format: graph::Format,
+        #[arg(long = "json")]
+        /// Print the packages (with their integrity, tarball url, and install path) and their dependencies as json instead.
+        json: bool,
+    },
+    /// Reports the licenses of the installed packages.
+    #[command(long_about = "
+Report the licenses of the installed packages: what their package.json says, and their license files (LICENSE, COPYING, NOTICE).
+--format bundle writes every license file one after the other, to ship with the game, like
+    gpm licenses --format bundle -o THIRD_PARTY_LICENSES.txt")]
+    Licenses {
+        #[arg(value_enum, default_value = "text", long = "format")]
+        /// What to write.
+        format: licenses::Format,
+        #[arg(long = "out", short = 'o')]
+        /// Where to write it. Defaults to stdout.
+        out: Option<PathBuf>,
+    },
+    /// Reports how much room each installed package takes, biggest first.
+    #[command(long_about = "
+Report how much room each installed package takes, biggest first: the size of its installed files,
+how many there are, and the size of its tarball (if it is in the package store). Produces output like
+   installed   download  files
+    2.10 MiB    612 KiB     84 @bendn/big-addon@1.0.0")]
+    Size,
+    /// Says at a glance if the config, the lockfile, and the installed packages agree.
+    #[command(long_about = "
+Say at a glance if the project drifted: if the lockfile has what the config wants, if the installed packages
+are the locked ones (and unchanged), how many newer versions there are, and which folders in the install dir
+gpm didnt install. Exits with 1 if anything but the newer versions is off, for ci.")]
+    Status,
+    /// Says which installed editor plugins the project.godot doesnt enable, and which enabled ones are gone.
+    #[command(long_about = "
+Compare the editor plugins (plugin.cfg) of the directly wanted packages with editor_plugins/enabled in project.godot:
+say which are installed but disabled, and which are enabled but missing. Exits with 1 if any are, unless --fix.")]
+    Plugins {
+        #[arg(long = "fix")]
+        /// Enable the installed plugins, and stop enabling the missing ones, in project.godot.
+        fix: bool,
+    },
+    /// Says which native libraries of the installed packages the export presets leave out.
+    #[command(long_about = "
+Find the native libraries the GDExtensions (.gdextension) and GDNative libraries (.gdnlib) of the installed packages load,
+and say which the export presets (export_presets.cfg) for their platform dont include in their filters, so exports dont ship without them.
+Exits with 1 if any are left out, unless --fix.")]
+    Exports {
+        #[arg(long = "fix")]
+        /// Add the left out libraries to the include filters of the presets.
+        fix: bool,
+    },
+    /// Checks the installed packages are ready to export.
+    #[command(long_about = "
+Check the installed packages before exporting the game, with every export preset (of export_presets.cfg):
+every GDExtension has a native library for the platform of the preset (and it is there), no dev package
+(\"dev\": true in the config) is autoloaded, or exported, and the exclude filter of the preset leaves no file of the other packages out.
+Exits with 1 if any of that but a dev package being exported is wrong.")]
+    ExportCheck {
+        #[arg(long = "preset")]
+        /// Only check the export preset with this name (like \"Windows Desktop\").
+        preset: Option<String>,
+    },
+    /// Says which wanted packages nothing in the project uses.
+    #[command(long_about = "
+Say which of the wanted packages nothing in the project uses: no script, scene, or resource (or the project.godot)
+outside the install dir has a res:// path into their folder, or uses a class_name of theirs. Exits with 1 if any are unused.")]
+    Unused,
+    /// Checks every package against the godot of the project.
+    #[command(long_about = "
+Check the godot range every resolved package declares (its engines.godot) against the godot of the project:
+the godot of the config, or of project.godot. Says which break, and which dont say. Exits with 1 if any break.")]
+    Compat {
+        #[arg(long = "godot")]
+        /// Check against this godot (like 4.2) instead.
+        godot: Option<String>,
+    },
+    /// Writes a report of the packages, to share.
+    #[command(long_about = "
+Write a report of the project packages, to share (like for a milestone review): the dependency tree, the version,
+license, and installed size of every package, and the findings (deprecated packages, packages that break with the
+godot of the project, unused packages), as markdown or a standalone html page, like
+    gpm report --format html -o packages.html")]
+    Report {
+        #[arg(value_enum, default_value = "md", long = "format")]
+        /// What to write.
+        format: report::Format,
+        #[arg(long = "out", short = 'o')]
+        /// Where to write it. Defaults to stdout.
+        out: Option<PathBuf>,
+    },
+    /// Makes a new godot project from a template.
+    #[command(long_about = "
+Make a new godot project from a template package (or git repository), for example
+    gpm new my-game --template @bendn/template
+The {{name}} and {{godot_version}} in the names and text files of the template are filled in.
+A godot.package with the dependencies of the template package.json is made, unless the template has one.")]
+    New {
+        /// Where to make the project. Has to be empty.
+        dir: PathBuf,
+        #[arg(long = "template", short = 't')]
+        /// The template: a package (@scope/name@version), or a git repository (git+https://host/repo, with #branch).
+        template: String,
+        #[arg(long = "name")]
+        /// The name of the project. Defaults to the name of the dir.
+        name: Option<String>,
+        #[arg(long = "godot")]
+        /// The godot version of the project (like 4.2). Defaults to the version of the godot on the PATH (or $GODOT).
+        godot: Option<String>,
+    },
+    /// Makes the godot.package from the manifest of another plugin manager.
+    #[command(long_about = "
+Make the godot.package from the manifest of another plugin manager, for example
+    gpm import --from gd-plug plug.gd
+Repositories become the tarball urls of their branch, tag or commit (on github).
+Those install if the repository has a package.json: if the plugin is on the registry, use its name instead.")]
+    Import {
+        #[arg(long = "from", value_enum)]
+        /// The plugin manager the manifest is for.
+        from: migrate::Manager,
+        /// The manifest, like plug.gd.
+        manifest: PathBuf,
+        #[arg(long = "force", short = 'f')]
+        /// Replace the config file, if there is one.
+        force: bool,
+    },
     /// Helpful initializer for the godot.package file.
     Init {
         #[arg(long = "packages", num_args = 0..)]
         packages: Vec<ParsedPackage>,
     },
+    /// Any other command `gpm foo` runs `gpm-foo` from the PATH, with the rest of the arguments.
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+#[derive(Subcommand)]
+enum CacheActions {
+    /// Lists the stored packages, with their sizes.
+    Ls,
+    /// Prints where the store is, and how big.
+    Info,
+    /// Deletes stored packages (by name, name@version, or key), or everything.
+    Clean {
+        #[arg(add = ArgValueCompleter::new(completions::packages))]
+        /// The packages to delete. Everything, if none are given.
+        packages: Vec<String>,
+    },
+    /// Checks every stored tarball still matches its hash.
+    Verify {
+        #[arg(long = "fix")]
+        /// Delete the broken ones, so they are downloaded again when needed.
+        fix: bool,
+    },
+    /// Puts tarballs from somewhere else (like package.tgz) into the store, so installing the packages they are works without the registry.
+    Add {
+        /// The tarballs to add.
+        #[arg(required = true)]
+        tarballs: Vec<PathBuf>,
+    },
+    /// Writes every package the project needs into one file, for gpm cache import on a machine without the registry.
+    Export {
+        /// The bundle to write, like bundle.tar.
+        bundle: PathBuf,
+    },
+    /// Puts the packages of a gpm cache export bundle into the store, to install them without the registry.
+    Import {
+        /// The bundle to read.
+        bundle: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum RegistryActions {
+    /// Writes the index.json of the tarballs in a dir, so the dir can be served from any static host (like s3) as a registry.
+    #[command(long_about = "
+Write the index.json of the tarballs (.tgz and .zip) in a dir, next to them.
+Upload the dir anywhere that serves files, and use it as a registry with
+    gpm --registry https://bucket.example.com/gpm/index.json update")]
+    Build {
+        /// The dir with the tarballs.
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksActions {
+    /// Installs a git hook that checks the lockfile is in sync with the config, and the installed packages, before committing.
+    InstallGit {
+        #[arg(long = "pre-push")]
+        /// Check before pushing, instead of before committing.
+        pre_push: bool,
+        #[arg(long = "force", short = 'f')]
+        /// Replace the hook, even if gpm didnt write it.
+        force: bool,
+    },
+    /// Checks the lockfile is in sync with the config, and the installed packages, as they were after the last update.
+    Check,
+}
+
+#[derive(Subcommand)]
+enum TagActions {
+    /// Points a tag at a version.
+    Add {
+        /// The package, and the version to tag.
+        package: ParsedPackage,
+        tag: String,
+        #[command(flatten)]
+        auth: Auth,
+    },
+    /// Removes a tag.
+    Rm {
+        package: String,
+        tag: String,
+        #[command(flatten)]
+        auth: Auth,
+    },
+    /// Lists the tags. Of the package in the current directory, if no package is given.
+    Ls { package: Option<String> },
+}
+
+/// Authentication for registry writes.
+#[derive(clap::Args)]
+struct Auth {
+    #[arg(long = "token")]
+    /// The registry token. Defaults to $GPM_TOKEN, then $NPM_TOKEN.
+    token: Option<String>,
+    #[arg(long = "otp")]
+    /// The one-time password, for accounts (or packages) with two factor auth. Prompted for if needed.
+    otp: Option<String>,
+}
+
+impl Auth {
+    fn token(&self) -> String {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("GPM_TOKEN").ok())
+            .or_else(|| std::env::var("NPM_TOKEN").ok())
+            .expect("Writing to the registry needs a token (pass --token, or set $GPM_TOKEN)")
+    }
+}
+
+impl Actions {
+    /// How far `update` should bump the wanted versions, if at all.
+    fn bump(&self) -> Option<upgrade::Bump> {
+        match self {
+            Self::Update { patch: true, .. } => Some(upgrade::Bump::Patch),
+            Self::Update { minor: true, .. } => Some(upgrade::Bump::Minor),
+            Self::Update { latest: true, .. }
+            | Self::Update {
+                interactive: true, ..
+            } => Some(upgrade::Bump::Major),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 /// Charset for the tree subcommand.
+#[allow(clippy::upper_case_acronyms)]
 enum CharSet {
     /// Unicode characters (├── └──).
     UTF8,
@@ -134,19 +651,208 @@ pub struct Client {
     real: RealClient,
     cache: Cache,
     registry: String,
+    /// Only fetch metadata, never tarballs.
+    metadata_only: bool,
+    /// Resolve ranges to prereleases too.
+    pre: bool,
+    /// The prerelease channel (like `beta`) of some packages, by name.
+    channels: Arc<HashMap<String, String>>,
+    /// The godot version of the project, to prefer package versions that work with it.
+    godot: Option<Version>,
+    /// The release asset globs of release packages (see [releases]), by name.
+    assets: Arc<HashMap<String, String>>,
+    /// Resolve ranges to their oldest version, instead of the newest.
+    minimal: bool,
+    /// Only install the packages vendored here (see [vendor]), never from the registry.
+    vendor: Option<Arc<PathBuf>>,
+    /// Records the packuments resolving uses, or replays them (see [snapshot]).
+    snapshot: Option<Arc<snapshot::Snapshot>>,
+    /// The credentials of registries, by url (see [user_config::credentials]).
+    registries: Arc<BTreeMap<String, user_config::Registry>>,
+    /// The index of a static registry (see [registry]), once its downloaded.
+    index: Arc<std::sync::OnceLock<vendor::Index>>,
+    /// The packages being resolved (as name@version), outermost first, to catch dependency cycles.
+    resolving: Arc<Vec<String>>,
+    limits: Limits,
+    /// How many packages were resolved, for [Limits::packages].
+    resolved: Arc<AtomicUsize>,
+}
+
+/// How far resolving goes, before it gives up: metadata that explodes the tree (by mistake, or not) stops there.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// How deep the dependencies may go.
+    pub depth: usize,
+    /// How many packages may be resolved, in all.
+    pub packages: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            depth: 64,
+            packages: 5000,
+        }
+    }
 }
 
 impl Client {
     pub fn wrap(real: RealClient, cache: Cache, registry: String) -> Self {
         Self {
             real,
-            registry,
+            // azure artifacts feeds are given with a trailing slash
+            registry: registry.trim_end_matches('/').to_owned(),
             cache,
+            metadata_only: false,
+            pre: false,
+            channels: Arc::default(),
+            godot: None,
+            assets: Arc::default(),
+            minimal: false,
+            vendor: None,
+            snapshot: None,
+            registries: Arc::default(),
+            index: Arc::default(),
+            resolving: Arc::default(),
+            limits: Limits::default(),
+            resolved: Arc::default(),
+        }
+    }
+
+    /// Lets ranges resolve to prereleases, like `--pre`.
+    pub fn prerelease(self) -> Self {
+        Self { pre: true, ..self }
+    }
+
+    /// Lets the packages in `channels` resolve to prereleases on their channel (`2.0.0-beta.3` is on `beta`).
+    pub fn with_channels(self, channels: HashMap<String, String>) -> Self {
+        Self {
+            channels: Arc::new(channels),
+            ..self
+        }
+    }
+
+    /// Prefers the versions of packages that say they work with `godot` (in their `engines.godot`).
+    pub fn with_godot(self, godot: Option<Version>) -> Self {
+        Self { godot, ..self }
+    }
+
+    /// Resolves ranges to the oldest version that matches, like `resolution = "minimal"`.
+    pub fn minimal(self) -> Self {
+        Self {
+            minimal: true,
+            ..self
+        }
+    }
+
+    /// Installs only from the vendor dir `dir`, like `vendor = "dir"` (see [vendor::load]).
+    pub fn with_vendor(self, dir: PathBuf) -> Self {
+        Self {
+            vendor: Some(Arc::new(dir)),
+            ..self
+        }
+    }
+
+    /// Authenticates reads from the `registries` that always want credentials.
+    pub fn with_registries(self, registries: BTreeMap<String, user_config::Registry>) -> Self {
+        Self {
+            registries: Arc::new(registries),
+            ..self
+        }
+    }
+
+    /// The client to resolve the dependencies of `id` (a name@version) with.
+    /// If `id` is being resolved already, that is a cycle: then its the path around it, like `a@1.0.0 -> b@1.0.0 -> a@1.0.0`.
+    pub fn resolving(&self, id: String) -> Result<Self, String> {
+        if let Some(i) = self.resolving.iter().position(|r| *r == id) {
+            let mut path = self.resolving[i..].to_vec();
+            path.push(id);
+            return Err(path.join(" -> "));
+        }
+        let mut resolving = (*self.resolving).clone();
+        resolving.push(id);
+        Ok(Self {
+            resolving: Arc::new(resolving),
+            ..self.clone()
+        })
+    }
+
+    /// Resolves no deeper than `depth`, and no more than `packages` (the defaults, for the ones not given), counting packages anew.
+    pub fn with_limits(self, depth: Option<usize>, packages: Option<usize>) -> Self {
+        let d = Limits::default();
+        Self {
+            limits: Limits {
+                depth: depth.unwrap_or(d.depth),
+                packages: packages.unwrap_or(d.packages),
+            },
+            resolved: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Counts `id` (a name@version, the last one [Client::resolving] took) as resolved, failing if that goes over the [Limits].
+    pub fn within_limits(&self, id: &str) -> Result<()> {
+        if self.resolving.len() > self.limits.depth {
+            anyhow::bail!(
+                "The dependencies go deeper than {} (max-depth in the user config): {}",
+                self.limits.depth,
+                self.resolving.join(" -> ")
+            );
+        }
+        let n = self.resolved.fetch_add(1, Ordering::Relaxed) + 1;
+        if n > self.limits.packages {
+            anyhow::bail!(
+                "More than {} packages were resolved (max-packages in the user config), the last was {id}",
+                self.limits.packages
+            );
+        }
+        Ok(())
+    }
+
+    /// Records the packuments resolving uses into `snapshot`, or, if it [replays](snapshot::Snapshot::is_replay), resolves from it alone.
+    pub fn with_snapshot(self, snapshot: Arc<snapshot::Snapshot>) -> Self {
+        Self {
+            snapshot: Some(snapshot),
+            ..self
+        }
+    }
+
+    /// Picks the release asset of the release packages in `assets` (see [releases::packument]).
+    pub fn with_assets(self, assets: HashMap<String, String>) -> Self {
+        Self {
+            assets: Arc::new(assets),
+            ..self
+        }
+    }
+
+    /// The release asset glob of the package `name`, if it has one.
+    pub fn asset(&self, name: &str) -> Option<&str> {
+        self.assets.get(name).map(String::as_str)
+    }
+
+    /// The prerelease channel of the package `name`, if it has one.
+    pub fn channel(&self, name: &str) -> Option<&str> {
+        self.channels.get(name).map(String::as_str)
+    }
+
+    /// For read only commands: packages that can only be resolved by downloading them (tarball urls) are left unresolved.
+    pub fn metadata_only(self) -> Self {
+        Self {
+            metadata_only: true,
+            ..self
+        }
+    }
+
+    /// A get, authenticated when it goes to a registry that always wants credentials.
+    pub fn get<U: IntoUrl + AsRef<str>>(&self, url: U) -> RequestBuilder {
+        match user_config::credentials(&self.registries, url.as_ref()) {
+            Some(r) => r.authorize(self.real.get(url)),
+            None => self.real.get(url),
         }
     }
 
-    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
-        self.real.get(url)
+    pub fn request<U: IntoUrl>(&self, method: reqwest::Method, url: U) -> RequestBuilder {
+        self.real.request(method, url)
     }
 
     pub fn cache(&self) -> Cache {
@@ -158,103 +864,1837 @@ impl Client {
     }
 }
 
-/// number of buffer slots
-const PARALLEL: usize = 6;
-lazy_static! {
-    static ref BEGIN: Instant = Instant::now();
+/// number of buffer slots
+const PARALLEL: usize = 6;
+/// Locked (in the project dir) while a gpm changes the project, with its pid in it.
+const PROJECT_LOCK: &str = ".gpm.lock";
+lazy_static! {
+    static ref BEGIN: Instant = Instant::now();
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    panic::set_hook(Box::new(|panic_info| {
+        eprint!("{:>12} ", putils::err());
+        if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
+            eprint!("{s}");
+        } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
+            eprint!("{s}");
+        } else {
+            eprint!("unknown");
+        };
+        if let Some(s) = panic_info.location() {
+            eprint!(" (@{}:{})", s.file(), s.line())
+        }
+        eprintln!();
+    }));
+    CompleteEnv::with_factory(Args::command).complete();
+    let args = Args::parse();
+    fn set_colors(val: bool) {
+        console::set_colors_enabled(val);
+        console::set_colors_enabled_stderr(val);
+    }
+    match args.colors {
+        ColorChoice::Always => set_colors(true),
+        ColorChoice::Never => set_colors(false),
+        ColorChoice::Auto => set_colors(Term::stdout().is_term() && Term::stderr().is_term()),
+    }
+    if args.timings {
+        timings::enable();
+    }
+    let user = UserConfig::load_or_default();
+    if let Some(cmd) = user.scan_command.clone() {
+        scan::set_command(cmd);
+    }
+    let mut client = mkclient(
+        args.registry.clone(),
+        Cache::load(Cache::dir(user.cache_dir.as_deref())),
+    )
+    .with_registries(user.registries.clone())
+    .with_limits(user.max_depth, user.max_packages);
+    if args.pre {
+        client = client.prerelease();
+    }
+    let _lock = lock_project(&args);
+    let c = client.clone();
+    match &args.action {
+        Actions::Completions { shell } => {
+            completions::write(*shell, &mut Args::command(), &mut std::io::stdout())
+                .expect("Writing completions should work");
+        }
+        Actions::External(argv) => external_command(&args, argv),
+        // the updates of the watch run in a gpm of their own, that doesnt watch
+        Actions::Update { watch: true, .. } if std::env::var_os(WATCHED).is_none() => {
+            watch_update(&args)
+        }
+        // exporting needs the config
+        Actions::Cache {
+            action: CacheActions::Export { bundle },
+        } => {
+            let project = Project::open(&args, c);
+            export_bundle(&project.config(&args).await, project.client, bundle, &args).await
+        }
+        Actions::Cache { action } => cache(action, client.cache_ref(), &args),
+        Actions::Registry {
+            action: RegistryActions::Build { dir },
+        } => build_registry(dir, &args),
+        Actions::Pack { out } => pack_package(out.as_deref(), &args),
+        Actions::Version { to, git } => bump_version(to, *git, &args),
+        Actions::Publish {
+            tag,
+            max_size,
+            provenance,
+            auth,
+        } => publish_package(tag, *max_size, *provenance, auth, &args, c).await,
+        Actions::Deprecate {
+            package,
+            message,
+            auth,
+        } => deprecate(package, message, auth, &args, c).await,
+        Actions::Tag { action } => tag(action, c, args.dry_run, args.verbosity.info()).await,
+        Actions::Diff {
+            package,
+            old,
+            new,
+            stat,
+        } => diff_versions(package, old, new, *stat, c).await,
+        Actions::New {
+            dir,
+            template,
+            name,
+            godot,
+        } => new_project(dir, template, name.as_deref(), godot.as_deref(), &args, c).await,
+        Actions::Import {
+            from,
+            manifest,
+            force,
+        } => import_manifest(*from, manifest, *force, &args),
+        Actions::Add {
+            package,
+            exact,
+            save_prefix,
+        } => add(package, *exact, save_prefix.as_deref(), &args, c).await,
+        Actions::Remove { packages } => remove(packages, &args),
+        Actions::Fetch => fetch_locked(&args, c).await,
+        Actions::Freeze => freeze_config(&args),
+        Actions::Hooks { action } => {
+            let project = Project::open(&args, c);
+            git_hooks(
+                action,
+                &args,
+                &project.contents,
+                &project.cwd,
+                &project.install_dir,
+            )
+        }
+        Actions::Adopt { yes } => {
+            let project = Project::open(&args, c);
+            let install_dir = project.cwd.join(&project.install_dir);
+            adopt(&args, *yes, project.contents, project.client, &install_dir).await
+        }
+        Actions::Update { .. } => update_project(Project::open(&args, c), &args, &user).await,
+        Actions::Vendor { dir } => vendor_project(Project::open(&args, c), dir, &args).await,
+        Actions::Purge => {
+            let project = Project::open(&args, c);
+            purge_project(project.config(&args).await, &args, &project.cwd)
+        }
+        Actions::Tree {
+            charset,
+            prefix,
+            print_tarballs,
+        } => {
+            let project = Project::open(&args, c);
+            let cfg = project.config(&args).await;
+            let tree = tree(&cfg, *charset, *prefix, *print_tarballs, project.client).await;
+            println!("{tree}")
+        }
+        Actions::Graph { format, json } => {
+            let project = Project::open(&args, c);
+            let cfg = project.config(&args).await;
+            if *json {
+                println!("{}", cfg.graph.json())
+            } else {
+                let title = project
+                    .cwd
+                    .file_name()
+                    .map_or(".".into(), |n| n.to_string_lossy());
+                print!("{}", cfg.graph.render(*format, &title))
+            }
+        }
+        Actions::Status => {
+            let project = Project::open(&args, c);
+            status(&project.config(&args).await, &project, &args).await
+        }
+        Actions::Plugins { fix } => {
+            let project = Project::open(&args, c);
+            check_plugins(&project.config(&args).await, &project.cwd, *fix, &args)
+        }
+        Actions::Exports { fix } => {
+            let project = Project::open(&args, c);
+            check_exports(&project.config(&args).await, &project.cwd, *fix, &args)
+        }
+        Actions::ExportCheck { preset } => {
+            let project = Project::open(&args, c);
+            export_check(
+                &project.config(&args).await,
+                &project.cwd,
+                preset.as_deref(),
+                &args,
+            )
+        }
+        Actions::Compat { godot } => {
+            let project = Project::open(&args, c);
+            check_compat(
+                &project.config(&args).await,
+                godot.as_deref(),
+                &project.client,
+                &args,
+            )
+        }
+        Actions::Unused => {
+            let project = Project::open(&args, c);
+            find_unused(&project.config(&args).await, &project.cwd, &args)
+        }
+        Actions::Report { format, out } => {
+            let project = Project::open(&args, c);
+            write_report(
+                &project.config(&args).await,
+                &project,
+                *format,
+                out.as_deref(),
+            )
+        }
+        Actions::Size => {
+            let project = Project::open(&args, c);
+            print_sizes(&project.config(&args).await, &project)
+        }
+        Actions::Licenses { format, out } => {
+            let project = Project::open(&args, c);
+            write_licenses(
+                &project.config(&args).await,
+                &project.cwd,
+                *format,
+                out.as_deref(),
+            )
+        }
+        Actions::Init { packages } => {
+            let project = Project::open(&args, c);
+            init(
+                packages
+                    .clone()
+                    .try_into_async(project.client.clone())
+                    .await
+                    .expect("Failed to parse `init` packages"),
+                project.client,
+                &project.cwd,
+                args.dry_run,
+            )
+            .await
+            .expect("Initializing cfg should be ok");
+        }
+    }
+    if args.timings {
+        eprint!("{}", timings::report(BEGIN.elapsed()));
+    }
+    if args.dry_run {
+        return;
+    }
+    if let Err(e) = client.cache_ref().resolutions().save() {
+        eprintln!(
+            "{:>12} Could not save resolution cache: {e}",
+            putils::warn()
+        );
+    }
+}
+
+/// Locks the project, for the commands that change it: two gpms changing the project at once
+/// (the editor plugin and a terminal) would mix up what they write.
+fn lock_project(args: &Args) -> Option<Lock> {
+    let watching = matches!(args.action, Actions::Update { watch: true, .. })
+        && std::env::var_os(WATCHED).is_none();
+    let changes = matches!(
+        args.action,
+        Actions::Add { .. }
+            | Actions::Remove { .. }
+            | Actions::Freeze
+            | Actions::Adopt { .. }
+            | Actions::Update { .. }
+            | Actions::Vendor { .. }
+            | Actions::Purge
+            | Actions::Init { .. }
+            | Actions::Plugins { fix: true }
+            | Actions::Exports { fix: true }
+            | Actions::Version { .. }
+            | Actions::Import { .. }
+    );
+    // the watching gpm only starts the ones that update, which lock it themselves
+    (!args.dry_run && changes && !watching).then(|| {
+        let lock = Lock::exclusive(Path::new(PROJECT_LOCK), cache::lock::timeout())
+            .expect("Locking the project should work");
+        let _ = lock.mark();
+        lock
+    })
+}
+
+/// Reads the config (from stdin, with `-`), and checks it strictly if the config or --strict says so.
+fn read_config(args: &Args) -> String {
+    let mut contents = String::from("");
+    if args.config_file == Path::new("-") {
+        let bytes = stdin()
+            .read_to_string(&mut contents)
+            .expect("Stdin read should be ok");
+        if bytes == 0 {
+            panic!("Stdin should not be empty");
+        };
+    } else {
+        contents = read_to_string(&args.config_file).expect("Reading config file should be ok");
+    };
+    annotate::set(args.output, args.config_file.clone(), contents.clone());
+    if args.strict || ConfigFile::strict_of(&contents) {
+        let problems = ConfigFile::check_strict(&contents).expect("Config should parse");
+        if !problems.is_empty() {
+            for (key, problem) in &problems {
+                annotate::report(Level::Error, Some(key), problem);
+            }
+            eprintln!(
+                "{:>12} The config isnt strict (see strict in the config, and --strict)",
+                putils::err()
+            );
+            std::process::exit(1);
+        }
+    }
+    contents
+}
+
+/// Writes the changed config `contents` back (to stdout, with `-`).
+fn write_config(args: &Args, contents: &str) {
+    if args.config_file == Path::new("-") {
+        println!("{contents}");
+    } else if args.dry_run {
+        println!(
+            "{:>12} write {}",
+            putils::would(),
+            args.config_file.display()
+        );
+    } else {
+        atomic::write(&args.config_file, contents).expect("Writing config file should be ok");
+    }
+}
+
+/// Writes the lock file of `cfg` (to stdout, with `-`).
+fn write_lock(cfg: &ConfigFile, args: &Args, cwd: &Path) {
+    let lockfile = cfg.lock(cwd);
+    let path = &args.lock_file;
+    if args.dry_run {
+        if path != Path::new("-") {
+            println!("{:>12} write {}", putils::would(), path.display());
+        }
+    } else if path == Path::new("-") {
+        println!("{lockfile}");
+    } else {
+        atomic::write(path, lockfile).expect("Writing lock file should be ok");
+    }
+}
+
+/// The project gpm runs in: its config, and where the packages go.
+struct Project {
+    contents: String,
+    cwd: PathBuf,
+    install_dir: PathBuf,
+    /// The vendor dir of the config (see [vendor]).
+    vendor: Option<PathBuf>,
+    /// Knows the godot of the project, and installs from the vendor dir (if the config has one).
+    client: Client,
+}
+
+impl Project {
+    /// Reads the config (see [read_config]) of the project in the current directory.
+    fn open(args: &Args, client: Client) -> Self {
+        let contents = read_config(args);
+        let cwd = current_dir().expect("Should be able to read cwd");
+        // the config can say otherwise
+        let mut client = client.with_godot(godot::project_version(&cwd));
+        let install_dir = args
+            .install_dir
+            .clone()
+            .or_else(|| ConfigFile::install_dir_of(&contents))
+            .unwrap_or_else(|| PathBuf::from(package::DEFAULT_INSTALL_DIR));
+        let vendor = ConfigFile::vendor_of(&contents);
+        if let (Some(dir), false) = (&vendor, matches!(args.action, Actions::Vendor { .. })) {
+            let dir = cwd.join(dir);
+            vendor::load(&dir, client.cache_ref()).expect("Reading the vendor dir should work");
+            client = client.with_vendor(dir);
+        }
+        Self {
+            contents,
+            cwd,
+            install_dir,
+            vendor,
+            client,
+        }
+    }
+
+    /// Resolves the config. Only the metadata, for the commands that only look at the packages (and with --dry-run).
+    async fn config(&self, args: &Args) -> ConfigFile {
+        let client = match args.action {
+            Actions::Tree { .. }
+            | Actions::Graph { .. }
+            | Actions::Licenses { .. }
+            | Actions::Size
+            | Actions::Status
+            | Actions::Plugins { .. }
+            | Actions::Exports { .. }
+            | Actions::ExportCheck { .. }
+            | Actions::Unused
+            | Actions::Compat { .. }
+            | Actions::Report { .. } => self.client.clone().metadata_only(),
+            _ if args.dry_run => self.client.clone().metadata_only(),
+            _ => self.client.clone(),
+        };
+        let mut cfg = ConfigFile::new(&self.contents, client).await;
+        overrides(&mut cfg, args);
+        let _ = BEGIN.elapsed(); // needed to initialize the instant for whatever reason
+        cfg
+    }
+}
+
+/// Runs the `gpm-<name>` on the PATH for a command gpm doesnt have, or says what was meant.
+fn external_command(args: &Args, argv: &[OsString]) -> ! {
+    let (name, rest) = argv.split_first().expect("clap gives the command name");
+    let name = name.to_string_lossy();
+    let Some(exe) = external::find(&name, &std::env::var_os("PATH").unwrap_or_default()) else {
+        let command = Args::command();
+        let commands = command
+            .get_subcommands()
+            .flat_map(|c| std::iter::once(c.get_name()).chain(c.get_all_aliases()));
+        match external::nearest(&name, commands) {
+            Some(c) => eprintln!(
+                "{:>12} `{name}` is not a gpm command (did you mean `{c}`?)",
+                putils::err()
+            ),
+            None => eprintln!(
+                "{:>12} `{name}` is not a gpm command (and there is no gpm-{name} on the PATH)",
+                putils::err()
+            ),
+        }
+        std::process::exit(1);
+    };
+    let cwd = current_dir().expect("Should be able to read cwd");
+    let status = std::process::Command::new(&exe)
+        .args(rest)
+        .envs(external_env(args, &cwd))
+        .status();
+    match status {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("{:>12} Could not run {}: {e}", putils::err(), exe.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+fn build_registry(dir: &Path, args: &Args) {
+    if args.dry_run {
+        println!(
+            "{:>12} write {}",
+            putils::would(),
+            dir.join(vendor::INDEX).display()
+        );
+        return;
+    }
+    let built =
+        registry::build(dir).unwrap_or_else(|e| panic!("Building the registry should work: {e:#}"));
+    if args.verbosity.info() {
+        println!(
+            "{:>12} {} package{} in {}",
+            putils::green("Indexed"),
+            built.len(),
+            if built.len() == 1 { "" } else { "s" },
+            dir.display()
+        );
+    }
+}
+
+fn pack_package(out: Option<&Path>, args: &Args) {
+    let cwd = current_dir().expect("Should be able to read cwd");
+    run_hook(&cwd, "prepack", args.dry_run);
+    let packed = pack::pack(&cwd).expect("Packing should work");
+    let out = out.map_or_else(|| cwd.join(packed.file_name()), Path::to_path_buf);
+    if args.verbosity.info() {
+        print_packed(&packed);
+    }
+    if args.dry_run {
+        println!("{:>12} write {}", putils::would(), out.display());
+    } else {
+        write(&out, &packed.tarball).expect("Writing tarball should work");
+    }
+}
+
+fn bump_version(to: &str, git: bool, args: &Args) {
+    let cwd = current_dir().expect("Should be able to read cwd");
+    let path = cwd.join("package.json");
+    let contents = read_to_string(&path).expect("Reading package.json should work");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&contents).expect("package.json should be valid json");
+    let current = manifest["version"]
+        .as_str()
+        .and_then(|v| Version::new(v).parse().ok())
+        .expect("package.json should have a valid version");
+    let new = version::next(&current, to).expect("Bumping the version should work");
+    if args.dry_run {
+        println!("{:>12} bump {current} -> {new}", putils::would());
+        return;
+    }
+    if git {
+        version::check_clean(&cwd).expect("Committing the version should work");
+    }
+    hooks::run(&manifest, "preversion", &cwd).expect("The preversion hook should work");
+    atomic::write(
+        &path,
+        version::set(&contents, &new).expect("Bumping the version should work"),
+    )
+    .expect("Writing package.json should work");
+    if git {
+        version::commit_and_tag(&cwd, &new).expect("Committing the version should work");
+    }
+    hooks::run(&manifest, "postversion", &cwd).expect("The postversion hook should work");
+    if args.verbosity.info() {
+        println!("{:>12} {current} -> {new}", putils::green("Bumped"));
+    }
+}
+
+async fn publish_package(
+    tag: &str,
+    max_size: u64,
+    provenance: bool,
+    auth: &Auth,
+    args: &Args,
+    client: Client,
+) {
+    let cwd = current_dir().expect("Should be able to read cwd");
+    for hook in ["prepublish", "prepack"] {
+        run_hook(&cwd, hook, args.dry_run);
+    }
+    let packed = pack::pack(&cwd).expect("Packing should work");
+    let problems = publish::validate(&packed, client.clone(), max_size).await;
+    if !problems.is_empty() {
+        let manifest = cwd.join("package.json");
+        let contents = read_to_string(&manifest).unwrap_or_default();
+        for (what, p) in &problems {
+            annotate::report_in(Level::Error, &manifest, &contents, what.as_deref(), p);
+        }
+        panic!(
+            "{} problem{} with the package (see above)",
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" }
+        );
+    }
+    if args.dry_run || args.verbosity.info() {
+        print_packed(&packed);
+    }
+    if args.dry_run {
+        println!(
+            "{:>12} publish {}@{} to {} (tag {tag})",
+            putils::would(),
+            packed.name,
+            packed.version,
+            client.registry,
+        );
+        return;
+    }
+    let token = auth.token();
+    let bundle = if provenance {
+        Some(
+            publish::provenance::bundle(&packed, client.clone())
+                .await
+                .expect("Generating provenance should work"),
+        )
+    } else {
+        None
+    };
+    with_otp(auth.otp.clone(), |otp| {
+        let (packed, client, token, bundle) = (&packed, client.clone(), &token, bundle.as_ref());
+        async move {
+            backend::registry(&client)
+                .publish(client.clone(), packed, tag, token, otp.as_deref(), bundle)
+                .await
+        }
+    })
+    .await
+    .expect("Publishing should work");
+    if args.verbosity.info() {
+        println!(
+            "{:>12} {}@{} ({tag})",
+            putils::green("Published"),
+            packed.name,
+            packed.version
+        );
+    }
+}
+
+async fn deprecate(
+    package: &ParsedPackage,
+    message: &str,
+    auth: &Auth,
+    args: &Args,
+    client: Client,
+) {
+    let range = package.version.to_string();
+    if args.dry_run {
+        println!(
+            "{:>12} deprecate {}@{range} ({message:?})",
+            putils::would(),
+            package.name
+        );
+        return;
+    }
+    let token = auth.token();
+    let changed = std::sync::Mutex::new(vec![]);
+    with_otp(auth.otp.clone(), |otp| {
+        let (client, token, range, changed) = (client.clone(), &token, &range, &changed);
+        async move {
+            let c = publish::deprecate::deprecate(
+                client,
+                &package.name,
+                range,
+                message,
+                token,
+                otp.as_deref(),
+            )
+            .await?;
+            *changed.lock().unwrap() = c;
+            Ok(())
+        }
+    })
+    .await
+    .expect("Deprecating should work");
+    if args.verbosity.info() {
+        for v in changed.into_inner().unwrap() {
+            println!(
+                "{:>12} {}@{v}",
+                putils::green(if message.is_empty() {
+                    "Undeprecated"
+                } else {
+                    "Deprecated"
+                }),
+                package.name
+            );
+        }
+    }
+}
+
+async fn diff_versions(package: &str, old: &str, new: &str, stat: bool, client: Client) {
+    let files = |v: &str| {
+        let (client, package, v) = (client.clone(), package.to_string(), v.to_string());
+        async move {
+            let p = Package::new(package, v, client.clone())
+                .await
+                .expect("Package should exist");
+            let name = p.to_string();
+            (
+                name,
+                p.files(client).await.expect("Reading tarball should work"),
+            )
+        }
+    };
+    let (old, new) = (files(old).await, files(new).await);
+    if stat {
+        println!("{}", diff::stat(&old.1, &new.1));
+    } else {
+        print!("{}", diff::unified(&old.1, &new.1, &old.0, &new.0));
+    }
+}
+
+fn import_manifest(from: migrate::Manager, manifest: &Path, force: bool, args: &Args) {
+    let src = read_to_string(manifest).expect("Reading the manifest should work");
+    let (cfg, lost) = migrate::import(from, &src)
+        .unwrap_or_else(|e| panic!("Importing {} should work: {e}", manifest.display()));
+    for l in &lost {
+        eprintln!("{:>12} {l}", putils::warn());
+    }
+    if args.config_file != Path::new("-") && args.config_file.exists() && !force {
+        panic!(
+            "{} exists already (use --force to replace it)",
+            args.config_file.display()
+        );
+    }
+    write_config(args, &cfg);
+    if !args.dry_run && args.config_file != Path::new("-") && args.verbosity.info() {
+        println!(
+            "{:>12} {} (run update to install the packages)",
+            putils::green("Imported"),
+            args.config_file.display()
+        );
+    }
+}
+
+async fn add(
+    package: &ParsedPackage,
+    exact: bool,
+    save_prefix: Option<&str>,
+    args: &Args,
+    client: Client,
+) {
+    let contents = read_config(args);
+    let range = wanted_range(package, exact, save_prefix, client).await;
+    if args.verbosity.info() {
+        println!("{:>12} {}@{range}", putils::green("Adding"), package.name);
+    }
+    let contents =
+        ConfigFile::add(&contents, &package.name, range).expect("Adding to the config should work");
+    write_config(args, &contents);
+}
+
+fn remove(packages: &[String], args: &Args) {
+    let contents = ConfigFile::remove(&read_config(args), packages)
+        .expect("Removing from the config should work");
+    if args.verbosity.info() {
+        for p in packages {
+            println!("{:>12} {p}", putils::green("Removing"));
+        }
+    }
+    write_config(args, &contents);
+}
+
+/// If a flag makes update do what the last one (the one [fingerprint] is of) maybe didnt, so it cant be skipped:
+/// --symlink, --pre, --ignore-scripts, --strict, and an --integrity that isnt the one of the lock file.
+fn flags_change_update(args: &Args) -> bool {
+    args.symlink
+        || args.pre
+        || args.ignore_scripts
+        || args.strict
+        || args
+            .integrity
+            .is_some_and(|a| a != lock_algorithm(&args.lock_file))
+}
+
+/// Downloads the packages of the lock file into the store (see [fetch]).
+async fn fetch_locked(args: &Args, client: Client) {
+    read_config(args);
+    let lockfile = read_to_string(&args.lock_file)
+        .expect("Reading the lock file should work (run update first)");
+    if args.dry_run {
+        println!("{:>12} fetch {}", putils::would(), args.lock_file.display());
+        return;
+    }
+    let cwd = current_dir().expect("Should be able to read cwd");
+    let client = client.with_godot(godot::project_version(&cwd));
+    let fetched = fetch::fetch(&lockfile, client)
+        .await
+        .expect("Fetching should work");
+    if args.verbosity.info() {
+        for f in &fetched {
+            let what = if f.downloaded { "Downloaded" } else { "Cached" };
+            println!("{:>12} {}", putils::green(what), f.package);
+        }
+    }
+}
+
+fn freeze_config(args: &Args) {
+    let contents = read_config(args);
+    let lockfile = read_to_string(&args.lock_file)
+        .expect("Reading the lock file should work (run update first)");
+    let (frozen, missing) =
+        freeze::freeze(&contents, &lockfile).expect("Freezing the config should work");
+    for name in missing {
+        eprintln!(
+            "{:>12} {name} is not in the lock file, so it was left as it was",
+            putils::warn()
+        );
+    }
+    write_config(args, &frozen);
+}
+
+async fn update_project(mut project: Project, args: &Args, user: &UserConfig) {
+    let Actions::Update {
+        force,
+        interactive,
+        changelog,
+        import,
+        packages,
+        snapshot: record,
+        from_snapshot: replay,
+        ..
+    } = &args.action
+    else {
+        unreachable!()
+    };
+    let resolutions = project.client.cache_ref().resolutions();
+    for p in packages {
+        resolutions.forget(&project.client.registry, p);
+    }
+    let snapshot = match (*record, *replay) {
+        (true, _) => Some(Arc::new(snapshot::Snapshot::default())),
+        (_, true) => Some(Arc::new(
+            snapshot::Snapshot::load(&snapshot::path(&args.lock_file))
+                .expect("Reading the snapshot should work"),
+        )),
+        _ => None,
+    };
+    if let Some(s) = &snapshot {
+        project.client = project.client.clone().with_snapshot(s.clone());
+    }
+    if !force
+        && args.action.bump().is_none()
+        && snapshot.is_none()
+        && packages.is_empty()
+        && !flags_change_update(args)
+        && args.lock_file != Path::new("-")
+        && fingerprint::is_fresh(
+            &project.contents,
+            &args.lock_file,
+            &project.cwd.join(&project.install_dir),
+        )
+    {
+        if args.verbosity.info() {
+            println!(
+                "{:>12} nothing changed since the last update (use --force to update anyway)",
+                putils::green("Finished")
+            );
+        }
+        return;
+    }
+    let mut cfg = project.config(args).await;
+    let (cwd, client) = (&project.cwd, &project.client);
+    let mut contents = project.contents.clone();
+    let algorithm = args
+        .integrity
+        .unwrap_or_else(|| lock_algorithm(&args.lock_file));
+    if let Some(scope) = args.action.bump() {
+        let upgrades = upgrade::available(&cfg, client.clone(), scope)
+            .await
+            .expect("Looking for updates should work");
+        if upgrades.is_empty() {
+            println!("{:>12} everything is up to date", putils::green("Finished"));
+            return;
+        }
+        let picked = if *interactive {
+            putils::multi_select(&upgrades, "Pick updates").expect("Picking updates should work")
+        } else {
+            (0..upgrades.len()).collect()
+        };
+        if picked.is_empty() {
+            return;
+        }
+        let picked = picked.into_iter().map(|i| &upgrades[i]).collect::<Vec<_>>();
+        if args.verbosity.info() {
+            for u in &picked {
+                if args.dry_run {
+                    println!("{:>12} bump {u}", putils::would());
+                } else {
+                    println!("{:>12} {u}", putils::green("Bumping"));
+                }
+            }
+        }
+        contents = ConfigFile::rewrite(&contents, |name, old| {
+            picked.iter().find(|u| u.name == name).map(|u| u.range(old))
+        })
+        .expect("Rewriting config should work");
+        write_config(args, &contents);
+        cfg = ConfigFile::new(
+            &contents,
+            if args.dry_run {
+                client.clone().metadata_only()
+            } else {
+                client.clone()
+            },
+        )
+        .await;
+        overrides(&mut cfg, args);
+    }
+    let overlaps = cfg.overlaps();
+    for (a, b) in &overlaps {
+        eprintln!(
+            "{:>12} {a} ({}) and {b} ({}) would overwrite each others files",
+            if *force {
+                putils::warn()
+            } else {
+                putils::err()
+            },
+            a.download_dir(Path::new("")).display(),
+            b.download_dir(Path::new("")).display(),
+        );
+    }
+    if !overlaps.is_empty() && !force && !args.dry_run {
+        panic!("Packages overlap (see above). Give them their own dir in the config, or use --force to install anyway");
+    }
+    let before = read_to_string(&args.lock_file).unwrap_or_default();
+    consent(&mut cfg, &before, args.ignore_scripts, !args.dry_run);
+    hooks::set_plan(hook_plan(&cfg, &before, false));
+    if args.dry_run {
+        plan_update(&cfg, cwd);
+    } else {
+        let recorded = recorded_folders(&cfg, cwd);
+        update(
+            &mut cfg,
+            true,
+            args.verbosity,
+            client.clone(),
+            cwd,
+            algorithm,
+            &before,
+        )
+        .await;
+        sync_translations(&cfg, cwd, args.verbosity, false, &recorded);
+        if *import {
+            if args.verbosity.info() {
+                println!("{:>12} the project", putils::green("Importing"));
+            }
+            if let Err(e) = godot::import(cwd) {
+                eprintln!("{:>12} {e:#}", putils::warn());
+            }
+        }
+        if *changelog {
+            print_changelogs(&cfg, &before, cwd);
+        }
+        evict(client.cache_ref(), user, args.verbosity);
+    }
+    write_lock(&cfg, args, cwd);
+    if !args.dry_run && args.lock_file != Path::new("-") {
+        if let Err(e) = fingerprint::save(&contents, &args.lock_file, &cwd.join(cfg.install_dir()))
+        {
+            eprintln!("{:>12} Could not save fingerprint: {e}", putils::warn());
+        }
+    }
+    if let Some(s) = snapshot.filter(|s| !s.is_replay()) {
+        let path = snapshot::path(&args.lock_file);
+        if args.dry_run {
+            println!("{:>12} write {}", putils::would(), path.display());
+        } else {
+            s.save(&path).expect("Writing the snapshot should work");
+        }
+    }
+}
+
+async fn vendor_project(project: Project, dir: &Option<PathBuf>, args: &Args) {
+    let cfg = project.config(args).await;
+    let dir = project.cwd.join(
+        dir.clone()
+            .or(project.vendor)
+            .unwrap_or_else(|| "vendor".into()),
+    );
+    if args.dry_run {
+        for (_, p) in cfg.graph.iter() {
+            let file = vendor::file(&p.name, &p.manifest.full_version());
+            println!("{:>12} write {}", putils::would(), dir.join(file).display());
+        }
+        return;
+    }
+    let vendored = vendor::vendor(&cfg, project.client, &dir)
+        .await
+        .expect("Vendoring should work");
+    if args.verbosity.info() {
+        for p in &vendored {
+            println!("{:>12} {p}", putils::green("Vendored"));
+        }
+        println!(
+            "{:>12} {} package{} into {} (set vendor in the config to install from there)",
+            putils::green("Finished"),
+            vendored.len(),
+            if vendored.len() == 1 { "" } else { "s" },
+            dir.display()
+        );
+    }
+}
+
+async fn export_bundle(cfg: &ConfigFile, client: Client, bundle: &Path, args: &Args) {
+    if args.dry_run {
+        println!("{:>12} write {}", putils::would(), bundle.display());
+        return;
+    }
+    let vendored = vendor::collect(cfg, client)
+        .await
+        .unwrap_or_else(|e| panic!("{e:#}"));
+    write(
+        bundle,
+        bundle::pack(&vendored).expect("Packing the bundle should work"),
+    )
+    .expect("Writing the bundle should work");
+    if args.verbosity.info() {
+        println!(
+            "{:>12} {} package{} into {}",
+            putils::green("Exported"),
+            vendored.packages.len(),
+            if vendored.packages.len() == 1 {
+                ""
+            } else {
+                "s"
+            },
+            bundle.display()
+        );
+    }
+}
+
+fn purge_project(mut cfg: ConfigFile, args: &Args, cwd: &Path) {
+    let before = read_to_string(&args.lock_file).unwrap_or_default();
+    consent(&mut cfg, &before, args.ignore_scripts, !args.dry_run);
+    hooks::set_plan(hook_plan(&cfg, &before, true));
+    if args.dry_run {
+        plan_purge(&cfg, cwd);
+    } else {
+        let recorded = recorded_folders(&cfg, cwd);
+        purge(&cfg, args.verbosity, cwd);
+        sync_translations(&cfg, cwd, args.verbosity, true, &recorded);
+    }
+    write_lock(&cfg, args, cwd);
+}
+
+async fn status(cfg: &ConfigFile, project: &Project, args: &Args) {
+    let s = status::Status::of(
+        cfg,
+        &project.contents,
+        &args.lock_file,
+        &project.cwd,
+        project.client.clone(),
+    )
+    .await;
+    if annotate::github() {
+        for (level, line) in s.lines() {
+            if let Some(level) = level {
+                annotate::report(level, None, &line);
+            }
+        }
+    } else {
+        print!("{s}");
+    }
+    if !s.is_clean() {
+        std::process::exit(1);
+    }
+}
+
+fn check_plugins(cfg: &ConfigFile, cwd: &Path, fix: bool, args: &Args) {
+    let path = cwd.join("project.godot");
+    let project = read_to_string(&path).expect("There should be a project.godot");
+    let r = plugins::report(&cfg.graph, &project, cwd);
+    let report = |plugin: &str, message: String| {
+        annotate::report_in(
+            Level::Warning,
+            Path::new("project.godot"),
+            &project,
+            Some(plugin),
+            &message,
+        )
+    };
+    for (p, plugin) in &r.disabled {
+        report(plugin, format!("{plugin} (of {p}) is not enabled"));
+    }
+    for plugin in &r.missing {
+        report(plugin, format!("{plugin} is enabled, but not there"));
+    }
+    if r == plugins::Report::default() {
+        if args.verbosity.info() {
+            println!(
+                "{:>12} every installed plugin is enabled",
+                putils::green("Ok")
+            );
+        }
+    } else if !fix {
+        std::process::exit(1);
+    } else if args.dry_run {
+        println!("{:>12} write {}", putils::would(), path.display());
+    } else {
+        let enable = r.disabled.into_iter().map(|(_, p)| p).collect::<Vec<_>>();
+        atomic::write(&path, plugins::fix(&project, &enable, &r.missing))
+            .expect("Writing project.godot should work");
+        if args.verbosity.info() {
+            println!("{:>12} {}", putils::green("Fixed"), path.display());
+        }
+    }
+}
+
+fn check_exports(cfg: &ConfigFile, cwd: &Path, fix: bool, args: &Args) {
+    let path = cwd.join("export_presets.cfg");
+    let presets = read_to_string(&path).expect("There should be an export_presets.cfg");
+    let gaps = exports::gaps(&presets, &exports::libraries(&cfg.graph, cwd));
+    for g in &gaps {
+        annotate::report_in(
+            Level::Warning,
+            Path::new("export_presets.cfg"),
+            &presets,
+            Some(&g.preset),
+            &format!(
+                "{} doesnt export {} (of {}, for {})",
+                g.preset, g.library.path, g.library.package, g.library.features
+            ),
+        );
+    }
+    if gaps.is_empty() {
+        if args.verbosity.info() {
+            println!(
+                "{:>12} every preset exports the native libraries it needs",
+                putils::green("Ok")
+            );
+        }
+    } else if !fix {
+        std::process::exit(1);
+    } else if args.dry_run {
+        println!("{:>12} write {}", putils::would(), path.display());
+    } else {
+        atomic::write(&path, exports::fix(&presets, &gaps))
+            .expect("Writing export_presets.cfg should work");
+        if args.verbosity.info() {
+            println!("{:>12} {}", putils::green("Fixed"), path.display());
+        }
+    }
+}
+
+fn export_check(cfg: &ConfigFile, cwd: &Path, preset: Option<&str>, args: &Args) {
+    let presets = read_to_string(cwd.join("export_presets.cfg"))
+        .expect("There should be an export_presets.cfg");
+    let project = read_to_string(cwd.join("project.godot")).unwrap_or_default();
+    let findings = exports::check(cfg, &presets, &project, cwd, preset);
+    for f in &findings {
+        let contents = match f.file {
+            "project.godot" => &project,
+            _ => &presets,
+        };
+        annotate::report_in(
+            f.level,
+            Path::new(f.file),
+            contents,
+            Some(&f.about),
+            &f.message,
+        );
+    }
+    if findings.iter().any(|f| f.level == Level::Error) {
+        std::process::exit(1);
+    }
+    if findings.is_empty() && args.verbosity.info() {
+        println!("{:>12} ready to export", putils::green("Ok"));
+    }
+}
+
+fn check_compat(cfg: &ConfigFile, godot: Option<&str>, client: &Client, args: &Args) {
+    let g = match godot {
+        Some(g) => godot::parse(g).unwrap_or_else(|e| panic!("{e}")),
+        None => cfg.godot().or_else(|| client.godot.clone()).expect(
+            "The godot of the project should be known: give it with --godot (or godot in the config)",
+        ),
+    };
+    if args.verbosity.info() {
+        println!("{:>12} godot {g}", putils::green("Checking"));
+    }
+    let rows = compat::matrix(&cfg.graph, &g);
+    for r in &rows {
+        match r.finding(&g) {
+            Some((level, message)) if annotate::github() => {
+                annotate::report(level, Some(&r.name), &message)
+            }
+            _ => println!("{r}"),
+        }
+    }
+    if rows.iter().any(|r| r.verdict == compat::Verdict::Breaks) {
+        std::process::exit(1);
+    }
+}
+
+fn find_unused(cfg: &ConfigFile, cwd: &Path, args: &Args) {
+    let unused = unused::find(&cfg.graph, cwd, cfg.install_dir());
+    for p in &unused {
+        annotate::report(
+            Level::Warning,
+            Some(p.alias.as_deref().unwrap_or(&p.name)),
+            &format!("nothing uses {p} (gpm remove it, if so)"),
+        );
+    }
+    if !unused.is_empty() {
+        std::process::exit(1);
+    }
+    if args.verbosity.info() {
+        println!("{:>12} every wanted package is used", putils::green("Ok"));
+    }
+}
+
+fn write_report(cfg: &ConfigFile, project: &Project, format: report::Format, out: Option<&Path>) {
+    let title = project
+        .cwd
+        .file_name()
+        .map_or("packages".into(), |n| n.to_string_lossy());
+    let facts = report::Facts::gather(
+        &title,
+        &cfg.graph,
+        &project.cwd,
+        cfg.install_dir(),
+        project.client.cache_ref().store(),
+        cfg.godot()
+            .or_else(|| project.client.godot.clone())
+            .as_ref(),
+    );
+    let report = facts.render(format);
+    match out {
+        Some(out) => std::fs::write(out, report)
+            .unwrap_or_else(|e| panic!("Could not write {}: {e}", out.display())),
+        None => print!("{report}"),
+    }
+}
+
+fn print_sizes(cfg: &ConfigFile, project: &Project) {
+    let sizes = size::measure(&cfg.graph, &project.cwd, project.client.cache_ref().store())
+        .expect("Should be able to read the installed packages");
+    println!("{:>12} {:>10} {:>6}", "installed", "download", "files");
+    for s in &sizes {
+        println!(
+            "{:>12} {:>10} {:>6} {}",
+            HumanBytes(s.installed).to_string(),
+            s.download.map_or("?".into(), |d| HumanBytes(d).to_string()),
+            s.files,
+            s.package
+        );
+    }
+    println!(
+        "{:>12} {:>10} {:>6} {} package{}",
+        HumanBytes(sizes.iter().map(|s| s.installed).sum()).to_string(),
+        HumanBytes(sizes.iter().filter_map(|s| s.download).sum()).to_string(),
+        sizes.iter().map(|s| s.files).sum::<u64>(),
+        sizes.len(),
+        if sizes.len() == 1 { "" } else { "s" }
+    );
+}
+
+fn write_licenses(cfg: &ConfigFile, cwd: &Path, format: licenses::Format, out: Option<&Path>) {
+    let found = licenses::collect(&cfg.graph, cwd);
+    for l in found
+        .iter()
+        .filter(|l| l.license.is_none() && l.files.is_empty())
+    {
+        eprintln!(
+            "{:>12} {}@{} says nothing about its license",
+            putils::warn(),
+            l.name,
+            l.version
+        );
+    }
+    let report = licenses::report(&found, format);
+    match out {
+        Some(out) => std::fs::write(out, report)
+            .unwrap_or_else(|e| panic!("Could not write {}: {e}", out.display())),
+        None => print!("{report}"),
+    }
+}
+
+/// The integrity algorithm used by the lock file at `path`, sha512 if theres nothing to go by.
+fn lock_algorithm(path: &Path) -> Algorithm {
+    read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Vec<serde_json::Value>>(&s).ok())
+        .and_then(|entries| {
+            entries.iter().find_map(|e| {
+                e.get("integrity")?
+                    .as_str()?
+                    .parse::<Integrity>()
+                    .ok()
+                    .map(|i| i.algorithm)
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Decides which packages may run their hooks: the ones the config allows, the ones whose hooks the `lockfile` says were allowed
+/// (if they didnt change since), and, if `ask`ing in a terminal, the ones the user allows when shown them.
+/// With `ignore`, none.
+fn consent(cfg: &mut ConfigFile, lockfile: &str, ignore: bool, ask: bool) {
+    if ignore {
+        return;
+    }
+    let consented = hooks::consented(lockfile);
+    let ask = ask && Term::stdout().is_term();
+    let ids = cfg.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+    for id in ids {
+        let p = cfg.graph.get(id);
+        let hooks = &p.manifest.hooks;
+        if hooks.is_empty() {
+            continue;
+        }
+        let allowed = cfg.allows_hooks(&p.name)
+            || consented.get(&p.name) == Some(hooks)
+            || ask && {
+                let shown = hooks
+                    .iter()
+                    .map(|(event, cmd)| format!("\n    {event}: {cmd}"))
+                    .collect::<String>();
+                putils::confirm(&format!("{p} wants to run{shown}\nAllow it?"), false)
+                    .expect("Asking should work")
+            };
+        if allowed {
+            cfg.graph.get_mut(id).allow_hooks = true;
+        } else {
+            eprintln!(
+                "{:>12} not running the hooks of {p} (allow them with allow_hooks in the config)",
+                putils::warn()
+            );
+        }
+    }
+}
+
+/// Set for the updates of [watch_update].
+const WATCHED: &str = "GPM_WATCHED";
+
+/// Updates (as `gpm update`, without `--watch`) whenever the config file changes, forever.
+/// Every update runs in its own gpm, so a failing one doesnt stop the watching, and the fingerprint keeps them incremental.
+fn watch_update(args: &Args) -> ! {
+    if args.config_file == Path::new("-") {
+        panic!("Cant watch the config file when it is read from stdin");
+    }
+    let exe = std::env::current_exe().expect("Should be able to find the gpm executable");
+    let rest = std::env::args_os().skip(1).collect::<Vec<_>>();
+    loop {
+        // local packages are installed from what is in their dirs, so they are watched too
+        let mut watched = vec![args.config_file.clone()];
+        watched.extend(ConfigFile::local_dirs(
+            &read_to_string(&args.config_file).unwrap_or_default(),
+        ));
+        if let Err(e) = std::process::Command::new(&exe)
+            .args(&rest)
+            .env(WATCHED, "1")
+            .status()
+        {
+            eprintln!("{:>12} Could not run the update: {e}", putils::err());
+        }
+        if args.verbosity.info() {
+            let watched = watched
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>();
+            println!(
+                "{:>12} for changes to {} (ctrl+c to stop)",
+                putils::green("Watching"),
+                watched.join(", ")
+            );
+        }
+        watch::wait(&watched, watch::EVERY);
+    }
+}
+
+/// What an external subcommand gets told (see [Actions::External]): the global options, and the project, as `GPM_*` variables.
+fn external_env(args: &Args, cwd: &Path) -> Vec<(&'static str, OsString)> {
+    let flag = |b: bool| OsString::from(if b { "1" } else { "" });
+    let mut env = vec![
+        ("GPM_PROJECT_ROOT", cwd.into()),
+        ("GPM_CONFIG_FILE", args.config_file.clone().into()),
+        ("GPM_LOCK_FILE", args.lock_file.clone().into()),
+        ("GPM_REGISTRY", args.registry.clone().into()),
+        (
+            "GPM_VERBOSITY",
+            args.verbosity
+                .to_possible_value()
+                .unwrap()
+                .get_name()
+                .into(),
+        ),
+        ("GPM_DRY_RUN", flag(args.dry_run)),
+        ("GPM_PRE", flag(args.pre)),
+        ("GPM_SYMLINK", flag(args.symlink)),
+        ("GPM_IGNORE_SCRIPTS", flag(args.ignore_scripts)),
+    ];
+    if let Some(dir) = &args.install_dir {
+        env.push(("GPM_INSTALL_DIR", dir.clone().into()));
+    }
+    env
+}
+
+/// The install plan package hooks get (see [hooks::set_plan]): what happens to every package, going by the `before` lockfile.
+fn hook_plan(cfg: &ConfigFile, before: &str, uninstall: bool) -> String {
+    let locked = changes::versions(before);
+    let plan = cfg
+        .graph
+        .iter()
+        .map(|(_, p)| {
+            let version = p.manifest.full_version();
+            let mut entry = serde_json::json!({
+                "name": p.name,
+                "version": version,
+                "dir": p.download_dir(Path::new("")),
+            });
+            let action = match locked.get(&p.name) {
+                _ if uninstall => "uninstall",
+                Some(vs) if vs.contains(&version) => "reinstall",
+                Some(vs) => {
+                    entry["from"] = vs.iter().map(String::as_str).collect::<Vec<_>>().into();
+                    "update"
+                }
+                None => "install",
+            };
+            entry["action"] = action.into();
+            entry
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string(&plan).unwrap()
+}
+
+/// Makes the project `dir` from the `template` (see [Actions::New]).
+async fn new_project(
+    dir: &Path,
+    template: &str,
+    name: Option<&str>,
+    godot: Option<&str>,
+    args: &Args,
+    client: Client,
+) {
+    let godot = match godot {
+        Some(g) => godot::parse(g).expect("The godot version should be valid"),
+        None => godot::installed()
+            .expect("Could not tell the godot version (no godot on the PATH): pass --godot"),
+    };
+    let godot = match godot.patch {
+        0 => format!("{}.{}", godot.major, godot.minor),
+        _ => godot.to_string(),
+    };
+    let name = name.map(str::to_owned).unwrap_or_else(|| {
+        dir.file_name()
+            .expect("The project dir should have a name")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let files = if template::is_git(template) {
+        template::clone(template).expect("Cloning the template should work")
+    } else {
+        let p: ParsedPackage = template.parse().expect("The template should be a package");
+        let p = p
+            .into_package(client.clone())
+            .await
+            .expect("The template package should exist");
+        p.files(client)
+            .await
+            .expect("Reading the template should work")
+    };
+    let files = template::instantiate(files, &[("name", &name), ("godot_version", &godot)])
+        .expect("Filling in the template should work");
+    if args.dry_run {
+        for path in files.keys() {
+            println!("{:>12} write {}", putils::would(), dir.join(path).display());
+        }
+        return;
+    }
+    template::write_all(&files, dir).expect("Writing the project should work");
+    if args.verbosity.info() {
+        println!(
+            "{:>12} {name} in {} (run gpm update there to install its packages)",
+            putils::green("Created"),
+            dir.display()
+        );
+    }
+}
+
+/// Keeps the package store under the max cache size of the `user` config, if it has one (see [cache::store::Store::evict]).
+/// A bad one is warned about: the packages are installed already.
+fn evict(cache: &Cache, user: &UserConfig, v: Verbosity) {
+    let max = match user.max_cache_size() {
+        Ok(Some(max)) => max,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("{:>12} {e:#}: the store is not shrunk", putils::warn());
+            return;
+        }
+    };
+    let Some(store) = cache.store() else {
+        return;
+    };
+    match store.evict(max) {
+        Ok(evicted) if !evicted.is_empty() && v.info() => println!(
+            "{:>12} {} package{} from the store ({}), to keep it under {}",
+            putils::green("Evicted"),
+            evicted.len(),
+            if evicted.len() == 1 { "" } else { "s" },
+            HumanBytes(evicted.iter().map(|s| s.size).sum()),
+            HumanBytes(max)
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("{:>12} Could not shrink the store: {e}", putils::warn()),
+    }
+}
+
+/// Runs `gpm cache`.
+fn cache(action: &CacheActions, cache: &Cache, args: &Args) {
+    let store = cache.store().expect("The cache should be on disk");
+    let stored = store.list().expect("Reading the store should work");
+    let remove = |s: &cache::store::Stored, why: &str| {
+        let name = s.package.as_deref().unwrap_or(&s.key);
+        if args.dry_run {
+            println!("{:>12} delete {name}{why}", putils::would());
+            return;
+        }
+        store
+            .remove(&s.key)
+            .unwrap_or_else(|e| panic!("Deleting {name} should work: {e}"));
+        if args.verbosity.info() {
+            println!("{:>12} {name}{why}", putils::green("Deleted"));
+        }
+    };
+    match action {
+        CacheActions::Ls => {
+            for s in &stored {
+                println!(
+                    "{:>12} {} ({})",
+                    HumanBytes(s.size).to_string(),
+                    s.package.as_deref().unwrap_or("?"),
+                    s.key
+                );
+            }
+        }
+        CacheActions::Info => {
+            println!("{:>12} {}", "dir", store.dir().display());
+            println!("{:>12} {}", "packages", stored.len());
+            println!(
+                "{:>12} {}",
+                "size",
+                HumanBytes(stored.iter().map(|s| s.size).sum())
+            );
+        }
+        CacheActions::Clean { packages } => {
+            let picked = |s: &&cache::store::Stored| {
+                packages.is_empty()
+                    || packages.iter().any(|p| {
+                        *p == s.key
+                            || s.package.as_deref().is_some_and(|n| {
+                                n == p || n.rsplit_once('@').is_some_and(|(name, _)| name == p)
+                            })
+                    })
+            };
+            let picked = stored.iter().filter(picked).collect::<Vec<_>>();
+            if picked.is_empty() && !packages.is_empty() {
+                panic!("None of {} are stored", packages.join(", "));
+            }
+            for s in &picked {
+                remove(s, "");
+            }
+            if packages.is_empty() && !args.dry_run {
+                // they would point to packages that have to be downloaded again anyway
+                let _ = std::fs::remove_file(store.dir().join("resolutions.json"));
+            }
+            if args.verbosity.info() && !args.dry_run {
+                println!(
+                    "{:>12} {} (projects symlinked to them need gpm update --force)",
+                    putils::green("Freed"),
+                    HumanBytes(picked.iter().map(|s| s.size).sum())
+                );
+            }
+        }
+        CacheActions::Verify { fix } => {
+            let broken = stored
+                .iter()
+                .filter(|s| store.verify(&s.key) == Some(false))
+                .collect::<Vec<_>>();
+            for s in &broken {
+                if *fix {
+                    remove(s, " (its tarball doesnt match its hash)");
+                } else {
+                    eprintln!(
+                        "{:>12} {}: its tarball doesnt match its hash",
+                        putils::err(),
+                        s.package.as_deref().unwrap_or(&s.key)
+                    );
+                }
+            }
+            if args.verbosity.info() {
+                println!(
+                    "{:>12} {} stored package{}, {} broken{}",
+                    putils::green("Verified"),
+                    stored.len(),
+                    if stored.len() == 1 { "" } else { "s" },
+                    broken.len(),
+                    match fix {
+                        true if !args.dry_run => format!(", {} repaired", broken.len()),
+                        _ => String::new(),
+                    }
+                );
+            }
+            if !fix && !broken.is_empty() {
+                eprintln!(
+                    "{:>12} run gpm cache verify --fix to repair them",
+                    putils::warn()
+                );
+                std::process::exit(1);
+            }
+        }
+        CacheActions::Import { bundle } => {
+            let bytes = std::fs::read(bundle).expect("Reading the bundle should work");
+            if args.dry_run {
+                println!("{:>12} import {}", putils::would(), bundle.display());
+                return;
+            }
+            let imported = bundle::import(&bytes, cache, &args.registry)
+                .expect("Importing the bundle should work");
+            cache
+                .resolutions()
+                .save()
+                .expect("Saving the resolution cache should work");
+            if args.verbosity.info() {
+                for p in &imported {
+                    println!("{:>12} {p}", putils::green("Imported"));
+                }
+            }
+        }
+        CacheActions::Add { tarballs } => {
+            for t in tarballs {
+                let bytes = std::fs::read(t)
+                    .unwrap_or_else(|e| panic!("Reading {} should work: {e}", t.display()));
+                let name = tarball_package(&bytes)
+                    .unwrap_or_else(|e| panic!("{} is not a package tarball: {e}", t.display()));
+                if args.dry_run {
+                    println!("{:>12} add {name}", putils::would());
+                    continue;
+                }
+                let [key, _] = store.add(&bytes).expect("Adding to the store should work");
+                if args.verbosity.info() {
+                    println!("{:>12} {name} ({key})", putils::green("Added"));
+                }
+            }
+        }
+        CacheActions::Export { .. } => unreachable!(), // handled with the config
+    }
+}
+
+/// The name@version of the package in the tarball `bytes`, from its package.json.
+fn tarball_package(bytes: &[u8]) -> anyhow::Result<String> {
+    let p = registry::package_json(bytes)?;
+    Ok(format!("{}@{}", p.name, p.version))
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() {
-    panic::set_hook(Box::new(|panic_info| {
-        eprint!("{:>12} ", putils::err());
-        if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
-            eprint!("{s}");
-        } else if let Some(s) = panic_info.payload().downcast_ref::<String>() {
-            eprint!("{s}");
-        } else {
-            eprint!("unknown");
+/// Runs `gpm adopt`.
+async fn adopt(args: &Args, yes: bool, mut contents: String, client: Client, install_dir: &Path) {
+    let wanted = ConfigFile::names(&contents).expect("The config should be valid");
+    let mut adopted = vec![];
+    for rel in adopt::unmanaged(install_dir).expect("Reading the install dir should work") {
+        let found = adopt::identify(&client, install_dir, &rel, &wanted)
+            .await
+            .unwrap_or_else(|e| panic!("Identifying {} should work: {e:#}", rel.display()));
+        let Some(found) = found else {
+            if args.verbosity.info() {
+                eprintln!(
+                    "{:>12} {} is no package gpm knows",
+                    putils::warn(),
+                    rel.display()
+                );
+            }
+            continue;
         };
-        if let Some(s) = panic_info.location() {
-            eprint!(" (@{}:{})", s.file(), s.line())
+        let how = match found.how {
+            adopt::Match::Content => "same files",
+            adopt::Match::Plugin => "by its plugin.cfg, with changes",
+        };
+        let what = format!(
+            "{} is {}@{} ({how})",
+            rel.display(),
+            found.package,
+            found.version
+        );
+        if args.dry_run {
+            println!("{:>12} adopt {what}", putils::would());
+            continue;
+        }
+        if yes || putils::confirm(&format!("{what}. Adopt it?"), true).unwrap_or(false) {
+            adopted.push(found);
         }
-        eprintln!();
-    }));
-    let args = Args::parse();
-    fn set_colors(val: bool) {
-        console::set_colors_enabled(val);
-        console::set_colors_enabled_stderr(val);
     }
-    match args.colors {
-        ColorChoice::Always => set_colors(true),
-        ColorChoice::Never => set_colors(false),
-        ColorChoice::Auto => set_colors(Term::stdout().is_term() && Term::stderr().is_term()),
+    if adopted.is_empty() {
+        return;
     }
-    let client = mkclient(args.registry);
-    let mut cfg = {
-        let mut contents = String::from("");
-        if args.config_file == Path::new("-") {
-            let bytes = stdin()
-                .read_to_string(&mut contents)
-                .expect("Stdin read should be ok");
-            if bytes == 0 {
-                panic!("Stdin should not be empty");
-            };
-        } else {
-            contents = read_to_string(args.config_file).expect("Reading config file should be ok");
-        };
-        ConfigFile::new(&contents, client.clone()).await
-    };
-    fn lock(cfg: &mut ConfigFile, path: PathBuf, cwd: &Path) {
-        let lockfile = cfg.lock(cwd);
-        if path == Path::new("-") {
-            println!("{lockfile}");
-        } else {
-            write(path, lockfile).expect("Writing lock file should be ok");
+    for f in &adopted {
+        contents = ConfigFile::adopt(&contents, &f.package, f.version.clone(), f.dir())
+            .expect("Adding to the config should work");
+    }
+    if args.config_file == Path::new("-") {
+        println!("{contents}");
+    } else {
+        atomic::write(&args.config_file, &contents).expect("Writing config file should be ok");
+    }
+    for f in &adopted {
+        f.take_over(install_dir)
+            .unwrap_or_else(|e| panic!("Taking over {} should work: {e:#}", f.folder.display()));
+        if args.verbosity.info() {
+            println!(
+                "{:>12} {}@{}",
+                putils::green("Adopted"),
+                f.package,
+                f.version
+            );
         }
     }
-    let _ = BEGIN.elapsed(); // needed to initialize the instant for whatever reason
-    let cwd = current_dir().expect("Should be able to read cwd");
-    match args.action {
-        Actions::Update => {
-            update(&mut cfg, true, args.verbosity, client.clone(), &cwd).await;
-            lock(&mut cfg, args.lock_file, &cwd);
+    if let Err(e) = client.cache_ref().resolutions().save() {
+        eprintln!(
+            "{:>12} Could not save resolution cache: {e}",
+            putils::warn()
+        );
+    }
+}
+
+/// Runs `gpm hooks`.
+fn git_hooks(action: &HooksActions, args: &Args, contents: &str, cwd: &Path, install_dir: &Path) {
+    match action {
+        HooksActions::InstallGit { pre_push, force } => {
+            let hooks = version::git(cwd, &["rev-parse", "--git-path", "hooks"])
+                .expect("The project should be in a git repository");
+            let name = if *pre_push { "pre-push" } else { "pre-commit" };
+            let gpm = std::env::current_exe().expect("Should be able to find the gpm executable");
+            let script = git_hook::script(&gpm, cwd, &args.config_file, &args.lock_file);
+            let hooks = cwd.join(hooks.trim());
+            if args.dry_run {
+                println!(
+                    "{:>12} write {}",
+                    putils::would(),
+                    hooks.join(name).display()
+                );
+                return;
+            }
+            let path = git_hook::install(&hooks, name, &script, *force)
+                .unwrap_or_else(|e| panic!("Installing the git hook should work: {e}"));
+            if args.verbosity.info() {
+                println!("{:>12} {}", putils::green("Installed"), path.display());
+            }
         }
-        Actions::Purge => {
-            purge(&mut cfg, args.verbosity, &cwd);
-            lock(&mut cfg, args.lock_file, &cwd);
+        HooksActions::Check => {
+            if !fingerprint::is_fresh(contents, &args.lock_file, &cwd.join(install_dir)) {
+                eprintln!(
+                    "{:>12} {} is not in sync with {} and the installed packages (run gpm update)",
+                    putils::err(),
+                    args.lock_file.display(),
+                    args.config_file.display()
+                );
+                std::process::exit(1);
+            }
         }
-        Actions::Tree {
-            charset,
-            prefix,
-            print_tarballs,
-        } => println!(
-            "{}",
-            tree(
-                &mut cfg, // no locking needed
-                charset,
-                prefix,
-                print_tarballs,
-                client
-            )
+    }
+}
+
+/// Runs a hook of the package in `dir`, or says it would.
+fn run_hook(dir: &Path, event: &str, dry_run: bool) {
+    let manifest = hooks::manifest(dir).expect("Reading package.json should work");
+    if dry_run {
+        if hooks::get(&manifest, event).is_some() {
+            println!("{:>12} run the {event} hook", putils::would());
+        }
+        return;
+    }
+    hooks::run(&manifest, event, dir)
+        .unwrap_or_else(|e| panic!("The {event} hook should work: {e}"));
+}
+
+async fn tag(action: &TagActions, client: Client, dry_run: bool, info: bool) {
+    match action {
+        TagActions::Ls { package } => {
+            let name = package.clone().unwrap_or_else(|| {
+                let cwd = current_dir().expect("Should be able to read cwd");
+                pack::pack(&cwd)
+                    .expect("Reading the package should work")
+                    .name
+            });
+            let tags = publish::tags::list(client, &name)
+                .await
+                .expect("Listing tags should work");
+            for (tag, v) in tags {
+                println!("{tag}: {v}");
+            }
+        }
+        TagActions::Add { package, tag, auth } => {
+            let version = package.version.to_string();
+            if dry_run {
+                println!(
+                    "{:>12} tag {}@{version} as {tag}",
+                    putils::would(),
+                    package.name
+                );
+                return;
+            }
+            let token = auth.token();
+            with_otp(auth.otp.clone(), |otp| {
+                let (client, token, version) = (client.clone(), &token, &version);
+                async move {
+                    publish::tags::add(client, &package.name, version, tag, token, otp.as_deref())
+                        .await
+                }
+            })
             .await
-        ),
-        Actions::Init { packages } => {
-            init(
-                packages
-                    .try_into_async(client.clone())
-                    .await
-                    .expect("Failed to parse `init` packages"),
-                client,
-                &cwd,
-            )
+            .expect("Tagging should work");
+            if info {
+                println!(
+                    "{:>12} {}@{version} as {tag}",
+                    putils::green("Tagged"),
+                    package.name
+                );
+            }
+        }
+        TagActions::Rm { package, tag, auth } => {
+            if dry_run {
+                println!("{:>12} remove {package} tag {tag}", putils::would());
+                return;
+            }
+            let token = auth.token();
+            with_otp(auth.otp.clone(), |otp| {
+                let (client, token) = (client.clone(), &token);
+                async move { publish::tags::rm(client, package, tag, token, otp.as_deref()).await }
+            })
             .await
-            .expect("Initializing cfg should be ok");
+            .expect("Removing the tag should work");
+            if info {
+                println!("{:>12} {package} tag {tag}", putils::green("Removed"));
+            }
+        }
+    }
+}
+
+/// The range `gpm add` writes for `package`: the range given, or the newest version with the save prefix.
+async fn wanted_range(
+    package: &ParsedPackage,
+    exact: bool,
+    save_prefix: Option<&str>,
+    client: Client,
+) -> String {
+    let prefix = match save_prefix {
+        _ if exact => String::new(),
+        Some(p) => user_config::prefix(p)
+            .expect("The save prefix should be valid")
+            .to_owned(),
+        None => UserConfig::load_or_default()
+            .save_prefix
+            .unwrap_or_else(|| "^".into()),
+    };
+    // through the dist-tag, not the /latest endpoint, which not every registry has
+    let wanted = match &package.version {
+        VersionType::Normal(v) => v.clone(),
+        VersionType::Latest => "latest".into(),
+    };
+    let p = Package::new(package.name.clone(), wanted, client.metadata_only())
+        .await
+        .expect("Package should exist");
+    match &package.version {
+        VersionType::Normal(v) if !exact && save_prefix.is_none() && !package::is_tag(v) => {
+            v.clone()
+        }
+        _ => format!("{prefix}{}", p.manifest.full_version()),
+    }
+}
+
+/// Applies the flags that override the config.
+fn overrides(cfg: &mut ConfigFile, args: &Args) {
+    if let Some(d) = &args.install_dir {
+        cfg.set_install_dir(d.clone())
+            .expect("Setting the install dir should work");
+    }
+    if args.symlink {
+        cfg.set_symlink(true);
+    }
+}
+
+/// Runs a registry write, asking for a one-time password (and retrying) whenever the registry wants one.
+async fn with_otp<F, Fut>(mut otp: Option<String>, f: F) -> Result<()>
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    loop {
+        match f(otp.clone()).await {
+            Err(e) if e.is::<publish::OtpRequired>() && Term::stderr().is_term() => {
+                if otp.is_some() {
+                    eprintln!("{:>12} that one-time password didnt work", putils::err());
+                }
+                otp = Some(putils::input("One-time password")?);
+            }
+            r => return r,
         }
     }
 }
 
-pub fn mkclient(r: String) -> Client {
+pub fn mkclient(r: String, cache: Cache) -> Client {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         "User-Agent",
@@ -270,16 +2710,29 @@ pub fn mkclient(r: String) -> Client {
             .default_headers(headers)
             .build()
             .unwrap(),
-        Cache::new(),
+        cache,
         r,
     )
 }
 
-async fn update(cfg: &mut ConfigFile, modify: bool, v: Verbosity, client: Client, cwd: &Path) {
-    if !cwd.join("addons").exists() {
-        create_dir(cwd.join("addons")).expect("Should be able to create addons folder");
+async fn update(
+    cfg: &mut ConfigFile,
+    modify: bool,
+    v: Verbosity,
+    client: Client,
+    cwd: &Path,
+    algorithm: Algorithm,
+    before: &str,
+) {
+    if !cwd.join(cfg.install_dir()).exists() {
+        create_dir_all(cwd.join(cfg.install_dir()))
+            .expect("Should be able to create addons folder");
     }
-    let packages = cfg.collect();
+    let packages = cfg
+        .graph
+        .iter()
+        .map(|(id, _)| (id, cfg.graph.with_deps(id)))
+        .collect::<Vec<_>>();
     if v.debug() {
         println!(
             "collecting {} packages took {}",
@@ -288,7 +2741,7 @@ async fn update(cfg: &mut ConfigFile, modify: bool, v: Verbosity, client: Client
         );
         print!("packages: [");
         let mut first = true;
-        for p in &packages {
+        for (_, p) in &packages {
             if first {
                 print!("{p}");
             } else {
@@ -302,10 +2755,34 @@ async fn update(cfg: &mut ConfigFile, modify: bool, v: Verbosity, client: Client
     if packages.is_empty() {
         panic!("No packages to update (modify the \"godot.package\" file to add packages)");
     }
+    let engine = cfg.godot().or_else(|| client.godot.clone());
+    if v.info() || annotate::github() {
+        for (_, p) in &packages {
+            let key = Some(p.alias.as_deref().unwrap_or(&p.name));
+            if let Some(why) = &p.manifest.deprecated {
+                annotate::report(Level::Warning, key, &format!("{p} is deprecated: {why}"));
+            }
+            if let Some(g) = &engine {
+                if godot::compatible(p.manifest.godot.as_deref(), g) == Some(false) {
+                    annotate::report(
+                        Level::Warning,
+                        key,
+                        &format!(
+                            "{p} says it works with godot {}, not {g} (and no version of it that does matches)",
+                            p.manifest.godot.as_deref().unwrap_or_default()
+                        ),
+                    );
+                }
+            }
+        }
+    }
     let bar;
     let p_count = packages.len() as u64;
-    if v.bar() {
-        bar = putils::bar(p_count);
+    // bars only make sense on a terminal: elsewhere, fall back to plain lines
+    let bars = v.bar() && Term::stderr().is_term();
+    let multi = MultiProgress::new();
+    if bars {
+        bar = multi.add(putils::bar(p_count));
         bar.set_prefix("Updating");
     } else {
         bar = ProgressBar::hidden();
@@ -316,28 +2793,10 @@ async fn update(cfg: &mut ConfigFile, modify: bool, v: Verbosity, client: Client
     }
     let bar_or_info = v.bar() || v.info();
     let (tx, rx) = bar_or_info.then(channel).unzip();
-    let buf = stream::iter(packages)
-        .map(|mut p| {
-            let p_name = p.to_string();
-            let tx = if bar_or_info { tx.clone() } else { None };
-            let client = client.clone();
-            async move {
-                if bar_or_info {
-                    tx.as_ref()
-                        .unwrap()
-                        .send(Status::Processing(p_name.clone()))
-                        .unwrap();
-                }
-                p.download(client, cwd).await;
-                if modify {
-                    p.modify(cwd);
-                };
-                if bar_or_info {
-                    tx.unwrap().send(Status::Finished(p_name.clone())).unwrap();
-                }
-            }
-        })
-        .buffer_unordered(PARALLEL);
+    // the per package bars go above this one
+    let overall = bar.clone();
+    // bytes, from the network (not the store)
+    let downloaded = AtomicU64::new(0);
     // use to test the difference in speed
     // for mut p in packages { p.download(client.clone()).await; if modify { p.modify().unwrap(); }; bar.inc(1); }
     let handler = if bar_or_info {
@@ -364,15 +2823,127 @@ async fn update(cfg: &mut ConfigFile, modify: bool, v: Verbosity, client: Client
     } else {
         None
     };
-    buf.for_each(|_| async {}).await; // wait till its done
+    // every tarball is gotten before anything is deleted or unpacked,
+    // so one that is gone (unpublished) stops the update with the project as it was
+    let fetched = stream::iter(packages)
+        .map(|(id, p)| {
+            let p_name = p.to_string();
+            let tx = if bar_or_info { tx.clone() } else { None };
+            let client = client.clone();
+            let progress = if bars {
+                multi.insert_before(&overall, putils::bytes_bar(&p_name))
+            } else {
+                ProgressBar::hidden()
+            };
+            let multi = multi.clone();
+            let downloaded = &downloaded;
+            async move {
+                if let Some(tx) = &tx {
+                    tx.send(Status::Processing(p_name)).unwrap();
+                }
+                let bytes = p.fetch(client, &progress).await;
+                downloaded.fetch_add(progress.position(), Ordering::Relaxed);
+                progress.finish_and_clear();
+                multi.remove(&progress);
+                match bytes {
+                    Ok(bytes) => Ok((id, p, bytes)),
+                    Err(e) => Err((p, e)),
+                }
+            }
+        })
+        .buffer_unordered(PARALLEL)
+        .collect::<Vec<_>>()
+        .await;
+    let (mut packages, mut failed) = (vec![], vec![]);
+    for f in fetched {
+        match f {
+            Ok(f) => packages.push(f),
+            Err(f) => failed.push(f),
+        }
+    }
+    if !failed.is_empty() {
+        overall.finish_and_clear();
+        for (p, e) in failed {
+            let key = Some(p.alias.as_deref().unwrap_or(&p.name));
+            annotate::report(Level::Error, key, &format!("{e:#}"));
+        }
+        eprintln!(
+            "{:>12} Nothing was installed: the packages above could not be downloaded",
+            putils::err()
+        );
+        std::process::exit(1);
+    }
+    // so a gpm killed in the middle of this doesnt leave half installed packages behind
+    let (journal, removed) = journal::Journal::begin(
+        &cwd.join(cfg.install_dir()),
+        packages
+            .iter()
+            .map(|(_, p, _)| p.folder_in_install_dir(cwd).1),
+    )
+    .expect("Starting the install journal should work");
+    for rel in removed {
+        eprintln!(
+            "{:>12} gpm was stopped while installing {}: removed it, to install it again",
+            putils::warn(),
+            rel.display()
+        );
+    }
+    let journal = Arc::new(journal);
+    let waits = packages
+        .into_iter()
+        .map(|(id, mut p, bytes)| {
+            let p_name = p.to_string();
+            let journal = journal.clone();
+            let tx = if bar_or_info { tx.clone() } else { None };
+            let cwd = cwd.to_path_buf();
+            let store = client.cache_ref().store().cloned();
+            // hashing and extracting is cpu bound, so hand it to the rayon pool
+            let (done, wait) = oneshot::channel();
+            rayon::spawn(move || {
+                let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    p.unpack(bytes, &cwd, algorithm, store.as_ref(), Some(&journal));
+                    if modify {
+                        p.modify(&cwd);
+                    };
+                    p.postinstall(&cwd);
+                    p.record(&cwd);
+                    journal
+                        .done(&p.folder_in_install_dir(&cwd).1)
+                        .unwrap_or_else(|e| panic!("{p}: {e:#}"));
+                    (id, p)
+                }));
+                if let Some(tx) = tx {
+                    tx.send(Status::Finished(p_name)).unwrap();
+                }
+                let _ = done.send(res);
+            });
+            wait
+        })
+        .collect::<Vec<_>>();
+    let mut unpacked = vec![];
+    for res in join_all(waits).await {
+        match res.expect("Unpack thread should not disappear") {
+            Ok(p) => unpacked.push(p),
+            Err(e) => panic::resume_unwind(e), // already reported by the panic hook
+        }
+    }
+    journal
+        .finish()
+        .expect("Ending the install journal should work");
+    // remember the integritys for the lockfile
+    for (id, p) in unpacked {
+        cfg.graph.get_mut(id).manifest.integrity = p.manifest.integrity;
+    }
     drop(tx); // drop the transmitter to break the reciever loop
     if bar_or_info {
         handler.unwrap().join().unwrap();
+        print!("{}", changes::Changes::between(before, &cfg.lock(cwd)));
         println!(
-            "{:>12} updated {} package{} in {}",
+            "{:>12} updated {} package{} ({} downloaded) in {}",
             putils::green("Finished"),
             HumanCount(p_count),
             if p_count > 0 { "s" } else { "" },
+            HumanBytes(downloaded.load(Ordering::Relaxed)),
             HumanDuration(BEGIN.elapsed())
         )
     }
@@ -401,15 +2972,141 @@ fn recursive_delete_empty(dir: &Path, cwd: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-fn purge(cfg: &mut ConfigFile, v: Verbosity, cwd: &Path) {
-    let mut packages = HashSet::new();
-    cfg.for_each(|p| {
-        if p.is_installed(cwd) {
-            packages.insert(p.clone());
+/// Prints the files in a packed package, its sizes, and checksums.
+fn print_packed(packed: &pack::Packed) {
+    for (path, size) in &packed.files {
+        println!("{:>12} {}", HumanBytes(*size).to_string(), path.display());
+    }
+    println!(
+        "{:>12} {}@{}: {} file{}, {} unpacked, {} packed",
+        putils::green("Packed"),
+        packed.name,
+        packed.version,
+        packed.files.len(),
+        if packed.files.len() == 1 { "" } else { "s" },
+        HumanBytes(packed.unpacked_size()),
+        HumanBytes(packed.tarball.len() as u64),
+    );
+    println!("{:>12} {}", "shasum", packed.shasum());
+    println!("{:>12} {}", "integrity", packed.integrity());
+}
+
+/// Prints the changelog entries of every package upgraded since the `before` lockfile.
+fn print_changelogs(cfg: &ConfigFile, before: &str, cwd: &Path) {
+    for (name, old, new) in changes::Changes::between(before, &cfg.lock(cwd)).upgraded {
+        // packages with multiple versions installed dont have a clear before and after
+        let (Ok(old), Ok(new)) = (Version::new(&old).parse(), Version::new(&new).parse()) else {
+            continue;
+        };
+        let Some((_, p)) = cfg
+            .graph
+            .iter()
+            .find(|(_, p)| p.name == name && p.manifest.version == new)
+        else {
+            continue;
+        };
+        match changelog::find(&p.download_dir(cwd)) {
+            Some(log) => println!(
+                "{:>12} {name} {old} -> {new}\n{}",
+                putils::green("Changelog"),
+                changelog::between(&log, &old, &new).trim_end()
+            ),
+            None => println!("{:>12} {name} has no changelog", putils::green("Changelog")),
         }
-    });
+    }
+}
+
+/// The `res://` folders (with a `/` at the end) of the packages gpm installed (see [record::recorded]).
+fn recorded_folders(cfg: &ConfigFile, cwd: &Path) -> Vec<String> {
+    let install_dir = cfg.install_dir();
+    package::record::recorded(&cwd.join(install_dir))
+        .into_iter()
+        .map(|rel| {
+            let dir = install_dir.join(rel).to_string_lossy().replace('\\', "/");
+            format!("res://{}/", dir.trim_matches('/'))
+        })
+        .collect()
+}
+
+/// Puts the translations the installed packages ship in the project.godot, and takes out the ones of packages
+/// that are gone (all of them, if `purged`) from the folders gpm installed them in: `recorded` (see [recorded_folders]),
+/// taken before they were (see [translations::sync]).
+fn sync_translations(
+    cfg: &ConfigFile,
+    cwd: &Path,
+    v: Verbosity,
+    purged: bool,
+    recorded: &[String],
+) {
+    let path = cwd.join("project.godot");
+    let Ok(project) = read_to_string(&path) else {
+        return;
+    };
+    let wanted = match purged {
+        true => vec![],
+        false => translations::wanted(&cfg.graph, cwd),
+    };
+    if let Some(synced) = translations::sync(&project, recorded, &wanted) {
+        atomic::write(&path, synced).expect("Writing project.godot should work");
+        if v.info() {
+            println!(
+                "{:>12} {} package translation{} in project.godot",
+                putils::green("Synced"),
+                wanted.len(),
+                if wanted.len() == 1 { "" } else { "s" }
+            );
+        }
+    }
+}
+
+/// Prints what [update] would download, and where to.
+fn plan_update(cfg: &ConfigFile, cwd: &Path) {
+    if cfg.graph.is_empty() {
+        panic!("No packages to update (modify the \"godot.package\" file to add packages)");
+    }
+    for (_, p) in cfg.graph.iter() {
+        let dir = p.download_dir(cwd);
+        println!(
+            "{:>12} {} {p} ({}) into {}",
+            putils::would(),
+            if p.is_installed(cwd) {
+                "replace"
+            } else {
+                "download"
+            },
+            p.manifest.tarball,
+            dir.strip_prefix(cwd).unwrap_or(&dir).display(),
+        );
+        if let Some(cmd) = p.hooks().get("postinstall") {
+            println!(
+                "{:>12} run the postinstall hook of {p} ({cmd})",
+                putils::would()
+            );
+        }
+    }
+}
+
+/// Prints what [purge] would delete.
+fn plan_purge(cfg: &ConfigFile, cwd: &Path) {
+    for (_, p) in cfg.graph.iter().filter(|(_, p)| p.is_installed(cwd)) {
+        let dir = p.download_dir(cwd);
+        println!(
+            "{:>12} delete {}",
+            putils::would(),
+            dir.strip_prefix(cwd).unwrap_or(&dir).display()
+        );
+    }
+}
+
+fn purge(cfg: &ConfigFile, v: Verbosity, cwd: &Path) {
+    let packages = cfg
+        .graph
+        .iter()
+        .map(|(_, p)| p)
+        .filter(|p| p.is_installed(cwd))
+        .collect::<Vec<_>>();
     if packages.is_empty() {
-        if cfg.packages.is_empty() {
+        if cfg.graph.is_empty() {
             panic!("No packages configured (modify the \"godot.package\" file to add packages)")
         } else {
             panic!("No packages installed (use \"gpm --update\" to install packages)")
@@ -441,7 +3138,7 @@ fn purge(cfg: &mut ConfigFile, v: Verbosity, cwd: &Path) {
 
     // run multiple times because the algorithm goes from top to bottom, stupidly.
     for _ in 0..3 {
-        if let Err(e) = recursive_delete_empty(&cwd.join("addons"), cwd) {
+        if let Err(e) = recursive_delete_empty(&cwd.join(cfg.install_dir()), cwd) {
             eprintln!("{e}")
         }
     }
@@ -457,7 +3154,7 @@ fn purge(cfg: &mut ConfigFile, v: Verbosity, cwd: &Path) {
 }
 
 async fn tree(
-    cfg: &mut ConfigFile,
+    cfg: &ConfigFile,
     charset: CharSet,
     prefix: PrefixType,
     print_tarballs: bool,
@@ -470,7 +3167,8 @@ async fn tree(
     };
     let mut count: u64 = 0;
     iter(
-        &mut cfg.packages,
+        &cfg.graph,
+        cfg.graph.roots(),
         "",
         &mut tree,
         match charset {
@@ -491,8 +3189,10 @@ async fn tree(
     tree.push_str(format!("{} dependencies", HumanCount(count)).as_str());
 
     #[async_recursion]
+    #[allow(clippy::too_many_arguments)]
     async fn iter(
-        packages: &mut Vec<Package>,
+        graph: &Graph,
+        ids: &[PackageId],
         prefix: &str,
         tree: &mut String,
         t: &str,
@@ -506,9 +3206,10 @@ async fn tree(
         // the index is used to decide if the package is the last package,
         // so we can use a L instead of a T.
         let mut tmp: String;
-        let mut index = packages.len();
+        let mut index = ids.len();
         *count += index as u64;
-        for p in packages {
+        for &id in ids {
+            let p = graph.get(id);
             let name = p.to_string();
             index -= 1;
             tree.push_str(
@@ -525,10 +3226,14 @@ async fn tree(
                 tree.push(' ');
                 tree.push_str(&p.manifest.tarball.to_string());
             }
+            if let Some(why) = &p.manifest.deprecated {
+                tree.push_str(&format!(" (deprecated: {why})"));
+            }
             tree.push('\n');
-            if p.has_deps() {
+            if !graph.deps(id).is_empty() {
                 iter(
-                    &mut p.manifest.dependencies,
+                    graph,
+                    graph.deps(id),
                     if prefix_type == PrefixType::Indent {
                         tmp = format!("{prefix}{}   ", if index != 0 { '│' } else { ' ' });
                         tmp.as_str()
@@ -551,7 +3256,7 @@ async fn tree(
     tree
 }
 
-async fn init(mut packages: Vec<Package>, client: Client, cwd: &Path) -> Result<()> {
+async fn init(mut packages: Vec<Package>, client: Client, cwd: &Path, dry_run: bool) -> Result<()> {
     let mut c = ConfigFile::empty();
     if packages.is_empty() {
         let mut has_asked = false;
@@ -577,8 +3282,8 @@ async fn init(mut packages: Vec<Package>, client: Client, cwd: &Path) -> Result<
             packages.push(res.unwrap());
         }
     };
-    c.packages = packages;
-    let types = vec![ConfigType::JSON, ConfigType::YAML, ConfigType::TOML];
+    c.graph = Graph::new(packages);
+    let types = ConfigType::ALL;
 
     let mut path = Path::new(&putils::input_with_default::<String>(
         "Config file save location?",
@@ -592,33 +3297,49 @@ async fn init(mut packages: Vec<Package>, client: Client, cwd: &Path) -> Result<
             path = Path::new(&putils::input::<String>("Config file save location?")?).to_path_buf();
         }
     }
-    while write(&path, "").is_err() {
+    // without emptying it: if the real write doesnt happen, the old config stays
+    while !dry_run
+        && std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .is_err()
+    {
         path = Path::new(&putils::input_with_default::<String>(
             "Chosen file not accessible, try again:",
             "godot.package".into(),
         )?)
         .to_path_buf();
     }
-    let c_text = c.print(types[putils::select(&types, "Language to save in:", 2)?]);
-    write(path, c_text)?;
+    let c_text = c.print(types[putils::select(types, "Language to save in:", types.len() - 1)?]);
+    if dry_run {
+        println!(
+            "{:>12} write {}:\n{c_text}",
+            putils::would(),
+            path.display()
+        );
+        return Ok(());
+    }
+    atomic::write(path, c_text)?;
     if putils::confirm("Would you like to view the dependency tree?", true)? {
         println!(
             "{}",
-            tree(
-                &mut c,
-                CharSet::UTF8,
-                PrefixType::Indent,
-                false,
-                client.clone()
-            )
-            .await
+            tree(&c, CharSet::UTF8, PrefixType::Indent, false, client.clone()).await
         );
     };
 
-    if !c.packages.is_empty()
-        && putils::confirm("Would you like to install your new packages?", true)?
+    if !c.graph.is_empty() && putils::confirm("Would you like to install your new packages?", true)?
     {
-        update(&mut c, true, Verbosity::Normal, client.clone(), cwd).await;
+        update(
+            &mut c,
+            true,
+            Verbosity::Normal,
+            client.clone(),
+            cwd,
+            Algorithm::default(),
+            "",
+        )
+        .await;
     };
     println!("Goodbye!");
     Ok(())
@@ -632,6 +3353,8 @@ mod test_utils {
     use tempfile::TempDir;
     use test_server::TestServer;
 
+    use crate::cache::{Cache, CacheEntry};
+    use crate::package::parsing::ParsedManifest;
     use crate::{mkclient, Client};
     type Handle = (TempDir, TestServer, Client);
 
@@ -645,10 +3368,26 @@ mod test_utils {
         (
             tmp_dir,
             TestServer::spawn(sock).await,
-            mkclient(format!("http://{sock}")),
+            mkclient(format!("http://{sock}"), Cache::new()),
         )
     }
 
+    /// Puts `name@version` in the cache of `c`, as if the registry had it: wanting each of `deps` at `^1`,
+    /// or the range after a space (`c ^1.2.0`).
+    pub fn known(c: &Client, name: &str, version: &str, deps: &[&str]) {
+        let m = ParsedManifest {
+            version: version.into(),
+            dependencies: deps
+                .iter()
+                .map(|d| d.split_once(' ').unwrap_or((d, "^1")))
+                .map(|(d, r)| (d.to_string(), r.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        c.cache_ref()
+            .insert(name.into(), version.into(), CacheEntry::Manifest(m));
+    }
+
     pub fn hashd(d: &Path) -> Vec<String> {
         let mut files = glob(format!("{}/**/*", d.display()).as_str())
             .unwrap()
@@ -666,35 +3405,116 @@ mod test_utils {
     }
 }
 
-#[tokio::test]
-async fn gpm() {
-    let t = test_utils::mktemp().await;
-    let c = t.2;
-    let cfg_file =
-        &mut config_file::ConfigFile::new(&r#"packages: {"@bendn/test":2.0.10}"#.into(), c.clone())
-            .await;
-    update(cfg_file, false, Verbosity::Verbose, c.clone(), t.0.path()).await;
-    assert_eq!(test_utils::hashd(&t.0.path().join("addons")).join("|"), "1c2fd93634817a9e5f3f22427bb6b487520d48cf3cbf33e93614b055bcbd1329|8e77e3adf577d32c8bc98981f05d40b2eb303271da08bfa7e205d3f27e188bd7|a625595a71b159e33b3d1ee6c13bea9fc4372be426dd067186fe2e614ce76e3c|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c850a9300388d6da1566c12a389927c3353bf931c4d6ea59b02beb302aac03ea|d060936e5f1e8b1f705066ade6d8c6de90435a91c51f122905a322251a181a5c|d711b57105906669572a0e53b8b726619e3a21463638aeda54e586a320ed0fc5|d794f3cee783779f50f37a53e1d46d9ebbc5ee7b37c36d7b6ee717773b6955cd|e4f9df20b366a114759282209ff14560401e316b0059c1746c979f478e363e87");
-    purge(cfg_file, Verbosity::Verbose, t.0.path());
+#[test]
+fn update_flags() {
+    let t = tempfile::TempDir::new().unwrap();
+    let lock = t.path().join("godot.lock");
+    let integrity = Integrity::of(Algorithm::Blake3, b"a");
+    std::fs::write(
+        &lock,
+        serde_json::json!([{"name": "a", "integrity": integrity.to_string()}]).to_string(),
+    )
+    .unwrap();
+    let changes = |flags: &[&str]| {
+        let lock = lock.to_string_lossy();
+        let argv = ["gpm", "update", "--lock-file", &lock];
+        flags_change_update(&Args::parse_from(argv.iter().chain(flags)))
+    };
+    assert!(!changes(&[]));
+    assert!(!changes(&["--integrity", "blake3"]));
+    assert!(changes(&["--integrity", "sha512"]));
+    assert!(changes(&["--strict"]));
+    assert!(changes(&["--ignore-scripts"]));
+    assert!(changes(&["--symlink"]));
+}
+
+#[test]
+fn bad_max_cache_size() {
+    let t = tempfile::TempDir::new().unwrap();
+    let cache = Cache::load(t.path().to_path_buf());
+    let user = UserConfig {
+        max_cache_size: Some("lots".into()),
+        ..Default::default()
+    };
     assert_eq!(
-        test_utils::hashd(&t.0.path().join("addons")),
-        vec![] as Vec<String>
+        user.max_cache_size().unwrap_err().to_string(),
+        "max-cache-size: lots is not a size (like 50M)"
     );
-    assert_eq!(
-        tree(
+    // warned about, not panicked on
+    evict(&cache, &user, Verbosity::Verbose);
+}
+
+#[tokio::test]
+async fn gpm() {
+    let configs = [
+        #[cfg(feature = "hjson")]
+        r#"packages: {"@bendn/test":2.0.10}"#,
+        r#"{"packages": {"@bendn/test": "2.0.10"}}"#,
+    ];
+    for contents in configs {
+        let t = test_utils::mktemp().await;
+        let c = t.2;
+        let cfg_file = &mut config_file::ConfigFile::new(&contents.into(), c.clone()).await;
+        update(
             cfg_file,
-            crate::CharSet::UTF8,
-            crate::PrefixType::Indent,
             false,
+            Verbosity::Verbose,
             c.clone(),
+            t.0.path(),
+            Algorithm::Sha512,
+            "",
         )
-        .await
-        .lines()
-        .skip(1)
-        .collect::<Vec<&str>>()
-        .join("\n"),
-        "└── @bendn/test@2.0.10\n    └── @bendn/gdcli@1.2.5\n2 dependencies"
-    );
+        .await;
+        assert_eq!(test_utils::hashd(&t.0.path().join("addons")).join("|"), "1c2fd93634817a9e5f3f22427bb6b487520d48cf3cbf33e93614b055bcbd1329|41900faaf7ae5012fc4fd8638153071ef1cc6a87eb6b07e8aa0edd694b372fb3|8c35475042eab250b5a90ed54cf5ab760a3971e1e0ada7ae0524c62a7c9f52bd|8e77e3adf577d32c8bc98981f05d40b2eb303271da08bfa7e205d3f27e188bd7|a625595a71b159e33b3d1ee6c13bea9fc4372be426dd067186fe2e614ce76e3c|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c850a9300388d6da1566c12a389927c3353bf931c4d6ea59b02beb302aac03ea|d060936e5f1e8b1f705066ade6d8c6de90435a91c51f122905a322251a181a5c|d711b57105906669572a0e53b8b726619e3a21463638aeda54e586a320ed0fc5|d794f3cee783779f50f37a53e1d46d9ebbc5ee7b37c36d7b6ee717773b6955cd|e4f9df20b366a114759282209ff14560401e316b0059c1746c979f478e363e87"); // with the install records
+        assert!(cfg_file
+            .lock(t.0.path())
+            .contains(r#""integrity": "sha512-"#));
+        purge(cfg_file, Verbosity::Verbose, t.0.path());
+        assert_eq!(
+            test_utils::hashd(&t.0.path().join("addons")),
+            vec![] as Vec<String>
+        );
+        assert_eq!(
+            tree(
+                cfg_file,
+                crate::CharSet::UTF8,
+                crate::PrefixType::Indent,
+                false,
+                c.clone(),
+            )
+            .await
+            .lines()
+            .skip(1)
+            .collect::<Vec<&str>>()
+            .join("\n"),
+            "└── @bendn/test@2.0.10\n    └── @bendn/gdcli@1.2.5\n2 dependencies"
+        );
+    }
+}
+
+#[tokio::test]
+async fn gpm_blake3() {
+    let t = test_utils::mktemp().await;
+    let c = t.2;
+    let cfg_file = &mut config_file::ConfigFile::new(
+        &r#"{"packages": {"@bendn/test": "2.0.10"}}"#.into(),
+        c.clone(),
+    )
+    .await;
+    update(
+        cfg_file,
+        false,
+        Verbosity::Verbose,
+        c.clone(),
+        t.0.path(),
+        Algorithm::Blake3,
+        "",
+    )
+    .await;
+    assert_eq!(test_utils::hashd(&t.0.path().join("addons")).join("|"), "1c2fd93634817a9e5f3f22427bb6b487520d48cf3cbf33e93614b055bcbd1329|41900faaf7ae5012fc4fd8638153071ef1cc6a87eb6b07e8aa0edd694b372fb3|8c35475042eab250b5a90ed54cf5ab760a3971e1e0ada7ae0524c62a7c9f52bd|8e77e3adf577d32c8bc98981f05d40b2eb303271da08bfa7e205d3f27e188bd7|a625595a71b159e33b3d1ee6c13bea9fc4372be426dd067186fe2e614ce76e3c|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c5566e4fbea9cc6dbebd9366b09e523b20870b1d69dc812249fccd766ebce48e|c850a9300388d6da1566c12a389927c3353bf931c4d6ea59b02beb302aac03ea|d060936e5f1e8b1f705066ade6d8c6de90435a91c51f122905a322251a181a5c|d711b57105906669572a0e53b8b726619e3a21463638aeda54e586a320ed0fc5|d794f3cee783779f50f37a53e1d46d9ebbc5ee7b37c36d7b6ee717773b6955cd|e4f9df20b366a114759282209ff14560401e316b0059c1746c979f478e363e87");
+    assert!(cfg_file
+        .lock(t.0.path())
+        .contains(r#""integrity": "blake3-"#));
 }
 
 /// Print utilities.
@@ -702,7 +3522,7 @@ async fn gpm() {
 pub mod putils {
     use crate::theme::BasicTheme;
     use console::{style, StyledObject};
-    use dialoguer::{theme::Theme, Confirm, Input, Select};
+    use dialoguer::{theme::Theme, Confirm, Input, MultiSelect, Select};
     use indicatif::{ProgressBar, ProgressStyle};
     use std::fmt;
     use std::io::Result;
@@ -722,6 +3542,14 @@ pub mod putils {
             .interact()
     }
 
+    #[inline]
+    pub fn multi_select<T: ToString>(items: &[T], p: &str) -> Result<Vec<usize>> {
+        MultiSelect::with_theme(&BasicTheme::default())
+            .items(items)
+            .with_prompt(p)
+            .interact()
+    }
+
     #[inline]
     pub fn confirm(p: &str, default: bool) -> Result<bool> {
         Confirm::with_theme(&BasicTheme::default())
@@ -765,11 +3593,33 @@ pub mod putils {
         style("Warn").yellow().bold()
     }
 
+    /// Prefix for the actions of a dry run.
+    #[inline]
+    pub fn would() -> StyledObject<&'static str> {
+        style("Would").cyan().bold()
+    }
+
     #[inline]
     pub fn green(t: &str) -> StyledObject<&str> {
         style(t).green().bold()
     }
 
+    /// A bar for the bytes of one download.
+    #[inline]
+    pub fn bytes_bar(name: &str) -> ProgressBar {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{prefix:>12.cyan.bold} [{bar:20.green}] {bytes}/{total_bytes}: {wide_msg}",
+            )
+            .unwrap()
+            .progress_chars("-> "),
+        );
+        bar.set_prefix("Downloading");
+        bar.set_message(name.to_string());
+        bar
+    }
+
     #[inline]
     pub fn bar(len: u64) -> ProgressBar {
         let bar = ProgressBar::new(len);
@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// Another plugin manager, whose manifest `gpm import` reads.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Manager {
+    /// gd-plug, and its plug.gd.
+    GdPlug,
+}
+
+/// A `plug("user/repo", {...})` of a plug.gd.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Plug {
+    #[serde(skip)]
+    pub repo: String,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub commit: Option<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub install_root: Option<String>,
+    pub dev: bool,
+}
+
+/// The `plug()` calls of the plug.gd `src`.
+pub fn gd_plug(src: &str) -> Result<Vec<Plug>> {
+    let mut plugs = vec![];
+    for line in src.lines() {
+        let line = uncommented(line).trim();
+        let Some(args) = line.strip_prefix("plug(") else {
+            continue;
+        };
+        let args = args
+            .strip_suffix(')')
+            .with_context(|| format!("{line:?} is not one plug() (put it on one line)"))?;
+        let (repo, options) = match args.split_once(',') {
+            Some((repo, options)) => (repo, Some(options)),
+            None => (args, None),
+        };
+        let repo = repo.trim().trim_matches(['"', '\'']).to_owned();
+        let mut plug = match options {
+            Some(o) => serde_json::from_str::<Plug>(&to_json(o))
+                .with_context(|| format!("the options of {repo} are not a dictionary gpm gets"))?,
+            None => Plug::default(),
+        };
+        plug.repo = repo;
+        plugs.push(plug);
+    }
+    Ok(plugs)
+}
+
+/// `line` without its comment: from the first `#` that isnt in a string.
+fn uncommented(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// A gdscript dictionary (literal), as json: single quotes become double, trailing commas go.
+fn to_json(dict: &str) -> String {
+    let mut out = String::new();
+    let mut quote = None;
+    for c in dict.trim().chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                out.push('"');
+            }
+            (Some(q), _) if c == q => {
+                quote = None;
+                out.push('"');
+            }
+            (None, '}' | ']') => {
+                let trimmed = out.trim_end().trim_end_matches(',').len();
+                out.truncate(trimmed);
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The tarball of a plug, on github. None if it isnt on github.
+pub fn tarball(p: &Plug) -> Option<String> {
+    let url = match p.repo.contains("://") {
+        true => p.repo.clone(),
+        false => format!("https://github.com/{}", p.repo),
+    };
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    if !url.starts_with("https://github.com/") {
+        return None;
+    }
+    Some(match (&p.commit, &p.tag, &p.branch) {
+        (Some(c), _, _) => format!("{url}/archive/{c}.zip"),
+        (None, Some(t), _) => format!("{url}/archive/refs/tags/{t}.zip"),
+        (None, None, Some(b)) => format!("{url}/archive/refs/heads/{b}.zip"),
+        (None, None, None) => format!("{url}/archive/HEAD.zip"),
+    })
+}
+
+/// Makes a godot.package (json) with the packages of `manifest`, written for `from`.
+/// Also returns what could not be carried over.
+pub fn import(from: Manager, manifest: &str) -> Result<(String, Vec<String>)> {
+    let plugs = match from {
+        Manager::GdPlug => gd_plug(manifest)?,
+    };
+    if plugs.is_empty() {
+        bail!("there are no plug()s in it");
+    }
+    let mut packages = Map::new();
+    let mut lost = vec![];
+    for p in plugs {
+        let Some(url) = tarball(&p) else {
+            lost.push(format!(
+                "{} is not on github, so it has no tarball url",
+                p.repo
+            ));
+            continue;
+        };
+        if !p.include.is_empty() {
+            lost.push(format!("{}: include (gpm only excludes)", p.repo));
+        }
+        if p.install_root.is_some() {
+            lost.push(format!(
+                "{}: install_root (it goes in the install dir)",
+                p.repo
+            ));
+        }
+        let spec = match (p.exclude.is_empty(), p.dev) {
+            (true, false) => json!("*"),
+            (true, true) => json!({"version": "*", "dev": true}),
+            (false, false) => json!({"version": "*", "exclude": p.exclude}),
+            (false, true) => json!({"version": "*", "exclude": p.exclude, "dev": true}),
+        };
+        packages.insert(url, spec);
+    }
+    Ok((
+        serde_json::to_string_pretty(&json!({ "packages": Value::Object(packages) }))?,
+        lost,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plug_gd() {
+        let src = r#"extends "res://addons/gd-plug/plug.gd"
+
+func _plugging():
+	plug("imjp94/gd-YAFSM") # state machines
+	plug('imjp94/UIDesignTool', {'tag': 'v1.0',})
+	plug("user/sharp", {"branch": "c#"}) # a # in a string is not a comment
+	plug("https://github.com/user/repo.git", {"commit": "abc123", "exclude": ["addons/x/tests"], "dev": true})
+	plug("https://gitlab.com/user/other")
+"#;
+        let plugs = gd_plug(src).unwrap();
+        assert_eq!(plugs.len(), 5);
+        assert_eq!(plugs[1].tag.as_deref(), Some("v1.0"));
+        assert_eq!(plugs[2].branch.as_deref(), Some("c#"));
+        let (cfg, lost) = import(Manager::GdPlug, src).unwrap();
+        let parsed: Value = serde_json::from_str(&cfg).unwrap();
+        let packages = parsed["packages"].as_object().unwrap();
+        assert_eq!(
+            packages["https://github.com/imjp94/gd-YAFSM/archive/HEAD.zip"],
+            "*"
+        );
+        assert!(packages
+            .contains_key("https://github.com/imjp94/UIDesignTool/archive/refs/tags/v1.0.zip"));
+        assert_eq!(
+            packages["https://github.com/user/repo/archive/abc123.zip"]["exclude"][0],
+            "addons/x/tests"
+        );
+        assert_eq!(
+            packages["https://github.com/user/repo/archive/abc123.zip"]["dev"],
+            true
+        );
+        assert_eq!(lost.len(), 1);
+        assert!(crate::config_file::ConfigFile::names(&cfg).is_ok());
+        assert!(import(Manager::GdPlug, "extends Node").is_err());
+    }
+}
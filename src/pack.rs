@@ -0,0 +1,289 @@
+use crate::integrity::{Algorithm, Integrity};
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glob::Pattern;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::fs::{read, read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// Never packed: version control, editor caches, and earlier packs.
+const ALWAYS_IGNORED: &[&str] = &[
+    ".git",
+    ".godot",
+    "node_modules",
+    ".DS_Store",
+    ".gpmignore",
+    ".gpm.lock",
+];
+
+/// Always packed, even if `files` doesnt list them (as long as they arent ignored).
+const ALWAYS_PACKED: &[&str] = &["package.json", "README.md", "LICENSE", "LICENSE.md"];
+
+#[derive(Deserialize)]
+struct PackageJson {
+    name: String,
+    version: String,
+    /// Globs of what to pack. Everything, if missing.
+    #[serde(default)]
+    files: Option<Vec<String>>,
+}
+
+/// A line in a .gpmignore, which works like a .gitignore.
+struct Rule {
+    pattern: Pattern,
+    /// `!pattern`: packs what an earlier rule ignored.
+    negate: bool,
+    /// `pattern/`: only matches directories.
+    dir_only: bool,
+    /// `/pattern`, or `a/pattern`: matches the path from the root, instead of just the name.
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(l) => (true, l),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        Some(Self {
+            pattern: Pattern::new(line.trim_start_matches('/')).ok()?,
+            negate,
+            dir_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, rel: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.pattern.matches_path(rel)
+        } else {
+            rel.file_name()
+                .is_some_and(|n| self.pattern.matches(&n.to_string_lossy()))
+        }
+    }
+}
+
+/// What to pack, from the .gpmignore and the `files` of the package.json.
+struct Rules {
+    ignore: Vec<Rule>,
+    files: Option<Vec<Pattern>>,
+}
+
+impl Rules {
+    fn load(dir: &Path, files: Option<&[String]>) -> Result<Self> {
+        let ignore = read_to_string(dir.join(".gpmignore")).unwrap_or_default();
+        Ok(Self {
+            ignore: ignore.lines().filter_map(Rule::parse).collect(),
+            files: files
+                .map(|f| {
+                    f.iter()
+                        .map(|p| Pattern::new(p.trim_start_matches("./").trim_start_matches('/')))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()
+                .context("parsing package.json files")?,
+        })
+    }
+
+    fn ignored(&self, rel: &Path, is_dir: bool) -> bool {
+        let name = rel.file_name().unwrap_or_default().to_string_lossy();
+        if ALWAYS_IGNORED.contains(&&*name) || (!is_dir && name.ends_with(".tgz")) {
+            return true;
+        }
+        // the last matching rule wins
+        self.ignore
+            .iter()
+            .rev()
+            .find(|r| r.matches(rel, is_dir))
+            .is_some_and(|r| !r.negate)
+    }
+
+    /// Is this file in `files`? Listing a directory lists everything in it.
+    fn wanted(&self, rel: &Path) -> bool {
+        let Some(files) = &self.files else {
+            return true;
+        };
+        if rel.parent() == Some(Path::new("")) && ALWAYS_PACKED.contains(&&*rel.to_string_lossy()) {
+            return true;
+        }
+        rel.ancestors()
+            .filter(|a| !a.as_os_str().is_empty())
+            .any(|a| files.iter().any(|f| f.matches_path(a)))
+    }
+}
+
+/// A packed package, ready to write out, or publish.
+pub struct Packed {
+    pub name: String,
+    pub version: String,
+    /// The whole package.json.
+    pub manifest: serde_json::Value,
+    /// Every packed file (relative to the package root), and its size.
+    pub files: Vec<(PathBuf, u64)>,
+    pub tarball: Vec<u8>,
+}
+
+impl Packed {
+    /// Where npm would put this tarball: `@scope/name` becomes `scope-name-<version>.tgz`.
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}-{}.tgz",
+            self.name.trim_start_matches('@').replace('/', "-"),
+            self.version
+        )
+    }
+
+    /// The sum of the file sizes.
+    pub fn unpacked_size(&self) -> u64 {
+        self.files.iter().map(|(_, s)| s).sum()
+    }
+
+    pub fn shasum(&self) -> String {
+        format!("{:x}", Sha1::digest(&self.tarball))
+    }
+
+    pub fn integrity(&self) -> Integrity {
+        Integrity::of(Algorithm::Sha512, &self.tarball)
+    }
+}
+
+/// Every file under `dir` that should be packed, sorted, relative to `dir`.
+/// Files ignored by the .gpmignore are skipped, and if the package.json has `files`, only those are packed.
+fn files(dir: &Path, rules: &Rules) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, rel: &Path, rules: &Rules, out: &mut Vec<PathBuf>) -> Result<()> {
+        let mut entries = read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for e in entries {
+            let rel = rel.join(e.file_name());
+            let is_dir = e.file_type()?.is_dir();
+            if rules.ignored(&rel, is_dir) {
+                continue;
+            }
+            if is_dir {
+                walk(&e.path(), &rel, rules, out)?;
+            } else if rules.wanted(&rel) {
+                out.push(rel);
+            }
+        }
+        Ok(())
+    }
+    let mut out = vec![];
+    walk(dir, Path::new(""), rules, &mut out)?;
+    Ok(out)
+}
+
+/// Packs the package in `dir` (which must have a package.json) into a registry compatible tarball,
+/// with every file under `package/`.
+pub fn pack(dir: &Path) -> Result<Packed> {
+    let json: serde_json::Value = serde_json::from_str(
+        &read_to_string(dir.join("package.json")).context("reading package.json")?,
+    )
+    .context("parsing package.json")?;
+    let manifest: PackageJson =
+        serde_json::from_value(json.clone()).context("package.json needs a name and a version")?;
+    let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::best()));
+    let rules = Rules::load(dir, manifest.files.as_deref())?;
+    let mut packed = vec![];
+    for path in files(dir, &rules)? {
+        let bytes = read(dir.join(&path))?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(499162500); // npm does this too (1985-10-26), so packing is reproducible
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new("package").join(&path), &bytes[..])?;
+        packed.push((path, bytes.len() as u64));
+    }
+    Ok(Packed {
+        name: manifest.name,
+        version: manifest.version,
+        manifest: json,
+        files: packed,
+        tarball: builder.into_inner()?.finish()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::Archive;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn roundtrip() {
+        let t = tempfile::TempDir::new().unwrap();
+        let d = t.path();
+        write(
+            d.join("package.json"),
+            r#"{"name":"@bendn/p","version":"1.0.0"}"#,
+        )
+        .unwrap();
+        create_dir_all(d.join("sub")).unwrap();
+        create_dir_all(d.join(".git")).unwrap();
+        write(d.join("sub/main.gd"), "extends Node").unwrap();
+        write(d.join(".git/HEAD"), "ref").unwrap();
+        write(d.join("old-1.0.0.tgz"), "").unwrap();
+        let p = pack(d).unwrap();
+        assert_eq!(p.file_name(), "bendn-p-1.0.0.tgz");
+        assert_eq!(
+            p.files.iter().map(|f| f.0.clone()).collect::<Vec<_>>(),
+            [PathBuf::from("package.json"), PathBuf::from("sub/main.gd")]
+        );
+        assert_eq!(pack(d).unwrap().tarball, p.tarball); // reproducible
+        let files = Archive::new_gzip(p.tarball, String::new()).files().unwrap();
+        assert_eq!(files[&PathBuf::from("sub/main.gd")], b"extends Node");
+    }
+
+    #[test]
+    fn rules() {
+        let t = tempfile::TempDir::new().unwrap();
+        let d = t.path();
+        for f in [
+            "main.gd",
+            "main.gd.import",
+            "icon.png",
+            "test/test.tscn",
+            "art/icon.kra",
+            "addons/p/keep.import",
+            "README.md",
+        ] {
+            create_dir_all(d.join(f).parent().unwrap()).unwrap();
+            write(d.join(f), "").unwrap();
+        }
+        write(
+            d.join(".gpmignore"),
+            "# caches\n*.import\n!addons/p/keep.import\ntest/\n/art\n",
+        )
+        .unwrap();
+        let list = |allow: Option<&[String]>| {
+            files(d, &Rules::load(d, allow).unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            list(None),
+            ["README.md", "addons/p/keep.import", "icon.png", "main.gd"]
+        );
+        assert_eq!(
+            list(Some(&["addons".into(), "*.gd".into()])),
+            ["README.md", "addons/p/keep.import", "main.gd"]
+        );
+    }
+}
@@ -1,26 +1,85 @@
 use crate::archive::*;
-use crate::cache::CacheEntry;
+use crate::cache::store::{clone_tree, link_tree, Store};
+use crate::cache::{resolutions, CacheEntry, Pick, VersionsCache};
 use crate::conversions::TryIntoAsync;
+use crate::hooks;
+use crate::integrity::{Algorithm, Integrity};
+use crate::journal::Journal;
+use crate::publish::escape;
+use crate::timings::{self, Phase};
 use crate::Client;
 
 use anyhow::bail;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use async_recursion::async_recursion;
+use indicatif::ProgressBar;
 use regex::{Captures, Regex};
-use semver_rs::{Range, Version};
-use serde::Serialize;
+use reqwest::StatusCode;
+use semver_rs::{Options, Range, Version};
 use sha1::{Digest, Sha1};
-use std::fs::{read_dir, read_to_string, remove_dir_all, write};
+use std::collections::BTreeMap;
+use std::fs::{read, read_dir, read_to_string, remove_dir_all, write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Instant;
 use std::{collections::HashMap, fmt};
 
+pub mod name;
 pub mod parsing;
+pub mod range;
+pub mod record;
+pub mod search;
 use parsing::*;
 
 type DepMap = HashMap<String, PathBuf>;
 
-#[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Default, Serialize, Hash)]
+/// Where packages go, relative to the project, unless the config says otherwise.
+pub const DEFAULT_INSTALL_DIR: &str = "addons";
+
+/// What folder a package goes in (see [folder]), unless the config says otherwise.
+pub const DEFAULT_DIR_TEMPLATE: &str = "{scope}/{name}";
+
+/// Wether `spec` is a dist-tag (like `latest`, or `next`), not a version range.
+pub fn is_tag(spec: &str) -> bool {
+    spec.starts_with(|c: char| c.is_ascii_alphabetic())
+        && spec
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        && Range::new(spec).parse().is_err()
+}
+
+/// Wether `e` is from not reaching the registry at all (no connection, or a timeout),
+/// not from it answering something wrong: only then do the versions known from before have to do.
+pub fn unreachable(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_connect() || e.is_timeout())
+}
+
+/// The folder for the package `name`: `template`, with `{scope}` (like `@bendn`, or nothing if unscoped)
+/// and `{name}` (without the scope) filled in. Empty path segments are dropped, so unscoped packages dont get a stray `/`.
+pub fn folder(template: &str, name: &str) -> PathBuf {
+    if let Some(repo) = crate::backend::repository(name) {
+        return template
+            .replace("{scope}", "")
+            .replace("{name}", repo)
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+    }
+    let (scope, bare) = match name.split_once('/') {
+        Some((scope, bare)) if scope.starts_with('@') => (scope, bare),
+        _ => ("", name),
+    };
+    template
+        .replace("{scope}", scope)
+        .replace("{name}", bare)
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Default, Hash)]
 /// The package struct.
 /// This struct powers the entire system, and manages
 /// - installation
@@ -28,23 +87,54 @@ type DepMap = HashMap<String, PathBuf>;
 /// - removal
 pub struct Package {
     pub name: String,
-    #[serde(skip)]
     pub indirect: bool,
-    #[serde(flatten)]
     pub manifest: Manifest,
-    #[serde(rename = "version")]
-    pub _lockfile_version_string: String, // for lockfile, do not use
+    /// The wanted version range, if this package was left unresolved (see [Client::metadata_only]).
+    pub unresolved: Option<String>,
+    /// Where packages go, relative to the project. [DEFAULT_INSTALL_DIR] if unset.
+    pub install_dir: Option<PathBuf>,
+    /// The folder (in the install dir) this package goes in, instead of its name. Only for direct deps.
+    pub dir: Option<String>,
+    /// How to name the packages folder (see [folder]). [DEFAULT_DIR_TEMPLATE] if unset.
+    pub dir_template: Option<String>,
+    /// Which of its files to install.
+    pub filter: Filter,
+    /// Symlink the download dir to an install in the [Store], instead of copying it.
+    pub symlink: bool,
+    /// The name the config wants this package under (`"mytest": "npm:@bendn/test@^2"`), if not its own.
+    /// It is installed under that name, so two versions (or a fork) can coexist. Only for direct deps.
+    pub alias: Option<String>,
+    /// Wether its hooks may run. Hooks run whatever the package wants, so this is only set when the user allows it.
+    pub allow_hooks: bool,
 }
 
-#[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Default, Debug, Serialize, Hash)]
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd, Default, Debug, Hash)]
 pub struct Manifest {
-    #[serde(skip)]
     pub shasum: Option<String>,
     pub tarball: CompressionType,
-    #[serde(skip)]
+    pub integrity: Option<Integrity>,
     pub dependencies: Vec<Package>,
-    #[serde(skip)]
+    /// The version ranges the `dependencies` are wanted at (as the package.json says), by name.
+    pub ranges: BTreeMap<String, String>,
     pub version: Version,
+    /// The build metadata of the version (`godot4.2` in `1.2.0+godot4.2`): ignored when comparing, but kept.
+    pub build: Option<String>,
+    /// Why this version should not be used, if the registry says so.
+    pub deprecated: Option<String>,
+    /// The godot versions this version says it works with (its `engines.godot`), like `>=4.2`.
+    pub godot: Option<String>,
+    /// The commands this version wants run when it is installed (`postinstall`) or deleted (`preuninstall`).
+    pub hooks: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// The version, with its build metadata.
+    pub fn full_version(&self) -> String {
+        match &self.build {
+            Some(b) => format!("{}+{b}", self.version),
+            None => self.version.to_string(),
+        }
+    }
 }
 
 #[macro_export]
@@ -62,25 +152,14 @@ macro_rules! get {
 }
 
 impl Package {
-    pub fn prepare_lock(&mut self) {
-        self.manifest.tarball.lock()
-    }
-
     pub fn from_manifest(m: Manifest, name: String) -> Self {
         Self {
-            _lockfile_version_string: m.version.to_string(),
             manifest: m,
             name,
             ..Default::default()
         }
     }
 
-    #[inline]
-    /// Does this package have dependencies?
-    pub fn has_deps(&mut self) -> bool {
-        !self.manifest.dependencies.is_empty()
-    }
-
     /// Creates a new [Package] from a name and version.
     /// Makes network calls to get the manifest (which makes network calls to get dependency manifests) (unless cached)
     #[async_recursion]
@@ -90,22 +169,65 @@ impl Package {
             // i forgot what this is for
             return Self::new_no_version(name, client).await;
         }
+        if !name.starts_with("http") && is_tag(version) {
+            return Self::from_tag(name, version, client).await;
+        }
+        let channel = client.channel(&name).map(str::to_owned);
+        let channel = channel.as_deref();
+        let opts = Options::builder()
+            .include_prerelease(client.pre || channel.is_some())
+            .build();
         let r = ctx!(
-            Range::new(version).parse(),
-            "parsing version range {version} for {name}"
-        )?; // this does ~ and ^  and >= and < and || e.q parsing
+            range::parse(version, opts),
+            "parsing the version range of {name}"
+        )?;
+        // a range resolves differently with prereleases, another godot, or the oldest versions, so each is remembered apart
+        let asset = client.asset(&name).map(str::to_owned);
+        let remembered = &resolutions::Key {
+            range: version,
+            channel,
+            pre: client.pre || channel.is_some(),
+            godot: client.godot.as_ref(),
+            minimal: client.minimal,
+            asset: asset.as_deref(),
+        };
+        let pick = Pick {
+            channel,
+            build: range::build(version),
+            godot: client.godot.as_ref(),
+            minimal: client.minimal,
+        };
         if name.starts_with("http") {
+            if client.vendor.is_some() {
+                bail!("{name} is a tarball url, so it cant be installed from the vendor dir");
+            }
+            if client.metadata_only {
+                return Ok(Self {
+                    manifest: Manifest {
+                        tarball: CompressionType::from(
+                            name.split('.').next_back().unwrap_or("zip"),
+                            vec![],
+                            name.clone(),
+                        ),
+                        ..Default::default()
+                    },
+                    name,
+                    unresolved: Some(version.to_owned()),
+                    ..Default::default()
+                });
+            }
             return Self::get_tarball(name, version.to_owned(), &r, client).await;
         }
 
         if let Some(got) = client.cache().get_mut(&name) {
             let mut vers = got.clone(); // clone to remove references to dashmap
             drop(got); // drop reference (let x = x doesnt drop original x until scope ends)
-            if let Some(mut find) = vers.find_version(&r) {
+            if let Some(mut find) = vers.find_version(&r, pick) {
                 // find is a reference to vers which is cloned (not ref to dashmap)
                 // this block was supposed to be
                 // Ok(find.parse(...).await?.get_package())
                 // but then it deadlocked because get_package() would recurse
+                Self::remember(&client, &name, remembered, find.value());
                 find.parse(client.clone(), name.clone()).await?;
                 let p = find.get_package();
                 client.cache_ref().insert(
@@ -116,10 +238,56 @@ impl Package {
                 return Ok(p);
             };
         }
-        let packument = ctx!(
-            Self::get_packument(client.clone(), &name).await,
-            "getting packument for {name}"
-        )?;
+        if client.vendor.is_some() {
+            bail!("{name}@{version} is not vendored (run gpm vendor)");
+        }
+        // a snapshot has to see (or give) every packument
+        if let Some(m) = client
+            .cache_ref()
+            .resolutions()
+            .get(&client.registry, &name, remembered)
+            .filter(|_| client.snapshot.is_none())
+        {
+            let v = m.version.clone();
+            let mut entry = CacheEntry::from(m);
+            entry.parse(client.clone(), name.clone()).await?;
+            let p = entry.get_package();
+            client.cache_ref().insert(name, v, entry);
+            return Ok(p);
+        }
+        let now = Instant::now();
+        let packument = match Self::get_packument(client.clone(), &name).await {
+            Ok(p) => p,
+            Err(e) if client.snapshot.is_none() && unreachable(&e) => {
+                // offline, the versions known from before (or imported, see crate::bundle) have to do
+                let mut known = VersionsCache::default();
+                for m in client
+                    .cache_ref()
+                    .resolutions()
+                    .known(&client.registry, &name)
+                {
+                    known.insert(m.version.clone(), CacheEntry::Manifest(m));
+                }
+                if let Some(mut find) = known.find_version(&r, pick) {
+                    eprintln!(
+                        "{:>12} {e}: using {name}@{} from before",
+                        crate::putils::warn(),
+                        find.key()
+                    );
+                    find.parse(client.clone(), name.clone()).await?;
+                    let p = find.get_package();
+                    client.cache_ref().insert(
+                        name,
+                        find.key().clone(),
+                        std::mem::take(find.value_mut()),
+                    );
+                    return Ok(p);
+                }
+                return ctx!(Err(e), "getting packument for {name}");
+            }
+            Err(e) => return ctx!(Err(e), "getting packument for {name}"),
+        };
+        let took = now.elapsed();
         let mut versions = {
             let mut e = client.cache_ref().entry(name.clone()).or_default();
             // clone to not have references to dashmap which causes deadlock
@@ -127,7 +295,9 @@ impl Package {
             e.insert_packument(packument).clone()
         };
         // do it again with the new entrys inserted
-        if let Some(mut find) = versions.find_version(&r) {
+        if let Some(mut find) = versions.find_version(&r, pick) {
+            Self::remember(&client, &name, remembered, find.value());
+            timings::record(Phase::Resolve, &format!("{name}@{}", find.key()), took);
             find.parse(client.clone(), name.clone()).await?;
             let p = find.get_package();
             client
@@ -135,12 +305,63 @@ impl Package {
                 .insert(name, find.key().clone(), std::mem::take(find.value_mut()));
             return Ok(p);
         }
+        if range::exact(version).is_some() {
+            bail!(
+                "{name}@{version} is not on the registry (anymore: it was probably unpublished). Tried versions: {versions:?}. \
+                Pick another version, and run `gpm update {name}` (it is not swapped for one silently)"
+            );
+        }
         bail!(
             "Failed to match version for package {name} matching {version}. Tried versions: {:?}",
             versions
         );
     }
 
+    /// Resolves the dist-tag `tag` (like `latest`) of `name` through the registry.
+    /// Tags move, so unlike ranges, this is never answered from the resolution cache.
+    async fn from_tag(name: String, tag: &str, client: Client) -> Result<Self> {
+        let packument = ctx!(
+            Self::get_packument(client.clone(), &name).await,
+            "getting packument for {name}"
+        )?;
+        let version = match packument.tags.get(tag) {
+            Some(v) => v.clone(),
+            // registries without dist-tags still have a newest release (the versions are newest first)
+            None if tag == "latest" => packument
+                .versions
+                .iter()
+                .map(|m| m.version.clone())
+                .find(|v| Version::new(v).parse().is_ok_and(|v| !v.has_prerelease()))
+                .with_context(|| format!("{name} has no releases"))?,
+            None => {
+                let mut tags = packument.tags.keys().cloned().collect::<Vec<_>>();
+                tags.sort();
+                bail!("{name} has no dist-tag {tag} (it has: {})", tags.join(", "));
+            }
+        };
+        // so resolving the version doesnt get the packument again
+        client
+            .cache_ref()
+            .entry(name.clone())
+            .or_default()
+            .insert_packument(packument);
+        Self::new(name, version, client).await
+    }
+
+    /// Records the decision that the range of `key` resolved to `entry` in the resolution cache.
+    fn remember(client: &Client, name: &str, key: &resolutions::Key, entry: &CacheEntry) {
+        let resolutions = client.cache_ref().resolutions();
+        let version = match entry {
+            CacheEntry::Manifest(m) => {
+                resolutions.insert_manifest(&client.registry, name, m);
+                m.version.clone()
+            }
+            CacheEntry::Parsed(p) => p.manifest.full_version(),
+            _ => return,
+        };
+        resolutions.insert(&client.registry, name, key, version);
+    }
+
     /// Create a package from a [str]. see also [ParsedPackage].
     #[allow(dead_code)] // used for tests
     pub async fn create_from_str(s: &str, client: Client) -> Result<Package> {
@@ -157,15 +378,17 @@ impl Package {
         client: Client,
     ) -> Result<Package> {
         if let Some(mut v) = client.cache().get_mut(&uri) {
-            if let Some(e) = v.find_version(range) {
+            if let Some(e) = v.find_version(range, Pick::default()) {
                 return Ok(e.get_package()); // no recursion, very safe
             }
         }
 
-        let resp = ctx!(get!(client.clone(), "{uri}"), "getting tarball {uri}")?;
-        let ty = uri.split('.').last().unwrap_or("zip");
-        let bytes = resp.bytes().await?.to_vec();
-        let mut entry = CacheEntry::from(CompressionType::from(ty, bytes, uri.clone()));
+        // its one version, whatever the range says
+        let packument = crate::backend::of(&client, &uri)
+            .resolve_versions(&client, &uri)
+            .await?;
+        let m = packument.versions.into_values().next().unwrap();
+        let mut entry = CacheEntry::from(m);
         entry.parse(client.clone(), uri.clone()).await?;
         let p = entry.get_package();
         client.cache().insert(uri, version.clone(), entry);
@@ -180,18 +403,24 @@ impl Package {
                 return Ok(marker.get_package()); // doesnt recurse
             }
         }
-        let resp = get!(client.clone(), "{}/{name}/latest", client.registry)?
-            .text()
-            .await?;
+        let resp = get!(
+            client.clone(),
+            "{}/{}/latest",
+            client.registry,
+            escape(&name)
+        )?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(search::not_found(client, &name).await);
+        }
+        let resp = resp.text().await?;
         if resp == "\"Not Found\"" {
-            return Err(anyhow!("Package {name} was not found"));
+            return Err(search::not_found(client, &name).await);
         };
         let resp: Manifest = serde_json::from_str::<ParsedManifest>(&resp)?
             .try_into_async(client.clone())
             .await?;
         let latest = Package {
             name: name.to_owned(),
-            _lockfile_version_string: resp.version.to_string(),
             manifest: resp,
             ..Default::default()
         };
@@ -206,71 +435,377 @@ impl Package {
         self.download_dir(cwd).exists()
     }
 
-    /// Deletes this [Package].
+    /// Deletes this [Package] (or the link to it).
+    /// Files that were not installed, or changed since, are moved aside first (see [record::set_aside]).
     pub fn purge(&self, cwd: &Path) {
-        if self.is_installed(cwd) {
-            remove_dir_all(self.download_dir(cwd)).expect("Should be able to remove download dir");
+        let dir = self.download_dir(cwd);
+        // a link into a store that was since cleaned would not exist, but would be in the way
+        if dir.symlink_metadata().is_err() {
+            return;
+        }
+        // only the consented command runs: the package.json on disk could say anything
+        if let Some(cmd) = self.hooks().get("preuninstall") {
+            if let Err(e) = self.hook(cmd, "preuninstall", cwd) {
+                eprintln!("{:>12} {self}: {e}", crate::putils::warn());
+            }
+        }
+        if !dir.is_symlink() {
+            let (install_dir, rel) = self.folder_in_install_dir(cwd);
+            let aside = record::aside(&install_dir, &rel);
+            match record::set_aside(&dir, &self.record_path(cwd), &aside) {
+                Ok(moved) if !moved.is_empty() => {
+                    for f in &moved {
+                        eprintln!(
+                            "{:>12} {} was changed or added in {self}: moved it to {}",
+                            crate::putils::warn(),
+                            f.display(),
+                            aside.strip_prefix(cwd).unwrap_or(&aside).join(f).display()
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    panic!("Could not keep the changed files of {self} ({e}), so not deleting them")
+                }
+            }
+        }
+        remove_dir_all(&dir).expect("Should be able to remove download dir");
+        let _ = std::fs::remove_file(self.record_path(cwd));
+    }
+
+    /// The absolute install dir, and the folder of this package in it.
+    pub fn folder_in_install_dir(&self, cwd: &Path) -> (PathBuf, PathBuf) {
+        let rel = self
+            .download_dir(Path::new(""))
+            .strip_prefix(self.install_dir())
+            .unwrap()
+            .to_path_buf();
+        (cwd.join(self.install_dir()), rel)
+    }
+
+    /// Where what was installed for this package is recorded.
+    pub fn record_path(&self, cwd: &Path) -> PathBuf {
+        let (install_dir, rel) = self.folder_in_install_dir(cwd);
+        record::path(&install_dir, &rel)
+    }
+
+    /// The hooks of this package that may run: none, unless they are allowed.
+    pub fn hooks(&self) -> &BTreeMap<String, String> {
+        static NONE: BTreeMap<String, String> = BTreeMap::new();
+        if self.allow_hooks {
+            &self.manifest.hooks
+        } else {
+            &NONE
+        }
+    }
+
+    /// Runs the hook command `cmd` of this package in its download dir, telling it what its for
+    /// (`GPM_PACKAGE_NAME`, `GPM_PACKAGE_VERSION`, `GPM_PROJECT_ROOT`, `GPM_EVENT` and `GPM_PLAN`, see [hooks::set_plan]).
+    fn hook(&self, cmd: &str, event: &str, cwd: &Path) -> Result<()> {
+        let version = self.manifest.full_version();
+        let root = cwd.to_string_lossy();
+        let env = [
+            ("GPM_PACKAGE_NAME", self.name.as_str()),
+            ("GPM_PACKAGE_VERSION", version.as_str()),
+            ("GPM_PROJECT_ROOT", &root),
+            ("GPM_PLAN", hooks::plan()),
+        ];
+        hooks::exec(cmd, event, &self.download_dir(cwd), cwd, &env)
+    }
+
+    /// Runs the `postinstall` hook of this package, if it has one, in its download dir.
+    /// Call after [Package::modify], and before [Package::record], so what the hook makes counts as installed.
+    pub fn postinstall(&self, cwd: &Path) {
+        if let Some(cmd) = self.hooks().get("postinstall") {
+            self.hook(cmd, "postinstall", cwd)
+                .unwrap_or_else(|e| panic!("The postinstall hook of {self} should work: {e}"));
+        }
+    }
+
+    /// Records what is installed for this package, so user changes can be told apart later (see [Package::purge]).
+    /// Call after [Package::modify]. Links into the store arent recorded: changes there are not in the project.
+    pub fn record(&self, cwd: &Path) {
+        let dir = self.download_dir(cwd);
+        if dir.is_symlink() {
+            return;
+        }
+        if let Err(e) = record::save(&dir, &self.record_path(cwd)) {
+            eprintln!(
+                "{:>12} Could not record the files of {self}: {e}",
+                crate::putils::warn()
+            );
+        }
+        // after the record, so they stay the projects
+        let (install_dir, rel) = self.folder_in_install_dir(cwd);
+        if let Err(e) = record::unstash(&record::stash_dir(&install_dir, &rel), &dir) {
+            eprintln!(
+                "{:>12} Could not put back the godot made files of {self}: {e}",
+                crate::putils::warn()
+            );
+        }
+    }
+
+    /// Keeps the `.import` and `.uid` files godot made in the installed folder of this package, to put back after it is reinstalled (see [record::stash]).
+    fn stash_generated(&self, cwd: &Path) {
+        let dir = self.download_dir(cwd);
+        if !dir.is_dir() || dir.is_symlink() {
+            return;
+        }
+        let (install_dir, rel) = self.folder_in_install_dir(cwd);
+        if let Err(e) = record::stash(
+            &dir,
+            &self.record_path(cwd),
+            &record::stash_dir(&install_dir, &rel),
+        ) {
+            eprintln!(
+                "{:>12} Could not keep the godot made files of {self} ({e}): godot makes new ones",
+                crate::putils::warn()
+            );
         }
     }
 
     /// Installs this [Package] to a download directory,
     /// depending on wether this package is a direct dependency or not.
-    pub async fn download(&mut self, client: Client, cwd: &Path) {
-        self.purge(cwd);
-        let bytes = get!(client.clone(), "{}", &self.manifest.tarball)
-            .expect("Tarball download should work")
-            .bytes()
-            .await
-            .unwrap()
-            .to_vec();
+    pub async fn download(
+        &mut self,
+        client: Client,
+        cwd: &Path,
+        algorithm: Algorithm,
+    ) -> Result<()> {
+        let bytes = self.fetch(client.clone(), &ProgressBar::hidden()).await?;
+        self.unpack(bytes, cwd, algorithm, client.cache_ref().store(), None);
+        Ok(())
+    }
 
-        let mut hasher = Sha1::new();
-        hasher.update(&bytes);
+    /// The key of this package in the [Store], if we know its checksum before downloading it.
+    pub fn store_key(&self) -> Option<String> {
+        if let Some(i) = &self.manifest.integrity {
+            return Some(i.key());
+        }
+        self.manifest.shasum.as_ref().map(|s| format!("sha1-{s}"))
+    }
+
+    /// Gets the tarball of this [Package], from the store if its there, else from the network.
+    /// Downloaded bytes are reported to `progress`.
+    pub async fn fetch(&self, client: Client, progress: &ProgressBar) -> Result<Vec<u8>> {
+        if let Some(dir) = &client.vendor {
+            let path = dir.join(crate::vendor::file(
+                &self.name,
+                &self.manifest.full_version(),
+            ));
+            return ctx!(
+                read(&path),
+                "{self} should be vendored in {} (run gpm vendor)",
+                path.display()
+            );
+        }
+        if let (Some(store), Some(key)) = (client.cache_ref().store(), self.store_key()) {
+            if let Some(bytes) = store.tarball(&key) {
+                if self.matches(&bytes) {
+                    return Ok(bytes);
+                }
+                // broken in the store: it has to be downloaded again anyway
+                eprintln!(
+                    "{:>12} {self} was broken in the store, downloading it again",
+                    crate::putils::warn()
+                );
+                if let Err(e) = store.remove(&key) {
+                    eprintln!("{:>12} Could not delete it: {e}", crate::putils::warn());
+                }
+            }
+        }
+        let now = Instant::now();
+        let bytes = crate::backend::of(&client, &self.name)
+            .fetch_tarball(&client, self, progress)
+            .await?;
+        timings::record(Phase::Download, &self.to_string(), now.elapsed());
+        Ok(bytes)
+    }
+
+    /// Wether the tarball `bytes` match the integrity (or checksum) of this package. True if it has neither.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        if let Some(integrity) = &self.manifest.integrity {
+            return integrity.check(bytes);
+        }
         if let Some(sha) = &self.manifest.shasum {
-            assert_eq!(
-                sha,
-                &format!("{:x}", hasher.finalize()),
-                "Tarball did not match checksum!"
+            return *sha == format!("{:x}", Sha1::digest(bytes));
+        }
+        true
+    }
+
+    /// Checks the tarball against the integrity (or checksum), and unpacks it to the download directory.
+    /// With a [Store], the tarball is unpacked into the store once, and then cloned (reflinked, where possible) into the download directory.
+    /// Afterwards, the integrity is in `algorithm`, for the lockfile.
+    /// This is blocking, cpu heavy work: keep it off the async runtime.
+    pub fn unpack(
+        &mut self,
+        bytes: Vec<u8>,
+        cwd: &Path,
+        algorithm: Algorithm,
+        store: Option<&Store>,
+        journal: Option<&Journal>,
+    ) {
+        let ext = match self.manifest.tarball {
+            CompressionType::Zip(_) => "zip",
+            _ => "tgz",
+        };
+        let file = format!(
+            "{}.{ext}",
+            self.to_string()
+                .replace(|c: char| !c.is_alphanumeric() && c != '.', "_")
+        );
+        crate::scan::check(crate::scan::command(), &bytes, &file)
+            .unwrap_or_else(|e| panic!("{self} did not pass the malware scan: {e}"));
+        // started before anything in the folder goes, so an interrupted unpack is always known
+        if let Some(j) = journal {
+            j.start(&self.folder_in_install_dir(cwd).1)
+                .unwrap_or_else(|e| panic!("{self}: {e:#}"));
+        }
+        self.stash_generated(cwd);
+        self.purge(cwd);
+        let key = self.store_key();
+        let name = self.to_string();
+        timings::time(Phase::Verify, &name, || {
+            if let Some(integrity) = &self.manifest.integrity {
+                assert!(integrity.check(&bytes), "Tarball did not match integrity!");
+            } else if let Some(sha) = &self.manifest.shasum {
+                let mut hasher = Sha1::new();
+                hasher.update(&bytes);
+                assert_eq!(
+                    sha,
+                    &format!("{:x}", hasher.finalize()),
+                    "Tarball did not match checksum!"
+                );
+            }
+            if !matches!(&self.manifest.integrity, Some(i) if i.algorithm == algorithm) {
+                self.manifest.integrity = Some(Integrity::of(algorithm, &bytes));
+            }
+        });
+        timings::time(Phase::Extract, &name, || {
+            self.extract(bytes, cwd, store.zip(key))
+        });
+    }
+
+    /// Unpacks the tarball to the download directory, through the store if given one.
+    fn extract(&self, bytes: Vec<u8>, cwd: &Path, store: Option<(&Store, String)>) {
+        self.check_paths(&bytes);
+        if let Some((store, key)) = store {
+            let _lock = store
+                .lock(&key)
+                .unwrap_or_else(|e| panic!("Should be able to use the store for {self}: {e}"));
+            if !store.has_tarball(&key) {
+                if let Err(e) = store.insert_tarball(&key, &bytes) {
+                    eprintln!("{:>12} Could not cache {self}: {e}", crate::putils::warn());
+                }
+            }
+            let ty = self.archive_type(bytes);
+            let unpacked = store
+                .unpacked(&key, |d| Archive::new(ty)?.unpack(d))
+                .expect("Tarball should unpack");
+            if self.symlink {
+                let map = self.dep_map(Path::new("")).unwrap();
+                // the links of every project with the same install of this package go to the same place
+                let layout = format!("{:?}{:?}", self.filter, BTreeMap::from_iter(&map));
+                let key = format!("{key}-{}", &blake3::hash(layout.as_bytes()).to_hex()[..16]);
+                let linked = store
+                    .linked(&key, |d| {
+                        clone_tree(&unpacked, d, &self.filter)?;
+                        self.recursive_modify(d.to_path_buf(), &map)
+                    })
+                    .expect("Should be able to install into the store");
+                link_tree(&linked, &self.download_dir(cwd))
+                    .expect("Should be able to link from the store");
+            } else {
+                clone_tree(&unpacked, &self.download_dir(cwd), &self.filter)
+                    .expect("Should be able to install from the store");
+            }
+        } else {
+            // println!(
+            //     "(\"{}\", hex::decode(\"{}\").unwrap()),",
+            //     self.manifest.tarball.replace(&(client.registry + "/"), ""),
+            //     hex::encode(&bytes)
+            // );
+            Archive::new(self.archive_type(bytes))
+                .unwrap()
+                .unpack_filtered(&self.download_dir(cwd), &self.filter)
+                .expect("Tarball should unpack");
+        }
+        if let Some(sub) = &self.filter.subdir {
+            let empty = read_dir(self.download_dir(cwd)).map_or(true, |mut d| d.next().is_none());
+            if empty {
+                panic!("{self} has no {} folder to install", sub.display());
+            }
+        }
+    }
+
+    /// Panics if the files of the tarball that would be installed differ only by case,
+    /// before any of them are written (they would overwrite each other on windows and macos).
+    /// Warns about the files windows cant have, and installs under another name.
+    fn check_paths(&self, bytes: &[u8]) {
+        let paths = Archive::new(self.archive_type(bytes.to_vec()))
+            .and_then(|mut a| a.paths())
+            .expect("Tarball should be readable");
+        let selected = paths.iter().filter_map(|p| self.filter.select(p));
+        let installed = selected
+            .map(|p| {
+                let renamed = if cfg!(windows) {
+                    windows_safe(&p)
+                } else {
+                    p.clone()
+                };
+                if renamed != p {
+                    eprintln!(
+                        "{:>12} {self} has {}, which windows cant have: installing it as {}",
+                        crate::putils::warn(),
+                        p.display(),
+                        renamed.display()
+                    );
+                }
+                renamed
+            })
+            .collect::<Vec<_>>();
+        let collisions = case_collisions(installed.iter().map(PathBuf::as_path));
+        for (a, b) in &collisions {
+            eprintln!(
+                "{:>12} {self} has {} and {}, which differ only by case",
+                crate::putils::err(),
+                a.display(),
+                b.display()
             );
         }
-        // println!(
-        //     "(\"{}\", hex::decode(\"{}\").unwrap()),",
-        //     self.manifest.tarball.replace(&(client.registry + "/"), ""),
-        //     hex::encode(&bytes)
-        // );
-        let ty = match self.manifest.tarball.clone() {
+        if !collisions.is_empty() {
+            panic!("{self} cant be installed on case insensitive filesystems (see above)");
+        }
+    }
+
+    /// Downloads the tarball of this [Package], and reads every file in it.
+    pub async fn files(&self, client: Client) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+        let bytes = self.fetch(client, &ProgressBar::hidden()).await?;
+        Archive::new(self.archive_type(bytes))?.files()
+    }
+
+    /// Wraps the tarball bytes in this packages [CompressionType].
+    fn archive_type(&self, bytes: Vec<u8>) -> CompressionType {
+        match self.manifest.tarball {
             CompressionType::Gzip(_) => CompressionType::Gzip(Data::new_bytes(bytes)),
             CompressionType::Zip(_) => CompressionType::Zip(Data::new_bytes(bytes)),
             _ => unreachable!(),
-        };
-        Archive::new(ty)
-            .unwrap()
-            .unpack(&self.download_dir(cwd))
-            .expect("Tarball should unpack");
+        }
     }
 
     pub async fn get_packument(client: Client, name: &str) -> Result<Packument> {
-        let resp = ctx!(
-            get!(client.clone(), "{}/{name}", client.registry)?
-                .text()
-                .await,
-            "getting packument from {}/{name}",
-            client.registry
-        )?;
-        if resp == "\"Not Found\"" {
-            return Err(anyhow!("Package {name} was not found",));
-        };
-        let res = ctx!(
-            serde_json::from_str::<ParsedPackument>(&resp),
-            "parsing packument from {}/{name}",
-            client.registry
-        )?;
-        // println!(
-        //     "(\"{name}\", r#\"{}\"#),",
-        //     serde_json::to_string(&res)
-        //         .unwrap()
-        //         .replace("https://registry.npmjs.org", "{REGISTRY}")
-        // );
+        if let Some(s) = client.snapshot.as_ref().filter(|s| s.is_replay()) {
+            return s
+                .get(name)
+                .map(Into::into)
+                .with_context(|| format!("{name} is not in the snapshot"));
+        }
+        let res = crate::backend::of(&client, name)
+            .resolve_versions(&client, name)
+            .await?;
+        if let Some(s) = &client.snapshot {
+            s.insert(name, &res);
+        }
         Ok(res.into())
     }
 
@@ -283,17 +818,35 @@ impl Package {
         }
     }
 
+    /// Where packages go, relative to the project.
+    pub fn install_dir(&self) -> &Path {
+        self.install_dir
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_INSTALL_DIR))
+    }
+
     /// The download directory if this package is a direct dep.
     fn direct_download_dir(&self, cwd: &Path) -> PathBuf {
-        cwd.join("addons").join(self.name.clone())
+        cwd.join(self.install_dir()).join(match &self.dir {
+            Some(dir) => PathBuf::from(dir),
+            None => self.folder(),
+        })
+    }
+
+    /// The folder this package goes in, from the [Package::dir_template].
+    fn folder(&self) -> PathBuf {
+        folder(
+            self.dir_template.as_deref().unwrap_or(DEFAULT_DIR_TEMPLATE),
+            self.alias.as_ref().unwrap_or(&self.name),
+        )
     }
 
     /// The download directory if this package is a indirect dep.
     fn indirect_download_dir(&self, cwd: &Path) -> PathBuf {
-        cwd.join("addons")
+        cwd.join(self.install_dir())
             .join("__gpm_deps")
-            .join(self.name.clone())
-            .join(self.manifest.version.to_string())
+            .join(self.folder())
+            .join(self.manifest.full_version())
     }
 }
 
@@ -416,14 +969,21 @@ impl Package {
         Ok(())
     }
 
-    fn dep_map(&mut self, cwd: &Path) -> Result<DepMap> {
+    fn dep_map(&self, cwd: &Path) -> Result<DepMap> {
         let mut dep_map = HashMap::<String, PathBuf>::new();
         fn add(p: &Package, dep_map: &mut DepMap, cwd: &Path) -> Result<()> {
             let d = p.download_dir(cwd);
             dep_map.insert(p.name.clone(), d.clone());
             // unscoped (@ben/cli => cli) (for compat)
             if let Some((_, s)) = p.name.split_once('/') {
-                dep_map.insert(s.into(), d);
+                dep_map.insert(s.into(), d.clone());
+            }
+            // what the config renamed it to
+            if let (Some(dir), false) = (&p.dir, p.indirect) {
+                dep_map.insert(dir.clone(), d.clone());
+            }
+            if let (Some(alias), false) = (&p.alias, p.indirect) {
+                dep_map.insert(alias.clone(), d);
             }
             Ok(())
         }
@@ -439,16 +999,32 @@ impl Package {
         if !self.is_installed(cwd) {
             panic!("Attempting to modify a package that is not installed");
         }
+        if self.download_dir(cwd).is_symlink() {
+            // linked from the store, which was modified when it was filled
+            return;
+        }
 
-        let map = &self.dep_map(cwd).unwrap();
+        // relative to the project, as res:// paths are
+        let map = &self.dep_map(Path::new("")).unwrap();
         self.recursive_modify(self.download_dir(cwd), map).unwrap();
     }
 }
 
 impl fmt::Display for Package {
-    /// Stringifies this [Package], format my_p@1.0.0.
+    /// Stringifies this [Package], format my_p@1.0.0 (or my_p@^1.0.0, if unresolved, and alias (my_p@1.0.0), if aliased).
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}@{}", self.name, self.manifest.version)
+        if let Some(alias) = &self.alias {
+            write!(f, "{alias} (")?;
+        }
+        if let Some(range) = &self.unresolved {
+            write!(f, "{}@{range}", self.name)?;
+        } else {
+            write!(f, "{}@{}", self.name, self.manifest.full_version())?;
+        }
+        if self.alias.is_some() {
+            write!(f, ")")?;
+        }
+        Ok(())
     }
 }
 
@@ -462,6 +1038,7 @@ impl fmt::Debug for Package {
 #[cfg(test)]
 mod tests {
     use crate::package::*;
+    use crate::test_utils::known;
 
     #[tokio::test]
     async fn download() {
@@ -470,7 +1047,9 @@ mod tests {
         let mut p = Package::create_from_str("@bendn/test:2.0.10", c.clone())
             .await
             .unwrap();
-        p.download(c.clone(), t.0.path()).await;
+        p.download(c.clone(), t.0.path(), Algorithm::Sha512)
+            .await
+            .unwrap();
         assert_eq!(
             crate::test_utils::hashd(&p.download_dir(t.0.path())),
             [
@@ -483,6 +1062,239 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn exclude() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let mut p = Package::create_from_str("@bendn/test:2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.filter.exclude = vec![glob::Pattern::new("**/sub*.gd").unwrap()];
+        p.download(c, t.0.path(), Algorithm::Sha512).await.unwrap();
+        let dir = p.download_dir(t.0.path());
+        assert!(dir.join("main.gd").exists());
+        assert_eq!(crate::test_utils::hashd(&dir).len(), 3);
+    }
+
+    #[test]
+    fn case() {
+        let paths = ["A/x.gd", "a/y.gd", "b.gd", "B.GD", "c.gd"].map(Path::new);
+        assert_eq!(
+            case_collisions(paths),
+            [
+                (PathBuf::from("A"), PathBuf::from("a")),
+                (PathBuf::from("b.gd"), PathBuf::from("B.GD")),
+            ]
+        );
+        assert!(case_collisions(["a/x.gd", "a/y.gd"].map(Path::new)).is_empty());
+    }
+
+    #[test]
+    fn windows_names() {
+        let safe = |p| windows_safe(Path::new(p));
+        assert_eq!(safe("a/con.gd"), Path::new("a/con_.gd"));
+        assert_eq!(safe("Aux/x"), Path::new("Aux_/x"));
+        assert_eq!(safe("lpt1"), Path::new("lpt1_"));
+        assert_eq!(safe("icon.gd"), Path::new("icon.gd"));
+        assert_eq!(safe("dots.. /a?b"), Path::new("dots_/a_b"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hooks() {
+        let t = tempfile::TempDir::new().unwrap();
+        let mut p = Package::from_manifest(Manifest::default(), "h".into());
+        p.allow_hooks = true;
+        p.manifest.hooks = BTreeMap::from([
+            (
+                "postinstall".into(),
+                "echo $GPM_EVENT $GPM_PACKAGE_NAME $GPM_PLAN > made".into(),
+            ),
+            ("preuninstall".into(), "touch ../cleaned".into()),
+        ]);
+        let dir = p.download_dir(t.path());
+        std::fs::create_dir_all(&dir).unwrap();
+        p.postinstall(t.path());
+        assert_eq!(
+            read_to_string(dir.join("made")).unwrap(),
+            "postinstall h []\n"
+        );
+        // what the installed package.json says wasnt consented to
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{"hooks": {"preuninstall": "touch ../unasked"}}"#,
+        )
+        .unwrap();
+        p.purge(t.path());
+        assert!(!dir.exists() && dir.with_file_name("cleaned").exists());
+        assert!(!dir.with_file_name("unasked").exists());
+    }
+
+    #[tokio::test]
+    async fn prereleases() {
+        let c = crate::test_utils::mktemp().await.2;
+        let newest = |c| async {
+            let p = Package::new("ms".into(), "*".into(), c).await.unwrap();
+            p.manifest.version.to_string()
+        };
+        assert_eq!(newest(c.clone()).await, "2.1.3");
+        assert_eq!(newest(c.clone().prerelease()).await, "3.0.0-canary.1");
+        let beta = c.with_channels(HashMap::from([("ms".into(), "beta".into())]));
+        assert_eq!(newest(beta).await, "3.0.0-beta.2");
+    }
+
+    #[tokio::test]
+    async fn builds() {
+        let c = crate::test_utils::mktemp().await.2;
+        for v in ["1.2.0+godot4.2", "1.2.0+godot4.3", "1.1.0"] {
+            let m = parsing::ParsedManifest {
+                version: v.into(),
+                ..Default::default()
+            };
+            c.cache_ref()
+                .insert("b".into(), v.into(), CacheEntry::Manifest(m));
+        }
+        let get = |v: &str| Package::new("b".into(), v.into(), c.clone());
+        let p = get("1.2.0+godot4.2").await.unwrap();
+        assert_eq!(p.to_string(), "b@1.2.0+godot4.2");
+        assert_eq!(p.manifest.version.to_string(), "1.2.0");
+        assert_eq!(
+            get("^1").await.unwrap().manifest.full_version(),
+            "1.2.0+godot4.3"
+        );
+        assert_eq!(
+            get("1.2.0").await.unwrap().manifest.full_version(),
+            "1.2.0+godot4.3"
+        );
+    }
+
+    #[tokio::test]
+    async fn godot() {
+        let c = crate::test_utils::mktemp().await.2;
+        for (v, godot) in [
+            ("0.9.0", None),
+            ("1.0.0", Some("4.x")),
+            ("1.1.0", Some("3.x")),
+        ] {
+            let m = parsing::ParsedManifest {
+                version: v.into(),
+                engines: godot
+                    .map(|g| HashMap::from([("godot".into(), g.into())]))
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            c.cache_ref()
+                .insert("g".into(), v.into(), CacheEntry::Manifest(m));
+        }
+        let newest = |g: Option<&str>| {
+            let c = c
+                .clone()
+                .with_godot(g.map(|g| crate::godot::parse(g).unwrap()));
+            async {
+                Package::new("g".into(), "*".into(), c)
+                    .await
+                    .unwrap()
+                    .to_string()
+            }
+        };
+        assert_eq!(newest(None).await, "g@1.1.0");
+        assert_eq!(newest(Some("4.2")).await, "g@1.0.0");
+        assert_eq!(newest(Some("3.5")).await, "g@1.1.0");
+        assert_eq!(newest(Some("5.0")).await, "g@0.9.0");
+    }
+
+    #[tokio::test]
+    async fn cycle() {
+        let c = crate::test_utils::mktemp().await.2;
+        known(&c, "a", "1.0.0", &["b"]);
+        known(&c, "b", "1.0.0", &["a"]);
+        let a = Package::new("a".into(), "^1".into(), c.clone())
+            .await
+            .unwrap();
+        let b = &a.manifest.dependencies[0];
+        assert_eq!(b.to_string(), "b@1.0.0");
+        // a comes around again, and stops there
+        assert_eq!(b.manifest.dependencies[0].to_string(), "a@1.0.0");
+        assert!(b.manifest.dependencies[0].manifest.dependencies.is_empty());
+        let Err(path) = c
+            .resolving("a@1.0.0".into())
+            .and_then(|c| c.resolving("b@1.0.0".into()))
+            .and_then(|c| c.resolving("a@1.0.0".into()))
+        else {
+            unreachable!()
+        };
+        assert_eq!(path, "a@1.0.0 -> b@1.0.0 -> a@1.0.0");
+    }
+
+    #[tokio::test]
+    async fn ordered() {
+        let c = crate::test_utils::mktemp().await.2;
+        let deps = ["z", "m", "b", "q", "a"];
+        for name in deps {
+            known(&c, name, "1.0.0", &[]);
+        }
+        known(&c, "root", "1.0.0", &deps);
+        let p = Package::new("root".into(), "^1".into(), c).await.unwrap();
+        let names = p.manifest.dependencies.iter().map(|d| d.name.as_str());
+        assert_eq!(names.collect::<Vec<_>>(), ["a", "b", "m", "q", "z"]);
+    }
+
+    #[tokio::test]
+    async fn limits() {
+        let c = crate::test_utils::mktemp().await.2;
+        // a -> b -> c -> d: four deep
+        known(&c, "a", "1.0.0", &["b"]);
+        known(&c, "b", "1.0.0", &["c"]);
+        known(&c, "c", "1.0.0", &["d"]);
+        known(&c, "d", "1.0.0", &[]);
+        let resolve = |depth, packages| {
+            let c = c.clone().with_limits(depth, packages);
+            async { Package::new("a".into(), "^1".into(), c).await }
+        };
+        let e = format!("{:#}", resolve(Some(2), None).await.unwrap_err());
+        assert!(e.contains("deeper than 2") && e.contains("a@1.0.0 -> b@1.0.0 -> c@1.0.0"));
+        let e = format!("{:#}", resolve(None, Some(3)).await.unwrap_err());
+        assert!(e.contains("More than 3 packages"));
+        assert!(resolve(Some(4), Some(4)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn tags() {
+        assert!(is_tag("latest") && is_tag("next") && is_tag("beta-2"));
+        assert!(!is_tag("x") && !is_tag("^1") && !is_tag("1.0.0"));
+        let c = crate::test_utils::mktemp().await.2;
+        let p = Package::new("@bendn/test".into(), "latest".into(), c.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.manifest.version.to_string(), "2.0.10");
+        assert!(Package::new("@bendn/test".into(), "next".into(), c)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("no dist-tag next"));
+    }
+
+    #[tokio::test]
+    async fn gone() {
+        let c = crate::test_utils::mktemp().await.2;
+        let e = Package::new("@bendn/test".into(), "9.9.9".into(), c)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("not on the registry") && e.contains("gpm update @bendn/test"));
+    }
+
+    #[tokio::test]
+    async fn metadata_only() {
+        // nothing listens here
+        let c = crate::test_utils::mktemp().await.2.metadata_only();
+        let p = Package::new("http://127.0.0.1:9/p.zip".into(), "1.0.0".into(), c)
+            .await
+            .unwrap();
+        assert_eq!(p.to_string(), "http://127.0.0.1:9/p.zip@1.0.0");
+        assert!(p.manifest.dependencies.is_empty());
+    }
+
     #[tokio::test]
     async fn dep_map() {
         // no fs was touched in the making of this test
@@ -508,6 +1320,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn folders() {
+        assert_eq!(
+            folder(DEFAULT_DIR_TEMPLATE, "@bendn/test"),
+            Path::new("@bendn/test")
+        );
+        assert_eq!(folder(DEFAULT_DIR_TEMPLATE, "gut"), Path::new("gut"));
+        assert_eq!(folder("{name}", "@bendn/test"), Path::new("test"));
+        assert_eq!(
+            folder("vendor/{scope}/{name}", "gut"),
+            Path::new("vendor/gut")
+        );
+    }
+
+    #[tokio::test]
+    async fn modify() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let mut p = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.install_dir = Some("third_party".into());
+        for d in &mut p.manifest.dependencies {
+            d.install_dir = Some("third_party".into());
+            d.download(c.clone(), t.0.path(), Algorithm::Sha512)
+                .await
+                .unwrap();
+        }
+        p.download(c, t.0.path(), Algorithm::Sha512).await.unwrap();
+        p.modify(t.0.path());
+        let main = read_to_string(p.download_dir(t.0.path()).join("main.gd")).unwrap();
+        assert!(
+            main.contains("load('res://third_party/__gpm_deps/@bendn/gdcli/1.2.5/Parser.gd')"),
+            "{main}"
+        );
+    }
+
     #[tokio::test]
     async fn modify_load() {
         let t = crate::test_utils::mktemp().await;
@@ -516,7 +1365,7 @@ mod tests {
             .await
             .unwrap();
         let dep_map = &p.dep_map(t.0.path()).unwrap();
-        p.download(c, t.0.path()).await;
+        p.download(c, t.0.path(), Algorithm::Sha512).await.unwrap();
         p.indirect = false;
         let cwd = t.0.path().join("addons/@bendn/test");
         assert_eq!(
@@ -0,0 +1,95 @@
+/// Wether `name` comes from somewhere other than the registry (a url, a repository, a dir), which has rules of its own.
+pub fn is_source(name: &str) -> bool {
+    name.starts_with("http") || crate::backend::repository(name).is_some()
+}
+
+/// `name`, as the registry has it: trimmed, with its scope lowercased (scopes dont care about case).
+pub fn normalize(name: &str) -> String {
+    let name = name.trim();
+    match name.split_once('/') {
+        Some((scope, bare)) if scope.starts_with('@') && !is_source(name) => {
+            format!("{}/{bare}", scope.to_lowercase())
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Checks a package name against the registry rules, explaining what is wrong with it.
+pub fn check(name: &str) -> Result<(), String> {
+    let (scope, bare) = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, bare)) if !scope.is_empty() => (scope, bare),
+            _ => return Err(format!("scoped name {name} should look like @scope/name")),
+        },
+        None => ("", name),
+    };
+    if name.len() > 214 {
+        return Err(format!("name {name} is longer than 214 characters"));
+    }
+    if bare.is_empty() {
+        return Err("name is empty".into());
+    }
+    if bare.starts_with(['.', '_']) {
+        return Err(format!("name {name} cant start with . or _"));
+    }
+    if name.to_lowercase() != name {
+        return Err(format!(
+            "name {name} cant have capital letters (try {})",
+            name.to_lowercase()
+        ));
+    }
+    if let Some(c) = scope
+        .chars()
+        .chain(bare.chars())
+        .find(|&c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')))
+    {
+        return Err(format!("name {name} cant contain {c:?}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert!(check("@bendn/test").is_ok());
+        assert!(check("gdcli").is_ok());
+        assert!(check("Gdcli").unwrap_err().contains("try gdcli"));
+        assert!(check("@bendn").is_err());
+        assert!(check("_p").is_err());
+        assert!(check("a b").is_err());
+        assert!(check("@ben dn/test").unwrap_err().contains("' '"));
+        assert_eq!(normalize(" @Bendn/test\n"), "@bendn/test");
+        assert_eq!(normalize("@bendn/Test"), "@bendn/Test");
+        assert_eq!(normalize("gh:User/repo"), "gh:User/repo");
+        assert!(is_source("gh:user/repo") && is_source("https://x/y.zip") && !is_source("ms"));
+    }
+
+    #[test]
+    fn parsed() {
+        use crate::package::parsing::{ParsedPackage, VersionType};
+        let p = |s: &str| s.parse::<ParsedPackage>().map(|p| (p.name, p.version));
+        let v = |v: &str| VersionType::Normal(v.into());
+        assert_eq!(
+            p(" @Bendn/test@^2").unwrap(),
+            ("@bendn/test".into(), v("^2"))
+        );
+        assert_eq!(p("gdcli:1.2.5").unwrap(), ("gdcli".into(), v("1.2.5")));
+        assert_eq!(
+            p("gh:user/addon@1.0.0").unwrap(),
+            ("gh:user/addon".into(), v("1.0.0"))
+        );
+        assert_eq!(
+            p("git+https://me@example.com/addon.git").unwrap(),
+            (
+                "git+https://me@example.com/addon.git".into(),
+                VersionType::Latest
+            )
+        );
+        let e = p("@bendn/Test").unwrap_err().to_string();
+        assert!(e.contains("try @bendn/test"), "{e}");
+        assert!(p("@bendn").is_err() && p("").is_err());
+    }
+}
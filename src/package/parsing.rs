@@ -7,7 +7,10 @@ use async_trait::async_trait;
 use futures::stream::{self, StreamExt};
 use semver_rs::Version;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct ParsedPackage {
@@ -15,7 +18,7 @@ pub struct ParsedPackage {
     pub version: VersionType,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum VersionType {
     /// Normal version, just use it
     Normal(String),
@@ -57,61 +60,45 @@ impl std::str::FromStr for ParsedPackage {
     type Err = anyhow::Error;
 
     /// Supports 3 version syntax variations: `:`, `=`, `@`, if version not specified, will fetch latest.
+    /// The name is normalized, and checked (see [crate::package::name]).
     /// see https://docs.npmjs.com/cli/v7/configuring-npm/package-json#name
     fn from_str(s: &str) -> Result<Self> {
-        #[inline]
-        fn not_too_long(s: &str) -> bool {
-            s.len() < 214
-        }
-        #[inline]
-        fn safe(s: &str) -> bool {
-            s.find([
-                ' ', '<', '>', '[', ']', '{', '}', '|', '\\', '^', '%', ':', '=',
-            ])
-            .is_none()
-        }
-        fn check(s: &str) -> Result<()> {
-            if not_too_long(s) && safe(s) {
-                Ok(())
-            } else {
-                Err(anyhow!("Invalid package name"))
-            }
-        }
-
-        fn split_p(s: &str, d: char) -> Result<ParsedPackage> {
-            let Some((p, v)) = s.split_once(d) else {
-                check(s)?;
-                return Ok(ParsedPackage {name: s.to_string(), version: VersionType::Latest });
+        use crate::package::name;
+        let s = s.trim();
+        let version = |v: Option<&str>| match v {
+            Some(v) => VersionType::Normal(v.to_string()),
+            None => VersionType::Latest,
+        };
+        // a source has `:`s (and `@`s, in urls) of its own, so only what is after its last `@` can be the version
+        if name::is_source(s) {
+            let (n, v) = match s.rfind('@').filter(|&i| !s[i..].contains('/')) {
+                Some(i) => (&s[..i], Some(&s[i + 1..])),
+                None => (s, None),
             };
-            check(p)?;
-            Ok(ParsedPackage {
-                name: p.to_string(),
-                version: VersionType::Normal(v.to_string()),
-            })
+            return Ok(ParsedPackage {
+                name: n.to_string(),
+                version: version(v),
+            });
         }
-        if s.contains(':') {
+        let (n, v) = if let Some((n, v)) = s.split_once(':') {
             // @bendn/gdcli:1.2.5
-            split_p(s, ':')
-        } else if s.contains('=') {
+            (n, Some(v))
+        } else if let Some((n, v)) = s.split_once('=') {
             // @bendn/gdcli=1.2.5
-            return split_p(s, '=');
+            (n, Some(v))
         } else {
-            // @bendn/gdcli@1.2.5
-            if s.as_bytes()[0] == b'@' {
-                let mut owned_s = s.to_string();
-                owned_s.remove(0);
-                let Some((p, v)) = owned_s.split_once('@') else {
-                    check(s)?;
-                    return Ok(ParsedPackage {name: s.to_string(), version: VersionType::Latest });
-                };
-                check(&format!("@{p}")[..])?;
-                return Ok(ParsedPackage {
-                    name: format!("@{p}"),
-                    version: VersionType::Normal(v.to_string()),
-                });
+            // @bendn/gdcli@1.2.5, where the scopes @ isnt the separator
+            match s.get(1..).and_then(|rest| rest.find('@')) {
+                Some(i) => (&s[..i + 1], Some(&s[i + 2..])),
+                None => (s, None),
             }
-            return split_p(s, '@');
-        }
+        };
+        let n = name::normalize(n);
+        name::check(&n).map_err(|e| anyhow!("Invalid package name: {e}"))?;
+        Ok(ParsedPackage {
+            name: n,
+            version: version(v),
+        })
     }
 }
 
@@ -121,6 +108,15 @@ pub struct ParsedManifest {
     #[serde(default)]
     pub dependencies: HashMap<String, String>,
     pub version: String,
+    /// The registrys deprecation message, if this version is deprecated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// Commands to run in the installed package, by event (like `postinstall`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<String, String>,
+    /// What this version works with, like `{"godot": ">=4.2"}`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub engines: HashMap<String, String>,
 }
 
 impl fmt::Debug for ParsedManifest {
@@ -132,16 +128,31 @@ impl fmt::Debug for ParsedManifest {
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
 pub struct ParsedManifestDist {
     pub shasum: String,
+    /// Checked to be there by [ParsedPackument::parse], so a version without one doesnt break the rest.
+    #[serde(default)]
     pub tarball: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
 }
 
 #[async_trait::async_trait]
 impl TryFromAsync<ParsedManifest> for Manifest {
     async fn try_from_async(value: ParsedManifest, client: Client) -> Result<Manifest> {
         Ok(Manifest {
-            shasum: Some(value.dist.shasum),
-            tarball: CompressionType::Gzip(Data::new_uri(value.dist.tarball)),
+            // release assets (see crate::releases) have no checksum, and can be zips
+            shasum: Some(value.dist.shasum).filter(|s| !s.is_empty()),
+            tarball: match value.dist.tarball.ends_with(".zip") {
+                true => CompressionType::Zip(Data::new_uri(value.dist.tarball)),
+                false => CompressionType::Gzip(Data::new_uri(value.dist.tarball)),
+            },
+            // unsupported algorithms (sha1-) fall back to the shasum
+            integrity: value.dist.integrity.and_then(|i| i.parse().ok()),
             version: Version::new(&value.version).parse()?,
+            build: crate::package::range::build(&value.version).map(String::from),
+            deprecated: value.deprecated.filter(|d| !d.is_empty()),
+            godot: value.engines.get("godot").cloned(),
+            hooks: value.hooks,
+            ranges: value.dependencies.clone().into_iter().collect(),
             dependencies: value.dependencies.try_into_async(client).await?,
         })
     }
@@ -150,11 +161,81 @@ impl TryFromAsync<ParsedManifest> for Manifest {
 #[derive(Serialize)]
 pub struct Packument {
     pub versions: Vec<ParsedManifest>, // note: unprocessed manifests because we dont want to make requests for versions we dont need
+    /// The dist-tags (like `latest`), and the versions they point to.
+    pub tags: HashMap<String, String>,
 }
 
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
 pub struct ParsedPackument {
+    /// Empty, if every version was unpublished.
+    #[serde(default)]
     pub versions: HashMap<String, ParsedManifest>,
+    #[serde(default)]
+    pub time: HashMap<String, serde_json::Value>,
+    #[serde(default, rename = "dist-tags")]
+    pub dist_tags: HashMap<String, String>,
+}
+
+/// A packument that doesnt parse (cut off, or a field of the wrong type).
+#[derive(Debug)]
+pub struct Malformed(pub String);
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Malformed {}
+
+impl ParsedPackument {
+    /// Parses the packument `body` of `name`, from `url`. What is wrong with it (like the field that isnt right) is said,
+    /// as a [Malformed]. Versions that are broken alone (no tarball, a bad integrity) are left out, with a warning.
+    pub fn parse(body: &str, name: &str, url: &str) -> Result<Self> {
+        let de = &mut serde_json::Deserializer::from_str(body);
+        let mut p: Self = match serde_path_to_error::deserialize(de) {
+            Ok(p) => p,
+            Err(e) if e.inner().is_eof() => {
+                return Err(Malformed(format!(
+                    "The packument of {name} from {url} is cut off, after {} bytes",
+                    body.len()
+                ))
+                .into())
+            }
+            Err(e) => {
+                return Err(Malformed(format!(
+                    "The packument of {name} from {url} is malformed, at {}: {}",
+                    e.path(),
+                    e.inner()
+                ))
+                .into())
+            }
+        };
+        p.versions.retain(|v, m| {
+            let problem = if m.dist.tarball.is_empty() {
+                Some("dist.tarball is missing".into())
+            } else {
+                m.dist
+                    .integrity
+                    .as_deref()
+                    .and_then(|i| Some((i, i.parse::<crate::integrity::Integrity>().err()?)))
+                    // an integrity of only unknown hashes (like sha1) is fine: the shasum does
+                    .filter(|(i, _)| {
+                        i.split_whitespace()
+                            .any(|h| h.starts_with("sha512-") || h.starts_with("blake3-"))
+                    })
+                    .map(|(_, e)| format!("dist.integrity is invalid ({e})"))
+            };
+            if let Some(problem) = &problem {
+                eprintln!(
+                    "{:>12} {name}@{v} from {url} is left out: its {problem}",
+                    crate::putils::warn()
+                );
+            }
+            problem.is_none()
+        });
+        Ok(p)
+    }
 }
 
 impl From<ParsedPackument> for Packument {
@@ -167,7 +248,10 @@ impl From<ParsedPackument> for Packument {
                 .unwrap()
                 .cmp(&Version::new(&a.version).parse().unwrap())
         });
-        Packument { versions }
+        Packument {
+            versions,
+            tags: val.dist_tags,
+        }
     }
 }
 
@@ -177,7 +261,10 @@ impl TryFromAsync<HashMap<String, String>> for Vec<Package> {
         value: HashMap<String, String>,
         client: Client,
     ) -> Result<Vec<Package>> {
-        stream::iter(value.into_iter())
+        // a hashmap has no order: by name, so every run resolves (and warns, and fails) alike
+        let mut value = value.into_iter().collect::<Vec<_>>();
+        value.sort();
+        stream::iter(value)
             .map(|(name, version)| async {
                 let client = client.clone();
                 async move {
@@ -189,7 +276,7 @@ impl TryFromAsync<HashMap<String, String>> for Vec<Package> {
                 }
                 .await
             })
-            .buffer_unordered(crate::PARALLEL)
+            .buffered(crate::PARALLEL)
             .collect::<Vec<Result<Package>>>()
             .await
             .into_iter()
@@ -200,15 +287,50 @@ impl TryFromAsync<HashMap<String, String>> for Vec<Package> {
 #[async_trait]
 impl TryFromAsync<Vec<ParsedPackage>> for Vec<Package> {
     async fn try_from_async(value: Vec<ParsedPackage>, client: Client) -> Result<Vec<Package>> {
-        stream::iter(value.into_iter())
+        stream::iter(value)
             .map(|pp| async {
                 let client = client.clone();
                 async move { pp.into_package(client).await }.await
             })
-            .buffer_unordered(crate::PARALLEL)
+            .buffered(crate::PARALLEL)
             .collect::<Vec<Result<Package>>>()
             .await
             .into_iter()
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed() {
+        let url = "http://registry/x";
+        let v = |version: &str, dist: &str| {
+            format!(r#""{version}": {{"version": "{version}", "dist": {{"shasum": "", {dist}}}}}"#)
+        };
+        let body = format!(
+            r#"{{"versions": {{{}, {}, {}, {}}}}}"#,
+            v("1.0.0", r#""tarball": "t""#),
+            v("1.1.0", r#""tarball": """#),
+            v("1.2.0", r#""tarball": "t", "integrity": "sha512-!!""#),
+            v("1.3.0", r#""tarball": "t", "integrity": "sha1-xyz""#),
+        );
+        let p = ParsedPackument::parse(&body, "x", url).unwrap();
+        let mut left = p.versions.into_keys().collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, ["1.0.0", "1.3.0"]);
+
+        let e = ParsedPackument::parse(&body[..body.len() / 2], "x", url).unwrap_err();
+        assert!(e.is::<Malformed>());
+        assert!(e.to_string().contains("is cut off"), "{e}");
+        let e = ParsedPackument::parse(r#"{"versions": {"1.0.0": {"version": 1}}}"#, "x", url)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            e.contains("from http://registry/x is malformed, at versions.1.0.0.version"),
+            "{e}"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{anyhow, Result};
+use semver_rs::{Options, Range, Version};
+
+/// Parses a version range, in the full node-semver grammar: `1.2.3`, `^1.2`, `~1`, `1.x`, `*`,
+/// `>=1 <2`, `1.0.0 - 2.0.0`, and any of those `||`ed together.
+/// If it doesnt parse, the error says which part is wrong.
+pub fn parse(spec: &str, opts: Options) -> Result<Range> {
+    Range::new(spec)
+        .with_options(opts)
+        .parse()
+        .map_err(|e| match bad_part(spec, opts) {
+            Some((at, part)) => anyhow!(
+                "`{part}` (at character {}) in the range `{spec}` is not a version or comparison",
+                at + 1
+            ),
+            None => anyhow!("`{spec}` is not a valid version range ({e})"),
+        })
+}
+
+/// The build metadata of `spec` (`godot4.2` in `1.2.0+godot4.2`), if it is one exact version with some.
+/// Versions that differ only in build metadata compare equal, so this is how they are told apart.
+pub fn build(spec: &str) -> Option<&str> {
+    exact(spec)?;
+    spec.split_once('+').map(|(_, build)| build)
+}
+
+/// The version `spec` is, if it is one exact version (`1.2.5`, `=1.2.5`), not a range.
+pub fn exact(spec: &str) -> Option<Version> {
+    let spec = spec.trim().trim_start_matches(['=', 'v']);
+    // anything that isnt a version parses to the empty one
+    Version::new(spec).parse().ok().filter(|v| !v.is_empty())
+}
+
+/// The operators a comparison can start with, which may be followed by a space (`>= 1.2`).
+const OPERATORS: &[&str] = &["<=", ">=", "<", ">", "=", "~>", "~", "^"];
+
+/// Finds the first part of `spec` that doesnt parse on its own, with its byte offset.
+fn bad_part(spec: &str, opts: Options) -> Option<(usize, &str)> {
+    let ok = |part: &str| Range::new(part).with_options(opts).parse().is_ok();
+    let mut at = 0;
+    for set in spec.split("||") {
+        let tokens = tokens(set, at);
+        at += set.len() + 2;
+        // `1.0.0 - 2.0.0` is one part, that only parses whole
+        if let [(_, from), (_, "-"), (_, to)] = tokens.as_slice() {
+            if ok(from) && ok(to) {
+                continue;
+            }
+        }
+        let mut i = 0;
+        while i < tokens.len() {
+            let (start, mut token) = tokens[i];
+            // an operator on its own goes with the version after it
+            if OPERATORS.contains(&token) && i + 1 < tokens.len() {
+                let (next, next_token) = tokens[i + 1];
+                token = &spec[start..next + next_token.len()];
+                i += 1;
+            }
+            if !ok(token) {
+                return Some((start, token));
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+/// The whitespace separated tokens of `set`, with their offsets (`set` starts at `offset`).
+fn tokens(set: &str, offset: usize) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in set.char_indices().chain([(set.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push((offset + s, &set[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let opts = Options::default();
+        for good in [
+            "1.2.3",
+            "^1.2",
+            "~1",
+            "1.x",
+            "1.2.X",
+            "*",
+            "",
+            ">=1 <2",
+            ">= 1.2.0",
+            "1.0.0 - 2.0.0",
+            "1.x || >=2.5.0 || 5.0.0 - 7.2.3",
+        ] {
+            assert!(parse(good, opts).is_ok(), "{good}");
+        }
+        let err = |spec| parse(spec, opts).unwrap_err().to_string();
+        assert_eq!(
+            err(">=1 || ^1.x.y.z"),
+            "`^1.x.y.z` (at character 8) in the range `>=1 || ^1.x.y.z` is not a version or comparison"
+        );
+        assert!(err(">= 1.2 <= nope").contains("`<= nope`"));
+        assert_eq!(build("=1.2.0+godot4.2"), Some("godot4.2"));
+        assert_eq!(build("1.2.0"), None);
+        assert_eq!(build(">=1.2.0+godot4.2"), None);
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read, read_dir, read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the records live, in the install dir. Hidden, so godot leaves it alone.
+const RECORDS: &str = ".gpm-files";
+
+/// Where files are moved aside to, in the install dir.
+const PRESERVED: &str = ".gpm-preserved";
+
+/// Where the files godot made in a package folder wait while it is reinstalled, in the install dir.
+const STASH: &str = ".gpm-generated";
+
+/// Files godot makes next to the installed ones, which are not anybodys work.
+const GENERATED: &[&str] = &["import", "uid"];
+
+/// What was installed in a package folder: file (relative to it, with `/`s) => blake3.
+type Record = BTreeMap<String, String>;
+
+/// The record of the package folder `rel` (relative to the `install_dir`).
+pub fn path(install_dir: &Path, rel: &Path) -> PathBuf {
+    let mut p = install_dir.join(RECORDS).join(rel).into_os_string();
+    p.push(".json");
+    p.into()
+}
+
+/// The package folders (relative to the `install_dir`) that have a record: the ones gpm installed.
+pub fn recorded(install_dir: &Path) -> Vec<PathBuf> {
+    files(&install_dir.join(RECORDS))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.extension().is_some_and(|e| e == "json"))
+        .map(|f| f.with_extension(""))
+        .collect()
+}
+
+/// Where the files godot made in the package folder `rel` wait while it is reinstalled (see [stash]).
+pub fn stash_dir(install_dir: &Path, rel: &Path) -> PathBuf {
+    install_dir.join(STASH).join(rel)
+}
+
+/// Where to move aside the files of the package folder `rel` (see [set_aside]): a new folder every time, so nothing is overwritten.
+pub fn aside(install_dir: &Path, rel: &Path) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    install_dir.join(PRESERVED).join(now.to_string()).join(rel)
+}
+
+fn files(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+        for e in read_dir(dir)? {
+            let e = e?;
+            let rel = rel.join(e.file_name());
+            if e.file_type()?.is_dir() {
+                walk(&e.path(), &rel, out)?;
+            } else {
+                out.push(rel);
+            }
+        }
+        Ok(())
+    }
+    let mut out = vec![];
+    walk(dir, Path::new(""), &mut out)?;
+    Ok(out)
+}
+
+fn key(rel: &Path) -> String {
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+fn hash(file: &Path) -> Result<String> {
+    Ok(blake3::hash(&read(file)?).to_hex().to_string())
+}
+
+/// Records what is in the package folder `dir` (as installed), into `record`.
+pub fn save(dir: &Path, record: &Path) -> Result<()> {
+    let r = files(dir)?
+        .into_iter()
+        .map(|f| Ok((key(&f), hash(&dir.join(&f))?)))
+        .collect::<Result<Record>>()?;
+    create_dir_all(record.parent().unwrap())?;
+    write(record, serde_json::to_string(&r)?).context("writing the install record")
+}
+
+/// Wether every file in `dir` (but the ones godot makes) is in `installed`, the same. Theres more to a tarball than an addon folder, so `installed` can have more.
+pub fn matches(dir: &Path, installed: &Path) -> Result<bool> {
+    let mut any = false;
+    for f in files(dir)? {
+        if f.extension()
+            .is_some_and(|e| GENERATED.iter().any(|g| e == *g))
+        {
+            continue;
+        }
+        let i = installed.join(&f);
+        if !i.is_file() || hash(&i)? != hash(&dir.join(&f))? {
+            return Ok(false);
+        }
+        any = true;
+    }
+    Ok(any)
+}
+
+/// The files in the package folder `dir` (relative to it) that were not installed (as per `record`), or were changed since.
+/// Without a record (installed by an older gpm), nothing can be told apart, so there are none.
+pub fn changed(dir: &Path, record: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(r) = read_to_string(record) else {
+        return Ok(vec![]);
+    };
+    let r: Record = serde_json::from_str(&r).context("parsing the install record")?;
+    let mut out = vec![];
+    for f in files(dir)? {
+        let generated = f
+            .extension()
+            .is_some_and(|e| GENERATED.iter().any(|g| e == *g));
+        if !generated && r.get(&key(&f)) != Some(&hash(&dir.join(&f))?) {
+            out.push(f);
+        }
+    }
+    Ok(out)
+}
+
+/// Moves the `.import` and `.uid` files godot made in the package folder `dir` (the ones the package didnt install, as per `record`, or that godot changed since)
+/// into `stash`, to be put back after a reinstall (see [unstash]): scenes use the uids in them, and a new uid would break them.
+/// Without a record, every one is kept.
+pub fn stash(dir: &Path, record: &Path, stash: &Path) -> Result<()> {
+    let r = match read_to_string(record) {
+        Ok(r) => serde_json::from_str(&r).context("parsing the install record")?,
+        Err(_) => Record::new(),
+    };
+    for f in files(dir)? {
+        let generated = f
+            .extension()
+            .is_some_and(|e| GENERATED.iter().any(|g| e == *g));
+        if generated && r.get(&key(&f)) != Some(&hash(&dir.join(&f))?) {
+            let to = stash.join(&f);
+            create_dir_all(to.parent().unwrap())?;
+            rename(dir.join(&f), to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Puts the files [stash] kept back into the reinstalled package folder `dir`, over the ones the package has:
+/// the ones of the project are what its scenes use. The ones of files the package doesnt have anymore are dropped.
+/// Returns the files put back (relative to `dir`).
+pub fn unstash(stash: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    if !stash.exists() {
+        return Ok(vec![]);
+    }
+    let mut back = vec![];
+    for f in files(stash)? {
+        // icon.png.import is about icon.png
+        if dir.join(f.with_extension("")).is_file() {
+            rename(stash.join(&f), dir.join(&f))?;
+            back.push(f);
+        }
+    }
+    std::fs::remove_dir_all(stash)?;
+    Ok(back)
+}
+
+/// Moves the [changed] files in the package folder `dir` into `aside`, so deleting the folder doesnt delete somebodys work.
+/// Returns the moved files (relative to `dir`).
+pub fn set_aside(dir: &Path, record: &Path, aside: &Path) -> Result<Vec<PathBuf>> {
+    let mut moved = vec![];
+    for f in changed(dir, record)? {
+        let to = aside.join(&f);
+        create_dir_all(to.parent().unwrap())?;
+        rename(dir.join(&f), to)?;
+        moved.push(f);
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_aside() {
+        let t = tempfile::TempDir::new().unwrap();
+        let (dir, rec, aside) = (
+            t.path().join("p"),
+            t.path().join("r.json"),
+            t.path().join("a"),
+        );
+        create_dir_all(dir.join("sub")).unwrap();
+        write(dir.join("a.gd"), "extends Node").unwrap();
+        write(dir.join("sub/b.gdshader"), "shader_type spatial;").unwrap();
+        assert!(set_aside(&dir, &rec, &aside).unwrap().is_empty());
+        save(&dir, &rec).unwrap();
+        save(&dir, &path(t.path(), Path::new("@a/b.c"))).unwrap();
+        assert_eq!(recorded(t.path()), [Path::new("@a/b.c")]);
+        write(dir.join("sub/b.gdshader"), "shader_type canvas_item;").unwrap();
+        write(dir.join("mine.gd"), "extends Node").unwrap();
+        write(dir.join("icon.png.import"), "[remap]").unwrap();
+        let mut moved = set_aside(&dir, &rec, &aside).unwrap();
+        moved.sort();
+        assert_eq!(moved, [Path::new("mine.gd"), Path::new("sub/b.gdshader")]);
+        assert!(aside.join("sub/b.gdshader").exists());
+        assert!(dir.join("a.gd").exists());
+    }
+
+    #[test]
+    fn generated() {
+        let t = tempfile::TempDir::new().unwrap();
+        let (dir, rec, stashed) = (
+            t.path().join("p"),
+            t.path().join("r.json"),
+            t.path().join("s"),
+        );
+        create_dir_all(&dir).unwrap();
+        write(dir.join("a.gd"), "extends Node").unwrap();
+        write(dir.join("b.gd"), "extends Node").unwrap();
+        write(dir.join("shipped.gd"), "extends Node").unwrap();
+        write(dir.join("shipped.gd.uid"), "uid://shipped").unwrap();
+        save(&dir, &rec).unwrap();
+        // godot gives the others uids
+        write(dir.join("a.gd.uid"), "uid://a").unwrap();
+        write(dir.join("b.gd.uid"), "uid://b").unwrap();
+        stash(&dir, &rec, &stashed).unwrap();
+        assert!(!dir.join("a.gd.uid").exists());
+        assert!(dir.join("shipped.gd.uid").exists());
+        // the new version ships a uid for a.gd, and has no b.gd
+        std::fs::remove_dir_all(&dir).unwrap();
+        create_dir_all(&dir).unwrap();
+        write(dir.join("a.gd"), "extends Node2D").unwrap();
+        write(dir.join("a.gd.uid"), "uid://new").unwrap();
+        assert_eq!(unstash(&stashed, &dir).unwrap(), [Path::new("a.gd.uid")]);
+        assert_eq!(read_to_string(dir.join("a.gd.uid")).unwrap(), "uid://a");
+        assert!(!dir.join("b.gd.uid").exists());
+        assert!(!stashed.exists());
+    }
+}
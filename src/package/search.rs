@@ -0,0 +1,68 @@
+use crate::Client;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct SearchResults {
+    objects: Vec<SearchObject>,
+}
+
+#[derive(Deserialize)]
+struct SearchObject {
+    package: SearchPackage,
+}
+
+#[derive(Deserialize)]
+struct SearchPackage {
+    name: String,
+}
+
+/// Searches the registry for packages matching `text`, returning at most `size` names.
+pub async fn search(client: Client, text: &str, size: usize) -> Result<Vec<String>> {
+    let resp = client
+        .get(format!("{}/-/v1/search", client.registry))
+        .query(&[("text", text), ("size", &size.to_string())])
+        .send()
+        .await?
+        .text()
+        .await?;
+    Ok(serde_json::from_str::<SearchResults>(&resp)?
+        .objects
+        .into_iter()
+        .map(|o| o.package.name)
+        .collect())
+}
+
+/// The candidate closest to `name`, if any is close enough to be a typo.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|c| (strsim::levenshtein(name, c), c))
+        .filter(|&(d, c)| d > 0 && d <= (name.len().max(c.len()) / 3).max(2))
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, c)| c)
+}
+
+/// The error for a package that does not exist, suggesting the closest match from the registry search.
+pub async fn not_found(client: Client, name: &str) -> anyhow::Error {
+    let found = search(client, name, 10).await.unwrap_or_default();
+    match closest(name, found.iter().map(String::as_str)) {
+        Some(c) => anyhow!("Package {name} was not found (did you mean {c}?)"),
+        None => anyhow!("Package {name} was not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos() {
+        let names = ["@bendn/test", "@bendn/gdcli", "@bendn/splitter"];
+        assert_eq!(closest("@bendn/tset", names), Some("@bendn/test"));
+        assert_eq!(closest("@bendn/splitr", names), Some("@bendn/splitter"));
+        assert_eq!(closest("@bendn/test", names), None);
+        assert_eq!(closest("godot-rust", names), None);
+    }
+}
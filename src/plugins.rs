@@ -0,0 +1,138 @@
+use crate::graph::Graph;
+
+use std::fs::read_dir;
+use std::path::Path;
+
+/// The editor plugins the installed packages have, and the ones the project.godot enables, that dont agree.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Installed plugins (like `res://addons/foo/plugin.cfg`) that arent enabled, by package.
+    pub disabled: Vec<(String, String)>,
+    /// Enabled plugins that arent there.
+    pub missing: Vec<String>,
+}
+
+/// The plugins `project` (a project.godot) enables, as `res://` paths to their plugin.cfg.
+/// Godot 3.0 listed them by folder (`"foo"`), which is `res://addons/foo/plugin.cfg`.
+pub fn enabled(project: &str) -> Vec<String> {
+    crate::godot::array(project, "editor_plugins", "enabled")
+        .into_iter()
+        .map(|p| match p.starts_with("res://") {
+            true => p.to_owned(),
+            false => format!("res://addons/{p}/plugin.cfg"),
+        })
+        .collect()
+}
+
+/// The plugin.cfgs in `dir` (and in the folders in it), as `res://` paths (relative to the project `cwd`).
+fn plugin_cfgs(dir: &Path, cwd: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    for e in entries.filter_map(|e| e.ok()) {
+        let p = e.path();
+        if p.is_dir() {
+            plugin_cfgs(&p, cwd, out);
+        } else if e.file_name() == "plugin.cfg" {
+            let rel = p.strip_prefix(cwd).unwrap_or(&p);
+            out.push(format!(
+                "res://{}",
+                rel.to_string_lossy().replace('\\', "/")
+            ));
+        }
+    }
+}
+
+/// Compares the plugins of the installed packages of `graph` with the ones the project.godot `project` (of the project `cwd`) enables.
+/// Only the directly wanted packages count as to be enabled: the plugins of dependencies are theirs to enable.
+pub fn report(graph: &Graph, project: &str, cwd: &Path) -> Report {
+    let enabled = enabled(project);
+    let mut r = Report::default();
+    for (_, p) in graph
+        .iter()
+        .filter(|(_, p)| !p.indirect && p.is_installed(cwd))
+    {
+        let mut cfgs = vec![];
+        plugin_cfgs(&p.download_dir(cwd), cwd, &mut cfgs);
+        cfgs.sort();
+        r.disabled.extend(
+            cfgs.into_iter()
+                .filter(|c| !enabled.contains(c))
+                .map(|c| (p.to_string(), c)),
+        );
+    }
+    r.missing = enabled
+        .into_iter()
+        .filter(|e| !cwd.join(e.trim_start_matches("res://")).is_file())
+        .collect();
+    r
+}
+
+/// `project` (a project.godot) with the plugins `enable` enabled, and the `disable` ones not.
+pub fn fix(project: &str, enable: &[String], disable: &[String]) -> String {
+    let mut plugins = enabled(project);
+    plugins.retain(|p| !disable.contains(p));
+    plugins.extend(
+        enable
+            .iter()
+            .filter(|p| !plugins.contains(p))
+            .cloned()
+            .collect::<Vec<_>>(),
+    );
+    crate::godot::set_array(project, "editor_plugins", "enabled", &plugins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn plugins() {
+        let godot4 = "config_version=5\n\n[application]\n\nconfig/name=\"x\"\n";
+        assert!(enabled(godot4).is_empty());
+        let fixed = fix(godot4, &["res://addons/a/plugin.cfg".into()], &[]);
+        assert_eq!(
+            fixed,
+            format!("{godot4}\n[editor_plugins]\n\nenabled=PackedStringArray(\"res://addons/a/plugin.cfg\")\n")
+        );
+        assert_eq!(enabled(&fixed), ["res://addons/a/plugin.cfg"]);
+        let godot3 = "config_version=4\n\n[editor_plugins]\n\nenabled=PoolStringArray( \"old\", \"res://addons/b/plugin.cfg\" )\n\n[gui]\n";
+        assert_eq!(
+            enabled(godot3),
+            ["res://addons/old/plugin.cfg", "res://addons/b/plugin.cfg"]
+        );
+        assert_eq!(
+            fix(godot3, &["res://addons/a/plugin.cfg".into()], &["res://addons/old/plugin.cfg".into()]),
+            "config_version=4\n\n[editor_plugins]\n\nenabled=PoolStringArray( \"res://addons/b/plugin.cfg\", \"res://addons/a/plugin.cfg\" )\n\n[gui]\n"
+        );
+
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let cwd = t.0.path();
+        let mut p = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.download(c, cwd, Default::default()).await.unwrap();
+        std::fs::write(cwd.join("addons/@bendn/test/plugin.cfg"), "[plugin]\n").unwrap();
+        let graph = Graph::new(vec![p]);
+        let r = report(&graph, godot3, cwd);
+        assert_eq!(
+            r.disabled,
+            [(
+                "@bendn/test@2.0.10".into(),
+                "res://addons/@bendn/test/plugin.cfg".into()
+            )]
+        );
+        assert_eq!(
+            r.missing,
+            ["res://addons/old/plugin.cfg", "res://addons/b/plugin.cfg"]
+        );
+        let fixed = fix(
+            godot3,
+            &["res://addons/@bendn/test/plugin.cfg".into()],
+            &r.missing,
+        );
+        assert_eq!(report(&graph, &fixed, cwd), Report::default());
+    }
+}
@@ -0,0 +1,325 @@
+pub mod deprecate;
+pub mod provenance;
+pub mod tags;
+
+use crate::pack::Packed;
+use crate::package::Package;
+use crate::Client;
+
+use anyhow::{bail, Result};
+use base64::Engine;
+use futures::future::join_all;
+use reqwest::Method;
+use semver_rs::Version;
+use serde_json::{json, Value};
+
+/// File types that dont belong in an addon: executables, installers, and archives (likely an accidental asset dump).
+/// Native libraries (.dll, .so, .dylib) are fine, GDExtensions need them.
+const SUSPICIOUS: &[&str] = &[
+    "exe", "msi", "bat", "cmd", "com", "scr", "vbs", "ps1", "jar", "apk", "dmg", "pkg", "deb",
+    "rpm", "zip", "7z", "rar", "pck",
+];
+
+/// Parses a size like `500K`, `50M`, `1G`, or a plain number of bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim().trim_end_matches(['b', 'B']);
+    let (n, mul) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    n.trim()
+        .parse::<u64>()
+        .map(|n| n * mul)
+        .map_err(|_| format!("{s} is not a size (like 50M)"))
+}
+
+/// Everything wrong with this package, that would make publishing it a bad idea.
+/// Dependencies are checked against the registry, so this makes network calls.
+/// Each problem comes with the package.json key its about, if it is about one (to find its line with [crate::annotate::line]).
+pub async fn validate(
+    packed: &Packed,
+    client: Client,
+    max_size: u64,
+) -> Vec<(Option<String>, String)> {
+    let mut problems = vec![];
+    let mut push =
+        |what: Option<&str>, message: String| problems.push((what.map(str::to_owned), message));
+    if packed.tarball.len() as u64 > max_size {
+        push(None, format!(
+            "the tarball is {}, more than the limit of {} (raise it with --max-size, or ignore files with a .gpmignore)",
+            indicatif::HumanBytes(packed.tarball.len() as u64),
+            indicatif::HumanBytes(max_size)
+        ));
+    }
+    for (p, _) in &packed.files {
+        if p.extension()
+            .is_some_and(|e| SUSPICIOUS.contains(&&*e.to_string_lossy().to_lowercase()))
+        {
+            push(
+                None,
+                format!(
+                    "{} does not look like it belongs in an addon (ignore it with a .gpmignore)",
+                    p.display()
+                ),
+            );
+        }
+    }
+    let m = &packed.manifest;
+    if let Err(e) = crate::package::name::check(&packed.name) {
+        push(Some("name"), e);
+    }
+    // the parser is loose, so 1.0 would be fine for it
+    let core = packed.version.split(['-', '+']).next().unwrap_or("");
+    if core.split('.').count() != 3 || Version::new(&packed.version).parse().is_err() {
+        push(
+            Some("version"),
+            format!(
+                "version {} is not a valid semver version (like 1.0.0)",
+                packed.version
+            ),
+        );
+    }
+    for field in ["description", "license"] {
+        if !m[field].is_string() {
+            push(None, format!("package.json is missing a {field:?}"));
+        }
+    }
+    let plugin = m["godot"]["plugin"].as_bool().unwrap_or(false);
+    if plugin
+        && !packed
+            .files
+            .iter()
+            .any(|(p, _)| p.file_name().is_some_and(|n| n == "plugin.cfg"))
+    {
+        push(
+            Some("plugin"),
+            "package.json says this is an editor plugin (godot.plugin), but no plugin.cfg is packed"
+                .into(),
+        );
+    }
+    let deps = m["dependencies"]
+        .as_object()
+        .map(|d| d.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let checked = join_all(deps.into_iter().map(|(name, range)| {
+        let client = client.clone();
+        async move {
+            let problem = |message: String| Some((Some(name.clone()), message));
+            let Some(range) = range.as_str() else {
+                return problem(format!("dependency {name} should have a version range"));
+            };
+            if range.starts_with("http") || name.starts_with("http") {
+                return None;
+            }
+            let r = match crate::package::range::parse(range, Default::default()) {
+                Ok(r) => r,
+                Err(e) => return problem(format!("dependency {name} has an invalid range: {e}")),
+            };
+            match Package::get_packument(client, name).await {
+                Ok(p) => (!p
+                    .versions
+                    .iter()
+                    .any(|v| Version::new(&v.version).parse().is_ok_and(|v| r.test(&v))))
+                .then(|| {
+                    (
+                        Some(name.clone()),
+                        format!("dependency {name}@{range} matches no published version"),
+                    )
+                }),
+                Err(_) => problem(format!("dependency {name} does not exist on the registry")),
+            }
+        }
+    }))
+    .await;
+    problems.extend(checked.into_iter().flatten());
+    problems
+}
+
+/// The document the registry wants PUT to publish a version.
+fn document(packed: &Packed, registry: &str, tag: &str) -> Value {
+    let file = packed.file_name();
+    let mut version = packed.manifest.clone();
+    version["_id"] = json!(format!("{}@{}", packed.name, packed.version));
+    version["dist"] = json!({
+        "shasum": packed.shasum(),
+        "integrity": packed.integrity().to_string(),
+        "tarball": format!("{registry}/{}/-/{file}", packed.name),
+    });
+    json!({
+        "_id": packed.name,
+        "name": packed.name,
+        "description": packed.manifest["description"],
+        "dist-tags": { tag: packed.version },
+        "versions": { &packed.version: version },
+        "_attachments": {
+            file: {
+                "content_type": "application/octet-stream",
+                "data": base64::engine::general_purpose::STANDARD.encode(&packed.tarball),
+                "length": packed.tarball.len(),
+            }
+        }
+    })
+}
+
+/// The registry wants a one-time password (2FA) for this (EOTP).
+#[derive(Debug)]
+pub struct OtpRequired;
+
+impl std::fmt::Display for OtpRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "the registry wants a one-time password (pass --otp)")
+    }
+}
+
+impl std::error::Error for OtpRequired {}
+
+/// Is this 401 an EOTP challenge? npm says so in the `www-authenticate` header, or just the body.
+fn wants_otp(www_authenticate: Option<&str>, body: &str) -> bool {
+    www_authenticate.is_some_and(|h| h.to_lowercase().contains("otp"))
+        || body.contains("one-time pass")
+        || body.contains("EOTP")
+}
+
+/// How a package name goes in a registry url: `@scope/name` becomes `@scope%2fname`.
+pub fn escape(name: &str) -> String {
+    name.replacen('/', "%2f", 1)
+}
+
+/// Sends a write to the registry, authenticated with `token` (and `otp`, if the registry wants 2FA).
+/// Returns the response body.
+/// Fails with [OtpRequired] if the registry wants a one-time password that wasnt given (or was wrong).
+pub async fn send(
+    client: Client,
+    method: Method,
+    path: &str,
+    token: &str,
+    otp: Option<&str>,
+    body: Option<String>,
+) -> Result<String> {
+    let mut req = client
+        .request(method, format!("{}/{path}", client.registry))
+        .bearer_auth(token);
+    if let Some(body) = body {
+        req = req
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+    }
+    if let Some(otp) = otp {
+        req = req.header("npm-otp", otp);
+    }
+    let resp = req.send().await?;
+    if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let header = resp
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_string);
+        let text = resp.text().await.unwrap_or_default();
+        if wants_otp(header.as_deref(), &text) {
+            return Err(OtpRequired.into());
+        }
+        bail!("registry refused the token: {text}");
+    }
+    if !resp.status().is_success() {
+        bail!(
+            "registry refused the request ({}): {}",
+            resp.status(),
+            resp.text().await.unwrap_or_default()
+        );
+    }
+    Ok(resp.text().await?)
+}
+
+/// Uploads the package to the registry, tagged `tag`, with its `provenance` bundle (if any).
+pub async fn publish(
+    packed: &Packed,
+    client: Client,
+    tag: &str,
+    token: &str,
+    otp: Option<&str>,
+    provenance: Option<&Value>,
+) -> Result<()> {
+    let mut doc = document(packed, &client.registry, tag);
+    if let Some(bundle) = provenance {
+        let bundle = bundle.to_string();
+        doc["_attachments"][format!("{}-{}.sigstore", packed.name, packed.version)] = json!({
+            "content_type": provenance::BUNDLE_TYPE,
+            "data": bundle,
+            "length": bundle.len(),
+        });
+    }
+    let path = escape(&packed.name);
+    send(
+        client,
+        Method::PUT,
+        &path,
+        token,
+        otp,
+        Some(doc.to_string()),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otp() {
+        assert!(wants_otp(Some("OTP"), ""));
+        assert!(wants_otp(
+            None,
+            r#"{"error":"This operation requires a one-time password."}"#
+        ));
+        assert!(wants_otp(None, r#"{"code":"EOTP"}"#));
+        assert!(!wants_otp(Some("Bearer"), r#"{"error":"bad token"}"#));
+    }
+
+    #[tokio::test]
+    async fn validation() {
+        let c = crate::test_utils::mktemp().await.2;
+        let t = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            t.path().join("package.json"),
+            r#"{"name":"@bendn/p","version":"1.0","godot":{"plugin":true},"description":"p",
+            "dependencies":{"@bendn/gdcli":"^1.2.5","@bendn/gdcli2":"1.0.0"}}"#,
+        )
+        .unwrap();
+        std::fs::write(t.path().join("setup.exe"), "MZ").unwrap();
+        let packed = crate::pack::pack(t.path()).unwrap();
+        let problems = validate(&packed, c, 10).await;
+        assert_eq!(problems.len(), 6, "{problems:?}");
+        let what = problems
+            .iter()
+            .map(|(w, _)| w.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            what,
+            [
+                None,
+                None,
+                Some("version"),
+                None,
+                Some("plugin"),
+                Some("@bendn/gdcli2")
+            ]
+        );
+        assert!(problems[0].1.contains("limit of 10 B"));
+        assert!(problems[1].1.contains("setup.exe"));
+        assert!(problems[2].1.contains("version 1.0"));
+        assert!(problems[3].1.contains("license"));
+        assert!(problems[4].1.contains("plugin.cfg"));
+        assert!(problems[5].1.contains("@bendn/gdcli2"));
+        assert_eq!(parse_size("50M"), Ok(50 << 20));
+        assert_eq!(parse_size("512kb"), Ok(512 << 10));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert!(parse_size("lots").is_err());
+        assert_eq!(
+            document(&packed, "http://r", "latest")["versions"]["1.0"]["dist"]["tarball"],
+            "http://r/@bendn/p/-/bendn-p-1.0.tgz"
+        );
+    }
+}
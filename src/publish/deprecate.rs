@@ -0,0 +1,83 @@
+use super::{escape, send};
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use reqwest::Method;
+use semver_rs::Version;
+use serde_json::Value;
+
+/// Marks every version in the packument `doc` that satisfies `range` as deprecated with `message`,
+/// or undeprecates them if the message is empty. Returns the changed versions.
+pub fn mark(doc: &mut Value, range: &str, message: &str) -> Result<Vec<String>> {
+    let r = crate::package::range::parse(range, Default::default())?;
+    let Some(versions) = doc.get_mut("versions").and_then(Value::as_object_mut) else {
+        bail!("the registry sent a packument without versions");
+    };
+    let mut changed = vec![];
+    for (v, manifest) in versions.iter_mut() {
+        if !Version::new(v).parse().is_ok_and(|v| r.test(&v)) {
+            continue;
+        }
+        let Some(manifest) = manifest.as_object_mut() else {
+            continue;
+        };
+        if message.is_empty() {
+            manifest.remove("deprecated");
+        } else {
+            manifest.insert("deprecated".into(), message.into());
+        }
+        changed.push(v.clone());
+    }
+    if changed.is_empty() {
+        bail!("no published version matches {range}");
+    }
+    Ok(changed)
+}
+
+/// Deprecates the versions of `name` that satisfy `range` (see [mark]).
+pub async fn deprecate(
+    client: Client,
+    name: &str,
+    range: &str,
+    message: &str,
+    token: &str,
+    otp: Option<&str>,
+) -> Result<Vec<String>> {
+    let path = escape(name);
+    // the full document, with the revision the write has to name
+    let mut doc: Value =
+        serde_json::from_str(&send(client.clone(), Method::GET, &path, token, None, None).await?)
+            .context("parsing packument")?;
+    let changed = mark(&mut doc, range, message)?;
+    let path = match doc.get("_rev").and_then(Value::as_str) {
+        Some(rev) => format!("{path}/-rev/{rev}"),
+        None => path,
+    };
+    send(
+        client,
+        Method::PUT,
+        &path,
+        token,
+        otp,
+        Some(doc.to_string()),
+    )
+    .await?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn marking() {
+        let mut doc = json!({"versions": {"1.0.0": {}, "1.1.0": {}, "2.0.0": {}}});
+        assert_eq!(mark(&mut doc, "<2", "use 2").unwrap(), ["1.0.0", "1.1.0"]);
+        assert_eq!(doc["versions"]["1.1.0"]["deprecated"], "use 2");
+        assert!(doc["versions"]["2.0.0"].get("deprecated").is_none());
+        assert_eq!(mark(&mut doc, "1.0.0", "").unwrap(), ["1.0.0"]);
+        assert!(doc["versions"]["1.0.0"].get("deprecated").is_none());
+        assert!(mark(&mut doc, "^3", "gone").is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use crate::pack::Packed;
+use crate::Client;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use p256::pkcs8::{EncodePublicKey, LineEnding};
+use reqwest::Method;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const FULCIO: &str = "https://fulcio.sigstore.dev";
+const REKOR: &str = "https://rekor.sigstore.dev";
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+pub const BUNDLE_TYPE: &str = "application/vnd.dev.sigstore.bundle+json;version=0.2";
+
+/// The CI the package is being built in, which vouches for where it was built.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Ci {
+    /// GitHub Actions, with `id-token: write` permissions.
+    GitHub,
+    /// GitLab CI, with a `SIGSTORE_ID_TOKEN` id token.
+    GitLab,
+}
+
+impl Ci {
+    pub fn detect(env: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if env("GITHUB_ACTIONS").as_deref() == Some("true")
+            && env("ACTIONS_ID_TOKEN_REQUEST_URL").is_some()
+        {
+            Some(Self::GitHub)
+        } else if env("GITLAB_CI").as_deref() == Some("true") && env("SIGSTORE_ID_TOKEN").is_some()
+        {
+            Some(Self::GitLab)
+        } else {
+            None
+        }
+    }
+}
+
+/// The package url of a package: `@scope/name` is `pkg:npm/%40scope/name@<version>`.
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:npm/{}@{version}", name.replacen('@', "%40", 1))
+}
+
+/// The SLSA provenance statement, saying this tarball was built by this CI run.
+pub fn statement(packed: &Packed, ci: Ci, env: impl Fn(&str) -> Option<String>) -> Value {
+    let var = |k: &str| env(k).unwrap_or_default();
+    let predicate = match ci {
+        Ci::GitHub => {
+            let (server, repo) = (var("GITHUB_SERVER_URL"), var("GITHUB_REPOSITORY"));
+            // owner/repo/.github/workflows/release.yml@refs/heads/main
+            let workflow = var("GITHUB_WORKFLOW_REF");
+            let (path, r#ref) = workflow
+                .trim_start_matches(&format!("{repo}/"))
+                .split_once('@')
+                .unwrap_or_default();
+            json!({
+                "buildDefinition": {
+                    "buildType": "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1",
+                    "externalParameters": {
+                        "workflow": {
+                            "ref": r#ref,
+                            "repository": format!("{server}/{repo}"),
+                            "path": path,
+                        }
+                    },
+                    "internalParameters": {
+                        "github": {
+                            "event_name": var("GITHUB_EVENT_NAME"),
+                            "repository_id": var("GITHUB_REPOSITORY_ID"),
+                            "repository_owner_id": var("GITHUB_REPOSITORY_OWNER_ID"),
+                        }
+                    },
+                    "resolvedDependencies": [{
+                        "uri": format!("git+{server}/{repo}@{}", var("GITHUB_REF")),
+                        "digest": { "gitCommit": var("GITHUB_SHA") },
+                    }],
+                },
+                "runDetails": {
+                    "builder": {
+                        "id": format!("https://github.com/actions/runner/{}", var("RUNNER_ENVIRONMENT")),
+                    },
+                    "metadata": {
+                        "invocationId": format!(
+                            "{server}/{repo}/actions/runs/{}/attempts/{}",
+                            var("GITHUB_RUN_ID"),
+                            var("GITHUB_RUN_ATTEMPT")
+                        ),
+                    },
+                },
+            })
+        }
+        Ci::GitLab => {
+            let project = var("CI_PROJECT_URL");
+            json!({
+                "buildDefinition": {
+                    "buildType": "https://github.com/npm/cli/gitlab/v0alpha1",
+                    "externalParameters": {
+                        "CI_PROJECT_PATH": var("CI_PROJECT_PATH"),
+                        "CI_CONFIG_PATH": var("CI_CONFIG_PATH"),
+                        "CI_COMMIT_REF_NAME": var("CI_COMMIT_REF_NAME"),
+                        "CI_PIPELINE_SOURCE": var("CI_PIPELINE_SOURCE"),
+                    },
+                    "internalParameters": {
+                        "CI_PIPELINE_ID": var("CI_PIPELINE_ID"),
+                        "CI_JOB_ID": var("CI_JOB_ID"),
+                        "CI_RUNNER_ID": var("CI_RUNNER_ID"),
+                    },
+                    "resolvedDependencies": [{
+                        "uri": format!("git+{project}@{}", var("CI_COMMIT_REF_NAME")),
+                        "digest": { "gitCommit": var("CI_COMMIT_SHA") },
+                    }],
+                },
+                "runDetails": {
+                    "builder": { "id": format!("{project}/-/runners/{}", var("CI_RUNNER_ID")) },
+                    "metadata": { "invocationId": format!("{project}/-/jobs/{}", var("CI_JOB_ID")) },
+                },
+            })
+        }
+    };
+    json!({
+        "_type": "https://in-toto.io/Statement/v1",
+        "subject": [{
+            "name": purl(&packed.name, &packed.version),
+            "digest": { "sha512": format!("{:x}", sha2::Sha512::digest(&packed.tarball)) },
+        }],
+        "predicateType": "https://slsa.dev/provenance/v1",
+        "predicate": predicate,
+    })
+}
+
+/// The DSSE pre-authentication encoding, which is what actually gets signed.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+fn unhex(s: &str) -> Result<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| anyhow!("{s} is not hex"))
+        })
+        .collect()
+}
+
+/// The base64 DER of a PEM certificate.
+fn der(pem: &str) -> String {
+    pem.lines().filter(|l| !l.starts_with("-----")).collect()
+}
+
+async fn json(req: reqwest::RequestBuilder, what: &str) -> Result<Value> {
+    let resp = req
+        .send()
+        .await
+        .with_context(|| format!("requesting {what}"))?;
+    if !resp.status().is_success() {
+        bail!(
+            "{what} failed ({}): {}",
+            resp.status(),
+            resp.text().await.unwrap_or_default()
+        );
+    }
+    serde_json::from_str(&resp.text().await?).with_context(|| format!("parsing {what}"))
+}
+
+/// The CIs OIDC token, for sigstore.
+async fn id_token(client: &Client, ci: Ci) -> Result<String> {
+    match ci {
+        Ci::GitHub => {
+            let url = std::env::var("ACTIONS_ID_TOKEN_REQUEST_URL")?;
+            let req = client
+                .get(format!("{url}&audience=sigstore"))
+                .bearer_auth(std::env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN")?);
+            json(req, "the GitHub OIDC token").await?["value"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("GitHub sent no OIDC token"))
+        }
+        Ci::GitLab => Ok(std::env::var("SIGSTORE_ID_TOKEN")?),
+    }
+}
+
+/// Gets Fulcio to certify `key` for the identity in the OIDC `token`. Returns the PEM certificate chain.
+async fn certificate(client: &Client, key: &SigningKey, token: &str) -> Result<Vec<String>> {
+    let claims = token.split('.').nth(1).context("malformed OIDC token")?;
+    let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims)?)?;
+    let identity = claims
+        .get("email")
+        .or_else(|| claims.get("sub"))
+        .and_then(Value::as_str)
+        .context("OIDC token has no subject")?;
+    let proof: Signature = key.sign(identity.as_bytes());
+    let public = key
+        .verifying_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| anyhow!("{e}"))?;
+    let req = client
+        .request(Method::POST, format!("{FULCIO}/api/v2/signingCert"))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(
+            json!({
+                "credentials": { "oidcIdentityToken": token },
+                "publicKeyRequest": {
+                    "publicKey": { "algorithm": "ECDSA", "content": public },
+                    "proofOfPossession": STANDARD.encode(proof.to_der()),
+                },
+            })
+            .to_string(),
+        );
+    let resp = json(req, "the signing certificate").await?;
+    let chain = resp
+        .get("signedCertificateEmbeddedSct")
+        .or_else(|| resp.get("signedCertificateDetachedSct"))
+        .and_then(|c| c["chain"]["certificates"].as_array())
+        .context("Fulcio sent no certificate")?;
+    Ok(chain
+        .iter()
+        .filter_map(|c| c.as_str().map(str::to_string))
+        .collect())
+}
+
+/// Records the signed envelope in the Rekor transparency log. Returns the bundles tlog entry.
+async fn tlog(client: &Client, payload: &[u8], sig: &[u8], cert: &str) -> Result<Value> {
+    let envelope = json!({
+        "payloadType": PAYLOAD_TYPE,
+        "payload": STANDARD.encode(payload),
+        "signatures": [{ "sig": STANDARD.encode(sig), "keyid": "" }],
+    });
+    // rekor wants everything in the proposed entry base64ed (again)
+    let entry = json!({
+        "apiVersion": "0.0.2",
+        "kind": "intoto",
+        "spec": {
+            "content": {
+                "envelope": {
+                    "payloadType": PAYLOAD_TYPE,
+                    "payload": STANDARD.encode(STANDARD.encode(payload)),
+                    "signatures": [{
+                        "sig": STANDARD.encode(STANDARD.encode(sig)),
+                        "publicKey": STANDARD.encode(cert),
+                    }],
+                },
+                "hash": { "algorithm": "sha256", "value": format!("{:x}", Sha256::digest(envelope.to_string())) },
+                "payloadHash": { "algorithm": "sha256", "value": format!("{:x}", Sha256::digest(payload)) },
+            }
+        }
+    });
+    let req = client
+        .request(Method::POST, format!("{REKOR}/api/v1/log/entries"))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(entry.to_string());
+    let resp = json(req, "the transparency log entry").await?;
+    let (_, e) = resp
+        .as_object()
+        .and_then(|m| m.iter().next())
+        .context("Rekor sent no entry")?;
+    let proof = &e["verification"]["inclusionProof"];
+    let hashes = proof["hashes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|h| Ok(STANDARD.encode(unhex(h)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "logIndex": e["logIndex"].to_string(),
+        "logId": { "keyId": STANDARD.encode(unhex(e["logID"].as_str().unwrap_or_default())?) },
+        "kindVersion": { "kind": "intoto", "version": "0.0.2" },
+        "integratedTime": e["integratedTime"].to_string(),
+        "inclusionPromise": { "signedEntryTimestamp": e["verification"]["signedEntryTimestamp"] },
+        "inclusionProof": {
+            "logIndex": proof["logIndex"].to_string(),
+            "rootHash": STANDARD.encode(unhex(proof["rootHash"].as_str().unwrap_or_default())?),
+            "treeSize": proof["treeSize"].to_string(),
+            "hashes": hashes,
+            "checkpoint": { "envelope": proof["checkpoint"] },
+        },
+        "canonicalizedBody": e["body"],
+    }))
+}
+
+/// Signs a provenance statement for the package with a short lived sigstore certificate,
+/// and logs it in the transparency log. Returns the sigstore bundle, to upload alongside the tarball.
+/// Only works in a CI that can vouch for the build (see [Ci]).
+pub async fn bundle(packed: &Packed, client: Client) -> Result<Value> {
+    let env = |k: &str| std::env::var(k).ok();
+    let Some(ci) = Ci::detect(env) else {
+        bail!("provenance needs GitHub Actions (with id-token: write), or GitLab CI (with a SIGSTORE_ID_TOKEN)");
+    };
+    let payload = statement(packed, ci, env).to_string().into_bytes();
+    let key = SigningKey::random(&mut rand_core::OsRng);
+    let token = id_token(&client, ci).await?;
+    let chain = certificate(&client, &key, &token).await?;
+    let leaf = chain.first().context("Fulcio sent an empty chain")?;
+    let sig: Signature = key.sign(&pae(PAYLOAD_TYPE, &payload));
+    let sig = sig.to_der().as_bytes().to_vec();
+    let entry = tlog(&client, &payload, &sig, leaf).await?;
+    Ok(json!({
+        "mediaType": BUNDLE_TYPE,
+        "verificationMaterial": {
+            "x509CertificateChain": {
+                "certificates": chain.iter().map(|c| json!({ "rawBytes": der(c) })).collect::<Vec<_>>(),
+            },
+            "tlogEntries": [entry],
+        },
+        "dsseEnvelope": {
+            "payload": STANDARD.encode(&payload),
+            "payloadType": PAYLOAD_TYPE,
+            "signatures": [{ "sig": STANDARD.encode(&sig), "keyid": "" }],
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn statements() {
+        let vars = HashMap::from([
+            ("GITHUB_ACTIONS", "true"),
+            ("ACTIONS_ID_TOKEN_REQUEST_URL", "http://t"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+            ("GITHUB_REPOSITORY", "bendn/p"),
+            (
+                "GITHUB_WORKFLOW_REF",
+                "bendn/p/.github/workflows/publish.yml@refs/tags/v1.0.0",
+            ),
+            ("GITHUB_SHA", "abc"),
+        ]);
+        let env = |k: &str| vars.get(k).map(|v| v.to_string());
+        assert_eq!(Ci::detect(env), Some(Ci::GitHub));
+        assert_eq!(Ci::detect(|_| None), None);
+        let packed = Packed {
+            name: "@bendn/p".into(),
+            version: "1.0.0".into(),
+            manifest: json!({}),
+            files: vec![],
+            tarball: b"tar".to_vec(),
+        };
+        let s = statement(&packed, Ci::GitHub, env);
+        assert_eq!(s["subject"][0]["name"], "pkg:npm/%40bendn/p@1.0.0");
+        let workflow = &s["predicate"]["buildDefinition"]["externalParameters"]["workflow"];
+        assert_eq!(workflow["path"], ".github/workflows/publish.yml");
+        assert_eq!(workflow["ref"], "refs/tags/v1.0.0");
+        assert_eq!(pae("t", b"abc"), b"DSSEv1 1 t 3 abc");
+        assert_eq!(unhex("00ff").unwrap(), [0, 255]);
+    }
+}
@@ -0,0 +1,78 @@
+use super::{escape, send};
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use reqwest::Method;
+use std::collections::BTreeMap;
+
+fn path(name: &str) -> String {
+    format!("-/package/{}/dist-tags", escape(name))
+}
+
+/// The dist-tags of `name`, and the versions they point to.
+pub async fn list(client: Client, name: &str) -> Result<BTreeMap<String, String>> {
+    let resp = client
+        .get(format!("{}/{}", client.registry, path(name)))
+        .send()
+        .await?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(crate::package::search::not_found(client, name).await);
+    }
+    let text = resp.text().await?;
+    if text == "\"Not Found\"" {
+        return Err(crate::package::search::not_found(client, name).await);
+    }
+    serde_json::from_str(&text).context("parsing dist-tags")
+}
+
+/// Points the dist-tag `tag` of `name` at `version`.
+pub async fn add(
+    client: Client,
+    name: &str,
+    version: &str,
+    tag: &str,
+    token: &str,
+    otp: Option<&str>,
+) -> Result<()> {
+    if semver_rs::Range::new(tag).parse().is_ok() {
+        // it would be ambiguous with a version range, when installing `name@tag`
+        bail!("tag {tag} cant look like a version range");
+    }
+    let path = format!("{}/{tag}", path(name));
+    let body = serde_json::to_string(version)?;
+    send(client, Method::PUT, &path, token, otp, Some(body)).await?;
+    Ok(())
+}
+
+/// Removes the dist-tag `tag` of `name`.
+pub async fn rm(
+    client: Client,
+    name: &str,
+    tag: &str,
+    token: &str,
+    otp: Option<&str>,
+) -> Result<()> {
+    if tag == "latest" {
+        bail!("the latest tag cant be removed (point it somewhere else with gpm tag add)");
+    }
+    let path = format!("{}/{tag}", path(name));
+    send(client, Method::DELETE, &path, token, otp, None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tags() {
+        let c = crate::test_utils::mktemp().await.2;
+        assert!(list(c.clone(), "@bendn/not-a-package").await.is_err());
+        assert!(add(c.clone(), "@bendn/test", "2.0.10", "^2", "t", None)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("range"));
+        assert!(rm(c, "@bendn/test", "latest", "t", None).await.is_err());
+    }
+}
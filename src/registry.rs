@@ -0,0 +1,189 @@
+use crate::integrity::{Algorithm, Integrity};
+use crate::package::parsing::{ParsedManifest, ParsedManifestDist, ParsedPackument};
+use crate::vendor::{Index, INDEX};
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read, read_dir, write};
+use std::path::Path;
+
+/// Wether `registry` is a static one: an [INDEX] (like `https://bucket.s3.amazonaws.com/gpm/index.json`),
+/// with the tarballs next to it. Any static host can serve those, see [build].
+pub fn is_static(registry: &str) -> bool {
+    registry.ends_with(&format!("/{INDEX}"))
+}
+
+/// What a static registry needs from the package.json of a tarball.
+#[derive(Deserialize)]
+pub struct PackageJson {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    dependencies: HashMap<String, String>,
+    #[serde(default)]
+    hooks: BTreeMap<String, String>,
+    #[serde(default)]
+    engines: HashMap<String, String>,
+}
+
+impl PackageJson {
+    /// The manifest of this version, with the tarball `dist`.
+    pub fn into_manifest(self, dist: ParsedManifestDist) -> ParsedManifest {
+        ParsedManifest {
+            dist,
+            dependencies: self.dependencies,
+            version: self.version,
+            hooks: self.hooks,
+            engines: self.engines,
+            ..Default::default()
+        }
+    }
+}
+
+/// The package.json of the tarball (or zip) `bytes`.
+pub fn package_json(bytes: &[u8]) -> Result<PackageJson> {
+    let ty = if bytes.starts_with(b"PK") {
+        "zip"
+    } else {
+        "tgz"
+    };
+    let mut archive = crate::archive::Archive::new(crate::archive::CompressionType::from(
+        ty,
+        bytes.to_vec(),
+        String::new(),
+    ))?;
+    let mut contents = String::new();
+    archive.get_file("package.json", &mut contents)?;
+    serde_json::from_str(&contents).context("its package.json has no name and version")
+}
+
+/// Writes the [INDEX] of the tarballs (`.tgz` and `.zip`) in `dir`, making it a static registry.
+/// The tarballs are pointed to relatively, so the dir can be uploaded anywhere. Returns the packages, like `@bendn/test@2.0.10`.
+pub fn build(dir: &Path) -> Result<Vec<String>> {
+    let mut files = read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .map(|e| Ok(e?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>>>()?;
+    files.retain(|f| f.ends_with(".tgz") || f.ends_with(".zip"));
+    files.sort();
+    let mut index = Index::default();
+    let mut packages = vec![];
+    for file in files {
+        let bytes = read(dir.join(&file))?;
+        let p = package_json(&bytes).with_context(|| format!("reading the package in {file}"))?;
+        let versions = index.packages.entry(p.name.clone()).or_default();
+        if versions.contains_key(&p.version) {
+            bail!(
+                "{}@{} is in two tarballs (one is {file})",
+                p.name,
+                p.version
+            );
+        }
+        packages.push(format!("{}@{}", p.name, p.version));
+        versions.insert(
+            p.version.clone(),
+            p.into_manifest(ParsedManifestDist {
+                shasum: format!("{:x}", Sha1::digest(&bytes)),
+                tarball: file,
+                integrity: Some(Integrity::of(Algorithm::Sha512, &bytes).to_string()),
+            }),
+        );
+    }
+    write(dir.join(INDEX), serde_json::to_string_pretty(&index)?)
+        .context("writing the registry index")?;
+    Ok(packages)
+}
+
+/// The packument of `name`, from the static registry of `client`. Its index is only downloaded once.
+pub async fn packument(client: Client, name: &str) -> Result<ParsedPackument> {
+    let index = match client.index.get() {
+        Some(index) => index,
+        None => {
+            let resp = client
+                .get(&client.registry)
+                .send()
+                .await
+                .with_context(|| format!("getting {}", client.registry))?;
+            if !resp.status().is_success() {
+                bail!("getting {} failed: {}", client.registry, resp.status());
+            }
+            let index: Index = serde_json::from_str(&resp.text().await?)
+                .with_context(|| format!("parsing {}", client.registry))?;
+            // if another package got it first, thats the same one
+            let _ = client.index.set(index);
+            client.index.get().unwrap()
+        }
+    };
+    let versions = index
+        .packages
+        .get(name)
+        .with_context(|| format!("{name} is not in {}", client.registry))?;
+    let base = reqwest::Url::parse(&client.registry)
+        .with_context(|| format!("{} is not a url", client.registry))?;
+    let mut p = ParsedPackument::default();
+    for (version, m) in versions {
+        let mut m = m.clone();
+        m.dist.tarball = base
+            .join(&m.dist.tarball)
+            .with_context(|| format!("the tarball of {name}@{version} is not a url"))?
+            .into();
+        p.versions.insert(version.clone(), m);
+    }
+    Ok(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn static_registry() {
+        let t = crate::test_utils::mktemp().await;
+        let c = crate::mkclient(t.2.registry.clone(), Cache::new());
+        let dir = t.0.path().join("registry");
+        std::fs::create_dir(&dir).unwrap();
+        for (name, version) in [("@bendn/test", "2.0.10"), ("@bendn/gdcli", "1.2.5")] {
+            let p = Package::new(name.into(), format!("={version}"), c.clone())
+                .await
+                .unwrap();
+            let bytes = p
+                .fetch(c.clone(), &indicatif::ProgressBar::hidden())
+                .await
+                .unwrap();
+            write(dir.join(crate::vendor::file(name, version)), bytes).unwrap();
+        }
+        let mut built = build(&dir).unwrap();
+        built.sort();
+        assert_eq!(built, ["@bendn/gdcli@1.2.5", "@bendn/test@2.0.10"]);
+        assert!(is_static("https://bucket.example.com/gpm/index.json"));
+        assert!(!is_static(&t.2.registry));
+
+        let index = serde_json::from_slice::<Index>(&read(dir.join(INDEX)).unwrap()).unwrap();
+        let m = &index.packages["@bendn/test"]["2.0.10"];
+        assert_eq!(m.dist.tarball, "@bendn_2ftest@2.0.10.tgz");
+        assert_eq!(m.dependencies["@bendn/gdcli"], "1.2.5");
+
+        // nothing listens here, the index is there already
+        let c = crate::mkclient("http://127.0.0.1:9/gpm/index.json".into(), Cache::new());
+        assert!(c.index.set(index).is_ok());
+        let p = Package::new("@bendn/test".into(), "^2.0.0".into(), c.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.to_string(), "@bendn/test@2.0.10");
+        assert_eq!(
+            p.manifest.tarball.to_string(),
+            "http://127.0.0.1:9/gpm/@bendn_2ftest@2.0.10.tgz"
+        );
+        assert_eq!(p.manifest.dependencies[0].to_string(), "@bendn/gdcli@1.2.5");
+        let err = Package::new("ms".into(), "*".into(), c).await.unwrap_err();
+        assert!(format!("{err:#}").contains("ms is not in"));
+
+        std::fs::copy(dir.join("@bendn_2ftest@2.0.10.tgz"), dir.join("again.tgz")).unwrap();
+        assert!(build(&dir).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use crate::package::parsing::{ParsedManifest, ParsedManifestDist, ParsedPackument};
+use crate::package::range;
+use crate::Client;
+
+use anyhow::{bail, Context, Result};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::env::var;
+
+/// Where the releases of a release package are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    /// `gh:user/repo`.
+    GitHub,
+    /// `gitlab:group/repo`, or `gitlab:gitlab.example.com/group/repo` for a self hosted one.
+    GitLab,
+    /// `gitea:owner/repo` (on codeberg), or `gitea:git.example.com/owner/repo`. `forgejo:` is the same.
+    Gitea,
+}
+
+/// What release packages are named like, and on which forge they are.
+const PREFIXES: [(&str, Forge); 4] = [
+    ("gh:", Forge::GitHub),
+    ("gitlab:", Forge::GitLab),
+    ("gitea:", Forge::Gitea),
+    ("forgejo:", Forge::Gitea),
+];
+
+/// The repository of a release package.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Source<'a> {
+    pub forge: Forge,
+    /// Like `https://gitlab.com`.
+    pub host: String,
+    /// Like `user/repo`.
+    pub repo: &'a str,
+}
+
+/// The repository of the release package `name`, if it is one.
+pub fn source(name: &str) -> Option<Source<'_>> {
+    let (forge, rest) = PREFIXES
+        .into_iter()
+        .find_map(|(prefix, forge)| Some((forge, name.strip_prefix(prefix)?)))?;
+    if forge == Forge::GitHub {
+        let host = var("GITHUB_SERVER_URL").unwrap_or_else(|_| "https://github.com".into());
+        return Some(Source {
+            forge,
+            host: host.trim_end_matches('/').to_owned(),
+            repo: rest,
+        });
+    }
+    // self hosted ones start with the host, which has a dot (users and groups dont)
+    let (host, repo) = match rest.split_once('/') {
+        Some((host, repo)) if host.contains('.') => (host, repo),
+        _ if forge == Forge::GitLab => ("gitlab.com", rest),
+        _ => ("codeberg.org", rest),
+    };
+    Some(Source {
+        forge,
+        host: format!("https://{host}"),
+        repo,
+    })
+}
+
+/// Wether `name` is a package on the releases of a repository, not on the registry.
+pub fn is_release(name: &str) -> bool {
+    source(name).is_some()
+}
+
+/// The name of the repository of the release package `name` (`repo`, for `gh:user/repo`), if it is one.
+pub fn repository(name: &str) -> Option<&str> {
+    source(name)?.repo.rsplit('/').next()
+}
+
+/// A release, whichever forge its from.
+pub struct Release {
+    tag: String,
+    /// Names and urls.
+    assets: Vec<(String, String)>,
+    /// The zip of the source of the tag.
+    source: String,
+}
+
+/// A release on github (or gitea, which has the same api).
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(default)]
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct GitLabAssets {
+    links: Vec<GitLabLink>,
+    sources: Vec<GitLabSource>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabSource {
+    format: String,
+    url: String,
+}
+
+/// The releases (not drafts, or upcoming ones) in the releases api response `body` of `s`.
+pub fn parse(s: &Source, body: &str) -> Result<Vec<Release>> {
+    Ok(match s.forge {
+        Forge::GitHub | Forge::Gitea => serde_json::from_str::<Vec<GitHubRelease>>(body)?
+            .into_iter()
+            .filter(|r| !r.draft)
+            .map(|r| Release {
+                source: match s.forge {
+                    Forge::GitHub => {
+                        format!("{}/{}/archive/refs/tags/{}.zip", s.host, s.repo, r.tag_name)
+                    }
+                    _ => format!("{}/{}/archive/{}.zip", s.host, s.repo, r.tag_name),
+                },
+                assets: r
+                    .assets
+                    .into_iter()
+                    .map(|a| (a.name, a.browser_download_url))
+                    .collect(),
+                tag: r.tag_name,
+            })
+            .collect(),
+        Forge::GitLab => serde_json::from_str::<Vec<GitLabRelease>>(body)?
+            .into_iter()
+            .filter(|r| !r.upcoming_release)
+            .map(|r| Release {
+                source: match r.assets.sources.into_iter().find(|s| s.format == "zip") {
+                    Some(zip) => zip.url,
+                    None => format!(
+                        "{}/{}/-/archive/{t}/{t}.zip",
+                        s.host,
+                        s.repo,
+                        t = r.tag_name
+                    ),
+                },
+                assets: r
+                    .assets
+                    .links
+                    .into_iter()
+                    .map(|l| (l.name, l.url))
+                    .collect(),
+                tag: r.tag_name,
+            })
+            .collect(),
+    })
+}
+
+/// The releases of `repo` (`user/repo`) as a packument: every release tagged with a version (like `v1.2.0`) is a version,
+/// whose tarball is the first asset matching the glob `asset`, or, without one, the source of the tag.
+/// Releases without a matching asset are left out.
+pub fn packument(
+    repo: &str,
+    releases: Vec<Release>,
+    asset: Option<&str>,
+) -> Result<ParsedPackument> {
+    let pattern = asset
+        .map(glob::Pattern::new)
+        .transpose()
+        .with_context(|| format!("the asset glob of {repo} is invalid"))?;
+    let mut p = ParsedPackument::default();
+    for r in releases {
+        let Some(version) = range::exact(&r.tag) else {
+            continue;
+        };
+        let tarball = match &pattern {
+            Some(pattern) => match r.assets.into_iter().find(|(name, _)| pattern.matches(name)) {
+                Some((_, url)) => url,
+                None => continue,
+            },
+            None => r.source,
+        };
+        let version = version.to_string();
+        p.versions.insert(
+            version.clone(),
+            ParsedManifest {
+                dist: ParsedManifestDist {
+                    tarball,
+                    ..Default::default()
+                },
+                version,
+                ..Default::default()
+            },
+        );
+    }
+    Ok(p)
+}
+
+/// The forge whose token goes to `url`: by its host, not by what package wants it, so a token only goes where it is for.
+/// github (or `GITHUB_API_URL` and `GITHUB_SERVER_URL`), gitlab.com, and codeberg.org. The tokens of self hosted forges
+/// go in the registries of the user config instead, like `{"https://git.studio.dev": {"token": "$GITLAB_TOKEN", "always-auth": true}}`.
+fn forge_at(url: &str) -> Option<Forge> {
+    let url = reqwest::Url::parse(url).ok()?;
+    if url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?;
+    let host_of = |u: String| reqwest::Url::parse(&u).ok()?.host_str().map(str::to_owned);
+    let github = [
+        host_of(var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".into())),
+        host_of(var("GITHUB_SERVER_URL").unwrap_or_else(|_| "https://github.com".into())),
+    ];
+    if github.iter().flatten().any(|h| h == host) {
+        return Some(Forge::GitHub);
+    }
+    match host {
+        "gitlab.com" => Some(Forge::GitLab),
+        "codeberg.org" => Some(Forge::Gitea),
+        _ => None,
+    }
+}
+
+/// Adds the token of the forge `url` goes to (see [forge_at]) to `req`, if its set:
+/// `GITHUB_TOKEN` (or `GH_TOKEN`), `GITLAB_TOKEN` or `GITEA_TOKEN`. They let private repositories be seen.
+pub fn authorize(req: RequestBuilder, url: &str) -> RequestBuilder {
+    match forge_at(url) {
+        Some(Forge::GitHub) => match var("GITHUB_TOKEN").or_else(|_| var("GH_TOKEN")) {
+            Ok(token) => req.bearer_auth(token),
+            Err(_) => req,
+        },
+        Some(Forge::GitLab) => match var("GITLAB_TOKEN") {
+            Ok(token) => req.header("PRIVATE-TOKEN", token),
+            Err(_) => req,
+        },
+        Some(Forge::Gitea) => match var("GITEA_TOKEN") {
+            Ok(token) => req.header("Authorization", format!("token {token}")),
+            Err(_) => req,
+        },
+        None => req,
+    }
+}
+
+/// Gets the packument of the release package `name` (see [packument]), with the asset glob of the config.
+/// `GITHUB_API_URL` (and `GITHUB_SERVER_URL`) point to another github (enterprise).
+pub async fn get(client: Client, name: &str) -> Result<ParsedPackument> {
+    let s = source(name).with_context(|| format!("{name} is not a release package"))?;
+    let repo = s.repo;
+    let url = match s.forge {
+        Forge::GitHub => {
+            let api = var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".into());
+            format!(
+                "{}/repos/{repo}/releases?per_page=100",
+                api.trim_end_matches('/')
+            )
+        }
+        Forge::GitLab => format!(
+            "{}/api/v4/projects/{}/releases?per_page=100",
+            s.host,
+            repo.replace('/', "%2F")
+        ),
+        Forge::Gitea => format!("{}/api/v1/repos/{repo}/releases?limit=50", s.host),
+    };
+    let resp = authorize(client.get(&url).header("Accept", "application/json"), &url)
+        .send()
+        .await
+        .with_context(|| format!("listing the releases of {repo}"))?;
+    match resp.status() {
+        StatusCode::NOT_FOUND => bail!(
+            "{repo} is not a repository on {} (or it is private, and its token isnt set)",
+            s.host
+        ),
+        st if !st.is_success() => bail!("listing the releases of {repo} failed: {st}"),
+        _ => {}
+    }
+    let releases = parse(&s, &resp.text().await?)
+        .with_context(|| format!("parsing the releases of {repo}"))?;
+    packument(repo, releases, client.asset(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn releases() {
+        let gh = source("gh:user/addon").unwrap();
+        let releases = || {
+            parse(
+                &gh,
+                r#"[
+                    {"tag_name": "v1.3.0", "draft": true, "assets": []},
+                    {"tag_name": "v1.2.0", "assets": [
+                        {"name": "notes.txt", "browser_download_url": "https://x/notes.txt"},
+                        {"name": "addon-1.2.0.zip", "browser_download_url": "https://x/addon-1.2.0.zip"}
+                    ]},
+                    {"tag_name": "1.1.0"},
+                    {"tag_name": "nightly"}
+                ]"#,
+            )
+            .unwrap()
+        };
+        let p = packument("user/addon", releases(), None).unwrap();
+        let mut versions = p.versions.keys().collect::<Vec<_>>();
+        versions.sort();
+        assert_eq!(versions, ["1.1.0", "1.2.0"]);
+        assert_eq!(
+            p.versions["1.2.0"].dist.tarball,
+            "https://github.com/user/addon/archive/refs/tags/v1.2.0.zip"
+        );
+        let p = packument("user/addon", releases(), Some("addon-*.zip")).unwrap();
+        assert_eq!(p.versions.len(), 1);
+        assert_eq!(
+            p.versions["1.2.0"].dist.tarball,
+            "https://x/addon-1.2.0.zip"
+        );
+        assert!(packument("user/addon", releases(), Some("[")).is_err());
+        assert_eq!(repository("gh:user/addon"), Some("addon"));
+    }
+
+    #[test]
+    fn forges() {
+        let lab = source("gitlab:git.studio.dev/tools/addon").unwrap();
+        assert_eq!(
+            (lab.forge, lab.host.as_str(), lab.repo),
+            (Forge::GitLab, "https://git.studio.dev", "tools/addon")
+        );
+        assert_eq!(
+            source("gitlab:group/sub/addon").unwrap().host,
+            "https://gitlab.com"
+        );
+        assert_eq!(
+            source("gitea:user/addon").unwrap().host,
+            "https://codeberg.org"
+        );
+        assert_eq!(source("forgejo:git.x.org/a/b").unwrap().forge, Forge::Gitea);
+        assert!(source("@bendn/test").is_none());
+        assert_eq!(forge_at("https://gitlab.com/api/v4/x"), Some(Forge::GitLab));
+        assert_eq!(forge_at("https://codeberg.org/a/b.zip"), Some(Forge::Gitea));
+        assert_eq!(
+            forge_at("https://github.com/a/b/archive/v1.zip"),
+            Some(Forge::GitHub)
+        );
+        // a release link can go anywhere: the token doesnt follow it
+        assert_eq!(forge_at("https://git.studio.dev/addon.zip"), None);
+        assert_eq!(forge_at("https://gitlab.com.evil.dev/x"), None);
+        assert_eq!(forge_at("http://gitlab.com/x"), None);
+        let releases = parse(
+            &lab,
+            r#"[
+                {"tag_name": "v2.0.0", "upcoming_release": true},
+                {"tag_name": "v1.0.0", "assets": {
+                    "links": [{"name": "addon.zip", "url": "https://git.studio.dev/addon.zip"}],
+                    "sources": [{"format": "tar.gz", "url": "https://s/1.tar.gz"}, {"format": "zip", "url": "https://s/1.zip"}]
+                }}
+            ]"#,
+        )
+        .unwrap();
+        let p = packument(lab.repo, releases, None).unwrap();
+        assert_eq!(p.versions.len(), 1);
+        assert_eq!(p.versions["1.0.0"].dist.tarball, "https://s/1.zip");
+        let tea = source("gitea:user/addon").unwrap();
+        let releases = parse(&tea, r#"[{"tag_name": "v0.1.0", "assets": []}]"#).unwrap();
+        assert_eq!(
+            packument(tea.repo, releases, None).unwrap().versions["0.1.0"]
+                .dist
+                .tarball,
+            "https://codeberg.org/user/addon/archive/v0.1.0.zip"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use crate::graph::{Graph, PackageId};
+use crate::size::Size;
+use crate::{compat, licenses, unused};
+
+use clap::ValueEnum;
+use indicatif::HumanBytes;
+use semver_rs::Version;
+use std::fmt::Write;
+use std::path::Path;
+
+/// What `gpm report` writes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Markdown, for a pull request or a wiki.
+    Md,
+    /// One standalone page.
+    Html,
+}
+
+/// What goes into a report, gathered from the project.
+pub struct Facts<'a> {
+    pub title: &'a str,
+    pub graph: &'a Graph,
+    pub licenses: Vec<licenses::License>,
+    pub sizes: Vec<Size>,
+    /// Deprecations, godot incompatibilities, and unused packages.
+    pub findings: Vec<String>,
+}
+
+impl<'a> Facts<'a> {
+    /// Gathers what is known about the packages of `graph`, installed in the project `cwd`.
+    /// Without the godot of the project (`godot`), they arent checked against it.
+    pub fn gather(
+        title: &'a str,
+        graph: &'a Graph,
+        cwd: &Path,
+        install_dir: &Path,
+        store: Option<&crate::cache::store::Store>,
+        godot: Option<&Version>,
+    ) -> Self {
+        let mut findings = vec![];
+        for (_, p) in graph.iter() {
+            if let Some(why) = &p.manifest.deprecated {
+                findings.push(format!("{p} is deprecated: {why}"));
+            }
+        }
+        if let Some(g) = godot {
+            findings.extend(
+                compat::matrix(graph, g)
+                    .iter()
+                    .filter(|r| r.verdict != compat::Verdict::Unknown)
+                    .filter_map(|r| Some(r.finding(g)?.1)),
+            );
+        }
+        findings.extend(
+            unused::find(graph, cwd, install_dir)
+                .into_iter()
+                .map(|p| format!("nothing in the project uses {p}")),
+        );
+        Self {
+            title,
+            graph,
+            licenses: licenses::collect(graph, cwd),
+            sizes: crate::size::measure(graph, cwd, store).unwrap_or_default(),
+            findings,
+        }
+    }
+
+    /// The cells of the packages table: package, direct, license, installed size, files. Sorted by package.
+    fn rows(&self) -> Vec<[String; 5]> {
+        let mut rows = self
+            .graph
+            .iter()
+            .map(|(_, p)| {
+                let license = self
+                    .licenses
+                    .iter()
+                    .find(|l| l.name == p.name && l.version == p.manifest.full_version())
+                    .and_then(|l| l.license.clone())
+                    .unwrap_or_else(|| "?".into());
+                let size = self.sizes.iter().find(|s| s.package == p.to_string());
+                [
+                    p.to_string(),
+                    if p.indirect { "no" } else { "yes" }.into(),
+                    license,
+                    size.map_or("not installed".into(), |s| {
+                        HumanBytes(s.installed).to_string()
+                    }),
+                    size.map_or(String::new(), |s| s.files.to_string()),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+        rows
+    }
+
+    /// The dependency tree, as (depth, package) lines. A package already above is not gone into again.
+    fn tree(&self) -> Vec<(usize, String)> {
+        fn walk(
+            g: &Graph,
+            id: PackageId,
+            path: &mut Vec<PackageId>,
+            out: &mut Vec<(usize, String)>,
+        ) {
+            let cycle = path.contains(&id);
+            let p = g.get(id);
+            out.push((
+                path.len(),
+                match cycle {
+                    true => format!("{p} (cycle)"),
+                    false => p.to_string(),
+                },
+            ));
+            if cycle {
+                return;
+            }
+            path.push(id);
+            for &d in g.deps(id) {
+                walk(g, d, path, out);
+            }
+            path.pop();
+        }
+        let mut out = vec![];
+        for &r in self.graph.roots() {
+            walk(self.graph, r, &mut vec![], &mut out);
+        }
+        out
+    }
+
+    fn summary(&self) -> String {
+        let direct = self.graph.roots().len();
+        format!(
+            "{} packages ({direct} wanted directly), {} installed, {} finding{}",
+            self.graph.iter().count(),
+            HumanBytes(self.sizes.iter().map(|s| s.installed).sum()),
+            self.findings.len(),
+            if self.findings.len() == 1 { "" } else { "s" }
+        )
+    }
+
+    /// Writes the report.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Md => self.markdown(),
+            Format::Html => self.html(),
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let cell = |s: &str| s.replace('|', "\\|");
+        let mut out = format!("# {}\n\n{}\n\n## Packages\n\n", self.title, self.summary());
+        out.push_str("| Package | Direct | License | Installed | Files |\n|---|---|---|---|---|\n");
+        for row in self.rows() {
+            let row = row.iter().map(|c| cell(c)).collect::<Vec<_>>();
+            writeln!(out, "| {} |", row.join(" | ")).unwrap();
+        }
+        out.push_str("\n## Dependency tree\n\n");
+        for (depth, p) in self.tree() {
+            writeln!(out, "{}- {}", "  ".repeat(depth), cell(&p)).unwrap();
+        }
+        out.push_str("\n## Findings\n\n");
+        if self.findings.is_empty() {
+            out.push_str("None.\n");
+        }
+        for f in &self.findings {
+            writeln!(out, "- {f}").unwrap();
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let e = |s: &str| {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #ccc; padding: 4px 8px; }}</style>\n\
+             </head>\n<body>\n<h1>{0}</h1>\n<p>{1}</p>\n<h2>Packages</h2>\n<table>\n\
+             <tr><th>Package</th><th>Direct</th><th>License</th><th>Installed</th><th>Files</th></tr>\n",
+            e(self.title),
+            e(&self.summary())
+        );
+        for row in self.rows() {
+            let cells = row
+                .iter()
+                .map(|c| format!("<td>{}</td>", e(c)))
+                .collect::<String>();
+            writeln!(out, "<tr>{cells}</tr>").unwrap();
+        }
+        out.push_str("</table>\n<h2>Dependency tree</h2>\n");
+        let mut depth = 0;
+        for (d, p) in self.tree() {
+            // open a list for each level deeper, close one for each level back up
+            for _ in depth..=d {
+                out.push_str("<ul>\n");
+            }
+            for _ in d + 1..depth {
+                out.push_str("</ul>\n");
+            }
+            depth = d + 1;
+            writeln!(out, "<li>{}</li>", e(&p)).unwrap();
+        }
+        out.push_str(&"</ul>\n".repeat(depth));
+        out.push_str("<h2>Findings</h2>\n");
+        if self.findings.is_empty() {
+            out.push_str("<p>None.</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for f in &self.findings {
+                writeln!(out, "<li>{}</li>", e(f)).unwrap();
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::{Manifest, Package};
+
+    #[test]
+    fn report() {
+        let p = |name: &str, deps: Vec<Package>| {
+            let mut p = Package::from_manifest(
+                Manifest {
+                    version: Version::new("1.0.0").parse().unwrap(),
+                    dependencies: deps,
+                    ..Default::default()
+                },
+                name.into(),
+            );
+            p.indirect = name.starts_with("dep");
+            p
+        };
+        let graph = Graph::new(vec![
+            p("a", vec![p("dep-b", vec![p("dep-c", vec![])])]),
+            p("<d>", vec![]),
+        ]);
+        let facts = Facts {
+            title: "game",
+            graph: &graph,
+            licenses: vec![],
+            sizes: vec![],
+            findings: vec!["a@1.0.0 is deprecated: old".into()],
+        };
+        let md = facts.render(Format::Md);
+        assert!(
+            md.starts_with("# game\n\n4 packages (2 wanted directly), 0 B installed, 1 finding\n"),
+            "{md}"
+        );
+        assert!(
+            md.contains("| a@1.0.0 | yes | ? | not installed |  |\n"),
+            "{md}"
+        );
+        assert!(
+            md.contains("- a@1.0.0\n  - dep-b@1.0.0\n    - dep-c@1.0.0\n- <d>@1.0.0\n"),
+            "{md}"
+        );
+        assert!(
+            md.ends_with("## Findings\n\n- a@1.0.0 is deprecated: old\n"),
+            "{md}"
+        );
+        let html = facts.render(Format::Html);
+        assert!(html.contains("<ul>\n<li>a@1.0.0</li>\n<ul>\n<li>dep-b@1.0.0</li>\n<ul>\n<li>dep-c@1.0.0</li>\n</ul>\n</ul>\n<li>&lt;d&gt;@1.0.0</li>\n</ul>\n"), "{html}");
+        assert_eq!(html.matches("<ul>").count(), html.matches("</ul>").count());
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// Sets the malware scanner (the `scan-command` of the user config), like `clamscan --no-summary`.
+pub fn set_command(cmd: String) {
+    let _ = COMMAND.set(cmd);
+}
+
+/// The malware scanner, if there is one (see [set_command]).
+pub fn command() -> Option<&'static str> {
+    COMMAND.get().map(String::as_str)
+}
+
+/// `path`, quoted for the shell [crate::hooks::shell] runs: cmd on windows (where paths cant have a `"`), sh elsewhere.
+fn quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    match cfg!(windows) {
+        true => format!("\"{path}\""),
+        false => format!("'{}'", path.replace('\'', r"'\''")),
+    }
+}
+
+/// Scans the downloaded tarball `bytes` (named `file`, like `test-2.0.10.tgz`) with the scanner `cmd`, if there is one.
+/// The scanner gets the path of the tarball (where `{}` is, or at the end), and failing means it found something.
+pub fn check(cmd: Option<&str>, bytes: &[u8], file: &str) -> Result<()> {
+    let Some(cmd) = cmd else {
+        return Ok(());
+    };
+    // a name nobody can guess (and so put something at first), that goes away when done
+    let mut tmp = tempfile::Builder::new()
+        .prefix("gpm-scan-")
+        .suffix(&format!("-{file}"))
+        .tempfile()
+        .context("making the file to scan")?;
+    tmp.write_all(bytes)
+        .context("writing the tarball to scan")?;
+    // closed, so the scanner can open it everywhere
+    let path = tmp.into_temp_path();
+    let quoted = quote(&path);
+    let full = match cmd.contains("{}") {
+        true => cmd.replace("{}", &quoted),
+        false => format!("{cmd} {quoted}"),
+    };
+    let status = crate::hooks::shell(&full).status();
+    drop(path);
+    let status = status.with_context(|| format!("running the scanner ({cmd})"))?;
+    if !status.success() {
+        bail!("the scanner ({cmd}) flagged {file}: {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn scans() {
+        assert!(check(None, b"evil", "a.tgz").is_ok());
+        let cmd = Some("! grep -q evil");
+        assert!(check(cmd, b"fine", "b.tgz").is_ok());
+        let e = check(cmd, b"evil", "c.tgz").unwrap_err().to_string();
+        assert_eq!(quote(Path::new("/tmp/it's")), r"'/tmp/it'\''s'");
+        assert!(
+            e.starts_with("the scanner (! grep -q evil) flagged c.tgz"),
+            "{e}"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use crate::cache::store::Store;
+use crate::graph::Graph;
+
+use std::fs::{metadata, read_dir};
+use std::io;
+use std::path::Path;
+
+/// How much room an installed package takes.
+#[derive(Debug)]
+pub struct Size {
+    /// Like `@bendn/test@2.0.10`.
+    pub package: String,
+    pub files: u64,
+    /// Of its installed files (in the store, if it is symlinked there: they still get exported).
+    pub installed: u64,
+    /// Of its tarball, if it is in the store.
+    pub download: Option<u64>,
+}
+
+/// How many files are in `p`, and how big they are. Symlinks are followed.
+fn walk(p: &Path) -> io::Result<(u64, u64)> {
+    let meta = metadata(p)?;
+    if !meta.is_dir() {
+        return Ok((1, meta.len()));
+    }
+    let mut total = (0, 0);
+    for e in read_dir(p)? {
+        let (files, bytes) = walk(&e?.path())?;
+        total = (total.0 + files, total.1 + bytes);
+    }
+    Ok(total)
+}
+
+/// The sizes of the installed packages of `graph`, in the project `cwd`, biggest first.
+pub fn measure(graph: &Graph, cwd: &Path, store: Option<&Store>) -> io::Result<Vec<Size>> {
+    let mut out = vec![];
+    for (_, p) in graph.iter().filter(|(_, p)| p.is_installed(cwd)) {
+        let (files, installed) = walk(&p.download_dir(cwd))?;
+        out.push(Size {
+            package: p.to_string(),
+            files,
+            installed,
+            // it was stored under its shasum if the registry gave no integrity (the one here was made on install)
+            download: store.and_then(|s| {
+                let sha1 = p.manifest.shasum.as_ref().map(|s| format!("sha1-{s}"));
+                [p.store_key(), sha1]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|k| s.tarball_size(&k))
+            }),
+        });
+    }
+    out.sort_by(|a, b| {
+        b.installed
+            .cmp(&a.installed)
+            .then(a.package.cmp(&b.package))
+    });
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Package;
+
+    #[tokio::test]
+    async fn sizes() {
+        let t = crate::test_utils::mktemp().await;
+        let cwd = t.0.path();
+        let c = crate::mkclient(
+            t.2.registry.clone(),
+            crate::cache::Cache::load(cwd.join("cache")),
+        );
+        let p = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        let mut graph = Graph::new(vec![p]);
+        let test = graph.roots()[0];
+        graph
+            .get_mut(test)
+            .download(c.clone(), cwd, Default::default())
+            .await
+            .unwrap();
+        // only test is installed
+        let sizes = measure(&graph, cwd, c.cache_ref().store()).unwrap();
+        assert_eq!(sizes.len(), 1);
+        assert_eq!(sizes[0].package, "@bendn/test@2.0.10");
+        assert_eq!(sizes[0].files, 5);
+        assert!(sizes[0].installed > 0);
+        assert!(sizes[0].download.is_some_and(|d| d > 0));
+    }
+}
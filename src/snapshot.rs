@@ -0,0 +1,93 @@
+use crate::package::parsing::ParsedPackument;
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The packuments a resolution used, by package name, so it can be made again exactly like that
+/// (with `update --from-snapshot`), whatever the registry says by then.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    packuments: Mutex<BTreeMap<String, ParsedPackument>>,
+    /// Answers from the packuments, instead of recording them.
+    replay: bool,
+}
+
+/// Where the snapshot of the lockfile `lock` goes: `godot-lock.json` => `godot-lock.snapshot.json`.
+pub fn path(lock: &Path) -> PathBuf {
+    lock.with_extension("snapshot.json")
+}
+
+impl Snapshot {
+    /// Loads the snapshot at `path`, to replay.
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = read_to_string(path)
+            .with_context(|| format!("reading {} (run update --snapshot)", path.display()))?;
+        Ok(Self {
+            packuments: Mutex::new(serde_json::from_str(&s).context("parsing the snapshot")?),
+            replay: true,
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    /// The packument of `name`, as it was.
+    pub fn get(&self, name: &str) -> Option<ParsedPackument> {
+        self.packuments.lock().unwrap().get(name).cloned()
+    }
+
+    /// Records the packument of `name`.
+    pub fn insert(&self, name: &str, packument: &ParsedPackument) {
+        self.packuments
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), packument.clone());
+    }
+
+    /// Writes the recorded packuments to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // through a value, so the versions are sorted, and the file diffs well
+        let v = serde_json::to_value(&*self.packuments.lock().unwrap())?;
+        write(path, serde_json::to_string_pretty(&v)?).context("writing the snapshot")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+    use crate::package::Package;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn replays() {
+        let t = crate::test_utils::mktemp().await;
+        let recording = Arc::new(Snapshot::default());
+        let c =
+            crate::mkclient(t.2.registry.clone(), Cache::new()).with_snapshot(recording.clone());
+        Package::new("@bendn/test".into(), "^2.0.0".into(), c)
+            .await
+            .unwrap();
+        let path = path(&t.0.path().join("godot-lock.json"));
+        recording.save(&path).unwrap();
+        assert!(path.ends_with("godot-lock.snapshot.json"));
+
+        // nothing listens here
+        let replay = Arc::new(Snapshot::load(&path).unwrap());
+        let offline =
+            crate::mkclient("http://127.0.0.1:9".into(), Cache::new()).with_snapshot(replay);
+        let p = Package::new("@bendn/test".into(), "^2.0.0".into(), offline.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.to_string(), "@bendn/test@2.0.10");
+        assert_eq!(p.manifest.dependencies[0].to_string(), "@bendn/gdcli@1.2.5");
+        let err = Package::new("ms".into(), "*".into(), offline)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("not in the snapshot"));
+    }
+}
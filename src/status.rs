@@ -0,0 +1,243 @@
+use crate::annotate::Level;
+use crate::changes::Changes;
+use crate::config_file::ConfigFile;
+use crate::package::record;
+use crate::putils;
+use crate::upgrade::{self, Bump, Upgrade};
+use crate::Client;
+
+use serde_json::json;
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// How far the project drifted from what the config wants, at a glance.
+#[derive(Debug)]
+pub struct Status {
+    /// What resolving the config gives, against the lockfile.
+    pub locked: Changes,
+    /// Wanted packages (`name@version`) that are not installed.
+    pub missing: Vec<String>,
+    /// Installed packages whose files were changed (or added to) since they were installed, with how many.
+    pub modified: Vec<(String, usize)>,
+    /// Newer versions of the directly wanted packages, or why the registry couldnt say.
+    pub updates: Result<Vec<Upgrade>, String>,
+    /// Folders in the install dir gpm didnt install (see [crate::adopt::unmanaged]).
+    pub unmanaged: Vec<PathBuf>,
+}
+
+impl Status {
+    /// Looks at the project `cwd`, with the config `contents` (resolved into `cfg`) and the lockfile `lockfile`.
+    /// Only looks into the installed files when something changed since the last update, so its fast when nothing did.
+    pub async fn of(
+        cfg: &ConfigFile,
+        contents: &str,
+        lockfile: &Path,
+        cwd: &Path,
+        client: Client,
+    ) -> Self {
+        let install_dir = cwd.join(cfg.install_dir());
+        // packages only resolved by downloading them are left unresolved: their locked versions are taken as is
+        let unresolved = cfg
+            .graph
+            .iter()
+            .filter(|(_, p)| p.unresolved.is_some())
+            .map(|(_, p)| p.name.as_str())
+            .collect::<Vec<_>>();
+        let locked = serde_json::from_str::<Vec<serde_json::Value>>(
+            &read_to_string(lockfile).unwrap_or_default(),
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|l| !l["name"].as_str().is_some_and(|n| unresolved.contains(&n)))
+        .collect::<Vec<_>>();
+        let resolved = cfg
+            .graph
+            .iter()
+            .filter(|(_, p)| p.unresolved.is_none())
+            .map(|(_, p)| json!({"name": p.name, "version": p.manifest.full_version()}))
+            .collect::<Vec<_>>();
+        let locked = Changes::between(&json!(locked).to_string(), &json!(resolved).to_string());
+        let missing = cfg
+            .graph
+            .iter()
+            .filter(|(_, p)| !p.is_installed(cwd))
+            .map(|(_, p)| p.to_string())
+            .collect();
+        let mut modified = vec![];
+        if !crate::fingerprint::is_fresh(contents, lockfile, &install_dir) {
+            for (_, p) in cfg.graph.iter().filter(|(_, p)| p.is_installed(cwd)) {
+                match record::changed(&p.download_dir(cwd), &p.record_path(cwd)) {
+                    Ok(c) if c.is_empty() => {}
+                    Ok(c) => modified.push((p.to_string(), c.len())),
+                    Err(e) => eprintln!("{:>12} Could not check {p}: {e}", putils::warn()),
+                }
+            }
+        }
+        Self {
+            locked,
+            missing,
+            modified,
+            updates: upgrade::available(cfg, client, Bump::Major)
+                .await
+                .map_err(|e| e.to_string()),
+            unmanaged: crate::adopt::unmanaged(&install_dir).unwrap_or_default(),
+        }
+    }
+
+    /// Wether the config, the lockfile, and the installed packages agree (newer versions dont count).
+    pub fn is_clean(&self) -> bool {
+        self.locked == Changes::default()
+            && self.missing.is_empty()
+            && self.modified.is_empty()
+            && self.unmanaged.is_empty()
+    }
+}
+
+impl Status {
+    /// A line for each check: [None] if it is fine, else how bad it is.
+    pub fn lines(&self) -> Vec<(Option<Level>, String)> {
+        let list = |v: &[String]| v.join(", ");
+        let mut out = vec![];
+        let l = &self.locked;
+        if *l == Changes::default() {
+            out.push((None, "the lockfile has what the config wants".into()));
+        } else {
+            let upgraded = l
+                .upgraded
+                .iter()
+                .map(|(n, old, new)| format!("{n} {old} -> {new}"))
+                .collect::<Vec<_>>();
+            let parts = [
+                ("new", &l.added),
+                ("changed", &upgraded),
+                ("gone", &l.removed),
+            ]
+            .into_iter()
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(what, v)| format!("{what} {}", list(v)))
+            .collect::<Vec<_>>();
+            out.push((
+                Some(Level::Warning),
+                format!(
+                    "the lockfile is behind the config: {} (run gpm update)",
+                    parts.join("; ")
+                ),
+            ));
+        }
+        if self.missing.is_empty() && self.modified.is_empty() {
+            out.push((None, "the installed packages are the locked ones".into()));
+        }
+        if !self.missing.is_empty() {
+            out.push((
+                Some(Level::Warning),
+                format!("not installed: {} (run gpm update)", list(&self.missing)),
+            ));
+        }
+        for (p, n) in &self.modified {
+            out.push((
+                Some(Level::Warning),
+                format!(
+                    "{p} has {n} changed file{} (an update moves them aside)",
+                    if *n == 1 { "" } else { "s" }
+                ),
+            ));
+        }
+        match &self.updates {
+            Ok(u) if u.is_empty() => out.push((None, "everything is at its newest version".into())),
+            Ok(u) => {
+                let major = u.iter().filter(|u| u.bump == Bump::Major).count();
+                out.push((
+                    Some(Level::Notice),
+                    format!(
+                        "{} update{} available ({major} major, see gpm update --latest --dry-run)",
+                        u.len(),
+                        if u.len() == 1 { "" } else { "s" }
+                    ),
+                ));
+            }
+            Err(e) => out.push((
+                Some(Level::Warning),
+                format!("could not look for updates: {e}"),
+            )),
+        }
+        if self.unmanaged.is_empty() {
+            out.push((None, "every folder in the install dir is a package".into()));
+        } else {
+            let folders = self
+                .unmanaged
+                .iter()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>();
+            out.push((
+                Some(Level::Warning),
+                format!("not installed by gpm: {} (see gpm adopt)", list(&folders)),
+            ));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (level, line) in self.lines() {
+            let label = match level {
+                None => putils::green("Ok"),
+                Some(Level::Notice) => putils::green("Updates"),
+                Some(_) => putils::warn(),
+            };
+            writeln!(f, "{label:>12} {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn status() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let cwd = t.0.path();
+        let contents = r#"{"packages": {"@bendn/test": "2.0.10"}}"#;
+        let mut cfg = ConfigFile::new(&contents.into(), c.clone()).await;
+        let lockfile = cwd.join("godot.lock");
+        std::fs::write(
+            &lockfile,
+            r#"[{"name": "@bendn/test", "version": "2.0.9"}]"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(cwd.join("addons/mine")).unwrap();
+        let s = Status::of(&cfg, contents, &lockfile, cwd, c.clone()).await;
+        assert_eq!(s.locked.added, ["@bendn/gdcli@1.2.5"], "{:?}", s.locked);
+        assert_eq!(
+            s.locked.upgraded,
+            [("@bendn/test".into(), "2.0.9".into(), "2.0.10".into())]
+        );
+        assert_eq!(s.missing.len(), 2);
+        assert_eq!(s.unmanaged, [Path::new("mine")]);
+        assert!(!s.is_clean());
+
+        let ids = cfg.graph.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        for id in ids {
+            cfg.graph
+                .get_mut(id)
+                .download(c.clone(), cwd, Default::default())
+                .await
+                .unwrap();
+            let p = cfg.graph.get(id);
+            let (dir, rel) = p.folder_in_install_dir(cwd);
+            record::save(&p.download_dir(cwd), &record::path(&dir, &rel)).unwrap();
+        }
+        std::fs::write(&lockfile, cfg.lock(cwd)).unwrap();
+        std::fs::remove_dir(cwd.join("addons/mine")).unwrap();
+        std::fs::write(cwd.join("addons/@bendn/test/main.gd"), "# mine now").unwrap();
+        let s = Status::of(&cfg, contents, &lockfile, cwd, c).await;
+        assert_eq!(s.locked, Changes::default());
+        assert!(s.missing.is_empty());
+        assert_eq!(s.modified, [("@bendn/test@2.0.10".into(), 1)]);
+        assert!(s.unmanaged.is_empty());
+    }
+}
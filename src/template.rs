@@ -0,0 +1,199 @@
+use crate::version::git;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read, read_dir, write};
+use std::path::{Path, PathBuf};
+
+/// Files, by path.
+pub type Files = BTreeMap<PathBuf, Vec<u8>>;
+
+/// Wether the template `spec` is a git repository (`git+https://..`, `git@host:repo`, `https://host/repo.git`), not a package.
+pub fn is_git(spec: &str) -> bool {
+    spec.starts_with("git+") || spec.starts_with("git@") || spec.starts_with("git://") || {
+        let url = spec.split('#').next().unwrap_or(spec);
+        url.ends_with(".git")
+    }
+}
+
+/// Clones the template repository `spec` (with an optional `#branch`, or tag), and reads its files.
+pub fn clone(spec: &str) -> Result<Files> {
+    let spec = spec.strip_prefix("git+").unwrap_or(spec);
+    let (url, branch) = match spec.split_once('#') {
+        Some((url, branch)) => (url, Some(branch)),
+        None => (spec, None),
+    };
+    let tmp = tempfile::Builder::new()
+        .prefix("gpm-template-")
+        .tempdir()
+        .context("making a dir to clone the template into")?;
+    let dir = tmp.path().join("t");
+    let d = dir.to_string_lossy();
+    let mut args = vec!["clone", "--depth", "1", "--quiet"];
+    if let Some(b) = branch {
+        args.extend(["--branch", b]);
+    }
+    args.extend(["--", url, &d]);
+    git(tmp.path(), &args)?;
+    walk(&dir)
+}
+
+/// Reads the files in `dir` (without the `.git` folder).
+/// A symlink could point anywhere (like `~/.ssh`), so a template with one is refused.
+fn walk(dir: &Path) -> Result<Files> {
+    fn go(dir: &Path, rel: &Path, out: &mut Files) -> Result<()> {
+        for e in read_dir(dir)? {
+            let e = e?;
+            if e.file_name() == ".git" {
+                continue;
+            }
+            let rel = rel.join(e.file_name());
+            let t = e.file_type()?;
+            if t.is_symlink() {
+                bail!(
+                    "the template has a symlink ({}), which it cant",
+                    rel.display()
+                );
+            }
+            if t.is_dir() {
+                go(&e.path(), &rel, out)?;
+            } else {
+                out.insert(rel, read(e.path())?);
+            }
+        }
+        Ok(())
+    }
+    let mut out = Files::new();
+    go(dir, Path::new(""), &mut out)?;
+    Ok(out)
+}
+
+/// Fills in the `{{variables}}` of `text`.
+fn fill(text: &str, vars: &[(&str, &str)]) -> String {
+    vars.iter().fold(text.to_owned(), |t, (k, v)| {
+        t.replace(&format!("{{{{{k}}}}}"), v)
+    })
+}
+
+/// The project a template makes: its files (but its package.json), with the `{{variables}}` filled in,
+/// in file names and text files. Templates without a godot.package get one with the dependencies of their package.json,
+/// and ones without a project.godot get one named `{{name}}`, for `{{godot_version}}`.
+pub fn instantiate(template: Files, vars: &[(&str, &str)]) -> Result<Files> {
+    let mut out = Files::new();
+    let mut dependencies = Value::Object(Default::default());
+    for (path, bytes) in template {
+        if path == Path::new("package.json") {
+            let manifest: Value = serde_json::from_slice(&bytes)
+                .context("parsing the package.json of the template")?;
+            if let Some(d) = manifest.get("dependencies") {
+                dependencies = d.clone();
+            }
+            continue;
+        }
+        let path = PathBuf::from(fill(&path.to_string_lossy(), vars));
+        let bytes = match String::from_utf8(bytes) {
+            Ok(text) => fill(&text, vars).into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        out.insert(path, bytes);
+    }
+    if !out.contains_key(Path::new("godot.package")) {
+        let cfg = serde_json::to_string_pretty(&json!({ "packages": dependencies }))?;
+        out.insert("godot.package".into(), cfg.into_bytes());
+    }
+    if !out.contains_key(Path::new("project.godot")) {
+        let get = |k| vars.iter().find(|(n, _)| *n == k).map_or("", |(_, v)| *v);
+        // godot 3 projects are another version, without features
+        let project = match get("godot_version").starts_with("3.") {
+            true => "config_version=4\n\n[application]\n\nconfig/name=\"{{name}}\"\n",
+            false => "config_version=5\n\n[application]\n\nconfig/name=\"{{name}}\"\nconfig/features=PackedStringArray(\"{{godot_version}}\")\n",
+        };
+        let project = fill(project, vars);
+        out.insert("project.godot".into(), project.into_bytes());
+    }
+    Ok(out)
+}
+
+/// Writes `files` into `dir`, which has to be empty (or not exist).
+/// Nothing is written if any of them would go outside of it.
+pub fn write_all(files: &Files, dir: &Path) -> Result<()> {
+    if read_dir(dir).is_ok_and(|mut d| d.next().is_some()) {
+        bail!("{} is not empty", dir.display());
+    }
+    // no escaping the project
+    if let Some(path) = files.keys().find(|p| {
+        p.components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    }) {
+        bail!("the template has a file outside of it ({})", path.display());
+    }
+    for (path, bytes) in files {
+        let to = dir.join(path);
+        create_dir_all(to.parent().unwrap())?;
+        write(&to, bytes).with_context(|| format!("writing {}", to.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks() {
+        let t = tempfile::tempdir().unwrap();
+        create_dir_all(t.path().join("a/.git")).unwrap();
+        write(t.path().join("a/b.gd"), "extends Node").unwrap();
+        write(t.path().join("a/.git/HEAD"), "ref").unwrap();
+        assert_eq!(
+            walk(&t.path().join("a"))
+                .unwrap()
+                .into_keys()
+                .collect::<Vec<_>>(),
+            [Path::new("b.gd")]
+        );
+        write(t.path().join("secret"), "key").unwrap();
+        std::os::unix::fs::symlink(t.path().join("secret"), t.path().join("a/key")).unwrap();
+        let e = walk(&t.path().join("a")).unwrap_err().to_string();
+        assert!(e.contains("symlink (key)"), "{e}");
+    }
+
+    #[test]
+    fn instantiates() {
+        let template = Files::from([
+            (
+                "package.json".into(),
+                br#"{"name": "t", "dependencies": {"@bendn/test": "^2.0.0"}}"#.to_vec(),
+            ),
+            (
+                "scenes/{{name}}.tscn".into(),
+                b"[gd_scene name=\"{{name}}\"]".to_vec(),
+            ),
+            ("icon.png".into(), vec![0xff, 0x00]),
+        ]);
+        let vars = [("name", "game"), ("godot_version", "4.2")];
+        let out = instantiate(template, &vars).unwrap();
+        assert_eq!(
+            out[Path::new("scenes/game.tscn")],
+            b"[gd_scene name=\"game\"]"
+        );
+        assert_eq!(out[Path::new("icon.png")], [0xff, 0x00]);
+        assert!(!out.contains_key(Path::new("package.json")));
+        let cfg: Value = serde_json::from_slice(&out[Path::new("godot.package")]).unwrap();
+        assert_eq!(cfg["packages"]["@bendn/test"], "^2.0.0");
+        let project = String::from_utf8(out[Path::new("project.godot")].clone()).unwrap();
+        assert!(project.contains("config/features=PackedStringArray(\"4.2\")"));
+
+        let t = tempfile::TempDir::new().unwrap();
+        write_all(&out, t.path()).unwrap();
+        assert!(t.path().join("scenes/game.tscn").exists());
+        assert!(write_all(&out, t.path()).is_err());
+        let escaping = Files::from([("a.gd".into(), vec![]), ("z/../../b.gd".into(), vec![])]);
+        let empty = tempfile::TempDir::new().unwrap();
+        assert!(write_all(&escaping, empty.path()).is_err());
+        assert!(!empty.path().join("a.gd").exists());
+        assert!(is_git("https://github.com/x/y.git#v1") && !is_git("@bendn/test@2"));
+    }
+}
@@ -0,0 +1,95 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The phases we time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Fetching metadata and picking versions (network).
+    Resolve,
+    /// Fetching tarballs (network).
+    Download,
+    /// Checking tarball integrity (cpu).
+    Verify,
+    /// Unpacking and modifying (disk).
+    Extract,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [Self::Resolve, Self::Download, Self::Verify, Self::Extract];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Resolve => "resolve",
+            Self::Download => "download",
+            Self::Verify => "verify",
+            Self::Extract => "extract",
+        }
+    }
+}
+
+lazy_static! {
+    static ref ENABLED: AtomicBool = AtomicBool::new(false);
+    static ref RECORDS: Mutex<Vec<(Phase, String, Duration)>> = Mutex::new(vec![]);
+}
+
+/// Start recording timings.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Records that `package` spent `d` in `phase`. Does nothing unless [enable]d.
+pub fn record(phase: Phase, package: &str, d: Duration) {
+    if ENABLED.load(Ordering::Relaxed) {
+        RECORDS
+            .lock()
+            .unwrap()
+            .push((phase, package.to_string(), d));
+    }
+}
+
+/// Runs `f`, recording how long it took.
+pub fn time<T>(phase: Phase, package: &str, f: impl FnOnce() -> T) -> T {
+    let now = Instant::now();
+    let res = f();
+    record(phase, package, now.elapsed());
+    res
+}
+
+/// Formats the per phase and per package report.
+/// Packages are processed in parallel, so the phase totals can add up to more than the wall time.
+pub fn report(total: Duration) -> String {
+    let records = RECORDS.lock().unwrap();
+    let mut phases = BTreeMap::<Phase, Duration>::new();
+    let mut packages = BTreeMap::<&str, BTreeMap<Phase, Duration>>::new();
+    for (phase, p, d) in records.iter() {
+        *phases.entry(*phase).or_default() += *d;
+        *packages.entry(p).or_default().entry(*phase).or_default() += *d;
+    }
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:>12} {total:.2?} total",
+        crate::putils::green("Timings"),
+    )
+    .unwrap();
+    for phase in Phase::ALL {
+        if let Some(d) = phases.get(&phase) {
+            writeln!(out, "{:>12} {d:.2?}", phase.name()).unwrap();
+        }
+    }
+    let width = packages.keys().map(|p| p.len()).max().unwrap_or(0);
+    for (p, times) in packages {
+        write!(out, "{:>12} {p:width$}", "").unwrap();
+        for phase in Phase::ALL {
+            if let Some(d) = times.get(&phase) {
+                write!(out, "  {} {d:.2?}", phase.name()).unwrap();
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,127 @@
+use crate::graph::Graph;
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// The translation files godot can load: gettext catalogs, and (imported) csvs.
+const CATALOGS: &[&str] = &["po", "mo", "translation"];
+
+/// The translations a package in `dir` ships, as paths in `dir`: the ones its package.json lists in `translations`
+/// (like `"translations": ["locale/fr.po", "locale/text.csv"]`).
+/// Godot doesnt load a csv, but the `text.<locale>.translation` it imports it into (one for each column but the keys).
+pub fn shipped(dir: &Path) -> Vec<String> {
+    let Ok(manifest) = crate::hooks::manifest(dir) else {
+        return vec![];
+    };
+    let Some(listed) = manifest["translations"].as_array() else {
+        return vec![];
+    };
+    let mut out = vec![];
+    for file in listed.iter().filter_map(|f| f.as_str()) {
+        let file = file.trim_start_matches("./");
+        let path = Path::new(file);
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("csv") => {
+                let Ok(csv) = read_to_string(dir.join(path)) else {
+                    continue;
+                };
+                let header = csv.lines().next().unwrap_or_default();
+                let stem = file.strip_suffix(".csv").unwrap();
+                out.extend(
+                    header
+                        .split(',')
+                        .skip(1)
+                        .map(|l| l.trim().trim_matches('"'))
+                        // godot skips the columns starting with _ (comments)
+                        .filter(|l| !l.is_empty() && !l.starts_with('_'))
+                        .map(|l| format!("{stem}.{l}.translation")),
+                );
+            }
+            Some(x) if CATALOGS.contains(&x) => out.push(file.to_owned()),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The `res://` paths of the translations every installed package of `graph` ships (see [shipped]).
+pub fn wanted(graph: &Graph, cwd: &Path) -> Vec<String> {
+    let mut out = vec![];
+    for (_, p) in graph.iter().filter(|(_, p)| p.is_installed(cwd)) {
+        let dir = p.download_dir(Path::new(""));
+        let dir = dir.to_string_lossy().replace('\\', "/");
+        for t in shipped(&p.download_dir(cwd)) {
+            let t = format!("res://{dir}/{t}");
+            if !out.contains(&t) {
+                out.push(t);
+            }
+        }
+    }
+    out
+}
+
+/// `project` (a project.godot) with `wanted` in its translations (its `locale/translations`), and no other ones
+/// from the package folders gpm installed (`recorded`, like `res://addons/@bendn/test/`): those are of packages that are gone.
+/// The projects own translations stay, also the ones in the install dir gpm didnt put there. None if nothing changes.
+pub fn sync(project: &str, recorded: &[String], wanted: &[String]) -> Option<String> {
+    let old = crate::godot::array(project, "internationalization", "locale/translations");
+    let mut new = old
+        .iter()
+        .filter(|t| !recorded.iter().any(|r| t.starts_with(r)) || wanted.contains(t))
+        .cloned()
+        .collect::<Vec<_>>();
+    new.extend(wanted.iter().filter(|t| !old.contains(t)).cloned());
+    (new != old).then(|| {
+        crate::godot::set_array(project, "internationalization", "locale/translations", &new)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn translations() {
+        let t = tempfile::tempdir().unwrap();
+        let dir = t.path();
+        create_dir_all(dir.join("locale")).unwrap();
+        write(
+            dir.join("package.json"),
+            r#"{"translations": ["./locale/fr.po", "locale/text.csv", "readme.md", "gone.csv"]}"#,
+        )
+        .unwrap();
+        write(
+            dir.join("locale/text.csv"),
+            "keys,en,_note,de\nHELLO,Hello,,Hallo\n",
+        )
+        .unwrap();
+        assert_eq!(
+            shipped(dir),
+            [
+                "locale/fr.po",
+                "locale/text.en.translation",
+                "locale/text.de.translation"
+            ]
+        );
+
+        let project = "config_version=5\n\n[internationalization]\n\nlocale/translations=PackedStringArray(\"res://own.po\", \"res://addons/gone/x.po\", \"res://addons/mine/y.po\")\n";
+        let recorded = [
+            "res://addons/gone/".to_owned(),
+            "res://addons/a/".to_owned(),
+        ];
+        let fr = vec!["res://addons/a/locale/fr.po".to_owned()];
+        let synced = sync(project, &recorded, &fr).unwrap();
+        assert_eq!(
+            synced,
+            "config_version=5\n\n[internationalization]\n\nlocale/translations=PackedStringArray(\"res://own.po\", \"res://addons/mine/y.po\", \"res://addons/a/locale/fr.po\")\n"
+        );
+        assert_eq!(sync(&synced, &recorded, &fr), None);
+        let removed = sync(&synced, &recorded, &[]).unwrap();
+        assert_eq!(
+            crate::godot::array(&removed, "internationalization", "locale/translations"),
+            ["res://own.po", "res://addons/mine/y.po"]
+        );
+        assert_eq!(sync("config_version=5\n", &recorded, &[]), None);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::graph::Graph;
+use crate::package::Package;
+
+use std::collections::HashSet;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+/// The files that can use an addon: scripts, scenes, resources, and the project.godot (autoloads, enabled plugins).
+const SOURCES: &[&str] = &["gd", "tscn", "tres", "godot"];
+
+/// The files of the project in `dir` that can use an addon (see [SOURCES]).
+/// The install dir (`skip`) is left out, as are hidden folders (like `.godot`).
+fn sources(dir: &Path, skip: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    for e in entries.filter_map(|e| e.ok()) {
+        let p = e.path();
+        if e.file_name().to_string_lossy().starts_with('.') || p == skip {
+            continue;
+        }
+        if p.is_dir() {
+            sources(&p, skip, out);
+        } else if p
+            .extension()
+            .is_some_and(|x| SOURCES.iter().any(|s| x == *s))
+        {
+            out.push(p);
+        }
+    }
+}
+
+/// The `class_name`s the scripts in `dir` declare: they can be used without a path.
+fn class_names(dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = read_dir(dir) else {
+        return;
+    };
+    for e in entries.filter_map(|e| e.ok()) {
+        let p = e.path();
+        if p.is_dir() {
+            class_names(&p, out);
+        } else if p.extension().is_some_and(|x| x == "gd") {
+            let script = read_to_string(&p).unwrap_or_default();
+            out.extend(script.lines().filter_map(|l| {
+                let name = l.trim().strip_prefix("class_name ")?;
+                Some(name.split_whitespace().next()?.to_owned())
+            }));
+        }
+    }
+}
+
+/// The directly wanted packages of `graph` nothing in the project `cwd` uses: no file (outside the install dir)
+/// has a `res://` path into their folder, or says one of their `class_name`s.
+pub fn find<'a>(graph: &'a Graph, cwd: &Path, install_dir: &Path) -> Vec<&'a Package> {
+    let mut files = vec![];
+    sources(cwd, &cwd.join(install_dir), &mut files);
+    let texts = files
+        .iter()
+        .filter_map(|f| read_to_string(f).ok())
+        .collect::<Vec<_>>();
+    let words = texts
+        .iter()
+        .flat_map(|t| t.split(|c: char| !c.is_alphanumeric() && c != '_'))
+        .collect::<HashSet<_>>();
+    graph
+        .roots()
+        .iter()
+        .map(|&id| graph.get(id))
+        .filter(|p| {
+            let dir = p.download_dir(Path::new(""));
+            let res = format!("res://{}/", dir.to_string_lossy().replace('\\', "/"));
+            if texts.iter().any(|t| t.contains(&res)) {
+                return false;
+            }
+            let mut names = vec![];
+            class_names(&cwd.join(dir), &mut names);
+            !names.iter().any(|n| words.contains(n.as_str()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[tokio::test]
+    async fn unused() {
+        let t = crate::test_utils::mktemp().await;
+        let c = t.2;
+        let cwd = t.0.path();
+        let mut p = Package::create_from_str("@bendn/test@2.0.10", c.clone())
+            .await
+            .unwrap();
+        p.download(c, cwd, Default::default()).await.unwrap();
+        let graph = Graph::new(vec![p]);
+        let addons = Path::new("addons");
+        // its own files dont count
+        assert_eq!(
+            find(&graph, cwd, addons)[0].to_string(),
+            "@bendn/test@2.0.10"
+        );
+        write(
+            cwd.join("main.tscn"),
+            "[ext_resource type=\"Script\" path=\"res://addons/@bendn/test/main.gd\" id=\"1\"]\n",
+        )
+        .unwrap();
+        assert!(find(&graph, cwd, addons).is_empty());
+        write(cwd.join("main.tscn"), "").unwrap();
+        write(
+            cwd.join("addons/@bendn/test/tester.gd"),
+            "class_name Tester\nextends Node\n",
+        )
+        .unwrap();
+        write(cwd.join("player.gd"), "var t = TesterThing.new()\n").unwrap();
+        assert_eq!(find(&graph, cwd, addons).len(), 1);
+        write(cwd.join("player.gd"), "var t := Tester.new()\n").unwrap();
+        assert!(find(&graph, cwd, addons).is_empty());
+    }
+}
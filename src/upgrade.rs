@@ -0,0 +1,136 @@
+use crate::config_file::ConfigFile;
+use crate::package::Package;
+use crate::Client;
+
+use anyhow::Result;
+use futures::future::join_all;
+use semver_rs::Version;
+use std::fmt;
+
+/// How big a version bump is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    pub fn between(from: &Version, to: &Version) -> Self {
+        if from.major != to.major {
+            Self::Major
+        } else if from.minor != to.minor {
+            Self::Minor
+        } else {
+            Self::Patch
+        }
+    }
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        })
+    }
+}
+
+/// A newer version of a directly wanted package.
+#[derive(Clone, Debug)]
+pub struct Upgrade {
+    pub name: String,
+    pub from: Version,
+    pub to: Version,
+    pub bump: Bump,
+}
+
+impl Upgrade {
+    /// Whats written to the config for this upgrade, keeping the `^`/`~` of the `old` range.
+    pub fn range(&self, old: &str) -> String {
+        match old.trim().chars().next() {
+            Some(c @ ('^' | '~')) => format!("{c}{}", self.to),
+            _ => self.to.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Upgrade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<5} {} {} -> {}",
+            self.bump, self.name, self.from, self.to
+        )
+    }
+}
+
+/// The newest (non prerelease) version after `from`, at most a `scope` bump away.
+pub fn newest<'a>(
+    from: &Version,
+    versions: impl Iterator<Item = &'a Version>,
+    scope: Bump,
+) -> Option<&'a Version> {
+    versions
+        .filter(|v| !v.has_prerelease() && *v > from && Bump::between(from, v) <= scope)
+        .max()
+}
+
+/// Looks for newer versions of the directly wanted packages, at most a `scope` bump away.
+/// Sorted by bump, then name.
+pub async fn available(cfg: &ConfigFile, client: Client, scope: Bump) -> Result<Vec<Upgrade>> {
+    let roots = cfg
+        .graph
+        .roots()
+        .iter()
+        .map(|&id| cfg.graph.get(id))
+        .filter(|p| !p.name.starts_with("http")); // tarballs dont have other versions
+    let found = join_all(roots.map(|p| {
+        let client = client.clone();
+        async move {
+            let packument = Package::get_packument(client, &p.name).await?;
+            let versions = packument
+                .versions
+                .iter()
+                .filter_map(|m| Version::new(&m.version).parse().ok())
+                .collect::<Vec<_>>();
+            let from = &p.manifest.version;
+            Ok::<_, anyhow::Error>(newest(from, versions.iter(), scope).map(|to| Upgrade {
+                name: p.name.clone(),
+                from: from.clone(),
+                to: to.clone(),
+                bump: Bump::between(from, to),
+            }))
+        }
+    }))
+    .await;
+    let mut upgrades = found
+        .into_iter()
+        .filter_map(Result::transpose)
+        .collect::<Result<Vec<_>>>()?;
+    upgrades.sort_by(|a, b| (a.bump, &a.name).cmp(&(b.bump, &b.name)));
+    Ok(upgrades)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn upgrades() {
+        let c = crate::test_utils::mktemp().await.2;
+        let cfg = ConfigFile::new(
+            &r#"{"packages": {"@bendn/test": "1.0.0"}}"#.into(),
+            c.clone(),
+        )
+        .await;
+        let major = available(&cfg, c.clone(), Bump::Major).await.unwrap();
+        assert_eq!(major[0].to_string(), "major @bendn/test 1.0.0 -> 2.0.10");
+        assert_eq!(major[0].range("^1.0.0"), "^2.0.10");
+        let minor = available(&cfg, c.clone(), Bump::Minor).await.unwrap();
+        assert_eq!(minor[0].to_string(), "minor @bendn/test 1.0.0 -> 1.2.6");
+        assert_eq!(minor[0].range("1.0.0"), "1.2.6");
+        assert!(available(&cfg, c, Bump::Patch).await.unwrap().is_empty());
+    }
+}
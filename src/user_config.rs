@@ -0,0 +1,215 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env::{var, var_os};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// The users own settings, for every project: `config.json` in [UserConfig::dir].
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct UserConfig {
+    /// What `gpm add` puts before the version it resolved (see [prefix]).
+    pub save_prefix: Option<String>,
+    /// The malware scanner every downloaded tarball goes through before it is unpacked (see [crate::scan::check]).
+    pub scan_command: Option<String>,
+    /// How big the package store may get (like 2G), before the least recently used packages are deleted from it.
+    pub max_cache_size: Option<String>,
+    /// Where the package store and the resolution cache go, instead of the platforms cache dir (see [crate::cache::Cache::dir]).
+    pub cache_dir: Option<PathBuf>,
+    /// How to authenticate with registries, by their url (like `https://npm.pkg.github.com`).
+    pub registries: BTreeMap<String, Registry>,
+    /// How deep dependencies may go, before resolving gives up (see [crate::Limits]).
+    pub max_depth: Option<usize>,
+    /// How many packages one run may resolve, before it gives up.
+    pub max_packages: Option<usize>,
+}
+
+/// The credentials of a registry, like a github packages or azure artifacts feed.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Registry {
+    /// Sent as a bearer token. `$NAME` reads it from the environment variable `NAME`.
+    pub token: Option<String>,
+    /// With the password, sent as basic auth instead (azure wants its personal access token as the password).
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Authenticate reads (packuments and tarballs) too, not only publishing. Private feeds need it.
+    pub always_auth: bool,
+    /// Another registry with the same packages, asked when this one sends a packument that doesnt parse.
+    pub mirror: Option<String>,
+}
+
+/// `s`, or the environment variable it names, if it is like `$NAME`.
+fn expand(s: &str) -> Option<String> {
+    match s.strip_prefix('$') {
+        Some(name) => var(name).ok(),
+        None => Some(s.to_owned()),
+    }
+}
+
+impl Registry {
+    /// The token of this registry, if it has one.
+    pub fn token(&self) -> Option<String> {
+        expand(self.token.as_deref()?)
+    }
+
+    /// Adds the credentials of this registry to `req`, if it has any.
+    pub fn authorize(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(token) = self.token() {
+            return req.bearer_auth(token);
+        }
+        match (&self.username, self.password.as_deref().and_then(expand)) {
+            (Some(user), Some(password)) => req.basic_auth(user, Some(password)),
+            _ => req,
+        }
+    }
+}
+
+/// The credentials in `registries` for `url`: of the longest registry url its in, if that one always authenticates.
+pub fn credentials<'a>(
+    registries: &'a BTreeMap<String, Registry>,
+    url: &str,
+) -> Option<&'a Registry> {
+    registries
+        .iter()
+        .filter(|(r, cfg)| cfg.always_auth && url.starts_with(r.trim_end_matches('/')))
+        .max_by_key(|(r, _)| r.len())
+        .map(|(_, cfg)| cfg)
+}
+
+/// The mirror in `registries` of the registry `registry`.
+pub fn mirror<'a>(registries: &'a BTreeMap<String, Registry>, registry: &str) -> Option<&'a str> {
+    registries
+        .iter()
+        .find(|(r, _)| r.trim_end_matches('/') == registry.trim_end_matches('/'))
+        .and_then(|(_, cfg)| cfg.mirror.as_deref())
+}
+
+/// Checks a save prefix: `^` (compatible updates), `~` (patch updates), or nothing (pinned).
+pub fn prefix(p: &str) -> Result<&str> {
+    match p {
+        "^" | "~" | "" => Ok(p),
+        _ => bail!("the save prefix {p:?} is not ^, ~, or nothing"),
+    }
+}
+
+impl UserConfig {
+    /// The [UserConfig::max_cache_size], in bytes.
+    pub fn max_cache_size(&self) -> Result<Option<u64>> {
+        self.max_cache_size
+            .as_deref()
+            .map(|s| {
+                crate::publish::parse_size(s).map_err(|e| anyhow::anyhow!("max-cache-size: {e}"))
+            })
+            .transpose()
+    }
+
+    /// The user config directory.
+    pub fn dir() -> PathBuf {
+        if let Some(d) = var_os("XDG_CONFIG_HOME") {
+            return PathBuf::from(d).join("gpm");
+        }
+        if let Some(d) = var_os("APPDATA") {
+            return PathBuf::from(d).join("gpm");
+        }
+        if let Some(h) = var_os("HOME") {
+            return PathBuf::from(h).join(".config").join("gpm");
+        }
+        std::env::temp_dir().join("gpm")
+    }
+
+    /// Loads the user config from [UserConfig::dir]. Theres nothing to load if it doesnt exist.
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::dir().join("config.json"))
+    }
+
+    /// [UserConfig::load], but a broken user config doesnt stop anything: it is warned about, and the defaults do.
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|e| {
+            eprintln!("{:>12} {e:#}: using the defaults", crate::putils::warn());
+            Self::default()
+        })
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let Ok(contents) = read_to_string(path) else {
+            return Ok(Self::default());
+        };
+        let cfg: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("parsing the user config {}", path.display()))?;
+        if let Some(p) = &cfg.save_prefix {
+            prefix(p).with_context(|| format!("in the user config {}", path.display()))?;
+        }
+        cfg.max_cache_size()
+            .with_context(|| format!("in the user config {}", path.display()))?;
+        Ok(cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load() {
+        let t = tempfile::TempDir::new().unwrap();
+        let p = t.path().join("config.json");
+        assert_eq!(UserConfig::load_from(&p).unwrap().save_prefix, None);
+        std::fs::write(&p, r#"{"save-prefix": "~"}"#).unwrap();
+        assert_eq!(
+            UserConfig::load_from(&p).unwrap().save_prefix.as_deref(),
+            Some("~")
+        );
+        std::fs::write(&p, r#"{"save-prefix": ">="}"#).unwrap();
+        assert!(UserConfig::load_from(&p).is_err());
+        std::fs::write(&p, r#"{"max-cache-size": "2K"}"#).unwrap();
+        let cfg = UserConfig::load_from(&p).unwrap();
+        assert_eq!(cfg.max_cache_size().unwrap(), Some(2048));
+        std::fs::write(&p, r#"{"max-cache-size": "lots"}"#).unwrap();
+        assert!(UserConfig::load_from(&p).is_err());
+        std::fs::write(&p, r#"{"cache-dir": "/mnt/cache"}"#).unwrap();
+        assert_eq!(
+            UserConfig::load_from(&p).unwrap().cache_dir.as_deref(),
+            Some(Path::new("/mnt/cache"))
+        );
+        std::fs::write(
+            &p,
+            r#"{"registries": {
+                "https://npm.pkg.github.com": {"token": "ghp_x", "always-auth": true},
+                "https://pkgs.dev.azure.com/org/_packaging/feed/npm/registry/": {"username": "me", "password": "pat", "always-auth": true},
+                "https://registry.example.com/": {"token": "x", "mirror": "https://mirror.example.com"}
+            }}"#,
+        )
+        .unwrap();
+        let cfg = UserConfig::load_from(&p).unwrap();
+        assert_eq!(
+            mirror(&cfg.registries, "https://registry.example.com"),
+            Some("https://mirror.example.com")
+        );
+        assert_eq!(mirror(&cfg.registries, "https://npm.pkg.github.com"), None);
+        let header = |url: &str| {
+            let req = reqwest::Client::new().get(url);
+            let req = match credentials(&cfg.registries, url) {
+                Some(r) => r.authorize(req),
+                None => req,
+            };
+            let req = req.build().unwrap();
+            req.headers()
+                .get("Authorization")
+                .map(|h| h.to_str().unwrap().to_owned())
+        };
+        assert_eq!(
+            header("https://npm.pkg.github.com/@me%2faddon").as_deref(),
+            Some("Bearer ghp_x")
+        );
+        assert_eq!(
+            header("https://pkgs.dev.azure.com/org/_packaging/feed/npm/registry/addon/-/addon-1.0.0.tgz")
+                .as_deref(),
+            Some("Basic bWU6cGF0")
+        );
+        // only for publishing
+        assert_eq!(header("https://registry.example.com/addon"), None);
+        assert_eq!(header("https://registry.npmjs.org/addon"), None);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::cache::{Cache, CacheEntry};
+use crate::config_file::ConfigFile;
+use crate::integrity::{Algorithm, Integrity};
+use crate::package::parsing::{ParsedManifest, ParsedManifestDist};
+use crate::package::Package;
+use crate::Client;
+
+use anyhow::{Context, Result};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_file, write};
+use std::path::Path;
+
+/// What is in the vendor dir, next to the tarballs.
+pub const INDEX: &str = "index.json";
+
+/// The vendored packages: name => version => manifest (its dependencies pinned to the vendored versions).
+#[derive(Default, Deserialize, Serialize)]
+pub struct Index {
+    pub packages: BTreeMap<String, BTreeMap<String, ParsedManifest>>,
+}
+
+/// The tarball of `name@version` in the vendor dir, like `@bendn_2ftest@2.0.10.tgz`. Every character of the name but
+/// letters, digits, `.`, `-`, `~` (and the `@` of a scope) is written as `_` and its hex, so no two packages share a file,
+/// and it is fine in a url and on every filesystem.
+pub fn file(name: &str, version: &str) -> String {
+    let (scope, rest) = match name.strip_prefix('@') {
+        Some(rest) => ("@", rest),
+        None => ("", name),
+    };
+    let mut out = scope.to_owned();
+    for c in rest.chars() {
+        if c.is_ascii_alphanumeric() || ".-~".contains(c) {
+            out.push(c);
+        } else {
+            for b in c.to_string().bytes() {
+                out.push_str(&format!("_{b:02x}"));
+            }
+        }
+    }
+    format!("{out}@{version}.tgz")
+}
+
+/// The packages of a config, ready to be written somewhere: their [Index], and their tarballs ([file] => bytes).
+#[derive(Default)]
+pub struct Vendored {
+    pub index: Index,
+    pub tarballs: BTreeMap<String, Vec<u8>>,
+    /// The vendored packages, like `@bendn/test@2.0.10`.
+    pub packages: Vec<String>,
+}
+
+/// Gets the tarballs of every package of `cfg`, with an index to resolve them from.
+/// Packages only known by their tarball url are left out.
+pub async fn collect(cfg: &ConfigFile, client: Client) -> Result<Vendored> {
+    let mut v = Vendored::default();
+    for (id, p) in cfg.graph.iter() {
+        if p.name.starts_with("http") {
+            eprintln!(
+                "{:>12} {p} is a tarball url, so it cant be vendored",
+                crate::putils::warn()
+            );
+            continue;
+        }
+        let version = p.manifest.full_version();
+        let bytes = p.fetch(client.clone(), &ProgressBar::hidden()).await?;
+        v.index
+            .packages
+            .entry(p.name.clone())
+            .or_default()
+            .insert(version.clone(), manifest(&cfg.graph.with_deps(id), &bytes));
+        v.tarballs.insert(file(&p.name, &version), bytes);
+        v.packages.push(p.to_string());
+    }
+    Ok(v)
+}
+
+/// Copies the tarballs of every package of `cfg` into `dir`, with an index to resolve them from (see [load]),
+/// and deletes the ones no longer needed. Returns the vendored packages.
+pub async fn vendor(cfg: &ConfigFile, client: Client, dir: &Path) -> Result<Vec<String>> {
+    create_dir_all(dir).context("making the vendor dir")?;
+    let v = collect(cfg, client).await?;
+    for (name, bytes) in &v.tarballs {
+        write(dir.join(name), bytes).context("writing a vendored tarball")?;
+    }
+    for e in read_dir(dir)? {
+        let name = e?.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".tgz") && !v.tarballs.contains_key(&name) {
+            remove_file(dir.join(name))?;
+        }
+    }
+    write(dir.join(INDEX), serde_json::to_string_pretty(&v.index)?)
+        .context("writing the vendor index")?;
+    Ok(v.packages)
+}
+
+/// The manifest of the vendored `p`, with the tarball `bytes`.
+fn manifest(p: &Package, bytes: &[u8]) -> ParsedManifest {
+    ParsedManifest {
+        dist: ParsedManifestDist {
+            shasum: format!("{:x}", Sha1::digest(bytes)),
+            tarball: p.manifest.tarball.to_string(),
+            integrity: Some(Integrity::of(Algorithm::Sha512, bytes).to_string()),
+        },
+        dependencies: p
+            .manifest
+            .dependencies
+            .iter()
+            .map(|d| (d.name.clone(), format!("={}", d.manifest.full_version())))
+            .collect(),
+        version: p.manifest.full_version(),
+        deprecated: p.manifest.deprecated.clone(),
+        hooks: p.manifest.hooks.clone(),
+        engines: p
+            .manifest
+            .godot
+            .iter()
+            .map(|g| ("godot".into(), g.clone()))
+            .collect(),
+    }
+}
+
+/// Makes the packages vendored in `dir` resolvable with `cache`, without the registry.
+pub fn load(dir: &Path, cache: &Cache) -> Result<()> {
+    let index = read_to_string(dir.join(INDEX))
+        .with_context(|| format!("reading {} (run gpm vendor)", dir.join(INDEX).display()))?;
+    let index: Index = serde_json::from_str(&index).context("parsing the vendor index")?;
+    for (name, versions) in index.packages {
+        for (version, m) in versions {
+            cache.insert(name.clone(), version, CacheEntry::Manifest(m));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn offline() {
+        let t = crate::test_utils::mktemp().await;
+        let c = crate::mkclient(t.2.registry.clone(), Cache::new());
+        let cfg = ConfigFile::new(
+            &r#"{"packages": {"@bendn/test": "^2.0.0"}}"#.into(),
+            c.clone(),
+        )
+        .await;
+        let dir = t.0.path().join("vendor");
+        create_dir_all(&dir).unwrap();
+        write(dir.join("old-1.0.0.tgz"), "").unwrap();
+        let vendored = vendor(&cfg, c, &dir).await.unwrap();
+        assert_eq!(vendored.len(), 2);
+        assert!(!dir.join("old-1.0.0.tgz").exists());
+        assert!(dir.join("@bendn_2fgdcli@1.2.5.tgz").exists());
+        assert_eq!(file("@a/b-c", "1.0.0"), "@a_2fb-c@1.0.0.tgz");
+        assert_ne!(file("@a/b-c", "1.0.0"), file("@a-b/c", "1.0.0"));
+        assert_ne!(file("a_2fb", "1.0.0"), file("a/b", "1.0.0"));
+        assert_eq!(file("gh:u/a", "1.0.0"), "gh_3au_2fa@1.0.0.tgz");
+
+        // nothing listens here, so this only works if nothing is fetched
+        let offline = crate::Client {
+            real: reqwest::Client::builder()
+                .proxy(reqwest::Proxy::all("http://127.0.0.1:9").unwrap())
+                .build()
+                .unwrap(),
+            ..crate::mkclient(t.2.registry.clone(), Cache::new())
+        }
+        .with_vendor(dir.clone());
+        load(&dir, offline.cache_ref()).unwrap();
+        let mut p = Package::new("@bendn/test".into(), "^2.0.0".into(), offline.clone())
+            .await
+            .unwrap();
+        assert_eq!(p.manifest.dependencies[0].to_string(), "@bendn/gdcli@1.2.5");
+        p.download(offline.clone(), t.0.path(), Algorithm::Sha512)
+            .await
+            .unwrap();
+        assert!(p.download_dir(t.0.path()).join("package.json").exists());
+        assert!(Package::new("@bendn/test".into(), "^3".into(), offline)
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("not vendored"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::upgrade::Bump;
+
+use anyhow::{anyhow, bail, Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use semver_rs::Version;
+use std::path::Path;
+use std::process::Command;
+
+lazy_static! {
+    static ref VERSION_KEY: Regex = Regex::new(r#""version"\s*:\s*"[^"]*""#).unwrap();
+}
+
+/// The version after `current`: `patch`, `minor`, `major`, or an exact version.
+pub fn next(current: &Version, to: &str) -> Result<Version> {
+    let bump = match to {
+        "patch" => Bump::Patch,
+        "minor" => Bump::Minor,
+        "major" => Bump::Major,
+        exact => {
+            let v = Version::new(exact)
+                .parse()
+                .map_err(|_| anyhow!("{exact} is not patch, minor, major, or a version"))?;
+            if v <= *current {
+                bail!("{v} is not newer than {current}");
+            }
+            return Ok(v);
+        }
+    };
+    let (major, minor, patch) = (current.major, current.minor, current.patch);
+    // bumping a prerelease releases it, like npm does
+    let pre = current.has_prerelease();
+    let v = match bump {
+        Bump::Patch if pre => format!("{major}.{minor}.{patch}"),
+        Bump::Patch => format!("{major}.{minor}.{}", patch + 1),
+        Bump::Minor if pre && patch == 0 => format!("{major}.{minor}.0"),
+        Bump::Minor => format!("{major}.{}.0", minor + 1),
+        Bump::Major if pre && minor == 0 && patch == 0 => format!("{major}.0.0"),
+        Bump::Major => format!("{}.0.0", major + 1),
+    };
+    Ok(Version::new(&v).parse().unwrap())
+}
+
+/// How deep in objects and arrays each byte of some json is.
+fn depths(json: &str) -> Vec<usize> {
+    let (mut depth, mut string, mut escaped) = (0usize, false, false);
+    json.bytes()
+        .map(|b| {
+            let at = depth;
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' if string => escaped = true,
+                b'"' => string = !string,
+                b'{' | b'[' if !string => depth += 1,
+                b'}' | b']' if !string => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            at
+        })
+        .collect()
+}
+
+/// Writes `version` into a package.json, keeping the rest as it was.
+pub fn set(contents: &str, version: &Version) -> Result<String> {
+    let depths = depths(contents);
+    let Some(m) = VERSION_KEY
+        .find_iter(contents)
+        .find(|m| depths[m.start()] == 1)
+    else {
+        bail!("package.json has no version");
+    };
+    Ok(format!(
+        r#"{}"version": "{version}"{}"#,
+        &contents[..m.start()],
+        &contents[m.end()..]
+    ))
+}
+
+/// Runs git in `dir`, returning what it printed.
+pub fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("running git")?;
+    if !out.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Fails if the git repository in `dir` has uncommitted changes, which would end up in the version commit.
+pub fn check_clean(dir: &Path) -> Result<()> {
+    if !git(dir, &["status", "--porcelain"])?.trim().is_empty() {
+        bail!("the git working directory is not clean (commit or stash first)");
+    }
+    Ok(())
+}
+
+/// Commits the package.json, and tags the commit `v<version>`.
+pub fn commit_and_tag(dir: &Path, version: &Version) -> Result<()> {
+    let tag = format!("v{version}");
+    git(dir, &["add", "package.json"])?;
+    git(dir, &["commit", "-m", &tag])?;
+    git(dir, &["tag", "-a", &tag, "-m", &tag])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bumps() {
+        let v = |s| Version::new(s).parse().unwrap();
+        let next = |from, to| next(&v(from), to).unwrap().to_string();
+        assert_eq!(next("1.2.3", "patch"), "1.2.4");
+        assert_eq!(next("1.2.3", "minor"), "1.3.0");
+        assert_eq!(next("1.2.3", "major"), "2.0.0");
+        assert_eq!(next("1.2.3-beta.1", "patch"), "1.2.3");
+        assert_eq!(next("2.0.0-rc.1", "major"), "2.0.0");
+        assert_eq!(next("1.2.3", "1.5.0"), "1.5.0");
+        assert!(super::next(&v("1.2.3"), "1.0.0").is_err());
+        assert!(super::next(&v("1.2.3"), "huge").is_err());
+        assert_eq!(
+            set(
+                "{\n  \"x\": {\"version\": \"9\"},\n  \"version\": \"1.2.3\"\n}",
+                &v("1.3.0")
+            )
+            .unwrap(),
+            "{\n  \"x\": {\"version\": \"9\"},\n  \"version\": \"1.3.0\"\n}"
+        );
+        assert!(set(r#"{"x": {"version": "9"}}"#, &v("1.3.0")).is_err());
+    }
+}
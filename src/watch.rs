@@ -0,0 +1,68 @@
+use std::fs::{metadata, read_dir};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched files are looked at.
+pub const EVERY: Duration = Duration::from_millis(500);
+
+/// When `p` was last modified (None if it doesnt exist). For a dir, when anything in it was.
+fn stamp(p: &Path) -> Option<SystemTime> {
+    let meta = metadata(p).ok()?;
+    let mut newest = meta.modified().ok()?;
+    if meta.is_dir() {
+        for e in read_dir(p).into_iter().flatten().flatten() {
+            newest = newest.max(stamp(&e.path()).unwrap_or(newest));
+        }
+    }
+    Some(newest)
+}
+
+/// When each of `paths` was last modified (see [stamp]).
+fn stamps(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths.iter().map(|p| stamp(p)).collect()
+}
+
+/// Blocks until one of `paths` (files, or dirs) changes (is written, made, or deleted), looking every `every`.
+/// Editors write a file more than once when saving, so this returns once it stops changing.
+pub fn wait(paths: &[PathBuf], every: Duration) {
+    let start = stamps(paths);
+    let mut last = start.clone();
+    loop {
+        sleep(every);
+        let now = stamps(paths);
+        if now != start && now == last {
+            return;
+        }
+        last = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits() {
+        let t = tempfile::TempDir::new().unwrap();
+        let p = t.path().join("godot.package");
+        let writer = {
+            let p = p.clone();
+            std::thread::spawn(move || {
+                sleep(Duration::from_millis(50));
+                std::fs::write(p, "").unwrap();
+            })
+        };
+        wait(std::slice::from_ref(&p), Duration::from_millis(10));
+        assert!(p.exists());
+        writer.join().unwrap();
+        // and in dirs, what is deep inside them
+        let deep = t.path().join("addon/sub");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(deep.join("a.gd"), "").unwrap();
+        let before = stamp(&t.path().join("addon"));
+        sleep(Duration::from_millis(20));
+        std::fs::write(deep.join("a.gd"), "extends Node").unwrap();
+        assert_ne!(stamp(&t.path().join("addon")), before);
+    }
+}